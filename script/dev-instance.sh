@@ -16,7 +16,8 @@ INSTANCE_NAME="${1:-$DEFAULT_INSTANCE_NAME}"
 # Sanitize the instance name to be filesystem-safe
 SAFE_INSTANCE_NAME=$(echo "$INSTANCE_NAME" | sed 's/[^a-zA-Z0-9_-]/_/g')
 
-# Create the unique identifier
+# Create the unique identifier. The app derives the same one from
+# CHORUS_INSTANCE_NAME, which is what keeps its data directories apart.
 IDENTIFIER="sh.chorus.app.dev.$SAFE_INSTANCE_NAME"
 
 # Calculate a unique port based on the instance name
@@ -0,0 +1,1072 @@
+// An opt-in HTTP server on localhost for scripting Chorus. It's turned on
+// with `settings.localApi` and every request needs the bearer token kept in
+// local-api.json, next to the database. Changes it makes are announced with
+// the same events the windows already listen for.
+
+use rand::RngCore;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Listener, Manager};
+
+use crate::history::{self, ProjectScope};
+use crate::search::{self, SearchFilters};
+use crate::{command, data_dir, db, migrator, profiles, vault};
+
+const CONFIG_FILE_NAME: &str = "local-api.json";
+const DEFAULT_PORT: u16 = 6070;
+pub const STATUS_EVENT: &str = "local-api-status";
+
+// Sent by SettingsManager.set in the frontend
+const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
+// Projects every install has, which can't be renamed or deleted
+const BUILT_IN_PROJECTS: [&str; 2] = ["default", "quick-chat"];
+
+// The request line and headers. The body is only read once they've been
+// authorized.
+const MAX_HEAD_BYTES: u64 = 64 * 1024;
+const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+// How often the accept loop checks whether it's been stopped
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Should match allowedExtensions in src/core/chorus/Models.ts
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "gif", "webp"];
+const TEXT_EXTENSIONS: [&str; 51] = [
+    "txt",
+    "md",
+    "rst",
+    "org",
+    "wiki",
+    "html",
+    "htm",
+    "css",
+    "scss",
+    "less",
+    "js",
+    "jsx",
+    "ts",
+    "tsx",
+    "json",
+    "py",
+    "java",
+    "cpp",
+    "c",
+    "h",
+    "cs",
+    "go",
+    "rs",
+    "rb",
+    "php",
+    "sql",
+    "swift",
+    "kt",
+    "scala",
+    "lua",
+    "pl",
+    "r",
+    "dart",
+    "ex",
+    "exs",
+    "erl",
+    "csv",
+    "yml",
+    "yaml",
+    "xml",
+    "ini",
+    "env",
+    "conf",
+    "toml",
+    "lock",
+    "properties",
+    "sh",
+    "bash",
+    "zsh",
+    "bat",
+    "ps1",
+];
+
+/// Local API preferences, read from `settings.localApi` in the settings store.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct ApiSettings {
+    enabled: bool,
+    port: u16,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+/// What's in local-api.json, so scripts can find the server
+#[derive(Serialize, Deserialize)]
+struct ApiConfig {
+    port: u16,
+    token: String,
+}
+
+struct Running {
+    port: u16,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct Inner {
+    running: Option<Running>,
+    token: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ApiState {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiStatus {
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    pub url: Option<String>,
+    pub token: String,
+    pub error: Option<String>,
+}
+
+fn read_settings(app_handle: &AppHandle) -> ApiSettings {
+    profiles::settings_store(app_handle)
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|settings| settings.get("localApi").cloned())
+        .and_then(|local_api| serde_json::from_value(local_api).ok())
+        .unwrap_or_default()
+}
+
+fn config_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let app_dir = data_dir::config_dir(app_handle)?;
+    fs::create_dir_all(&app_dir).map_err(|e| e.to_string())?;
+    Ok(app_dir.join(CONFIG_FILE_NAME))
+}
+
+pub fn generate_token() -> String {
+    let mut token = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut token);
+    hex::encode(token)
+}
+
+fn write_config(app_handle: &AppHandle, port: u16, token: &str) -> Result<(), String> {
+    let config = ApiConfig {
+        port,
+        token: token.to_string(),
+    };
+    let contents = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    vault::write_private(&config_path(app_handle)?, &contents)
+}
+
+/// The token requests have to carry, created the first time it's needed.
+fn token(app_handle: &AppHandle, inner: &mut Inner) -> Result<String, String> {
+    if let Some(token) = &inner.token {
+        return Ok(token.clone());
+    }
+    let path = config_path(app_handle)?;
+    let saved = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<ApiConfig>(&contents).ok())
+        .map(|config| config.token)
+        .filter(|token| !token.is_empty());
+    let token = match saved {
+        Some(token) => token,
+        None => {
+            let token = generate_token();
+            write_config(app_handle, read_settings(app_handle).port, &token)?;
+            token
+        }
+    };
+    inner.token = Some(token.clone());
+    Ok(token)
+}
+
+pub fn status(app_handle: &AppHandle) -> Result<ApiStatus, String> {
+    let settings = read_settings(app_handle);
+    let state = app_handle.state::<ApiState>();
+    let mut inner = state.inner.lock().unwrap();
+    let token = token(app_handle, &mut inner)?;
+    let port = inner
+        .running
+        .as_ref()
+        .map(|running| running.port)
+        .unwrap_or(settings.port);
+    Ok(ApiStatus {
+        enabled: settings.enabled,
+        running: inner.running.is_some(),
+        port,
+        url: inner
+            .running
+            .as_ref()
+            .map(|running| format!("http://127.0.0.1:{}/v1", running.port)),
+        token,
+        error: inner.error.clone(),
+    })
+}
+
+/// Replaces the token. Scripts using the old one stop working straight away.
+pub fn regenerate_token(app_handle: &AppHandle) -> Result<String, String> {
+    let token = generate_token();
+    let port = {
+        let state = app_handle.state::<ApiState>();
+        let mut inner = state.inner.lock().unwrap();
+        inner.token = Some(token.clone());
+        inner
+            .running
+            .as_ref()
+            .map(|running| running.port)
+            .unwrap_or_else(|| read_settings(app_handle).port)
+    };
+    write_config(app_handle, port, &token)?;
+    emit_status(app_handle);
+    Ok(token)
+}
+
+fn emit_status(app_handle: &AppHandle) {
+    if let Ok(status) = status(app_handle) {
+        let _ = app_handle.emit(STATUS_EVENT, status);
+    }
+}
+
+fn stop_server(running: Running) {
+    running.stop.store(true, Ordering::SeqCst);
+    // Wait for the accept loop to let go of the port
+    let _ = running.thread.join();
+}
+
+/// Starts, stops or moves the server to match the settings.
+fn apply_settings(app_handle: &AppHandle) {
+    let settings = read_settings(app_handle);
+    {
+        let state = app_handle.state::<ApiState>();
+        let mut inner = state.inner.lock().unwrap();
+        let wanted = settings.enabled.then_some(settings.port);
+        let current = inner.running.as_ref().map(|running| running.port);
+        if current == wanted && inner.error.is_none() {
+            return;
+        }
+        if let Some(running) = inner.running.take() {
+            stop_server(running);
+            println!("Stopped the local API");
+        }
+        inner.error = None;
+        if settings.enabled {
+            match serve(app_handle, &mut inner, settings.port) {
+                Ok(running) => {
+                    println!("Local API listening on http://127.0.0.1:{}", running.port);
+                    inner.running = Some(running);
+                }
+                Err(e) => {
+                    println!("Failed to start the local API: {}", e);
+                    inner.error = Some(e);
+                }
+            }
+        }
+    }
+    emit_status(app_handle);
+}
+
+fn serve(app_handle: &AppHandle, inner: &mut Inner, port: u16) -> Result<Running, String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+        .map_err(|e| format!("Couldn't listen on port {}: {}", port, e))?;
+    write_config(app_handle, port, &token(app_handle, inner)?)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let app_handle = app_handle.clone();
+    // Non-blocking, so the loop sees the stop flag without a connection to
+    // wake it
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let thread = std::thread::spawn(move || {
+        while !stopped.load(Ordering::SeqCst) {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            if let Err(e) = stream.set_nonblocking(false) {
+                println!("Local API connection failed: {}", e);
+                continue;
+            }
+            let app_handle = app_handle.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = handle_connection(&app_handle, port, stream) {
+                    println!("Local API connection failed: {}", e);
+                }
+            });
+        }
+    });
+    Ok(Running { port, stop, thread })
+}
+
+/// Starts the server if it's enabled, once migrations have finished, and
+/// follows changes to the settings from then on.
+pub fn start(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        if migrator::wait(&app_handle).is_err() {
+            return;
+        }
+        apply_settings(&app_handle);
+        let handle = app_handle.clone();
+        app_handle.listen(SETTINGS_CHANGED_EVENT, move |_| apply_settings(&handle));
+    });
+}
+
+pub fn stop(app_handle: &AppHandle) {
+    let state = app_handle.state::<ApiState>();
+    let running = state.inner.lock().unwrap().running.take();
+    if let Some(running) = running {
+        stop_server(running);
+    }
+}
+
+struct Request {
+    method: String,
+    segments: Vec<String>,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .get(name)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    fn query_number(&self, name: &str) -> Result<Option<u32>, ApiError> {
+        self.query_param(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| ApiError::bad_request(format!("Invalid {}: {}", name, value)))
+            })
+            .transpose()
+    }
+
+    fn json<T: for<'de> Deserialize<'de>>(&self) -> Result<T, ApiError> {
+        let body: &[u8] = if self.body.is_empty() {
+            b"{}"
+        } else {
+            &self.body
+        };
+        serde_json::from_slice(body)
+            .map_err(|e| ApiError::bad_request(format!("Invalid JSON body: {}", e)))
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: &impl Serialize) -> Result<Response, ApiError> {
+        Ok(Response {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec(value).map_err(|e| e.to_string())?,
+        })
+    }
+
+    fn ok(value: &impl Serialize) -> Result<Response, ApiError> {
+        Response::json(200, value)
+    }
+}
+
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> ApiError {
+        ApiError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn bad_request(message: impl Into<String>) -> ApiError {
+        ApiError::new(400, message)
+    }
+
+    fn not_found(message: impl Into<String>) -> ApiError {
+        ApiError::new(404, message)
+    }
+}
+
+impl From<String> for ApiError {
+    fn from(message: String) -> ApiError {
+        ApiError::new(500, message)
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn head_too_large() -> ApiError {
+    ApiError::new(431, "Request headers are too large")
+}
+
+// Reads the request line and the headers, leaving the body for `read_body`
+fn read_head(reader: &mut BufReader<TcpStream>) -> Result<Option<Request>, ApiError> {
+    let mut head = reader.take(MAX_HEAD_BYTES);
+    let mut line = String::new();
+    if head.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') {
+        return Err(head_too_large());
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(ApiError::bad_request("Malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        segments: path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode)
+            .collect(),
+        query: query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(key), percent_decode(value))
+            })
+            .collect(),
+        headers: HashMap::new(),
+        body: Vec::new(),
+    };
+
+    loop {
+        line.clear();
+        head.read_line(&mut line).map_err(|e| e.to_string())?;
+        if !line.ends_with('\n') {
+            return Err(match head.limit() {
+                0 => head_too_large(),
+                _ => ApiError::bad_request("The request ended in its headers"),
+            });
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            request
+                .headers
+                .insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    Ok(Some(request))
+}
+
+fn read_body(reader: &mut BufReader<TcpStream>, request: &mut Request) -> Result<(), ApiError> {
+    if request.header("transfer-encoding").is_some() {
+        return Err(ApiError::new(
+            411,
+            "Send a Content-Length instead of chunks",
+        ));
+    }
+    let length: usize = request
+        .header("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        return Err(ApiError::new(413, "Request body is too large"));
+    }
+    request.body = vec![0; length];
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+// Compares without stopping at the first difference
+pub fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn authorize(app_handle: &AppHandle, port: u16, request: &Request) -> Result<(), ApiError> {
+    // A web page can point its own hostname at 127.0.0.1, so only answer
+    // requests addressed to localhost
+    let host = request.header("host").unwrap_or_default();
+    let allowed_hosts = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+    if !allowed_hosts.iter().any(|allowed| allowed == host) {
+        return Err(ApiError::new(
+            403,
+            "Requests must be addressed to localhost",
+        ));
+    }
+
+    let expected = {
+        let state = app_handle.state::<ApiState>();
+        let mut inner = state.inner.lock().unwrap();
+        token(app_handle, &mut inner)?
+    };
+    let given = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !same_token(given.trim(), &expected) {
+        return Err(ApiError::new(401, "Missing or invalid bearer token"));
+    }
+    Ok(())
+}
+
+fn handle_connection(app_handle: &AppHandle, port: u16, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    // Whoever isn't allowed in is answered before their body is read
+    let result = match read_head(&mut reader) {
+        Ok(Some(mut request)) => authorize(app_handle, port, &request)
+            .and_then(|_| read_body(&mut reader, &mut request))
+            .and_then(|_| route(app_handle, &request)),
+        Ok(None) => return Ok(()),
+        Err(e) => Err(e),
+    };
+    let response = result.unwrap_or_else(|e| Response {
+        status: e.status,
+        content_type: "application/json",
+        body: json!({ "error": e.message }).to_string().into_bytes(),
+    });
+    write_response(&mut stream, &response)
+}
+
+fn route(app_handle: &AppHandle, request: &Request) -> Result<Response, ApiError> {
+    let segments: Vec<&str> = request.segments.iter().map(String::as_str).collect();
+    let method = request.method.as_str();
+    match (method, segments.as_slice()) {
+        ("GET", ["v1"]) => Response::ok(&json!({ "version": env!("CARGO_PKG_VERSION") })),
+        ("GET", ["v1", "projects"]) => list_projects(app_handle),
+        ("POST", ["v1", "projects"]) => create_project(app_handle, request),
+        ("GET", ["v1", "projects", id]) => get_project(app_handle, id),
+        ("PATCH", ["v1", "projects", id]) => update_project(app_handle, request, id),
+        ("DELETE", ["v1", "projects", id]) => delete_project(app_handle, id),
+        ("POST", ["v1", "projects", id, "attachments"]) => {
+            upload_attachment(app_handle, request, Association::Project(id))
+        }
+        ("GET", ["v1", "chats"]) => list_chats(app_handle, request),
+        ("POST", ["v1", "chats"]) => create_chat(app_handle, request),
+        ("GET", ["v1", "chats", "search"]) => search_chats(app_handle, request),
+        ("GET", ["v1", "chats", id]) => get_chat(app_handle, id),
+        ("PATCH", ["v1", "chats", id]) => update_chat(app_handle, request, id),
+        ("DELETE", ["v1", "chats", id]) => delete_chat(app_handle, id),
+        ("GET", ["v1", "chats", id, "export"]) => export_chat(app_handle, request, id),
+        ("POST", ["v1", "chats", id, "attachments"]) => {
+            upload_attachment(app_handle, request, Association::Draft(id))
+        }
+        ("POST", ["v1", "chats", id, "open"]) => open_chat(app_handle, id),
+        (
+            _,
+            ["v1"]
+            | ["v1", "projects"]
+            | ["v1", "projects", _]
+            | ["v1", "projects", _, "attachments"]
+            | ["v1", "chats"]
+            | ["v1", "chats", _]
+            | ["v1", "chats", _, "export" | "attachments" | "open"],
+        ) => Err(ApiError::new(
+            405,
+            format!("{} isn't supported here", method),
+        )),
+        _ => Err(ApiError::not_found("No such endpoint")),
+    }
+}
+
+fn connection(app_handle: &AppHandle) -> Result<Connection, ApiError> {
+    db::open(app_handle).map_err(|e| ApiError::new(503, e))
+}
+
+fn chat_exists(conn: &Connection, chat_id: &str) -> Result<(), ApiError> {
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM chats WHERE id = ?1)",
+            params![chat_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        return Err(ApiError::not_found(format!("No chat with id {}", chat_id)));
+    }
+    Ok(())
+}
+
+fn project_exists(conn: &Connection, project_id: &str) -> Result<(), ApiError> {
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM projects WHERE id = ?1)",
+            params![project_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        return Err(ApiError::not_found(format!(
+            "No project with id {}",
+            project_id
+        )));
+    }
+    Ok(())
+}
+
+fn list_projects(app_handle: &AppHandle) -> Result<Response, ApiError> {
+    let conn = connection(app_handle)?;
+    let projects = history::list_projects(&conn, &ProjectScope::All)?;
+    Response::ok(&json!({ "projects": projects }))
+}
+
+fn get_project(app_handle: &AppHandle, project_id: &str) -> Result<Response, ApiError> {
+    let conn = connection(app_handle)?;
+    let project = history::project_context(&conn, &ProjectScope::All, project_id)?
+        .ok_or_else(|| ApiError::not_found(format!("No project with id {}", project_id)))?;
+    Response::ok(&project)
+}
+
+#[derive(Deserialize)]
+struct ProjectInput {
+    name: Option<String>,
+    context_text: Option<String>,
+}
+
+fn create_project(app_handle: &AppHandle, request: &Request) -> Result<Response, ApiError> {
+    let input: ProjectInput = request.json()?;
+    let name = input.name.as_deref().map(str::trim).unwrap_or_default();
+    if name.is_empty() {
+        return Err(ApiError::bad_request("A project needs a name"));
+    }
+    let conn = connection(app_handle)?;
+    let project_id: String = conn
+        .query_row(
+            "INSERT INTO projects (id, name, context_text)
+             VALUES (lower(hex(randomblob(16))), ?1, ?2) RETURNING id",
+            params![name, input.context_text],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    command::refresh_projects_state(app_handle.clone());
+    let project = history::project_context(&conn, &ProjectScope::All, &project_id)?;
+    Response::json(201, &project)
+}
+
+fn update_project(
+    app_handle: &AppHandle,
+    request: &Request,
+    project_id: &str,
+) -> Result<Response, ApiError> {
+    if BUILT_IN_PROJECTS.contains(&project_id) {
+        return Err(ApiError::new(409, "Built-in projects can't be changed"));
+    }
+    let input: ProjectInput = request.json()?;
+    let name = input.name.as_deref().map(str::trim);
+    if name.is_some_and(str::is_empty) {
+        return Err(ApiError::bad_request("A project needs a name"));
+    }
+    let conn = connection(app_handle)?;
+    project_exists(&conn, project_id)?;
+    conn.execute(
+        "UPDATE projects SET
+            name = coalesce(?1, name),
+            context_text = coalesce(?2, context_text),
+            updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?3",
+        params![name, input.context_text, project_id],
+    )
+    .map_err(|e| e.to_string())?;
+    command::refresh_projects_state(app_handle.clone());
+    let project = history::project_context(&conn, &ProjectScope::All, project_id)?;
+    Response::ok(&project)
+}
+
+fn delete_project(app_handle: &AppHandle, project_id: &str) -> Result<Response, ApiError> {
+    if BUILT_IN_PROJECTS.contains(&project_id) {
+        return Err(ApiError::new(409, "Built-in projects can't be deleted"));
+    }
+    let conn = connection(app_handle)?;
+    project_exists(&conn, project_id)?;
+    let mut stmt = conn
+        .prepare("SELECT id FROM chats WHERE project_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let chat_ids = stmt
+        .query_map(params![project_id], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    // A trigger deletes the project's chats along with it
+    conn.execute("DELETE FROM projects WHERE id = ?1", params![project_id])
+        .map_err(|e| e.to_string())?;
+    for chat_id in &chat_ids {
+        command::chat_deleted(app_handle.clone(), chat_id.clone());
+    }
+    command::refresh_projects_state(app_handle.clone());
+    Response::ok(&json!({ "deleted": project_id, "deleted_chats": chat_ids }))
+}
+
+fn list_chats(app_handle: &AppHandle, request: &Request) -> Result<Response, ApiError> {
+    let conn = connection(app_handle)?;
+    let chats = history::list_chats(
+        &conn,
+        &ProjectScope::All,
+        request.query_param("project_id"),
+        request.query_number("limit")?,
+        request.query_number("offset")?.unwrap_or(0),
+    )?;
+    Response::ok(&json!({ "chats": chats }))
+}
+
+fn search_chats(app_handle: &AppHandle, request: &Request) -> Result<Response, ApiError> {
+    let query = request
+        .query_param("q")
+        .ok_or_else(|| ApiError::bad_request("Missing search query `q`"))?;
+    let filters = SearchFilters {
+        model: request.query_param("model").map(String::from),
+        project_id: request.query_param("project_id").map(String::from),
+        after: request.query_param("after").map(String::from),
+        before: request.query_param("before").map(String::from),
+        ..Default::default()
+    };
+    let conn = connection(app_handle)?;
+    let page = search::search(
+        &conn,
+        query,
+        filters,
+        request.query_param("cursor"),
+        request.query_number("limit")?,
+    )
+    .map_err(ApiError::bad_request)?;
+    Response::ok(&page)
+}
+
+fn get_chat(app_handle: &AppHandle, chat_id: &str) -> Result<Response, ApiError> {
+    let conn = connection(app_handle)?;
+    let chat = history::get_chat(&conn, &ProjectScope::All, chat_id)?
+        .ok_or_else(|| ApiError::not_found(format!("No chat with id {}", chat_id)))?;
+    Response::ok(&chat)
+}
+
+fn export_chat(
+    app_handle: &AppHandle,
+    request: &Request,
+    chat_id: &str,
+) -> Result<Response, ApiError> {
+    let conn = connection(app_handle)?;
+    let chat = history::get_chat(&conn, &ProjectScope::All, chat_id)?
+        .ok_or_else(|| ApiError::not_found(format!("No chat with id {}", chat_id)))?;
+    match request.query_param("format").unwrap_or("markdown") {
+        "markdown" | "md" => Ok(Response {
+            status: 200,
+            content_type: "text/markdown; charset=utf-8",
+            body: history::chat_markdown(&chat).into_bytes(),
+        }),
+        "json" => Response::ok(&chat),
+        format => Err(ApiError::bad_request(format!(
+            "Unknown export format '{}', expected markdown or json",
+            format
+        ))),
+    }
+}
+
+#[derive(Deserialize)]
+struct NewChatInput {
+    project_id: Option<String>,
+    title: Option<String>,
+    /// Text to leave in the message box
+    draft: Option<String>,
+}
+
+/// Creates an empty chat, like the New Chat button. It stays out of chat
+/// lists until a message is sent in it.
+fn create_chat(app_handle: &AppHandle, request: &Request) -> Result<Response, ApiError> {
+    let input: NewChatInput = request.json()?;
+    let project_id = input.project_id.as_deref().unwrap_or("default");
+    let mut conn = connection(app_handle)?;
+    project_exists(&conn, project_id)?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let chat_id: String = tx
+        .query_row(
+            "INSERT INTO chats (id, title, created_at, updated_at, is_new_chat, project_id, quick_chat)
+             VALUES (lower(hex(randomblob(16))), ?1, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP, 1, ?2, ?3)
+             RETURNING id",
+            params![input.title, project_id, project_id == "quick-chat"],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if let Some(draft) = input.draft.filter(|draft| !draft.is_empty()) {
+        tx.execute(
+            "INSERT OR REPLACE INTO message_drafts (chat_id, content) VALUES (?1, ?2)",
+            params![chat_id, draft],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    command::refresh_projects_state(app_handle.clone());
+    let chat = history::get_chat(&conn, &ProjectScope::All, &chat_id)?;
+    Response::json(201, &chat)
+}
+
+#[derive(Deserialize)]
+struct ChatUpdate {
+    title: Option<String>,
+    project_id: Option<String>,
+}
+
+fn update_chat(
+    app_handle: &AppHandle,
+    request: &Request,
+    chat_id: &str,
+) -> Result<Response, ApiError> {
+    let input: ChatUpdate = request.json()?;
+    let conn = connection(app_handle)?;
+    chat_exists(&conn, chat_id)?;
+    if let Some(project_id) = &input.project_id {
+        project_exists(&conn, project_id)?;
+    }
+    conn.execute(
+        "UPDATE chats SET
+            title = coalesce(?1, title),
+            project_id = coalesce(?2, project_id),
+            quick_chat = CASE WHEN ?2 IS NULL THEN quick_chat ELSE ?2 = 'quick-chat' END
+         WHERE id = ?3",
+        params![input.title, input.project_id, chat_id],
+    )
+    .map_err(|e| e.to_string())?;
+    command::refresh_projects_state(app_handle.clone());
+    let chat = history::get_chat(&conn, &ProjectScope::All, chat_id)?;
+    Response::ok(&chat)
+}
+
+fn delete_chat(app_handle: &AppHandle, chat_id: &str) -> Result<Response, ApiError> {
+    let conn = connection(app_handle)?;
+    chat_exists(&conn, chat_id)?;
+    conn.execute("DELETE FROM chats WHERE id = ?1", params![chat_id])
+        .map_err(|e| e.to_string())?;
+    command::chat_deleted(app_handle.clone(), chat_id.to_string());
+    command::refresh_projects_state(app_handle.clone());
+    Response::ok(&json!({ "deleted": chat_id }))
+}
+
+fn open_chat(app_handle: &AppHandle, chat_id: &str) -> Result<Response, ApiError> {
+    let conn = connection(app_handle)?;
+    chat_exists(&conn, chat_id)?;
+    if app_handle.get_webview_window("main").is_none() {
+        return Err(ApiError::new(503, "The main window isn't open"));
+    }
+    command::open_in_main_window(app_handle.clone(), chat_id.to_string());
+    Response::ok(&json!({ "opened": chat_id }))
+}
+
+enum Association<'a> {
+    Project(&'a str),
+    /// The message box of a chat, so it goes out with the next message
+    Draft(&'a str),
+}
+
+fn attachment_type(extension: &str) -> Option<&'static str> {
+    if IMAGE_EXTENSIONS.contains(&extension) {
+        Some("image")
+    } else if extension == "pdf" {
+        Some("pdf")
+    } else if TEXT_EXTENSIONS.contains(&extension) {
+        Some("text")
+    } else {
+        None
+    }
+}
+
+/// Where uploads are kept, matching ensureUploadsOriginalsDirectory in
+/// src/core/chorus/AttachmentsHelpers.ts
+fn uploads_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let dir = profiles::data_dir(app_handle)?
+        .join("uploads")
+        .join("originals");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// Saves an upload under a random name. Large images are shrunk the same way
+/// the message box shrinks them.
+fn store_upload(app_handle: &AppHandle, extension: &str, data: &[u8]) -> Result<PathBuf, String> {
+    let mut name = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut name);
+    let stem = hex::encode(name);
+    let path = uploads_dir(app_handle)?.join(format!("{}.{}", stem, extension));
+    fs::write(&path, data).map_err(|e| e.to_string())?;
+
+    let is_large_image = IMAGE_EXTENSIONS.contains(&extension)
+        && u64::try_from(data.len()).unwrap_or(u64::MAX) > command::TARGET_SIZE_BYTES;
+    if !is_large_image {
+        return Ok(path);
+    }
+    let resized = command::resize_image(
+        path.to_string_lossy().to_string(),
+        command::TARGET_SIZE_BYTES,
+    )?;
+    let resized = Path::new(&resized);
+    if resized == path {
+        return Ok(path);
+    }
+    let final_path = path.with_extension("jpg");
+    fs::copy(resized, &final_path).map_err(|e| e.to_string())?;
+    let _ = fs::remove_file(resized);
+    let _ = fs::remove_file(&path);
+    Ok(final_path)
+}
+
+fn upload_attachment(
+    app_handle: &AppHandle,
+    request: &Request,
+    association: Association,
+) -> Result<Response, ApiError> {
+    let name = request
+        .query_param("name")
+        .ok_or_else(|| ApiError::bad_request("Pass the file name as `name`"))?;
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    let kind = attachment_type(&extension)
+        .ok_or_else(|| ApiError::new(415, format!("Files like '{}' can't be attached", name)))?;
+    if request.body.is_empty() {
+        return Err(ApiError::bad_request("The file is empty"));
+    }
+
+    let mut conn = connection(app_handle)?;
+    match association {
+        Association::Project(project_id) => project_exists(&conn, project_id)?,
+        Association::Draft(chat_id) => chat_exists(&conn, chat_id)?,
+    }
+    let path = store_upload(app_handle, &extension, &request.body)?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let attachment_id: String = tx
+        .query_row(
+            "INSERT INTO attachments (id, type, original_name, path, is_loading, ephemeral)
+             VALUES (lower(hex(randomblob(16))), ?1, ?2, ?3, 0, 0) RETURNING id",
+            params![kind, name, path.to_string_lossy()],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    match association {
+        Association::Project(project_id) => tx.execute(
+            "INSERT INTO project_attachments (project_id, attachment_id) VALUES (?1, ?2)",
+            params![project_id, attachment_id],
+        ),
+        Association::Draft(chat_id) => tx.execute(
+            "INSERT INTO draft_attachments (chat_id, attachment_id) VALUES (?1, ?2)",
+            params![chat_id, attachment_id],
+        ),
+    }
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    command::refresh_projects_state(app_handle.clone());
+
+    let attachment: Option<Value> = conn
+        .query_row(
+            "SELECT json_object('id', id, 'type', type, 'name', original_name, 'path', path)
+             FROM attachments WHERE id = ?1",
+            params![attachment_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .and_then(|json| serde_json::from_str(&json).ok());
+    Response::json(201, &attachment)
+}
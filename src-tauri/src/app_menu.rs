@@ -0,0 +1,535 @@
+// The app menu, built the same way on every platform. On macOS it's the menu
+// bar, elsewhere it's shown on the main window only. Besides the fixed items
+// it has three submenus built from the chat database: recent chats, projects
+// to move the main window's chat to, and the models selected for new
+// messages. It's rebuilt whenever those change, so the check marks match the
+// database.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::Mutex;
+use tauri::menu::{
+    CheckMenuItem, Menu, MenuBuilder, MenuItem, PredefinedMenuItem, Submenu, SubmenuBuilder,
+};
+use tauri::{AppHandle, Emitter, Listener, Manager, Wry};
+
+use crate::deep_link::{self, DeepLink};
+use crate::{command, db, migrator, quick_chat, shortcut, shutdown, SPOTLIGHT_LABEL};
+
+const RECENT_CHATS: i64 = 10;
+// Longer titles are cut, so the menus stay narrow
+const MAX_TITLE_CHARS: usize = 40;
+// Followed by the chat's, project's or model config's id
+const CHAT_ID_PREFIX: &str = "menu-chat:";
+const PROJECT_ID_PREFIX: &str = "menu-project:";
+const MODEL_ID_PREFIX: &str = "menu-model:";
+// Chats in it aren't in a project
+const DEFAULT_PROJECT_ID: &str = "default";
+// Holds the quick chats, which aren't moved from the menu
+const QUICK_CHAT_PROJECT_ID: &str = "quick-chat";
+
+/// The chat open in the main window, which the Projects submenu moves
+#[derive(Default)]
+pub struct AppMenuState {
+    current_chat: Mutex<Option<String>>,
+}
+
+pub struct MenuChat {
+    pub id: String,
+    pub title: String,
+}
+
+struct MenuProject {
+    id: String,
+    name: String,
+}
+
+struct MenuModel {
+    id: String,
+    name: String,
+    selected: bool,
+}
+
+struct MenuContents {
+    recent: Vec<MenuChat>,
+    projects: Vec<MenuProject>,
+    /// The current chat's project, if there's a current chat
+    current_project: Option<String>,
+    models: Vec<MenuModel>,
+}
+
+/// The chats the sidebar lists, most recently updated first. `pinned` picks
+/// only pinned or unpinned ones, and a negative `limit` means no limit.
+pub fn query_chats(
+    conn: &Connection,
+    pinned: Option<bool>,
+    limit: i64,
+) -> Result<Vec<MenuChat>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title FROM chats
+             WHERE reply_to_id IS NULL AND is_new_chat = 0
+               AND project_id != 'quick-chat' AND (?1 IS NULL OR pinned = ?1)
+             ORDER BY updated_at DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let chats = stmt
+        .query_map(params![pinned, limit], |row| {
+            Ok(MenuChat {
+                id: row.get(0)?,
+                title: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(chats)
+}
+
+/// A title short enough for a menu item
+pub fn menu_title(title: &str, untitled: &str) -> String {
+    let title = title.trim();
+    if title.is_empty() {
+        return untitled.to_string();
+    }
+    if title.chars().count() <= MAX_TITLE_CHARS {
+        return title.to_string();
+    }
+    let cut: String = title.chars().take(MAX_TITLE_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+fn query_projects(conn: &Connection) -> Result<Vec<MenuProject>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, name FROM projects
+             WHERE id NOT IN ('default', 'quick-chat')
+             ORDER BY name COLLATE NOCASE",
+        )
+        .map_err(|e| e.to_string())?;
+    let projects = stmt
+        .query_map([], |row| {
+            Ok(MenuProject {
+                id: row.get(0)?,
+                name: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(projects)
+}
+
+// Every model that can be picked, and any selected one that no longer can
+fn query_models(conn: &Connection) -> Result<Vec<MenuModel>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT mc.id, mc.display_name,
+                    mc.id IN (SELECT value FROM json_each((
+                        SELECT value FROM app_metadata
+                        WHERE key = 'selected_model_configs_compare'
+                    ))) AS selected
+             FROM model_configs mc JOIN models m ON mc.model_id = m.id
+             WHERE selected
+                OR (m.is_enabled = 1 AND m.is_internal = 0 AND m.is_deprecated = 0)
+             ORDER BY mc.display_name COLLATE NOCASE",
+        )
+        .map_err(|e| e.to_string())?;
+    let models = stmt
+        .query_map([], |row| {
+            Ok(MenuModel {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                selected: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(models)
+}
+
+fn current_chat(app_handle: &AppHandle) -> Option<String> {
+    let state = app_handle.state::<AppMenuState>();
+    let current_chat = state.current_chat.lock().ok()?;
+    current_chat.clone()
+}
+
+fn read_contents(app_handle: &AppHandle) -> Result<MenuContents, String> {
+    let conn = db::open_read_only(app_handle)?;
+    let current_project = match current_chat(app_handle) {
+        Some(chat_id) => conn
+            .query_row(
+                "SELECT project_id FROM chats WHERE id = ?1",
+                params![chat_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?,
+        None => None,
+    };
+    Ok(MenuContents {
+        recent: query_chats(&conn, None, RECENT_CHATS)?,
+        projects: query_projects(&conn)?,
+        current_project,
+        models: query_models(&conn)?,
+    })
+}
+
+fn app_submenu(app_handle: &AppHandle) -> tauri::Result<Submenu<Wry>> {
+    let mut submenu = SubmenuBuilder::new(app_handle, "Chorus")
+        .item(&MenuItem::with_id(
+            app_handle,
+            "about-chorus",
+            "About Chorus",
+            true,
+            None::<&str>,
+        )?)
+        .separator()
+        .item(&MenuItem::with_id(
+            app_handle,
+            "settings",
+            "Settings",
+            true,
+            Some(shortcut::SETTINGS),
+        )?)
+        .separator();
+    // Only macOS can hide the app
+    if cfg!(target_os = "macos") {
+        submenu = submenu
+            .item(&PredefinedMenuItem::hide(app_handle, None)?)
+            .item(&PredefinedMenuItem::hide_others(app_handle, None)?)
+            .item(&PredefinedMenuItem::show_all(app_handle, None)?)
+            .separator();
+    }
+    // Not the predefined item, so quitting waits for the database writes
+    submenu
+        .item(&MenuItem::with_id(
+            app_handle,
+            "quit",
+            "Quit Chorus",
+            true,
+            Some(shortcut::QUIT),
+        )?)
+        .build()
+}
+
+fn recent_chats_submenu(
+    app_handle: &AppHandle,
+    contents: Option<&MenuContents>,
+) -> tauri::Result<Submenu<Wry>> {
+    let chats = contents.map_or(&[][..], |contents| contents.recent.as_slice());
+    let mut submenu = SubmenuBuilder::new(app_handle, "Recent Chats");
+    if chats.is_empty() {
+        submenu = submenu.item(&MenuItem::new(
+            app_handle,
+            "No chats yet",
+            false,
+            None::<&str>,
+        )?);
+    }
+    for chat in chats {
+        submenu = submenu.text(
+            format!("{}{}", CHAT_ID_PREFIX, chat.id),
+            menu_title(&chat.title, "Untitled Chat"),
+        );
+    }
+    submenu.build()
+}
+
+// Checks the project the current chat is in. Without a current chat there's
+// nothing to move, and quick chats aren't offered the projects, so the items
+// are disabled.
+fn projects_submenu(
+    app_handle: &AppHandle,
+    contents: Option<&MenuContents>,
+) -> tauri::Result<Submenu<Wry>> {
+    let current_project = contents.and_then(|contents| contents.current_project.as_deref());
+    let enabled = current_project.is_some_and(|id| id != QUICK_CHAT_PROJECT_ID);
+    let check = |id: &str, name: &str| {
+        CheckMenuItem::with_id(
+            app_handle,
+            format!("{}{}", PROJECT_ID_PREFIX, id),
+            name,
+            enabled,
+            current_project == Some(id),
+            None::<&str>,
+        )
+    };
+    let mut submenu = SubmenuBuilder::new(app_handle, "Projects")
+        .item(&check(DEFAULT_PROJECT_ID, "No Project")?)
+        .separator();
+    for project in contents.map_or(&[][..], |contents| contents.projects.as_slice()) {
+        submenu = submenu.item(&check(
+            &project.id,
+            &menu_title(&project.name, "New Project"),
+        )?);
+    }
+    submenu.build()
+}
+
+fn models_submenu(
+    app_handle: &AppHandle,
+    contents: Option<&MenuContents>,
+) -> tauri::Result<Submenu<Wry>> {
+    let mut submenu = SubmenuBuilder::new(app_handle, "Models");
+    for model in contents.map_or(&[][..], |contents| contents.models.as_slice()) {
+        submenu = submenu.item(&CheckMenuItem::with_id(
+            app_handle,
+            format!("{}{}", MODEL_ID_PREFIX, model.id),
+            &model.name,
+            true,
+            model.selected,
+            None::<&str>,
+        )?);
+    }
+    submenu.build()
+}
+
+fn build_menu(app_handle: &AppHandle, with_contents: bool) -> tauri::Result<Menu<Wry>> {
+    // A locked or missing database still leaves the fixed menus
+    let contents = if with_contents {
+        read_contents(app_handle)
+            .map_err(|e| println!("Failed to read the app menu's contents: {}", e))
+            .ok()
+    } else {
+        None
+    };
+    let contents = contents.as_ref();
+
+    let edit_menu = SubmenuBuilder::new(app_handle, "Edit")
+        .item(&PredefinedMenuItem::undo(app_handle, None)?)
+        .item(&PredefinedMenuItem::redo(app_handle, None)?)
+        .separator()
+        .item(&PredefinedMenuItem::cut(app_handle, None)?)
+        .item(&PredefinedMenuItem::copy(app_handle, None)?)
+        .item(&PredefinedMenuItem::paste(app_handle, None)?)
+        .item(&PredefinedMenuItem::select_all(app_handle, None)?)
+        .build()?;
+
+    let window_menu = SubmenuBuilder::new(app_handle, "Window")
+        .item(&PredefinedMenuItem::minimize(app_handle, None)?)
+        .item(&PredefinedMenuItem::maximize(app_handle, None)?)
+        .separator()
+        .item(&PredefinedMenuItem::close_window(app_handle, None)?)
+        .build()?;
+
+    let shortcuts_menu = SubmenuBuilder::new(app_handle, "Shortcuts")
+        .item(&MenuItem::with_id(
+            app_handle,
+            "new-chat",
+            "New chat",
+            true,
+            Some(shortcut::NEW_CHAT),
+        )?)
+        .item(&MenuItem::with_id(
+            app_handle,
+            "new-project",
+            "New project",
+            true,
+            Some(shortcut::NEW_PROJECT),
+        )?)
+        .separator()
+        .item(&MenuItem::with_id(
+            app_handle,
+            "settings-shortcut",
+            "Settings",
+            true,
+            Some(shortcut::SETTINGS),
+        )?)
+        .build()?;
+
+    let updates_menu = SubmenuBuilder::new(app_handle, "Updates")
+        .item(&MenuItem::with_id(
+            app_handle,
+            "changelog",
+            "Changelog",
+            true,
+            None::<&str>,
+        )?)
+        .build()?;
+
+    let mut menu = MenuBuilder::new(app_handle)
+        .item(&app_submenu(app_handle)?)
+        .item(&edit_menu);
+    // Full screen is only a menu item on macOS
+    if cfg!(target_os = "macos") {
+        menu = menu.item(
+            &SubmenuBuilder::new(app_handle, "View")
+                .item(&PredefinedMenuItem::fullscreen(app_handle, None)?)
+                .build()?,
+        );
+    }
+    menu.item(&window_menu)
+        .item(&shortcuts_menu)
+        .item(&recent_chats_submenu(app_handle, contents)?)
+        .item(&projects_submenu(app_handle, contents)?)
+        .item(&models_submenu(app_handle, contents)?)
+        .item(&updates_menu)
+        .build()
+}
+
+fn set_menu(app_handle: &AppHandle, menu: Menu<Wry>) -> tauri::Result<()> {
+    #[cfg(target_os = "macos")]
+    app_handle.set_menu(menu)?;
+
+    // Set on the app it would show on the quick chat window too
+    #[cfg(not(target_os = "macos"))]
+    if let Some(window) = app_handle.get_webview_window("main") {
+        window.set_menu(menu)?;
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the menu from the database
+pub fn refresh(app_handle: &AppHandle) {
+    let result = build_menu(app_handle, true).and_then(|menu| set_menu(app_handle, menu));
+    if let Err(e) = result {
+        println!("Failed to update the app menu: {}", e);
+    }
+}
+
+/// Rebuilds the menu once the database is migrated, which also means it's
+/// unlocked
+pub fn refresh_when_ready(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || match migrator::wait(&app_handle) {
+        Ok(()) => refresh(&app_handle),
+        Err(e) => println!("Not filling in the app menu: {}", e),
+    });
+}
+
+/// Called by the main window when it opens another chat, or changes the
+/// chats, projects or models itself
+pub fn update(app_handle: &AppHandle, current_chat: Option<String>) -> Result<(), String> {
+    let state = app_handle.state::<AppMenuState>();
+    *state.current_chat.lock().map_err(|e| e.to_string())? = current_chat;
+    refresh(app_handle);
+    Ok(())
+}
+
+fn move_current_chat(app_handle: &AppHandle, project_id: &str) -> Result<(), String> {
+    let chat_id = current_chat(app_handle).ok_or("No chat is open")?;
+    let conn = db::open(app_handle)?;
+    conn.execute(
+        "UPDATE chats SET project_id = ?1, quick_chat = (?1 = 'quick-chat')
+         WHERE id = ?2",
+        params![project_id, chat_id],
+    )
+    .map_err(|e| e.to_string())?;
+    command::refresh_projects_state(app_handle.clone());
+    Ok(())
+}
+
+// Adds the model config to the ones selected for new messages, or takes it
+// out. Their order is kept, and a new one goes last.
+fn toggle_model(app_handle: &AppHandle, model_config_id: &str) -> Result<(), String> {
+    let conn = db::open(app_handle)?;
+    let selected: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_metadata WHERE key = 'selected_model_configs_compare'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let mut selected: Vec<String> = match selected {
+        Some(selected) => serde_json::from_str(&selected).map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+    match selected.iter().position(|id| id == model_config_id) {
+        Some(index) => {
+            selected.remove(index);
+        }
+        None => selected.push(model_config_id.to_string()),
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO app_metadata (key, value)
+         VALUES ('selected_model_configs_compare', ?1)",
+        params![serde_json::to_string(&selected).map_err(|e| e.to_string())?],
+    )
+    .map_err(|e| e.to_string())?;
+    command::refresh_projects_state(app_handle.clone());
+    Ok(())
+}
+
+/// Handles the app menu's items. Most are passed to the webviews, each of
+/// which checks whether it's focused before acting.
+pub fn on_menu_event(app_handle: &AppHandle, id: &str) {
+    let result = match id {
+        "new-chat" => app_handle
+            .emit("menu-new-chat", ())
+            .map_err(|e| e.to_string()),
+        "new-project" => app_handle
+            .emit("menu-new-project", ())
+            .map_err(|e| e.to_string()),
+        "settings" | "settings-shortcut" => app_handle
+            .emit("menu-settings", ())
+            .map_err(|e| e.to_string()),
+        "about-chorus" => app_handle.emit("menu-about", ()).map_err(|e| e.to_string()),
+        "changelog" => app_handle
+            .emit("menu-changelog", ())
+            .map_err(|e| e.to_string()),
+        "quit" => {
+            shutdown::quit(app_handle);
+            Ok(())
+        }
+        _ => {
+            if let Some(chat_id) = id.strip_prefix(CHAT_ID_PREFIX) {
+                quick_chat::hide(app_handle);
+                deep_link::deliver(
+                    app_handle,
+                    DeepLink::OpenChat {
+                        chat_id: chat_id.to_string(),
+                    },
+                );
+                Ok(())
+            } else if let Some(project_id) = id.strip_prefix(PROJECT_ID_PREFIX) {
+                move_current_chat(app_handle, project_id)
+            } else if let Some(model_config_id) = id.strip_prefix(MODEL_ID_PREFIX) {
+                toggle_model(app_handle, model_config_id)
+            } else {
+                Ok(())
+            }
+        }
+    };
+    if let Err(e) = result {
+        println!("Failed to handle the menu item {}: {}", id, e);
+        // Check items flip when clicked, so they're put back
+        refresh(app_handle);
+    }
+}
+
+/// Sets the menu and keeps it up to date
+pub fn setup(app_handle: &AppHandle) -> tauri::Result<()> {
+    // Without the database's contents until it's ready
+    set_menu(app_handle, build_menu(app_handle, false)?)?;
+
+    // Sent when the local API or the menus change the database
+    for event in ["refresh_projects_state", "chat_deleted"] {
+        let handle = app_handle.clone();
+        app_handle.listen(event, move |_| refresh(&handle));
+    }
+
+    // The quick chat window can't create projects
+    let handle = app_handle.clone();
+    app_handle.listen("tauri://focus", move |event| {
+        let Ok(window_info) = serde_json::from_str::<serde_json::Value>(event.payload()) else {
+            return;
+        };
+        let Some(label) = window_info.get("label").and_then(|label| label.as_str()) else {
+            return;
+        };
+        if let Some(item) = handle
+            .menu()
+            .and_then(|menu| menu.get("new-project"))
+            .and_then(|item| item.as_menuitem().cloned())
+        {
+            let _ = item.set_enabled(label != SPOTLIGHT_LABEL);
+        }
+    });
+
+    refresh_when_ready(app_handle);
+    Ok(())
+}
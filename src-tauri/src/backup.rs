@@ -0,0 +1,298 @@
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::{db, migrations, migrator, profiles, single_instance};
+
+const BACKUPS_DIR: &str = "backups";
+const FILE_PREFIX: &str = "chats-";
+const FILE_EXTENSION: &str = ".db";
+const FILE_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+// Copy in small steps so the webview's connection is never blocked for long
+const PAGES_PER_STEP: i32 = 256;
+const PAUSE_BETWEEN_STEPS: Duration = Duration::from_millis(10);
+
+// How often the scheduler wakes up to check whether a snapshot is due
+const SCHEDULER_TICK: Duration = Duration::from_secs(60 * 60);
+
+// Serializes snapshot, prune and restore operations
+static BACKUP_LOCK: Mutex<()> = Mutex::new(());
+
+/// Backup preferences, read from `settings.backups` in the settings store.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct BackupSettings {
+    enabled: bool,
+    interval_hours: u64,
+    keep_daily: usize,
+    keep_weekly: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: 24,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub path: String,
+    pub created_at: String,
+    pub size_bytes: u64,
+    #[serde(skip)]
+    timestamp: DateTime<Local>,
+}
+
+fn read_settings(app_handle: &AppHandle) -> BackupSettings {
+    profiles::settings_store(app_handle)
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|settings| settings.get("backups").cloned())
+        .and_then(|backups| serde_json::from_value(backups).ok())
+        .unwrap_or_default()
+}
+
+fn backups_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let dir = profiles::config_dir(app_handle)?.join(BACKUPS_DIR);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+fn parse_file_name(file_name: &str) -> Option<DateTime<Local>> {
+    let timestamp = file_name
+        .strip_prefix(FILE_PREFIX)?
+        .strip_suffix(FILE_EXTENSION)?;
+    let naive = NaiveDateTime::parse_from_str(timestamp, FILE_TIMESTAMP_FORMAT).ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+fn backup_info(path: &Path) -> Option<BackupInfo> {
+    let file_name = path.file_name()?.to_str()?.to_string();
+    let timestamp = parse_file_name(&file_name)?;
+    let size_bytes = fs::metadata(path).ok()?.len();
+    Some(BackupInfo {
+        file_name,
+        path: path.to_string_lossy().to_string(),
+        created_at: timestamp.to_rfc3339(),
+        size_bytes,
+        timestamp,
+    })
+}
+
+/// Lists snapshots in the backups folder, newest first.
+pub fn list(app_handle: &AppHandle) -> Result<Vec<BackupInfo>, String> {
+    let dir = backups_dir(app_handle)?;
+    let mut backups: Vec<BackupInfo> = fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| backup_info(&entry.path()))
+        .collect();
+    backups.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(backups)
+}
+
+fn check_integrity(conn: &Connection) -> Result<(), String> {
+    let result: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if result != "ok" {
+        return Err(format!("Integrity check failed: {}", result));
+    }
+    Ok(())
+}
+
+/// Takes a snapshot of the live database using SQLite's online backup API,
+/// then verifies it before moving it into place.
+fn snapshot(app_handle: &AppHandle) -> Result<BackupInfo, String> {
+    if !db::db_path(app_handle)?.exists() {
+        return Err("There is no database to back up yet".to_string());
+    }
+    let dir = backups_dir(app_handle)?;
+    let file_name = format!(
+        "{}{}{}",
+        FILE_PREFIX,
+        Local::now().format(FILE_TIMESTAMP_FORMAT),
+        FILE_EXTENSION
+    );
+    let final_path = dir.join(&file_name);
+    let partial_path = dir.join(format!("{}.partial", file_name));
+    let _ = fs::remove_file(&partial_path);
+
+    let result = (|| {
+        let source = db::open(app_handle)?;
+        let mut dest = db::open_copy(&partial_path, OpenFlags::default())?;
+        Backup::new(&source, &mut dest)
+            .and_then(|backup| backup.run_to_completion(PAGES_PER_STEP, PAUSE_BETWEEN_STEPS, None))
+            .map_err(|e| format!("Backup failed: {}", e))?;
+
+        // Make sure the snapshot is a single self-contained file, so it can be
+        // copied around or opened read-only later
+        dest.pragma_update(None, "journal_mode", "DELETE")
+            .map_err(|e| e.to_string())?;
+        check_integrity(&dest)
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial_path);
+        return Err(e);
+    }
+
+    fs::rename(&partial_path, &final_path).map_err(|e| e.to_string())?;
+    backup_info(&final_path).ok_or_else(|| "Failed to read backup metadata".to_string())
+}
+
+/// Picks the snapshots that fall outside the retention policy: the newest
+/// snapshot of each of the last `keep_daily` days and of each of the last
+/// `keep_weekly` ISO weeks is kept, as is the newest snapshot overall.
+fn expired(backups: &[BackupInfo], keep_daily: usize, keep_weekly: usize) -> Vec<&BackupInfo> {
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut expired = Vec::new();
+
+    // `backups` is sorted newest first, so the first snapshot seen for a given
+    // day or week is the one to keep
+    for (index, backup) in backups.iter().enumerate() {
+        let day = backup.timestamp.date_naive();
+        let week = (day.iso_week().year(), day.iso_week().week());
+
+        let mut keep = index == 0;
+        if !days.contains(&day) && days.len() < keep_daily {
+            days.insert(day);
+            keep = true;
+        }
+        if !weeks.contains(&week) && weeks.len() < keep_weekly {
+            weeks.insert(week);
+            keep = true;
+        }
+        if !keep {
+            expired.push(backup);
+        }
+    }
+    expired
+}
+
+fn prune(app_handle: &AppHandle, settings: &BackupSettings) -> Result<(), String> {
+    let backups = list(app_handle)?;
+    for backup in expired(&backups, settings.keep_daily, settings.keep_weekly) {
+        println!("Removing expired backup {}", backup.file_name);
+        fs::remove_file(&backup.path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Takes a snapshot and applies the retention policy.
+pub fn create(app_handle: &AppHandle) -> Result<BackupInfo, String> {
+    let _guard = BACKUP_LOCK.lock().map_err(|e| e.to_string())?;
+    let info = snapshot(app_handle)?;
+    prune(app_handle, &read_settings(app_handle))?;
+    Ok(info)
+}
+
+/// Runs `f` while no snapshot or restore can start.
+pub fn paused<T>(f: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    let _guard = BACKUP_LOCK.lock().map_err(|e| e.to_string())?;
+    f()
+}
+
+/// Replaces the live database with the contents of a snapshot, then restarts
+/// the app so every window reloads its state.
+pub fn restore(app_handle: &AppHandle, file_name: &str) -> Result<(), String> {
+    let _guard = BACKUP_LOCK.lock().map_err(|e| e.to_string())?;
+
+    if parse_file_name(file_name).is_none() {
+        return Err(format!("Not a backup file: {}", file_name));
+    }
+    let snapshot_path = backups_dir(app_handle)?.join(file_name);
+    if !snapshot_path.is_file() {
+        return Err(format!("Backup not found: {}", file_name));
+    }
+
+    let source = db::open_copy(&snapshot_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    check_integrity(&source)?;
+
+    // Older snapshots are fine, the pending migrations run on the next launch.
+    // A snapshot from a newer version of the app can't be migrated back.
+    let latest_migration = migrations::migrations()
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0);
+    let snapshot_version: i64 = source
+        .query_row("SELECT MAX(version) FROM _sqlx_migrations", [], |row| {
+            row.get::<_, Option<i64>>(0)
+        })
+        .map_err(|e| format!("Backup has no migration history: {}", e))?
+        .unwrap_or(0);
+    if snapshot_version > latest_migration {
+        return Err(format!(
+            "Backup was made by a newer version of Chorus (schema version {}, this version supports {})",
+            snapshot_version, latest_migration
+        ));
+    }
+
+    // Keep a copy of the current state in case the restore isn't what the
+    // user wanted
+    let safety = snapshot(app_handle)?;
+    println!(
+        "Saved current database to {} before restoring",
+        safety.file_name
+    );
+
+    let mut dest = db::open(app_handle)?;
+    Backup::new(&source, &mut dest)
+        .and_then(|backup| backup.run_to_completion(PAGES_PER_STEP, PAUSE_BETWEEN_STEPS, None))
+        .map_err(|e| format!("Restore failed: {}", e))?;
+    check_integrity(&dest)?;
+
+    drop(dest);
+    drop(source);
+    drop(_guard);
+    single_instance::restart(app_handle);
+}
+
+/// Starts a background thread that snapshots the database whenever the newest
+/// backup is older than the configured interval.
+pub fn start_scheduler(app_handle: AppHandle) {
+    std::thread::spawn(move || loop {
+        // Don't snapshot a database that's halfway through an upgrade
+        if migrator::wait(&app_handle).is_err() {
+            return;
+        }
+
+        let settings = read_settings(&app_handle);
+        if settings.enabled {
+            let interval = chrono::Duration::hours(settings.interval_hours.max(1) as i64);
+            let is_due = match list(&app_handle) {
+                Ok(backups) => backups
+                    .first()
+                    .map(|newest| Local::now() - newest.timestamp >= interval)
+                    .unwrap_or(true),
+                Err(e) => {
+                    println!("Failed to list backups: {}", e);
+                    false
+                }
+            };
+            if is_due {
+                match create(&app_handle) {
+                    Ok(info) => println!("Created scheduled backup {}", info.file_name),
+                    Err(e) => println!("Scheduled backup failed: {}", e),
+                }
+            }
+        }
+        std::thread::sleep(SCHEDULER_TICK);
+    });
+}
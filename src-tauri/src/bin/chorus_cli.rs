@@ -0,0 +1,759 @@
+// A command line companion for the Chorus chat history, for grepping and
+// piping chats without opening the app. It only reads chats.db; `open` hands
+// the chat to the running app.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+#[path = "../data_location.rs"]
+#[allow(dead_code)]
+mod data_location;
+
+#[path = "../db_encryption.rs"]
+#[allow(dead_code)]
+mod db_encryption;
+
+#[path = "../profile_registry.rs"]
+#[allow(dead_code)]
+mod profile_registry;
+
+#[path = "../search.rs"]
+#[allow(dead_code)]
+mod search;
+
+#[path = "../history.rs"]
+#[allow(dead_code)]
+mod history;
+
+use history::{Chat, ChatSummary, ProjectScope};
+use search::{SearchFilters, HIGHLIGHT_CLOSE, HIGHLIGHT_OPEN};
+
+const USAGE: &str = "Usage: chorus_cli [--database <path>] [--format <format>] <command> [args]
+
+Reads the Chorus chat history from the terminal. The database is opened
+read-only.
+
+Commands:
+  list                          List chats, most recently updated first
+  show <chat>                   Print a chat's conversation
+  search <query>                Full-text search over chats and messages.
+                                Supports \"quoted phrases\", prefix*, AND/OR/NOT
+                                and filters like model: or after:YYYY-MM-DD.
+  export [<chat>...]            Print chats, or write one file per chat with
+                                --output
+  stats                         Count chats, messages and responses per model
+  projects                      List projects
+  open <chat>                   Open a chat in the running app
+
+A <chat> is its id, the start of its id (at least 4 characters), or a
+chorus://chat/<id> link.
+
+Options:
+      --database <path>         The chat database. Defaults to the active
+                                profile's chats.db in the Chorus data directory,
+                                which CHORUS_DATA_DIR overrides (per
+                                CHORUS_INSTANCE_NAME for dev instances).
+                                Encrypted databases are opened with the key in
+                                the OS keyring, or the passphrase in
+                                CHORUS_DB_PASSPHRASE.
+  -f, --format <format>         table, json or markdown. Defaults to table;
+                                show and export print Markdown unless given
+                                json.
+  -p, --project <id or name>    Only list, search or export this project.
+  -n, --limit <count>           How many chats or hits to print (list, search).
+      --offset <count>          Skip this many chats (list).
+      --model <model>           Only search responses from this model.
+      --after <date>            Only search from this date, as YYYY-MM-DD.
+      --before <date>           Only search before this date, as YYYY-MM-DD.
+      --all                     Export every chat (export).
+  -o, --output <directory>      Write each exported chat to its own file.
+  -h, --help                    Show this message.
+
+Examples:
+  chorus_cli search 'borrow checker' --after 2025-01-01
+  chorus_cli list --format json | jq -r '.[].title'
+  chorus_cli export --project Work --output ~/chorus-export";
+
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+const EXPORT_PAGE_SIZE: u32 = 500;
+const MIN_ID_PREFIX_LENGTH: usize = 4;
+const SHORT_ID_LENGTH: usize = 8;
+const MAX_CELL_WIDTH: usize = 60;
+const CHAT_LINK_PREFIX: &str = "chorus://chat/";
+// Written by the app when its local API is on (see api.rs)
+const LOCAL_API_CONFIG_FILE: &str = "local-api.json";
+const LOCAL_API_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Table,
+    Json,
+    Markdown,
+}
+
+enum Command {
+    List,
+    Show(String),
+    Search(String),
+    Export(Vec<String>),
+    Stats,
+    Projects,
+    Open(String),
+}
+
+struct Options {
+    database: Option<PathBuf>,
+    format: Format,
+    command: Command,
+    project: Option<String>,
+    limit: Option<u32>,
+    offset: u32,
+    model: Option<String>,
+    after: Option<String>,
+    before: Option<String>,
+    output: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct LocalApiConfig {
+    port: u16,
+    token: String,
+}
+
+fn parse_count(flag: &str, value: Option<String>) -> Result<u32, String> {
+    let value = value.ok_or_else(|| format!("{} needs a number", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("{} needs a number, not '{}'", flag, value))
+}
+
+fn parse_args() -> Result<Option<Options>, String> {
+    let mut database = None;
+    let mut format = Format::Table;
+    let mut project = None;
+    let mut limit = None;
+    let mut offset = 0;
+    let mut model = None;
+    let mut after = None;
+    let mut before = None;
+    let mut all = false;
+    let mut output = None;
+    // Which options were given, to reject ones the command ignores
+    let mut given: Vec<&'static str> = Vec::new();
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let flag = match arg.as_str() {
+            "--database" => {
+                database = Some(args.next().ok_or("--database needs a path")?.into());
+                "--database"
+            }
+            "-f" | "--format" => {
+                format = match args.next().as_deref() {
+                    Some("table") => Format::Table,
+                    Some("json") => Format::Json,
+                    Some("markdown" | "md") => Format::Markdown,
+                    Some(other) => {
+                        return Err(format!(
+                            "Unknown format '{}'. Use table, json or markdown.",
+                            other
+                        ))
+                    }
+                    None => return Err("--format needs table, json or markdown".to_string()),
+                };
+                "--format"
+            }
+            "-p" | "--project" => {
+                project = Some(args.next().ok_or("--project needs an id or name")?);
+                "--project"
+            }
+            "-n" | "--limit" => {
+                limit = Some(parse_count("--limit", args.next())?);
+                "--limit"
+            }
+            "--offset" => {
+                offset = parse_count("--offset", args.next())?;
+                "--offset"
+            }
+            "--model" => {
+                model = Some(args.next().ok_or("--model needs a model")?);
+                "--model"
+            }
+            "--after" => {
+                after = Some(args.next().ok_or("--after needs a date")?);
+                "--after"
+            }
+            "--before" => {
+                before = Some(args.next().ok_or("--before needs a date")?);
+                "--before"
+            }
+            "--all" => {
+                all = true;
+                "--all"
+            }
+            "-o" | "--output" => {
+                output = Some(args.next().ok_or("--output needs a directory")?.into());
+                "--output"
+            }
+            "-h" | "--help" => return Ok(None),
+            // Lets a search start with a dash
+            "--" => {
+                positional.extend(args.by_ref());
+                continue;
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("Unknown option: {}", arg))
+            }
+            _ => {
+                positional.push(arg);
+                continue;
+            }
+        };
+        given.push(flag);
+    }
+
+    let mut positional = positional.into_iter();
+    let Some(name) = positional.next() else {
+        return Ok(None);
+    };
+    let rest: Vec<String> = positional.collect();
+    let one_chat = || -> Result<String, String> {
+        match rest.as_slice() {
+            [chat] => Ok(chat.clone()),
+            [] => Err(format!("{} needs a chat", name)),
+            _ => Err(format!("{} takes one chat", name)),
+        }
+    };
+    let no_args = || -> Result<(), String> {
+        if rest.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("{} takes no arguments", name))
+        }
+    };
+    let (command, allowed): (Command, &[&str]) = match name.as_str() {
+        "list" => {
+            no_args()?;
+            (Command::List, &["--project", "--limit", "--offset"])
+        }
+        "show" => (Command::Show(one_chat()?), &[]),
+        // The query can be given unquoted
+        "search" if !rest.is_empty() => (
+            Command::Search(rest.join(" ")),
+            &["--project", "--limit", "--model", "--after", "--before"],
+        ),
+        "search" => return Err("search needs a query".to_string()),
+        "export" => (
+            Command::Export(rest.clone()),
+            &["--project", "--all", "--output"],
+        ),
+        "stats" => {
+            no_args()?;
+            (Command::Stats, &[])
+        }
+        "projects" => {
+            no_args()?;
+            (Command::Projects, &[])
+        }
+        "open" => (Command::Open(one_chat()?), &[]),
+        _ => return Err(format!("Unknown command: {}", name)),
+    };
+    if let Some(flag) = given
+        .iter()
+        .find(|flag| !matches!(**flag, "--database" | "--format") && !allowed.contains(flag))
+    {
+        return Err(format!("{} doesn't apply to {}", flag, name));
+    }
+    if let Command::Export(chats) = &command {
+        if chats.is_empty() && !all && project.is_none() {
+            return Err("export needs chats, --project or --all".to_string());
+        }
+        if !chats.is_empty() && (all || project.is_some()) {
+            return Err("export takes chats or --project/--all, not both".to_string());
+        }
+    }
+
+    Ok(Some(Options {
+        database,
+        format,
+        command,
+        project,
+        limit,
+        offset,
+        model,
+        after,
+        before,
+        output,
+    }))
+}
+
+/// Finds the chat a command line argument refers to
+fn resolve_chat(conn: &Connection, arg: &str) -> Result<String, String> {
+    let id = arg.strip_prefix(CHAT_LINK_PREFIX).unwrap_or(arg);
+    let id = id.trim_end_matches('/');
+    let exact: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM chats WHERE id = ?1)",
+            params![id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if exact {
+        return Ok(id.to_string());
+    }
+    if id.chars().count() < MIN_ID_PREFIX_LENGTH {
+        return Err(format!("No chat has the id '{}'", id));
+    }
+    let mut stmt = conn
+        .prepare(
+            "SELECT id FROM chats WHERE substr(id, 1, length(?1)) = ?1 AND gc_prototype_chat = 0
+             LIMIT 2",
+        )
+        .map_err(|e| e.to_string())?;
+    let matches = stmt
+        .query_map(params![id], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    match matches.as_slice() {
+        [only] => Ok(only.clone()),
+        [] => Err(format!("No chat has an id starting with '{}'", id)),
+        _ => Err(format!(
+            "More than one chat has an id starting with '{}'. Give more of it.",
+            id
+        )),
+    }
+}
+
+fn get_chat(conn: &Connection, arg: &str) -> Result<Chat, String> {
+    let id = resolve_chat(conn, arg)?;
+    history::get_chat(conn, &ProjectScope::All, &id)?
+        .ok_or_else(|| format!("Chat not found: {}", id))
+}
+
+/// Resolves --project to an id
+fn project_id(conn: &Connection, project: Option<&String>) -> Result<Option<String>, String> {
+    match project {
+        None => Ok(None),
+        Some(project) => match ProjectScope::resolve(conn, std::slice::from_ref(project))? {
+            ProjectScope::Only(ids) => Ok(ids.into_iter().next()),
+            ProjectScope::All => Ok(None),
+        },
+    }
+}
+
+fn project_names(conn: &Connection) -> Result<HashMap<String, String>, String> {
+    Ok(history::list_projects(conn, &ProjectScope::All)?
+        .into_iter()
+        .map(|project| (project.id, project.name))
+        .collect())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(value)
+        .map(|json| json + "\n")
+        .map_err(|e| e.to_string())
+}
+
+// Table cells are one line, and long ones are cut short
+fn cell(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= MAX_CELL_WIDTH {
+        return text;
+    }
+    let mut short: String = text.chars().take(MAX_CELL_WIDTH - 1).collect();
+    short.push('…');
+    short
+}
+
+fn short_id(id: &str) -> String {
+    id.chars().take(SHORT_ID_LENGTH).collect()
+}
+
+/// Drops the seconds and anything after them from a timestamp
+fn short_date(date: Option<&str>) -> String {
+    date.map(|date| date.chars().take(16).collect())
+        .unwrap_or_default()
+}
+
+fn render_table(headers: &[&str], rows: &[Vec<String>], format: Format) -> String {
+    let mut out = String::new();
+    if format == Format::Markdown {
+        let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+        out.push_str(&line(headers.iter().map(|h| h.to_string()).collect()));
+        out.push_str(&line(headers.iter().map(|_| "---".to_string()).collect()));
+        for row in rows {
+            out.push_str(&line(row.iter().map(|c| c.replace('|', "\\|")).collect()));
+        }
+        return out;
+    }
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let headers: Vec<String> = headers.iter().map(|h| h.to_uppercase()).collect();
+    for row in std::iter::once(&headers).chain(rows) {
+        let mut line = String::new();
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if i + 1 == row.len() {
+                line.push_str(cell);
+            } else {
+                let padding = width - cell.chars().count();
+                line.push_str(&format!("{}{}  ", cell, " ".repeat(padding)));
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn chat_rows(
+    chats: &[ChatSummary],
+    names: &HashMap<String, String>,
+    format: Format,
+) -> Vec<Vec<String>> {
+    chats
+        .iter()
+        .map(|chat| {
+            let id = if format == Format::Table {
+                short_id(&chat.id)
+            } else {
+                chat.id.clone()
+            };
+            vec![
+                id,
+                short_date(chat.updated_at.as_deref().or(chat.created_at.as_deref())),
+                cell(names.get(&chat.project_id).unwrap_or(&chat.project_id)),
+                cell(chat.title.as_deref().unwrap_or("Untitled chat")),
+            ]
+        })
+        .collect()
+}
+
+fn highlight(snippet: &str, format: Format) -> String {
+    let (open, close) = match format {
+        Format::Markdown => ("**", "**"),
+        // Bold in a terminal, plain text when piped
+        _ if io::stdout().is_terminal() => ("\x1b[1m", "\x1b[0m"),
+        _ => ("", ""),
+    };
+    snippet
+        .replace(HIGHLIGHT_OPEN, open)
+        .replace(HIGHLIGHT_CLOSE, close)
+}
+
+fn list(conn: &Connection, options: &Options) -> Result<String, String> {
+    let project_id = project_id(conn, options.project.as_ref())?;
+    let chats = history::list_chats(
+        conn,
+        &ProjectScope::All,
+        project_id.as_deref(),
+        options.limit,
+        options.offset,
+    )?;
+    if options.format == Format::Json {
+        return to_json(&chats);
+    }
+    let names = project_names(conn)?;
+    Ok(render_table(
+        &["id", "updated", "project", "title"],
+        &chat_rows(&chats, &names, options.format),
+        options.format,
+    ))
+}
+
+fn show(conn: &Connection, chat: &str, format: Format) -> Result<String, String> {
+    let chat = get_chat(conn, chat)?;
+    match format {
+        Format::Json => to_json(&chat),
+        _ => Ok(history::chat_markdown(&chat)),
+    }
+}
+
+fn search(conn: &Connection, query: &str, options: &Options) -> Result<String, String> {
+    let filters = SearchFilters {
+        model: options.model.clone(),
+        project_id: project_id(conn, options.project.as_ref())?,
+        after: options.after.clone(),
+        before: options.before.clone(),
+        ..Default::default()
+    };
+    let limit = options.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let hits = history::search_chats(conn, &ProjectScope::All, query, filters, Some(limit))?;
+    if options.format == Format::Json {
+        return to_json(&hits);
+    }
+    if options.format == Format::Markdown {
+        let mut out = String::new();
+        for hit in &hits {
+            out.push_str(&format!(
+                "- **{}** ({}{}, {}): {}\n",
+                hit.title.as_deref().unwrap_or("Untitled chat"),
+                CHAT_LINK_PREFIX,
+                hit.chat_id,
+                short_date(hit.created_at.as_deref()),
+                highlight(&cell(&hit.snippet), Format::Markdown),
+            ));
+        }
+        return Ok(out);
+    }
+    let rows: Vec<Vec<String>> = hits
+        .iter()
+        .map(|hit| {
+            vec![
+                short_id(&hit.chat_id),
+                short_date(hit.created_at.as_deref()),
+                match hit.message_set_type.as_deref() {
+                    Some("user") => "You".to_string(),
+                    _ => cell(hit.model.as_deref().unwrap_or("")),
+                },
+                cell(hit.title.as_deref().unwrap_or("Untitled chat")),
+                // Cut before highlighting so escape codes don't count
+                highlight(&cell(&hit.snippet), Format::Table),
+            ]
+        })
+        .collect();
+    Ok(render_table(
+        &["chat", "date", "model", "title", "match"],
+        &rows,
+        Format::Table,
+    ))
+}
+
+/// Every chat in the project, or every chat if there's no project
+fn all_chats(conn: &Connection, project: Option<&String>) -> Result<Vec<String>, String> {
+    let project_id = project_id(conn, project)?;
+    let mut ids = Vec::new();
+    loop {
+        let page = history::list_chats(
+            conn,
+            &ProjectScope::All,
+            project_id.as_deref(),
+            Some(EXPORT_PAGE_SIZE),
+            u32::try_from(ids.len()).map_err(|e| e.to_string())?,
+        )?;
+        let done = page.len() < EXPORT_PAGE_SIZE as usize;
+        ids.extend(page.into_iter().map(|chat| chat.id));
+        if done {
+            return Ok(ids);
+        }
+    }
+}
+
+fn export(conn: &Connection, chats: &[String], options: &Options) -> Result<String, String> {
+    let ids = if chats.is_empty() {
+        all_chats(conn, options.project.as_ref())?
+    } else {
+        chats
+            .iter()
+            .map(|chat| resolve_chat(conn, chat))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let json = options.format == Format::Json;
+
+    let Some(dir) = &options.output else {
+        let mut exported = Vec::new();
+        for id in &ids {
+            exported.push(get_chat(conn, id)?);
+        }
+        return if json {
+            to_json(&exported)
+        } else {
+            Ok(exported
+                .iter()
+                .map(history::chat_markdown)
+                .collect::<Vec<_>>()
+                .join("\n"))
+        };
+    };
+
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Couldn't create {}: {}", dir.display(), e))?;
+    for id in &ids {
+        let chat = get_chat(conn, id)?;
+        let (contents, extension) = if json {
+            (to_json(&chat)?, "json")
+        } else {
+            (history::chat_markdown(&chat), "md")
+        };
+        let path = dir.join(format!("{}.{}", id, extension));
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Couldn't write {}: {}", path.display(), e))?;
+    }
+    eprintln!("Exported {} chats to {}", ids.len(), dir.display());
+    Ok(String::new())
+}
+
+fn stats(conn: &Connection, format: Format) -> Result<String, String> {
+    let stats = history::stats(conn)?;
+    if format == Format::Json {
+        return to_json(&stats);
+    }
+    let totals = vec![
+        vec!["Projects".to_string(), stats.projects.to_string()],
+        vec!["Chats".to_string(), stats.chats.to_string()],
+        vec!["Your messages".to_string(), stats.user_messages.to_string()],
+        vec![
+            "Responses".to_string(),
+            stats.assistant_messages.to_string(),
+        ],
+        vec!["Attachments".to_string(), stats.attachments.to_string()],
+        vec![
+            "First chat".to_string(),
+            short_date(stats.first_chat_at.as_deref()),
+        ],
+        vec![
+            "Last activity".to_string(),
+            short_date(stats.last_chat_at.as_deref()),
+        ],
+    ];
+    let models: Vec<Vec<String>> = stats
+        .models
+        .iter()
+        .map(|usage| vec![usage.model.clone(), usage.responses.to_string()])
+        .collect();
+    Ok(format!(
+        "{}\n{}",
+        render_table(&["totals", ""], &totals, format),
+        render_table(&["model", "responses"], &models, format)
+    ))
+}
+
+fn projects(conn: &Connection, format: Format) -> Result<String, String> {
+    let projects = history::list_projects(conn, &ProjectScope::All)?;
+    if format == Format::Json {
+        return to_json(&projects);
+    }
+    let rows: Vec<Vec<String>> = projects
+        .iter()
+        .map(|project| {
+            vec![
+                project.id.clone(),
+                project.chat_count.to_string(),
+                short_date(Some(&project.updated_at)),
+                cell(&project.name),
+            ]
+        })
+        .collect();
+    Ok(render_table(
+        &["id", "chats", "updated", "name"],
+        &rows,
+        format,
+    ))
+}
+
+/// Asks the app's local API to show the chat. Returns false if the API is
+/// off or the app isn't running.
+fn open_with_local_api(chat_id: &str) -> bool {
+    let Some(config) = history::default_data_dir()
+        .ok()
+        .and_then(|dir| std::fs::read(dir.join(LOCAL_API_CONFIG_FILE)).ok())
+        .and_then(|bytes| serde_json::from_slice::<LocalApiConfig>(&bytes).ok())
+    else {
+        return false;
+    };
+    let address = SocketAddr::from(([127, 0, 0, 1], config.port));
+    let Ok(mut stream) = TcpStream::connect_timeout(&address, LOCAL_API_TIMEOUT) else {
+        return false;
+    };
+    let _ = stream.set_read_timeout(Some(LOCAL_API_TIMEOUT));
+    let request = format!(
+        "POST /v1/chats/{}/open HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nAuthorization: Bearer {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        chat_id, config.port, config.token
+    );
+    if stream.write_all(request.as_bytes()).is_err() {
+        return false;
+    }
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    response
+        .split_whitespace()
+        .nth(1)
+        .is_some_and(|status| status.starts_with('2'))
+}
+
+/// Opens a chorus:// link, which starts the app if it isn't running
+fn open_deep_link(url: &str) -> Result<(), String> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    let status = command
+        .arg(url)
+        .status()
+        .map_err(|e| format!("Couldn't open {}: {}", url, e))?;
+    if !status.success() {
+        return Err(format!("Couldn't open {}", url));
+    }
+    Ok(())
+}
+
+fn open(conn: &Connection, chat: &str) -> Result<String, String> {
+    let id = resolve_chat(conn, chat)?;
+    if !open_with_local_api(&id) {
+        open_deep_link(&format!("{}{}", CHAT_LINK_PREFIX, id))?;
+    }
+    Ok(String::new())
+}
+
+fn run(options: Options) -> Result<String, String> {
+    let path = match &options.database {
+        Some(path) => path.clone(),
+        None => history::default_db_path()?,
+    };
+    let conn = db_encryption::open_read_only(&path)?;
+    match &options.command {
+        Command::List => list(&conn, &options),
+        Command::Show(chat) => show(&conn, chat, options.format),
+        Command::Search(query) => search(&conn, query, &options),
+        Command::Export(chats) => export(&conn, chats, &options),
+        Command::Stats => stats(&conn, options.format),
+        Command::Projects => projects(&conn, options.format),
+        Command::Open(chat) => open(&conn, chat),
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    let output = match run(options) {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    // Output piped into `head` may be cut off, which isn't an error
+    match io::stdout().lock().write_all(output.as_bytes()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,416 @@
+// An MCP server that lets other agents (editors, command line tools) read the
+// Chorus chat history. It speaks MCP over stdio and never writes to chats.db.
+
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[path = "../data_location.rs"]
+#[allow(dead_code)]
+mod data_location;
+
+#[path = "../db_encryption.rs"]
+#[allow(dead_code)]
+mod db_encryption;
+
+#[path = "../profile_registry.rs"]
+#[allow(dead_code)]
+mod profile_registry;
+
+#[path = "../search.rs"]
+#[allow(dead_code)]
+mod search;
+
+#[path = "../history.rs"]
+#[allow(dead_code)]
+mod history;
+
+use history::ProjectScope;
+use search::SearchFilters;
+
+const USAGE: &str = "Usage: chorus_mcp [--database <path>] [--project <id or name>]...
+
+Serves the Chorus chat history to MCP clients over stdio. The database is
+opened read-only.
+
+Options:
+      --database <path>         The chat database. Defaults to the active
+                                profile's chats.db in the Chorus data directory,
+                                which CHORUS_DATA_DIR overrides (per
+                                CHORUS_INSTANCE_NAME for dev instances).
+                                Encrypted databases are opened with the key in
+                                the OS keyring, or the passphrase in
+                                CHORUS_DB_PASSPHRASE.
+  -p, --project <id or name>    Only expose this project. Repeat to expose
+                                more. Defaults to the comma separated list in
+                                CHORUS_MCP_PROJECTS, or every project.
+  -h, --help                    Show this message.
+
+Example client config:
+  { \"command\": \"chorus_mcp\", \"args\": [\"--project\", \"Work\"] }";
+
+const PROJECTS_ENV: &str = "CHORUS_MCP_PROJECTS";
+const SERVER_NAME: &str = "chorus-history";
+const DEFAULT_PROTOCOL_VERSION: &str = "2025-03-26";
+const CHAT_URI_PREFIX: &str = "chorus://chat/";
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+const RESOURCES_PAGE_SIZE: u32 = 100;
+
+// JSON-RPC and MCP error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+const RESOURCE_NOT_FOUND: i64 = -32002;
+
+struct Options {
+    database: Option<PathBuf>,
+    projects: Vec<String>,
+}
+
+struct Server {
+    conn: Connection,
+    scope: ProjectScope,
+}
+
+// An error answered in place of a result
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn invalid_params(message: impl Into<String>) -> Self {
+        RpcError {
+            code: INVALID_PARAMS,
+            message: message.into(),
+        }
+    }
+}
+
+fn parse_args() -> Result<Option<Options>, String> {
+    let mut options = Options {
+        database: None,
+        projects: Vec::new(),
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--database" => {
+                options.database = Some(args.next().ok_or("--database needs a path")?.into())
+            }
+            "-p" | "--project" => options
+                .projects
+                .push(args.next().ok_or("--project needs an id or name")?),
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    if options.projects.is_empty() {
+        if let Ok(projects) = std::env::var(PROJECTS_ENV) {
+            options.projects = projects
+                .split(',')
+                .map(|project| project.trim().to_string())
+                .filter(|project| !project.is_empty())
+                .collect();
+        }
+    }
+    Ok(Some(options))
+}
+
+fn to_text<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(value).map_err(|e| e.to_string())
+}
+
+fn string_arg<'a>(args: &'a Value, name: &str) -> Result<&'a str, String> {
+    args.get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("`{}` is required", name))
+}
+
+fn u32_arg(args: &Value, name: &str) -> Option<u32> {
+    args.get(name)
+        .and_then(Value::as_u64)
+        .map(|value| u32::try_from(value).unwrap_or(u32::MAX))
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "search_chats",
+            "description": "Full-text search over Chorus chats and messages. Supports \"quoted phrases\", prefix*, AND/OR/NOT, and filters like model:, project:, after:YYYY-MM-DD and before:YYYY-MM-DD.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "project_id": { "type": "string", "description": "Only search this project" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 200 },
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "list_chats",
+            "description": "Lists chats, most recently updated first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "project_id": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 500 },
+                    "offset": { "type": "integer", "minimum": 0 },
+                },
+            },
+        },
+        {
+            "name": "get_chat",
+            "description": "Returns a chat's conversation as Markdown.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "chat_id": { "type": "string" } },
+                "required": ["chat_id"],
+            },
+        },
+        {
+            "name": "list_projects",
+            "description": "Lists the projects chats are organized in.",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "get_project_context",
+            "description": "Returns a project's context: the instructions the user wrote for it, its attached files, and summaries of its chats.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "project_id": { "type": "string" } },
+                "required": ["project_id"],
+            },
+        },
+    ])
+}
+
+impl Server {
+    fn handle(&self, message: &Value) -> Option<Value> {
+        // Notifications get no response
+        let id = message.get("id")?;
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let response = match self.dispatch(method, &params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": e.code, "message": e.message },
+            }),
+        };
+        Some(response)
+    }
+
+    fn dispatch(&self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": params
+                    .get("protocolVersion")
+                    .and_then(Value::as_str)
+                    .unwrap_or(DEFAULT_PROTOCOL_VERSION),
+                "capabilities": { "tools": {}, "resources": {} },
+                "serverInfo": { "name": SERVER_NAME, "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+            "tools/call" => {
+                let name = string_arg(params, "name").map_err(RpcError::invalid_params)?;
+                let args = params.get("arguments").cloned().unwrap_or(json!({}));
+                // Failures are reported to the model rather than as protocol errors
+                Ok(match self.call_tool(name, &args) {
+                    None => {
+                        return Err(RpcError::invalid_params(format!("Unknown tool: {}", name)))
+                    }
+                    Some(Ok(text)) => json!({ "content": [{ "type": "text", "text": text }] }),
+                    Some(Err(e)) => json!({
+                        "content": [{ "type": "text", "text": e }],
+                        "isError": true,
+                    }),
+                })
+            }
+            "resources/list" => self.list_resources(params),
+            "resources/templates/list" => Ok(json!({
+                "resourceTemplates": [{
+                    "uriTemplate": format!("{}{{chat_id}}", CHAT_URI_PREFIX),
+                    "name": "Chat",
+                    "description": "A Chorus chat as Markdown",
+                    "mimeType": "text/markdown",
+                }],
+            })),
+            "resources/read" => self.read_resource(params),
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Method not found: {}", method),
+            }),
+        }
+    }
+
+    /// Runs a tool, or returns None if there's no tool with that name
+    fn call_tool(&self, name: &str, args: &Value) -> Option<Result<String, String>> {
+        let result = match name {
+            "search_chats" => string_arg(args, "query").and_then(|query| {
+                let filters = SearchFilters {
+                    project_id: args
+                        .get("project_id")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    ..Default::default()
+                };
+                let limit = u32_arg(args, "limit").unwrap_or(DEFAULT_SEARCH_LIMIT);
+                to_text(&history::search_chats(
+                    &self.conn,
+                    &self.scope,
+                    query,
+                    filters,
+                    Some(limit),
+                )?)
+            }),
+            "list_chats" => history::list_chats(
+                &self.conn,
+                &self.scope,
+                args.get("project_id").and_then(Value::as_str),
+                u32_arg(args, "limit"),
+                u32_arg(args, "offset").unwrap_or(0),
+            )
+            .and_then(|chats| to_text(&chats)),
+            "get_chat" => string_arg(args, "chat_id").and_then(|chat_id| {
+                history::get_chat(&self.conn, &self.scope, chat_id)?
+                    .map(|chat| history::chat_markdown(&chat))
+                    .ok_or_else(|| format!("Chat not found: {}", chat_id))
+            }),
+            "list_projects" => history::list_projects(&self.conn, &self.scope)
+                .and_then(|projects| to_text(&projects)),
+            "get_project_context" => string_arg(args, "project_id").and_then(|project_id| {
+                let context = history::project_context(&self.conn, &self.scope, project_id)?
+                    .ok_or_else(|| format!("Project not found: {}", project_id))?;
+                to_text(&context)
+            }),
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    fn list_resources(&self, params: &Value) -> Result<Value, RpcError> {
+        let offset = params
+            .get("cursor")
+            .and_then(Value::as_str)
+            .map(|cursor| {
+                cursor
+                    .parse::<u32>()
+                    .map_err(|_| RpcError::invalid_params("Invalid cursor"))
+            })
+            .transpose()?
+            .unwrap_or(0);
+        let chats = history::list_chats(
+            &self.conn,
+            &self.scope,
+            None,
+            Some(RESOURCES_PAGE_SIZE),
+            offset,
+        )
+        .map_err(internal_error)?;
+        let next_cursor = (chats.len() == RESOURCES_PAGE_SIZE as usize)
+            .then(|| (offset + RESOURCES_PAGE_SIZE).to_string());
+        let resources: Vec<Value> = chats
+            .into_iter()
+            .map(|chat| {
+                json!({
+                    "uri": format!("{}{}", CHAT_URI_PREFIX, chat.id),
+                    "name": chat.title.unwrap_or_else(|| "Untitled chat".to_string()),
+                    "mimeType": "text/markdown",
+                })
+            })
+            .collect();
+        let mut result = json!({ "resources": resources });
+        if let Some(cursor) = next_cursor {
+            result["nextCursor"] = json!(cursor);
+        }
+        Ok(result)
+    }
+
+    fn read_resource(&self, params: &Value) -> Result<Value, RpcError> {
+        let uri = string_arg(params, "uri").map_err(RpcError::invalid_params)?;
+        let not_found = || RpcError {
+            code: RESOURCE_NOT_FOUND,
+            message: format!("Resource not found: {}", uri),
+        };
+        let chat_id = uri.strip_prefix(CHAT_URI_PREFIX).ok_or_else(not_found)?;
+        let chat = history::get_chat(&self.conn, &self.scope, chat_id)
+            .map_err(internal_error)?
+            .ok_or_else(not_found)?;
+        Ok(json!({
+            "contents": [{
+                "uri": uri,
+                "mimeType": "text/markdown",
+                "text": history::chat_markdown(&chat),
+            }],
+        }))
+    }
+}
+
+fn internal_error(message: String) -> RpcError {
+    RpcError {
+        code: INTERNAL_ERROR,
+        message,
+    }
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let path = match options.database {
+        Some(path) => path,
+        None => history::default_db_path()?,
+    };
+    let conn = db_encryption::open_read_only(&path)?;
+    let scope = ProjectScope::resolve(&conn, &options.projects)?;
+    let server = Server { conn, scope };
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    for line in stdin.lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => server.handle(&message),
+            Err(e) => Some(json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": format!("Parse error: {}", e) },
+            })),
+        };
+        if let Some(response) = response {
+            writeln!(stdout, "{}", response).map_err(|e| e.to_string())?;
+            stdout.flush().map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    // stdout carries the protocol, so errors go to stderr
+    match run(options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
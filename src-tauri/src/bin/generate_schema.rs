@@ -1,20 +1,144 @@
-use tauri_plugin_sql::MigrationKind;
 use rusqlite::{Connection, Result};
-use std::fs::File;
-use std::io::Write;
+use serde::Serialize;
+use std::fs;
 use std::path::Path;
+use std::process::ExitCode;
+use tauri_plugin_sql::MigrationKind;
 
 // Import migrations from the main module
 #[path = "../migrations.rs"]
 mod migrations;
 
+#[path = "../db_encryption.rs"]
 #[allow(dead_code)]
+mod db_encryption;
+
+const DEFAULT_MARKDOWN_PATH: &str = "../SCHEMA.md";
+
+const USAGE: &str = "Usage: generate_schema [--format <format>] [--output <path>] [--check]
+                       [--database <path>]
+
+Applies every migration to an in-memory database and documents the result.
+
+Options:
+  -f, --format <format>  markdown, json, mermaid, dot or sql. Defaults to the
+                         output file's extension, or markdown.
+  -o, --output <path>    Where to write the schema. Use - for stdout. Defaults
+                         to ../SCHEMA.md for markdown and stdout otherwise.
+      --check            Compare against the existing output file instead of
+                         writing it, and exit non-zero if it's out of date.
+      --database <path>  Document an existing database instead, e.g. a user's
+                         chats.db. Output goes to stdout unless -o is given.
+                         Encrypted databases are opened with the key in the OS
+                         keyring, or the passphrase in CHORUS_DB_PASSPHRASE.
+  -h, --help             Show this message.";
+
+// Suffixes of the shadow tables FTS5 creates for each virtual table
+const FTS5_SHADOW_SUFFIXES: &[&str] = &["_data", "_idx", "_content", "_docsize", "_config"];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Markdown,
+    Json,
+    Mermaid,
+    Dot,
+    Sql,
+}
+
+impl Format {
+    fn parse(name: &str) -> Option<Format> {
+        match name.to_lowercase().as_str() {
+            "markdown" | "md" => Some(Format::Markdown),
+            "json" => Some(Format::Json),
+            "mermaid" | "mmd" => Some(Format::Mermaid),
+            "dot" | "graphviz" | "gv" => Some(Format::Dot),
+            "sql" => Some(Format::Sql),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &str) -> Option<Format> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        match extension {
+            "mermaid" => Some(Format::Mermaid),
+            _ => Format::parse(extension),
+        }
+    }
+}
+
+struct Options {
+    format: Format,
+    output: Option<String>,
+    check: bool,
+    database: Option<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut format = None;
+    let mut output = None;
+    let mut check = false;
+    let mut database = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" | "-f" => {
+                let value = args.next().ok_or("--format needs a value")?;
+                format = Some(Format::parse(&value).ok_or(format!("Unknown format '{}'", value))?);
+            }
+            "--output" | "-o" => {
+                output = Some(args.next().ok_or("--output needs a value")?);
+            }
+            "--check" => check = true,
+            "--database" => {
+                database = Some(args.next().ok_or("--database needs a path")?);
+            }
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ => return Err(format!("Unknown argument '{}'\n\n{}", arg, USAGE)),
+        }
+    }
+
+    let format = format
+        .or_else(|| output.as_deref().and_then(Format::from_path))
+        .unwrap_or(Format::Markdown);
+    let output = match output {
+        Some(path) if path == "-" => None,
+        Some(path) => Some(path),
+        None if format == Format::Markdown && database.is_none() => {
+            Some(DEFAULT_MARKDOWN_PATH.to_string())
+        }
+        None => None,
+    };
+    if check && output.is_none() {
+        return Err("--check needs an output file to compare against".to_string());
+    }
+
+    Ok(Options {
+        format,
+        output,
+        check,
+        database,
+    })
+}
+
+#[derive(Serialize)]
+struct Schema {
+    tables: Vec<TableInfo>,
+    virtual_tables: Vec<VirtualTableInfo>,
+    views: Vec<ViewInfo>,
+    triggers: Vec<TriggerInfo>,
+}
+
+#[derive(Serialize)]
 struct TableInfo {
     name: String,
     sql: String,
+    columns: Vec<ColumnInfo>,
+    indices: Vec<IndexInfo>,
+    foreign_keys: Vec<ForeignKeyInfo>,
 }
 
-#[allow(dead_code)]
+#[derive(Serialize)]
 struct ColumnInfo {
     cid: i32,
     name: String,
@@ -24,106 +148,184 @@ struct ColumnInfo {
     is_primary: bool,
 }
 
+#[derive(Serialize)]
 struct IndexInfo {
     name: String,
     table_name: String,
+    columns: String,
     sql: Option<String>,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Create an in-memory SQLite database
-    let conn = Connection::open_in_memory()?;
-    
-    // Get migrations
-    let migrations = migrations::migrations();
-    
-    // Apply all migrations
-    for migration in &migrations {
-        if matches!(migration.kind, MigrationKind::Up) {
-            println!("Applying migration {}: {}", migration.version, migration.description);
-            conn.execute_batch(&migration.sql)?;
+#[derive(Serialize)]
+struct ForeignKeyInfo {
+    column: String,
+    references_table: String,
+    references_column: String,
+    /// True if declared with FOREIGN KEY, false if inferred from an `_id` column
+    declared: bool,
+}
+
+#[derive(Serialize)]
+struct VirtualTableInfo {
+    name: String,
+    sql: String,
+}
+
+#[derive(Serialize)]
+struct ViewInfo {
+    name: String,
+    sql: String,
+}
+
+#[derive(Serialize)]
+struct TriggerInfo {
+    name: String,
+    table_name: String,
+    sql: String,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match run(&options) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
         }
     }
-    
+}
+
+fn run(options: &Options) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let conn = match &options.database {
+        Some(path) => db_encryption::open_read_only(Path::new(path))?,
+        None => {
+            // Create an in-memory SQLite database
+            let conn = Connection::open_in_memory()?;
+
+            // Apply all migrations
+            for migration in &migrations::migrations() {
+                if matches!(migration.kind, MigrationKind::Up) {
+                    eprintln!(
+                        "Applying migration {}: {}",
+                        migration.version, migration.description
+                    );
+                    conn.execute_batch(migration.sql)?;
+                }
+            }
+            conn
+        }
+    };
+
     // Query the schema
-    let tables = get_tables(&conn)?;
-    let indices = get_indices(&conn)?;
-    
-    // Generate SQL_SCHEMA.md in the root directory
-    let schema_path = Path::new("../SQL_SCHEMA.md");
-    let mut file = File::create(schema_path)?;
-    
-    // Write header
-    writeln!(file, "# Database Schema")?;
-    writeln!(file)?;
-    writeln!(file, "_This file is auto-generated from migrations.rs. Do not edit manually._")?;
-    writeln!(file)?;
-    writeln!(file, "Last updated: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"))?;
-    writeln!(file)?;
-    
-    // Write table of contents
-    writeln!(file, "## Tables")?;
-    writeln!(file)?;
-    for table in &tables {
-        writeln!(file, "- [{}](#{})", table.name, table.name.to_lowercase())?;
+    let schema = get_schema(&conn)?;
+
+    let rendered = match options.format {
+        Format::Markdown => render_markdown(&schema),
+        Format::Json => serde_json::to_string_pretty(&schema)? + "\n",
+        Format::Mermaid => render_mermaid(&schema),
+        Format::Dot => render_dot(&schema),
+        Format::Sql => render_sql(&schema),
+    };
+
+    let Some(output) = &options.output else {
+        print!("{}", rendered);
+        return Ok(ExitCode::SUCCESS);
+    };
+
+    if options.check {
+        let existing = fs::read_to_string(output).unwrap_or_default();
+        if comparable(&existing) == comparable(&rendered) {
+            eprintln!("{} is up to date", output);
+            return Ok(ExitCode::SUCCESS);
+        }
+        eprintln!(
+            "{} is out of date, run generate_schema to update it",
+            output
+        );
+        print_diff(&existing, &rendered);
+        return Ok(ExitCode::FAILURE);
     }
-    writeln!(file)?;
-    
-    // Write detailed table information
-    for table in &tables {
-        writeln!(file, "## {}", table.name)?;
-        writeln!(file)?;
-        
-        // Get column information
-        let columns = get_columns(&conn, &table.name)?;
-        
-        // Write table
-        writeln!(file, "| Column | Type | Constraints | Default |")?;
-        writeln!(file, "|--------|------|-------------|---------|")?;
-        
-        for col in &columns {
-            let constraints = format!("{}{}",
-                if col.not_null { "NOT NULL " } else { "" },
-                if col.is_primary { "PRIMARY KEY" } else { "" }
-            ).trim().to_string();
-            
-            let default = col.default_value.as_deref().unwrap_or("-");
-            
-            writeln!(file, "| {} | {} | {} | {} |", 
-                col.name, 
-                col.data_type, 
-                if constraints.is_empty() { "-" } else { &constraints },
-                default
-            )?;
-        }
-        writeln!(file)?;
-        
-        // Write indices for this table
-        let table_indices: Vec<&IndexInfo> = indices.iter()
+
+    fs::write(output, rendered)?;
+    eprintln!("Schema generated successfully at {}", output);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Strips the generation timestamp so that --check only reports real changes.
+fn comparable(contents: &str) -> Vec<&str> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with("Last updated:"))
+        .collect()
+}
+
+/// Prints the lines that differ between the two files, without context.
+fn print_diff(existing: &str, rendered: &str) {
+    let existing = comparable(existing);
+    let rendered = comparable(rendered);
+    for line in &existing {
+        if !rendered.contains(line) {
+            eprintln!("- {}", line);
+        }
+    }
+    for line in &rendered {
+        if !existing.contains(line) {
+            eprintln!("+ {}", line);
+        }
+    }
+}
+
+fn get_schema(conn: &Connection) -> Result<Schema, rusqlite::Error> {
+    let virtual_tables = get_virtual_tables(conn)?;
+    let shadow_tables: Vec<String> = virtual_tables
+        .iter()
+        .flat_map(|vtab| {
+            FTS5_SHADOW_SUFFIXES
+                .iter()
+                .map(move |suffix| format!("{}{}", vtab.name, suffix))
+        })
+        .collect();
+
+    let mut tables = get_tables(conn)?;
+    tables.retain(|table| {
+        !shadow_tables.contains(&table.name)
+            && !virtual_tables.iter().any(|vtab| vtab.name == table.name)
+    });
+
+    let table_names: Vec<String> = tables.iter().map(|table| table.name.clone()).collect();
+    let indices = get_indices(conn)?;
+    for table in &mut tables {
+        table.columns = get_columns(conn, &table.name)?;
+        table.foreign_keys = get_foreign_keys(conn, &table.name, &table.columns, &table_names)?;
+        table.indices = indices
+            .iter()
             .filter(|idx| idx.table_name == table.name && !idx.name.starts_with("sqlite_autoindex"))
+            .map(|idx| IndexInfo {
+                name: idx.name.clone(),
+                table_name: idx.table_name.clone(),
+                columns: idx.columns.clone(),
+                sql: idx.sql.clone(),
+            })
             .collect();
-        
-        if !table_indices.is_empty() {
-            writeln!(file, "### Indices")?;
-            writeln!(file)?;
-            for idx in table_indices {
-                writeln!(file, "- **{}**", idx.name)?;
-                if let Some(sql) = &idx.sql {
-                    // Extract the column list from CREATE INDEX statement
-                    if let Some(start) = sql.find('(') {
-                        if let Some(end) = sql.find(')') {
-                            let columns = &sql[start+1..end];
-                            writeln!(file, "  - Columns: {}", columns)?;
-                        }
-                    }
-                }
-            }
-            writeln!(file)?;
-        }
     }
-    
-    println!("Schema generated successfully at ../SQL_SCHEMA.md");
-    Ok(())
+
+    let mut triggers = get_triggers(conn)?;
+    // FTS5 sync triggers are documented with their virtual table
+    triggers.sort_by(|a, b| a.table_name.cmp(&b.table_name).then(a.name.cmp(&b.name)));
+
+    Ok(Schema {
+        tables,
+        virtual_tables,
+        views: get_views(conn)?,
+        triggers,
+    })
 }
 
 fn get_tables(conn: &Connection) -> Result<Vec<TableInfo>, rusqlite::Error> {
@@ -131,10 +333,29 @@ fn get_tables(conn: &Connection) -> Result<Vec<TableInfo>, rusqlite::Error> {
     let tables = stmt.query_map([], |row| {
         Ok(TableInfo {
             name: row.get(0)?,
-            sql: row.get(1)?,
+            sql: dedent(&row.get::<_, String>(1)?),
+            columns: Vec::new(),
+            indices: Vec::new(),
+            foreign_keys: Vec::new(),
         })
     })?;
-    
+
+    let mut result = Vec::new();
+    for table in tables {
+        result.push(table?);
+    }
+    Ok(result)
+}
+
+fn get_virtual_tables(conn: &Connection) -> Result<Vec<VirtualTableInfo>, rusqlite::Error> {
+    let mut stmt = conn.prepare("SELECT name, sql FROM sqlite_master WHERE type='table' AND sql LIKE 'CREATE VIRTUAL TABLE%' ORDER BY name")?;
+    let tables = stmt.query_map([], |row| {
+        Ok(VirtualTableInfo {
+            name: row.get(0)?,
+            sql: dedent(&row.get::<_, String>(1)?),
+        })
+    })?;
+
     let mut result = Vec::new();
     for table in tables {
         result.push(table?);
@@ -151,10 +372,10 @@ fn get_columns(conn: &Connection, table_name: &str) -> Result<Vec<ColumnInfo>, r
             data_type: row.get(2)?,
             not_null: row.get(3)?,
             default_value: row.get(4)?,
-            is_primary: row.get(5)?,
+            is_primary: row.get::<_, i32>(5)? > 0,
         })
     })?;
-    
+
     let mut result = Vec::new();
     for col in columns {
         result.push(col?);
@@ -162,19 +383,422 @@ fn get_columns(conn: &Connection, table_name: &str) -> Result<Vec<ColumnInfo>, r
     Ok(result)
 }
 
+/// Returns declared foreign keys, plus references inferred from `<name>_id`
+/// columns when a table called `<name>s` or `<name>` exists. Most tables
+/// don't declare their relationships, so the inferred ones make the ERD useful.
+fn get_foreign_keys(
+    conn: &Connection,
+    table_name: &str,
+    columns: &[ColumnInfo],
+    table_names: &[String],
+) -> Result<Vec<ForeignKeyInfo>, rusqlite::Error> {
+    let mut stmt = conn.prepare(&format!("PRAGMA foreign_key_list({})", table_name))?;
+    let declared = stmt.query_map([], |row| {
+        Ok(ForeignKeyInfo {
+            column: row.get(3)?,
+            references_table: row.get(2)?,
+            references_column: row.get::<_, Option<String>>(4)?.unwrap_or("id".to_string()),
+            declared: true,
+        })
+    })?;
+
+    let mut result = Vec::new();
+    for fk in declared {
+        result.push(fk?);
+    }
+
+    for column in columns {
+        if result.iter().any(|fk| fk.column == column.name) {
+            continue;
+        }
+        let Some(stem) = column.name.strip_suffix("_id") else {
+            continue;
+        };
+        // Try the longest suffix first, so `parent_chat_id` maps to `chats`
+        // and `model_config_id` to `model_configs`
+        let words: Vec<&str> = stem.split('_').collect();
+        let referenced = (0..words.len()).find_map(|start| {
+            let name = words[start..].join("_");
+            [format!("{}s", name), name]
+                .into_iter()
+                .find(|candidate| table_names.contains(candidate))
+        });
+        if let Some(references_table) = referenced {
+            result.push(ForeignKeyInfo {
+                column: column.name.clone(),
+                references_table,
+                references_column: "id".to_string(),
+                declared: false,
+            });
+        }
+    }
+    Ok(result)
+}
+
 fn get_indices(conn: &Connection) -> Result<Vec<IndexInfo>, rusqlite::Error> {
-    let mut stmt = conn.prepare("SELECT name, tbl_name, sql FROM sqlite_master WHERE type='index' ORDER BY name")?;
+    let mut stmt = conn.prepare(
+        "SELECT name, tbl_name, sql FROM sqlite_master WHERE type='index' ORDER BY name",
+    )?;
     let indices = stmt.query_map([], |row| {
+        let sql: Option<String> = row.get(2)?;
+        // Extract the column list from the CREATE INDEX statement
+        let columns = sql
+            .as_deref()
+            .and_then(|sql| {
+                let start = sql.find('(')?;
+                let end = sql.rfind(')')?;
+                Some(sql[start + 1..end].trim().to_string())
+            })
+            .unwrap_or_default();
         Ok(IndexInfo {
             name: row.get(0)?,
             table_name: row.get(1)?,
-            sql: row.get(2)?,
+            columns,
+            sql,
         })
     })?;
-    
+
     let mut result = Vec::new();
     for idx in indices {
         result.push(idx?);
     }
     Ok(result)
-}
\ No newline at end of file
+}
+
+fn get_views(conn: &Connection) -> Result<Vec<ViewInfo>, rusqlite::Error> {
+    let mut stmt =
+        conn.prepare("SELECT name, sql FROM sqlite_master WHERE type='view' ORDER BY name")?;
+    let views = stmt.query_map([], |row| {
+        Ok(ViewInfo {
+            name: row.get(0)?,
+            sql: dedent(&row.get::<_, String>(1)?),
+        })
+    })?;
+
+    let mut result = Vec::new();
+    for view in views {
+        result.push(view?);
+    }
+    Ok(result)
+}
+
+fn get_triggers(conn: &Connection) -> Result<Vec<TriggerInfo>, rusqlite::Error> {
+    let mut stmt = conn.prepare(
+        "SELECT name, tbl_name, sql FROM sqlite_master WHERE type='trigger' ORDER BY name",
+    )?;
+    let triggers = stmt.query_map([], |row| {
+        Ok(TriggerInfo {
+            name: row.get(0)?,
+            table_name: row.get(1)?,
+            sql: dedent(&row.get::<_, String>(2)?),
+        })
+    })?;
+
+    let mut result = Vec::new();
+    for trigger in triggers {
+        result.push(trigger?);
+    }
+    Ok(result)
+}
+
+/// Removes the indentation that statements inherit from the raw strings in
+/// migrations.rs. The first line never carries it, so the closing line (`END`,
+/// `)`) tells how much to strip from the others.
+fn dedent(sql: &str) -> String {
+    let lines: Vec<&str> = sql.lines().collect();
+    let indent = match lines.as_slice() {
+        [_, .., last] => last.len() - last.trim_start().len(),
+        _ => 0,
+    };
+
+    let mut result = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let leading = line.len() - line.trim_start().len();
+        if index == 0 || leading < indent {
+            result.push(line.trim());
+        } else {
+            result.push(line[indent..].trim_end());
+        }
+    }
+    result.join("\n")
+}
+
+/// Renders a Markdown table with padded columns, the way Prettier formats it.
+fn markdown_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len().max(3)).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let format_row = |cells: Vec<String>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        format!("| {} |\n", padded.join(" | "))
+    };
+
+    let mut table = format_row(headers.iter().map(|h| h.to_string()).collect());
+    table.push_str(&format_row(widths.iter().map(|w| "-".repeat(*w)).collect()));
+    for row in rows {
+        table.push_str(&format_row(row.clone()));
+    }
+    table
+}
+
+fn render_markdown(schema: &Schema) -> String {
+    let mut out = String::new();
+
+    // Write header
+    out.push_str("# Database Schema\n\n");
+    out.push_str("_This file is auto-generated from migrations.rs. Do not edit manually._\n\n");
+    out.push_str(&format!(
+        "Last updated: {}\n\n",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    ));
+
+    // Write table of contents
+    out.push_str("## Tables\n\n");
+    for table in &schema.tables {
+        out.push_str(&format!(
+            "-   [{}](#{})\n",
+            table.name,
+            table.name.to_lowercase()
+        ));
+    }
+    out.push('\n');
+
+    // Write detailed table information
+    for table in &schema.tables {
+        out.push_str(&format!("## {}\n\n", table.name));
+
+        let rows: Vec<Vec<String>> = table
+            .columns
+            .iter()
+            .map(|col| {
+                let constraints = format!(
+                    "{}{}",
+                    if col.not_null { "NOT NULL " } else { "" },
+                    if col.is_primary { "PRIMARY KEY" } else { "" }
+                )
+                .trim()
+                .to_string();
+                vec![
+                    col.name.clone(),
+                    col.data_type.clone(),
+                    if constraints.is_empty() {
+                        "-".to_string()
+                    } else {
+                        constraints
+                    },
+                    col.default_value
+                        .as_deref()
+                        .unwrap_or("-")
+                        .replace('|', "\\|"),
+                ]
+            })
+            .collect();
+        out.push_str(&markdown_table(
+            &["Column", "Type", "Constraints", "Default"],
+            &rows,
+        ));
+        out.push('\n');
+
+        if !table.foreign_keys.is_empty() {
+            out.push_str("### References\n\n");
+            for fk in &table.foreign_keys {
+                out.push_str(&format!(
+                    "-   {} → [{}](#{}).{}{}\n",
+                    fk.column,
+                    fk.references_table,
+                    fk.references_table.to_lowercase(),
+                    fk.references_column,
+                    if fk.declared { "" } else { " (inferred)" }
+                ));
+            }
+            out.push('\n');
+        }
+
+        // Write indices for this table
+        if !table.indices.is_empty() {
+            out.push_str("### Indices\n\n");
+            for idx in &table.indices {
+                out.push_str(&format!("-   **{}**\n", idx.name));
+                if !idx.columns.is_empty() {
+                    out.push_str(&format!("    -   Columns: {}\n", idx.columns));
+                }
+            }
+            out.push('\n');
+        }
+
+        let triggers: Vec<&TriggerInfo> = schema
+            .triggers
+            .iter()
+            .filter(|trigger| trigger.table_name == table.name)
+            .collect();
+        if !triggers.is_empty() {
+            out.push_str("### Triggers\n\n");
+            for trigger in triggers {
+                out.push_str(&format!("-   **{}**\n", trigger.name));
+            }
+            out.push('\n');
+        }
+    }
+
+    if !schema.virtual_tables.is_empty() {
+        out.push_str("## Virtual Tables\n\n");
+        for vtab in &schema.virtual_tables {
+            out.push_str(&format!(
+                "### {}\n\n```sql\n{}\n```\n\n",
+                vtab.name, vtab.sql
+            ));
+        }
+    }
+
+    if !schema.views.is_empty() {
+        out.push_str("## Views\n\n");
+        for view in &schema.views {
+            out.push_str(&format!(
+                "### {}\n\n```sql\n{}\n```\n\n",
+                view.name, view.sql
+            ));
+        }
+    }
+
+    if !schema.triggers.is_empty() {
+        out.push_str("## Trigger Definitions\n\n");
+        for trigger in &schema.triggers {
+            out.push_str(&format!(
+                "### {}\n\n```sql\n{}\n```\n\n",
+                trigger.name, trigger.sql
+            ));
+        }
+    }
+
+    // Prettier leaves no blank line at the end of the file
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    out.push('\n');
+    out
+}
+
+/// Mermaid attribute types must be a single word
+fn mermaid_type(data_type: &str) -> String {
+    if data_type.is_empty() {
+        "ANY".to_string()
+    } else {
+        data_type.replace(|c: char| !c.is_alphanumeric(), "_")
+    }
+}
+
+fn render_mermaid(schema: &Schema) -> String {
+    let mut out = String::from("erDiagram\n");
+    for table in &schema.tables {
+        out.push_str(&format!("    {} {{\n", table.name));
+        for col in &table.columns {
+            let is_foreign = table.foreign_keys.iter().any(|fk| fk.column == col.name);
+            let keys = match (col.is_primary, is_foreign) {
+                (true, true) => " PK, FK",
+                (true, false) => " PK",
+                (false, true) => " FK",
+                (false, false) => "",
+            };
+            out.push_str(&format!(
+                "        {} {}{}\n",
+                mermaid_type(&col.data_type),
+                col.name,
+                keys
+            ));
+        }
+        out.push_str("    }\n");
+    }
+    for table in &schema.tables {
+        for fk in &table.foreign_keys {
+            out.push_str(&format!(
+                "    {} ||--o{{ {} : \"{}\"\n",
+                fk.references_table, table.name, fk.column
+            ));
+        }
+    }
+    out
+}
+
+/// Escapes characters that have a meaning inside Graphviz record labels
+fn dot_escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '{' | '}' | '|' | '<' | '>' | '"' | '\\' => vec!['\\', c],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+fn render_dot(schema: &Schema) -> String {
+    let mut out = String::from("digraph schema {\n");
+    out.push_str("    rankdir=LR;\n");
+    out.push_str("    node [shape=record, fontname=\"Helvetica\", fontsize=10];\n\n");
+    for table in &schema.tables {
+        let columns: Vec<String> = table
+            .columns
+            .iter()
+            .map(|col| {
+                format!(
+                    "{}{} : {}\\l",
+                    if col.is_primary { "* " } else { "" },
+                    dot_escape(&col.name),
+                    dot_escape(&col.data_type)
+                )
+            })
+            .collect();
+        out.push_str(&format!(
+            "    {} [label=\"{{{}|{}}}\"];\n",
+            table.name,
+            dot_escape(&table.name),
+            columns.join("")
+        ));
+    }
+    out.push('\n');
+    for table in &schema.tables {
+        for fk in &table.foreign_keys {
+            out.push_str(&format!(
+                "    {} -> {} [label=\"{}\"{}];\n",
+                table.name,
+                fk.references_table,
+                fk.column,
+                if fk.declared { "" } else { ", style=dashed" }
+            ));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn render_sql(schema: &Schema) -> String {
+    let mut out = String::from(
+        "-- Generated from migrations.rs by generate_schema. Do not edit manually.\n\n",
+    );
+    for table in &schema.tables {
+        out.push_str(&format!("{};\n\n", table.sql));
+        for idx in &table.indices {
+            if let Some(sql) = &idx.sql {
+                out.push_str(&format!("{};\n\n", dedent(sql)));
+            }
+        }
+    }
+    for vtab in &schema.virtual_tables {
+        out.push_str(&format!("{};\n\n", vtab.sql));
+    }
+    for view in &schema.views {
+        out.push_str(&format!("{};\n\n", view.sql));
+    }
+    for trigger in &schema.triggers {
+        out.push_str(&format!("{};\n\n", trigger.sql));
+    }
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    out.push('\n');
+    out
+}
@@ -0,0 +1,229 @@
+use rusqlite::Connection;
+use sha2::{Digest, Sha384};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+use tauri_plugin_sql::MigrationKind;
+
+// Import migrations from the main module
+#[path = "../migrations.rs"]
+mod migrations;
+
+// Checksums of every migration that has been released. Maintained with
+// `cargo run --bin lint_migrations -- --update`.
+const MANIFEST_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/migrations.lock");
+
+// Versions that were skipped before this linter existed. They can't be filled
+// in without renumbering released migrations, so they're allowed.
+const KNOWN_GAPS: &[i64] = &[103];
+
+struct ManifestEntry {
+    checksum: String,
+    description: String,
+}
+
+fn checksum(sql: &str) -> String {
+    // Same digest sqlx stores in _sqlx_migrations
+    hex::encode(Sha384::digest(sql.as_bytes()))
+}
+
+fn read_manifest(path: &Path) -> Result<BTreeMap<i64, ManifestEntry>, String> {
+    let mut manifest = BTreeMap::new();
+    if !path.exists() {
+        return Ok(manifest);
+    }
+
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.splitn(3, ' ');
+        let (Some(version), Some(checksum)) = (parts.next(), parts.next()) else {
+            return Err(format!(
+                "{}:{}: malformed line",
+                path.display(),
+                line_number + 1
+            ));
+        };
+        let version = version
+            .parse::<i64>()
+            .map_err(|_| format!("{}:{}: invalid version", path.display(), line_number + 1))?;
+        manifest.insert(
+            version,
+            ManifestEntry {
+                checksum: checksum.to_string(),
+                description: parts.next().unwrap_or_default().to_string(),
+            },
+        );
+    }
+    Ok(manifest)
+}
+
+fn write_manifest(path: &Path, manifest: &BTreeMap<i64, ManifestEntry>) -> Result<(), String> {
+    let mut contents = String::new();
+    contents.push_str("# Checksums of released migrations in src/migrations.rs.\n");
+    contents.push_str(
+        "# Generated by `cargo run --bin lint_migrations -- --update`. Do not edit manually.\n",
+    );
+    for (version, entry) in manifest {
+        contents.push_str(&format!(
+            "{} {} {}\n",
+            version, entry.checksum, entry.description
+        ));
+    }
+    fs::write(path, contents).map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let update = std::env::args().any(|arg| arg == "--update");
+    let migrations = migrations::migrations();
+    let mut problems = Vec::new();
+    let mut notes = Vec::new();
+
+    // Versions must be strictly increasing, without duplicates or gaps
+    let mut seen = HashSet::new();
+    let mut previous: Option<i64> = None;
+    for migration in &migrations {
+        let version = migration.version;
+        if !seen.insert(version) {
+            problems.push(format!("Duplicate migration version {}", version));
+        }
+        if let Some(previous) = previous {
+            if version <= previous {
+                problems.push(format!(
+                    "Migration {} comes after {}, versions must be strictly increasing",
+                    version, previous
+                ));
+            } else {
+                for missing in previous + 1..version {
+                    if KNOWN_GAPS.contains(&missing) {
+                        notes.push(format!("Version {} is skipped (known gap)", missing));
+                    } else {
+                        problems.push(format!(
+                            "Version {} is missing between {} and {}",
+                            missing, previous, version
+                        ));
+                    }
+                }
+            }
+        }
+        previous = Some(previous.map_or(version, |previous| previous.max(version)));
+
+        if !matches!(migration.kind, MigrationKind::Up) {
+            problems.push(format!(
+                "Migration {} is not an Up migration, which the migration runner ignores",
+                version
+            ));
+        }
+        if migration.description.trim().is_empty() {
+            problems.push(format!("Migration {} has no description", version));
+        }
+    }
+
+    // Released migrations must never change
+    let manifest_path = Path::new(MANIFEST_PATH);
+    let mut manifest = match read_manifest(manifest_path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", manifest_path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut added = Vec::new();
+    for migration in &migrations {
+        let actual = checksum(migration.sql);
+        match manifest.get(&migration.version) {
+            Some(entry) if entry.checksum != actual => problems.push(format!(
+                "Migration {} ({}) was modified after it was recorded. Never change a previous migration, add a new one instead",
+                migration.version, entry.description
+            )),
+            Some(_) => {}
+            None => added.push((migration.version, actual, migration.description)),
+        }
+    }
+    for version in manifest.keys() {
+        if !seen.contains(version) {
+            problems.push(format!(
+                "Migration {} is recorded in the manifest but was removed",
+                version
+            ));
+        }
+    }
+    if !added.is_empty() {
+        if update {
+            for (version, checksum, description) in &added {
+                notes.push(format!("Recorded migration {}", version));
+                manifest.insert(
+                    *version,
+                    ManifestEntry {
+                        checksum: checksum.clone(),
+                        description: description.to_string(),
+                    },
+                );
+            }
+        } else {
+            for (version, _, _) in &added {
+                problems.push(format!(
+                    "Migration {} is not recorded in {}, run with --update to add it",
+                    version,
+                    manifest_path.display()
+                ));
+            }
+        }
+    }
+
+    // Every migration must apply cleanly, with foreign keys enforced like at
+    // runtime
+    let mut conn = match Connection::open_in_memory() {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("Failed to open in-memory database: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(e) = conn.pragma_update(None, "foreign_keys", "ON") {
+        eprintln!("Failed to enable foreign keys: {}", e);
+        return ExitCode::FAILURE;
+    }
+    for migration in &migrations {
+        let result = conn.transaction().and_then(|tx| {
+            tx.execute_batch(migration.sql)?;
+            tx.commit()
+        });
+        if let Err(e) = result {
+            problems.push(format!(
+                "Migration {} ({}) fails to apply: {}",
+                migration.version, migration.description, e
+            ));
+        }
+    }
+
+    for note in &notes {
+        println!("note: {}", note);
+    }
+
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("error: {}", problem);
+        }
+        eprintln!(
+            "\n{} problem(s) found in {} migrations",
+            problems.len(),
+            migrations.len()
+        );
+        return ExitCode::FAILURE;
+    }
+
+    if update && !added.is_empty() {
+        if let Err(e) = write_manifest(manifest_path, &manifest) {
+            eprintln!("Failed to write {}: {}", manifest_path.display(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    println!("All {} migrations look good", migrations.len());
+    ExitCode::SUCCESS
+}
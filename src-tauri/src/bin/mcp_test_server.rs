@@ -0,0 +1,423 @@
+// A stand-in for a remote MCP server, for trying out the HTTP transports
+// without a real one. It speaks just enough HTTP and MCP for the client:
+// initialize, ping, and a couple of tools.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "Usage: mcp_test_server [--port <port>] [--token <token>] [--sse-responses]
+
+Serves a test MCP server on localhost.
+
+  Streamable HTTP:  http://127.0.0.1:<port>/mcp
+  SSE:              http://127.0.0.1:<port>/sse
+
+Tools:
+  echo            Returns its `text` argument.
+  end_session     Forgets the current session, so the client has to
+                  reconnect.
+
+Options:
+      --port <port>    Port to listen on. Defaults to 3920.
+      --token <token>  Require `Authorization: Bearer <token>`.
+      --sse-responses  Answer Streamable HTTP requests with an event stream
+                       instead of a JSON body.
+  -h, --help           Show this message.";
+
+const DEFAULT_PORT: u16 = 3920;
+const SERVER_NAME: &str = "chorus-test-server";
+const DEFAULT_PROTOCOL_VERSION: &str = "2025-03-26";
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+struct Options {
+    port: u16,
+    token: Option<String>,
+    sse_responses: bool,
+}
+
+#[derive(Default)]
+struct Session {
+    // Where responses go for the SSE transport
+    stream: Option<TcpStream>,
+}
+
+struct Server {
+    options: Options,
+    sessions: Mutex<HashMap<String, Session>>,
+    next_session: AtomicU64,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+}
+
+fn parse_args() -> Result<Option<Options>, String> {
+    let mut options = Options {
+        port: DEFAULT_PORT,
+        token: None,
+        sse_responses: false,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                let port = args.next().ok_or("--port needs a value")?;
+                options.port = port
+                    .parse()
+                    .map_err(|_| format!("Invalid port: {}", port))?;
+            }
+            "--token" => options.token = Some(args.next().ok_or("--token needs a value")?),
+            "--sse-responses" => options.sse_responses = true,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    Ok(Some(options))
+}
+
+fn read_request(reader: &mut BufReader<TcpStream>) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: query.to_string(),
+        headers: HashMap::new(),
+        body: Vec::new(),
+    };
+
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            request
+                .headers
+                .insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let length = request
+        .header("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body)?;
+    Ok(Some(request))
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+// Starts an event stream. It has no length and ends when the connection closes.
+fn start_event_stream(stream: &mut TcpStream, headers: &[(&str, &str)]) -> io::Result<()> {
+    let mut head =
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n"
+            .to_string();
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.flush()
+}
+
+fn write_event(stream: &mut TcpStream, event: &str, data: &str) -> io::Result<()> {
+    write!(stream, "event: {}\ndata: {}\n\n", event, data)?;
+    stream.flush()
+}
+
+// Holds an event stream open until the client goes away
+fn keep_alive(stream: &mut TcpStream) {
+    loop {
+        std::thread::sleep(KEEPALIVE_INTERVAL);
+        if stream
+            .write_all(b": keepalive\n\n")
+            .and_then(|_| stream.flush())
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+impl Server {
+    fn new_session(&self) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos())
+            .unwrap_or_default();
+        let count = self.next_session.fetch_add(1, Ordering::Relaxed);
+        let id = format!("{:x}-{}", nanos, count);
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(id.clone(), Session::default());
+        id
+    }
+
+    fn has_session(&self, id: &str) -> bool {
+        self.sessions.lock().unwrap().contains_key(id)
+    }
+
+    fn end_session(&self, id: &str) {
+        self.sessions.lock().unwrap().remove(id);
+    }
+
+    fn authorized(&self, request: &Request) -> bool {
+        match &self.options.token {
+            Some(token) => request.header("authorization") == Some(&format!("Bearer {}", token)),
+            None => true,
+        }
+    }
+
+    /// Handles one JSON-RPC message. Returns the response to a request.
+    fn handle_message(&self, session: &str, message: &Value) -> Option<Value> {
+        let id = message.get("id")?;
+        let method = message.get("method").and_then(Value::as_str)?;
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        println!("[{}] {}", session, method);
+
+        let result = match method {
+            "initialize" => json!({
+                "protocolVersion": params
+                    .get("protocolVersion")
+                    .and_then(Value::as_str)
+                    .unwrap_or(DEFAULT_PROTOCOL_VERSION),
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": SERVER_NAME, "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "ping" => json!({}),
+            "tools/list" => json!({
+                "tools": [
+                    {
+                        "name": "echo",
+                        "description": "Returns the text it's given",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": { "text": { "type": "string" } },
+                            "required": ["text"],
+                        },
+                    },
+                    {
+                        "name": "end_session",
+                        "description": "Ends the session, so the client has to reconnect",
+                        "inputSchema": { "type": "object", "properties": {} },
+                    },
+                ],
+            }),
+            "tools/call" => {
+                let name = params.get("name").and_then(Value::as_str).unwrap_or("");
+                let text = match name {
+                    "echo" => params
+                        .pointer("/arguments/text")
+                        .and_then(Value::as_str)
+                        .unwrap_or("")
+                        .to_string(),
+                    "end_session" => {
+                        self.end_session(session);
+                        "The session has ended".to_string()
+                    }
+                    _ => return Some(error_response(id, -32602, "Unknown tool")),
+                };
+                json!({ "content": [{ "type": "text", "text": text }] })
+            }
+            _ => return Some(error_response(id, -32601, "Method not found")),
+        };
+        Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    fn handle(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let Some(request) = read_request(&mut reader)? else {
+            return Ok(());
+        };
+        if !self.authorized(&request) {
+            return respond(&mut stream, "401 Unauthorized", &[], b"Unauthorized");
+        }
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/mcp") => self.post_streamable(&mut stream, &request),
+            ("GET", "/mcp") => self.get_streamable(&mut stream, &request),
+            ("DELETE", "/mcp") => {
+                if let Some(session) = request.header("mcp-session-id") {
+                    self.end_session(session);
+                }
+                respond(&mut stream, "200 OK", &[], b"")
+            }
+            ("GET", "/sse") => self.open_sse(stream),
+            ("POST", "/messages") => self.post_sse(&mut stream, &request),
+            _ => respond(&mut stream, "404 Not Found", &[], b"Not found"),
+        }
+    }
+
+    fn post_streamable(&self, stream: &mut TcpStream, request: &Request) -> io::Result<()> {
+        let Ok(message) = serde_json::from_slice::<Value>(&request.body) else {
+            return respond(stream, "400 Bad Request", &[], b"Invalid JSON");
+        };
+        let is_initialize = message.get("method").and_then(Value::as_str) == Some("initialize");
+        let session = match request.header("mcp-session-id") {
+            _ if is_initialize => self.new_session(),
+            Some(session) if self.has_session(session) => session.to_string(),
+            Some(_) => return respond(stream, "404 Not Found", &[], b"Unknown session"),
+            None => return respond(stream, "400 Bad Request", &[], b"Missing session"),
+        };
+
+        let Some(response) = self.handle_message(&session, &message) else {
+            return respond(stream, "202 Accepted", &[], b"");
+        };
+        let headers = [("Mcp-Session-Id", session.as_str())];
+        let body = response.to_string();
+        if self.options.sse_responses {
+            start_event_stream(stream, &headers)?;
+            write_event(stream, "message", &body)
+        } else {
+            let mut headers = headers.to_vec();
+            headers.push(("Content-Type", "application/json"));
+            respond(stream, "200 OK", &headers, body.as_bytes())
+        }
+    }
+
+    fn get_streamable(&self, stream: &mut TcpStream, request: &Request) -> io::Result<()> {
+        match request.header("mcp-session-id") {
+            Some(session) if self.has_session(session) => {}
+            Some(_) => return respond(stream, "404 Not Found", &[], b"Unknown session"),
+            None => return respond(stream, "400 Bad Request", &[], b"Missing session"),
+        }
+        // Nothing is ever pushed, but the stream stays open like a real server's
+        start_event_stream(stream, &[])?;
+        keep_alive(stream);
+        Ok(())
+    }
+
+    fn open_sse(&self, mut stream: TcpStream) -> io::Result<()> {
+        let session = self.new_session();
+        start_event_stream(&mut stream, &[])?;
+        write_event(
+            &mut stream,
+            "endpoint",
+            &format!("/messages?sessionId={}", session),
+        )?;
+        if let Some(entry) = self.sessions.lock().unwrap().get_mut(&session) {
+            entry.stream = Some(stream.try_clone()?);
+        }
+        keep_alive(&mut stream);
+        self.end_session(&session);
+        Ok(())
+    }
+
+    fn post_sse(&self, stream: &mut TcpStream, request: &Request) -> io::Result<()> {
+        let session = request.query_param("sessionId").unwrap_or_default();
+        let events = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(session)
+            .and_then(|entry| entry.stream.as_ref())
+            .and_then(|stream| stream.try_clone().ok());
+        let Some(mut events) = events else {
+            return respond(stream, "404 Not Found", &[], b"Unknown session");
+        };
+        let Ok(message) = serde_json::from_slice::<Value>(&request.body) else {
+            return respond(stream, "400 Bad Request", &[], b"Invalid JSON");
+        };
+        respond(stream, "202 Accepted", &[], b"Accepted")?;
+        if let Some(response) = self.handle_message(session, &message) {
+            write_event(&mut events, "message", &response.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args() {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let listener = match TcpListener::bind(("127.0.0.1", options.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Couldn't listen on port {}: {}", options.port, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Streamable HTTP: http://127.0.0.1:{}/mcp", options.port);
+    println!("SSE:             http://127.0.0.1:{}/sse", options.port);
+
+    let server = Arc::new(Server {
+        options,
+        sessions: Mutex::new(HashMap::new()),
+        next_session: AtomicU64::new(0),
+    });
+    for stream in listener.incoming().flatten() {
+        let server = server.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = server.handle(stream) {
+                eprintln!("Connection failed: {}", e);
+            }
+        });
+    }
+    ExitCode::SUCCESS
+}
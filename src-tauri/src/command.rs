@@ -3,51 +3,43 @@ use crate::window::WebviewWindowExt;
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 #[cfg(not(target_os = "macos"))]
 use screenshots::Screen;
+use std::collections::HashMap;
 use tauri::{AppHandle, Emitter, Manager};
-#[cfg(target_os = "macos")]
-use tauri_nspanel::ManagerExt;
 
-use crate::SPOTLIGHT_LABEL;
+use crate::api::{self, ApiStatus};
+use crate::app_menu;
+use crate::backup::{self, BackupInfo};
+use crate::data_dir::{self, DataDirStatus};
+use crate::db::{EncryptionStatus, KeySource};
+use crate::deep_link::{self, DeepLink};
+use crate::global_actions::{self, Action};
+use crate::mcp::{McpSupervisor, ServerInfo, ServerParams};
+use crate::mcp_config::{
+    self, ConfigFormat, ConfigLocation, ExportResult, ImportPreview, ImportSelection,
+};
+use crate::profile_registry::Profile;
+use crate::profiles::{self, ActiveProfile, ProfileList};
+use crate::quick_chat::{self, QuickChatDraft};
+use crate::search::{self, SearchFilters, SearchPage};
+use crate::shutdown;
+use crate::single_instance;
+use crate::vault::{self, VaultStatus};
+use crate::{db, history, migrator, SPOTLIGHT_LABEL};
 
 // Target size in bytes (3.5MB) for image resizing
 // This is used as the maximum size for images in the application
 // and should match TARGET_IMAGE_SIZE_BYTES in src/ui/hooks/useAttachments.ts
 // Changing this value will affect the size of all images processed by the application
-const TARGET_SIZE_BYTES: u64 = 4_500_000;
+pub const TARGET_SIZE_BYTES: u64 = 4_500_000;
 
 #[tauri::command]
 pub fn show(app_handle: AppHandle) {
-    #[cfg(target_os = "macos")]
-    {
-        let panel = app_handle.get_webview_panel(SPOTLIGHT_LABEL).unwrap();
-        panel.show();
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    {
-        if let Some(window) = app_handle.get_window(SPOTLIGHT_LABEL) {
-            let _ = window.show();
-            let _ = window.set_focus();
-        }
-    }
+    quick_chat::show(&app_handle);
 }
 
 #[tauri::command]
 pub fn hide(app_handle: AppHandle) {
-    #[cfg(target_os = "macos")]
-    {
-        let panel = app_handle.get_webview_panel(SPOTLIGHT_LABEL).unwrap();
-        if panel.is_visible() {
-            panel.order_out(None);
-        }
-    }
-
-    #[cfg(not(target_os = "macos"))]
-    {
-        if let Some(window) = app_handle.get_window(SPOTLIGHT_LABEL) {
-            let _ = window.hide();
-        }
-    }
+    quick_chat::hide(&app_handle);
 }
 
 #[tauri::command]
@@ -61,9 +53,14 @@ pub fn open_in_main_window(app_handle: AppHandle, chat_id: String) {
     }
 }
 
+/// Starts a new chat in the quick chat window, with `draft` in it
 #[tauri::command]
-pub fn new_quick_chat(app_handle: AppHandle) {
-    app_handle.emit_to("main", "new_quick_chat", ()).unwrap();
+pub fn new_quick_chat(app_handle: AppHandle, draft: Option<QuickChatDraft>) {
+    let _ = app_handle.emit_to(
+        SPOTLIGHT_LABEL,
+        quick_chat::NEW_CHAT_EVENT,
+        draft.unwrap_or_default(),
+    );
 }
 
 #[tauri::command]
@@ -84,9 +81,14 @@ pub fn update_panel_theme(app_handle: AppHandle, is_dark_mode: bool) {
     }
 }
 
+// Named instances can run side by side, so their temp files are kept apart
+fn temp_path(app_handle: &AppHandle, file_name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}-{}", app_handle.config().identifier, file_name))
+}
+
 #[tauri::command]
 #[cfg(target_os = "macos")]
-pub fn capture_window() -> Result<String, String> {
+pub fn capture_window(app_handle: AppHandle) -> Result<String, String> {
     use std::fs;
     use std::process::Command;
     use std::time::Instant;
@@ -96,7 +98,7 @@ pub fn capture_window() -> Result<String, String> {
     println!("Starting window capture...");
 
     // Create a temporary file path
-    let raw_screenshot_path = std::env::temp_dir().join("screenshot_raw.png");
+    let raw_screenshot_path = temp_path(&app_handle, "screenshot_raw.png");
 
     // Run screencapture command
     let capture_time = Instant::now();
@@ -160,7 +162,7 @@ pub fn capture_whole_screen(app_handle: AppHandle) -> Result<String, String> {
     println!("Starting screenshot capture...");
 
     // Create temporary file path for raw screenshot
-    let raw_screenshot_path = std::env::temp_dir().join("screenshot_raw.png");
+    let raw_screenshot_path = temp_path(&app_handle, "screenshot_raw.png");
 
     // Get information about the current window
     if let Some(window) = app_handle.get_webview_window(SPOTLIGHT_LABEL) {
@@ -170,7 +172,7 @@ pub fn capture_whole_screen(app_handle: AppHandle) -> Result<String, String> {
 
             // First, get the main display bounds to determine if we're on a secondary display
             // Use a temporary script to get this info
-            let script_path = std::env::temp_dir().join("display_info.sh");
+            let script_path = temp_path(&app_handle, "display_info.sh");
             let script_content = r#"#!/bin/bash
 /usr/sbin/system_profiler SPDisplaysDataType | grep -A 15 "Display Type: Built-in" | grep "Resolution:" | head -n 1 | awk -F': ' '{print $2}' | sed 's/ Retina//' | awk -F' x ' '{print $1, $2}'
 "#;
@@ -352,8 +354,7 @@ pub fn capture_whole_screen(app_handle: AppHandle) -> Result<String, String> {
                     let pixels = image.as_raw();
 
                     // Create a temporary file to save the raw screenshot
-                    let temp_dir = std::env::temp_dir();
-                    let raw_screenshot_path = temp_dir.join("screenshot_raw.png");
+                    let raw_screenshot_path = temp_path(&app_handle, "screenshot_raw.png");
 
                     // Create a buffer to store the image data
                     let compress_time = Instant::now();
@@ -419,8 +420,7 @@ pub fn capture_whole_screen(app_handle: AppHandle) -> Result<String, String> {
     let pixels = image.as_raw();
 
     // Create a temporary file to save the raw screenshot
-    let temp_dir = std::env::temp_dir();
-    let raw_screenshot_path = temp_dir.join("screenshot_raw.png");
+    let raw_screenshot_path = temp_path(&app_handle, "screenshot_raw.png");
 
     // Create a buffer to store the image data
     let compress_time = Instant::now();
@@ -722,11 +722,8 @@ pub fn open_screen_recording_settings() -> Result<(), String> {
 
 #[tauri::command]
 pub fn get_instance_name() -> Result<String, String> {
-    // Get the instance name from the environment variable set by our script
-    match std::env::var("CHORUS_INSTANCE_NAME") {
-        Ok(name) => Ok(name),
-        Err(_) => Ok("".to_string()), // Return empty string if not set
-    }
+    // Empty unless this is a named instance (see script/dev-instance.sh)
+    Ok(history::instance_name().unwrap_or_default())
 }
 
 #[tauri::command]
@@ -770,3 +767,399 @@ pub fn get_file_metadata(path: String) -> Result<serde_json::Value, String> {
         "isDirectory": metadata.is_dir()
     }))
 }
+
+#[tauri::command]
+pub async fn search_messages(
+    app_handle: AppHandle,
+    query: String,
+    filters: Option<SearchFilters>,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> Result<SearchPage, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let conn = db::open_read_only(&app_handle)?;
+        search::search(
+            &conn,
+            &query,
+            filters.unwrap_or_default(),
+            cursor.as_deref(),
+            limit,
+        )
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn create_backup(app_handle: AppHandle) -> Result<BackupInfo, String> {
+    tauri::async_runtime::spawn_blocking(move || backup::create(&app_handle))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub fn list_backups(app_handle: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    backup::list(&app_handle)
+}
+
+#[tauri::command]
+pub async fn restore_backup(app_handle: AppHandle, file_name: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || backup::restore(&app_handle, &file_name))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn wait_for_migrations(app_handle: AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || migrator::wait(&app_handle))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub fn vault_status(app_handle: AppHandle) -> Result<VaultStatus, String> {
+    vault::status(&app_handle)
+}
+
+#[tauri::command]
+pub async fn unlock_vault(app_handle: AppHandle, password: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || vault::unlock(&app_handle, &password))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Protects the vault with a password, or goes back to the machine key if
+/// `password` is None.
+#[tauri::command]
+pub async fn set_vault_password(
+    app_handle: AppHandle,
+    password: Option<String>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        vault::set_password(&app_handle, password.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Stores a secret and returns the reference to save in its place.
+#[tauri::command]
+pub async fn put_secret(
+    app_handle: AppHandle,
+    name: String,
+    value: String,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        vault::put(&app_handle, &name, &value)?;
+        Ok(vault::to_ref(&name))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_secret(app_handle: AppHandle, name: String) -> Result<Option<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || vault::get(&app_handle, &name))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn delete_secret(app_handle: AppHandle, name: String) -> Result<bool, String> {
+    tauri::async_runtime::spawn_blocking(move || vault::delete(&app_handle, &name))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn list_secrets(app_handle: AppHandle) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || vault::list(&app_handle))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn resolve_secret_refs(
+    app_handle: AppHandle,
+    values: HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    tauri::async_runtime::spawn_blocking(move || vault::resolve(&app_handle, values))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub fn database_encryption_status(app_handle: AppHandle) -> Result<EncryptionStatus, String> {
+    db::encryption_status(&app_handle)
+}
+
+#[tauri::command]
+pub async fn unlock_database(app_handle: AppHandle, passphrase: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || db::unlock(&app_handle, &passphrase))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Encrypts the chat database and its backups, then restarts the app. The
+/// webview must close its database connection before calling this.
+#[tauri::command]
+pub async fn encrypt_database(app_handle: AppHandle, key: KeySource) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || db::encrypt(&app_handle, key))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Re-encrypts the chat database with a new key, then restarts the app.
+#[tauri::command]
+pub async fn rekey_database(app_handle: AppHandle, key: KeySource) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || db::rekey(&app_handle, key))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Turns encryption off again, then restarts the app.
+#[tauri::command]
+pub async fn decrypt_database(app_handle: AppHandle) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || db::decrypt(&app_handle))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Registers an MCP server for the calling window. Messages from the server
+/// are delivered on `on_message`. Without `params`, the server is looked up
+/// by name in the custom toolsets.
+#[tauri::command]
+pub async fn mcp_start(
+    app_handle: AppHandle,
+    window: tauri::Window,
+    name: String,
+    params: Option<ServerParams>,
+    on_message: tauri::ipc::Channel<serde_json::Value>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app_handle.state::<McpSupervisor>().start(
+            &app_handle,
+            window.label(),
+            &name,
+            params,
+            on_message,
+        )
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn mcp_send(
+    app_handle: AppHandle,
+    window: tauri::Window,
+    name: String,
+    message: serde_json::Value,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app_handle
+            .state::<McpSupervisor>()
+            .send(window.label(), &name, message)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn mcp_stop(
+    app_handle: AppHandle,
+    window: tauri::Window,
+    name: String,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app_handle
+            .state::<McpSupervisor>()
+            .stop(window.label(), &name)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn mcp_restart(
+    app_handle: AppHandle,
+    window: tauri::Window,
+    name: String,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app_handle
+            .state::<McpSupervisor>()
+            .restart(window.label(), &name)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Recent stderr output of an MCP server.
+#[tauri::command]
+pub fn mcp_logs(
+    app_handle: AppHandle,
+    window: tauri::Window,
+    name: String,
+) -> Result<Vec<String>, String> {
+    app_handle
+        .state::<McpSupervisor>()
+        .logs(window.label(), &name)
+}
+
+#[tauri::command]
+pub async fn mcp_list(app_handle: AppHandle) -> Result<Vec<ServerInfo>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app_handle.state::<McpSupervisor>().list(&app_handle)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub fn mcp_config_locations(app_handle: AppHandle) -> Result<Vec<ConfigLocation>, String> {
+    mcp_config::locations(&app_handle)
+}
+
+#[tauri::command]
+pub async fn preview_mcp_import(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<ImportPreview, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        mcp_config::preview(&app_handle, std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn import_mcp_servers(
+    app_handle: AppHandle,
+    path: String,
+    selections: Vec<ImportSelection>,
+) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        mcp_config::import(&app_handle, std::path::Path::new(&path), selections)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub async fn export_mcp_servers(
+    app_handle: AppHandle,
+    format: ConfigFormat,
+    names: Option<Vec<String>>,
+    path: String,
+) -> Result<ExportResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        mcp_config::export(&app_handle, format, names, std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub fn local_api_status(app_handle: AppHandle) -> Result<ApiStatus, String> {
+    api::status(&app_handle)
+}
+
+#[tauri::command]
+pub fn regenerate_local_api_token(app_handle: AppHandle) -> Result<String, String> {
+    api::regenerate_token(&app_handle)
+}
+
+/// Deep links that arrived before the calling window was listening
+#[tauri::command]
+pub fn take_pending_deep_links(app_handle: AppHandle, window: tauri::Window) -> Vec<DeepLink> {
+    deep_link::take_pending(&app_handle, window.label())
+}
+
+#[tauri::command]
+pub fn get_active_profile(app_handle: AppHandle) -> Result<ActiveProfile, String> {
+    profiles::active_profile(&app_handle)
+}
+
+#[tauri::command]
+pub fn list_profiles(app_handle: AppHandle) -> Result<ProfileList, String> {
+    profiles::list(&app_handle)
+}
+
+#[tauri::command]
+pub fn create_profile(app_handle: AppHandle, name: String) -> Result<Profile, String> {
+    profiles::create(&app_handle, &name)
+}
+
+#[tauri::command]
+pub fn rename_profile(app_handle: AppHandle, id: String, name: String) -> Result<Profile, String> {
+    profiles::rename(&app_handle, &id, &name)
+}
+
+#[tauri::command]
+pub async fn delete_profile(app_handle: AppHandle, id: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || profiles::delete(&app_handle, &id))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Windows reload once the switch is done, so the caller's database
+/// connection has to be closed beforehand
+#[tauri::command]
+pub async fn switch_profile(app_handle: AppHandle, id: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || profiles::switch(&app_handle, &id))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[tauri::command]
+pub fn get_data_directory(app_handle: AppHandle) -> Result<DataDirStatus, String> {
+    data_dir::status(&app_handle)
+}
+
+/// Copies the app's data to `path` and restarts the app using it. The
+/// webview must close its database connection before calling this.
+#[tauri::command]
+pub async fn move_data_directory(app_handle: AppHandle, path: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || data_dir::move_to(&app_handle, &path))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Checks the action's new shortcut and swaps it in for the old one. An empty
+/// shortcut removes the action's. The webview saves it in the settings only
+/// if this succeeds.
+#[tauri::command]
+pub fn set_global_shortcut(
+    app_handle: AppHandle,
+    action: Action,
+    shortcut: String,
+) -> Result<(), String> {
+    global_actions::set_shortcut(&app_handle, action, &shortcut)
+}
+
+/// Tells the app menu which chat the main window has open, or that it has
+/// none, and rebuilds it. Also called after the webview changes the chats,
+/// projects or selected models itself.
+#[tauri::command]
+pub async fn update_app_menu(
+    app_handle: AppHandle,
+    current_chat_id: Option<String>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || app_menu::update(&app_handle, current_chat_id))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Called by each webview once it has finished its database writes and closed
+/// its connection, after the app asked it to with `quit_requested`
+#[tauri::command]
+pub fn ready_to_quit(app_handle: AppHandle, window: tauri::Window) -> Result<(), String> {
+    shutdown::webview_ready(&app_handle, window.label())
+}
+
+/// Restarts the app, like after an update was installed
+#[tauri::command]
+pub fn restart_app(app_handle: AppHandle) {
+    single_instance::restart(&app_handle);
+}
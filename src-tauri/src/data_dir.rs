@@ -0,0 +1,393 @@
+// The app's side of data_location.rs: which directories the app uses, and
+// moving them somewhere else. A move copies everything into the new
+// directory, checks each copy against the original's checksum, points the
+// attachments at their new paths and restarts the app. The old files are left
+// where they were, in case anything went wrong.
+
+use rusqlite::backup::Backup;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::data_location::{self, DataLocation, Source};
+use crate::db_encryption::KeyringSlot;
+use crate::profile_registry::{self, Registry};
+use crate::{backup, db, db_encryption, migrator, single_instance, vault};
+
+// They belong to the running app or to the old location, not to the data
+const SKIPPED_FILES: [&str; 3] = [
+    single_instance::LOCK_FILE_NAME,
+    single_instance::ADDRESS_FILE_NAME,
+    data_location::LOCATION_FILE_NAME,
+];
+// Kept in each profile's data dir, and loaded by the webview through the
+// asset protocol
+const ASSET_DIRS: [&str; 2] = ["uploads", "generated_images"];
+// Files SQLite keeps next to a database. The databases are copied with the
+// backup API instead, which folds these into the copy.
+const DATABASE_SUFFIXES: [&str; 4] = ["", "-wal", "-shm", "-journal"];
+
+/// Found once at startup. The app keeps using the same directories until it
+/// restarts, even if the setting changes.
+pub struct DataDirState {
+    location: DataLocation,
+}
+
+impl DataDirState {
+    pub fn new(location: DataLocation) -> Self {
+        Self { location }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataDirStatus {
+    /// Holds the databases, backups and the vault
+    pub config_dir: String,
+    /// Holds the stores, uploads and generated images. It's the same as
+    /// config_dir unless the app uses its default dirs.
+    pub data_dir: String,
+    pub source: Source,
+    pub movable: bool,
+}
+
+fn location(app_handle: &AppHandle) -> DataLocation {
+    app_handle.state::<DataDirState>().location.clone()
+}
+
+fn default_config_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_config_dir()
+        .map_err(|e| e.to_string())
+}
+
+/// Replaces the app config dir everywhere
+pub fn config_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    match location(app_handle).root {
+        Some(root) => Ok(root),
+        None => default_config_dir(app_handle),
+    }
+}
+
+/// Replaces the app data dir everywhere
+pub fn data_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    match location(app_handle).root {
+        Some(root) => Ok(root),
+        None => app_handle.path().app_data_dir().map_err(|e| e.to_string()),
+    }
+}
+
+pub fn status(app_handle: &AppHandle) -> Result<DataDirStatus, String> {
+    let location = location(app_handle);
+    Ok(DataDirStatus {
+        config_dir: config_dir(app_handle)?.to_string_lossy().to_string(),
+        data_dir: data_dir(app_handle)?.to_string_lossy().to_string(),
+        source: location.source,
+        movable: location.is_movable(),
+    })
+}
+
+/// Lets the webview load a profile's uploads and generated images from a
+/// directory that the asset scope in tauri.conf.json doesn't cover
+pub fn allow_assets(app_handle: &AppHandle, profile_data_dir: &Path) {
+    if location(app_handle).root.is_none() {
+        return;
+    }
+    for dir in ASSET_DIRS {
+        let dir = profile_data_dir.join(dir);
+        if let Err(e) = app_handle
+            .asset_protocol_scope()
+            .allow_directory(&dir, true)
+        {
+            println!("Failed to allow assets in {}: {}", dir.display(), e);
+        }
+    }
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn checksum(path: &Path) -> Result<Vec<u8>, String> {
+    let mut file =
+        File::open(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(hasher.finalize().to_vec())
+}
+
+// Copies everything under `from` into `to` except the files in `skipped`,
+// checking each file against the original. Fails rather than overwrite a file,
+// which can only happen if the app config and data dirs both have it.
+fn copy_tree(from: &Path, to: &Path, skipped: &[PathBuf]) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+    let entries =
+        fs::read_dir(from).map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name();
+        if SKIPPED_FILES.iter().any(|skipped| name == *skipped) {
+            continue;
+        }
+        let source = entry.path();
+        if skipped.contains(&source) {
+            continue;
+        }
+        let target = to.join(&name);
+        let file_type = entry.file_type().map_err(|e| e.to_string())?;
+        if file_type.is_dir() {
+            copy_tree(&source, &target, skipped)?;
+        } else if file_type.is_file() {
+            if target.exists() {
+                return Err(format!("{} would overwrite another file", source.display()));
+            }
+            fs::copy(&source, &target)
+                .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+            if checksum(&source)? != checksum(&target)? {
+                return Err(format!(
+                    "The copy of {} doesn't match the original",
+                    source.display()
+                ));
+            }
+        } else {
+            println!("Not copying {}, which isn't a file", source.display());
+        }
+    }
+    Ok(())
+}
+
+// Attachments are stored with absolute paths, so the ones under the old data
+// dir are pointed at the same files under the new one
+fn rewrite_attachment_paths(db_path: &Path, old_dir: &Path, new_dir: &Path) -> Result<(), String> {
+    let conn = match db_encryption::find_key(db_path)? {
+        Some(key) => db_encryption::open(db_path, Some(&key), OpenFlags::default())?,
+        // Not encrypted, or only the active profile's passphrase opens it
+        None => db::open_copy(db_path, OpenFlags::default())?,
+    };
+    conn.execute(
+        "UPDATE attachments SET path = ?2 || substr(path, length(?1) + 1)
+         WHERE substr(path, 1, length(?1)) = ?1
+           AND substr(path, length(?1) + 1, 1) IN ('/', '\\')",
+        params![old_dir.to_string_lossy(), new_dir.to_string_lossy()],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Hashes every row of every table. The copy of a database differs from the
+// original byte for byte, since the WAL is folded into it and encrypted pages
+// get new IVs, but its rows have to be the same. The backup API copies the
+// pages as they are, so both are read in the same order.
+fn content_checksum(conn: &Connection) -> Result<Vec<u8>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND sql NOT LIKE 'CREATE VIRTUAL TABLE%'
+             ORDER BY name",
+        )
+        .map_err(|e| e.to_string())?;
+    let tables = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut hasher = Sha256::new();
+    for table in tables {
+        hasher.update(table.as_bytes());
+        let mut stmt = conn
+            .prepare(&format!("SELECT * FROM \"{}\"", table.replace('"', "\"\"")))
+            .map_err(|e| e.to_string())?;
+        let columns = stmt.column_count();
+        let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            for i in 0..columns {
+                match row.get_ref(i).map_err(|e| e.to_string())? {
+                    ValueRef::Null => hasher.update([0u8]),
+                    ValueRef::Integer(value) => {
+                        hasher.update([1u8]);
+                        hasher.update(value.to_le_bytes());
+                    }
+                    ValueRef::Real(value) => {
+                        hasher.update([2u8]);
+                        hasher.update(value.to_bits().to_le_bytes());
+                    }
+                    ValueRef::Text(bytes) | ValueRef::Blob(bytes) => {
+                        hasher.update([3u8]);
+                        hasher.update((bytes.len() as u64).to_le_bytes());
+                        hasher.update(bytes);
+                    }
+                }
+            }
+        }
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+// Copies a database with SQLite's backup API, which takes what's still in the
+// WAL along, then checks the copy against the original. The copy gets the
+// original's key.
+fn copy_database(from: &Path, to: &Path) -> Result<(), String> {
+    let read_only = OpenFlags::SQLITE_OPEN_READ_ONLY;
+    let (source, mut dest) = match db_encryption::find_key(from)? {
+        Some(key) => (
+            db_encryption::open(from, Some(&key), read_only)?,
+            db_encryption::open(to, Some(&key), OpenFlags::default())?,
+        ),
+        // Only the active profile's passphrase opens it
+        None if db_encryption::is_encrypted(from)? => (
+            db::open_copy(from, read_only)?,
+            db::open_copy(to, OpenFlags::default())?,
+        ),
+        None => (
+            db_encryption::open(from, None, read_only)?,
+            db_encryption::open(to, None, OpenFlags::default())?,
+        ),
+    };
+    Backup::new(&source, &mut dest)
+        .and_then(|backup| backup.run_to_completion(256, Duration::from_millis(0), None))
+        .map_err(|e| format!("Failed to copy {}: {}", from.display(), e))?;
+    // A single file, like the other copies
+    dest.pragma_update(None, "journal_mode", "DELETE")
+        .map_err(|e| e.to_string())?;
+    if content_checksum(&source)? != content_checksum(&dest)? {
+        return Err(format!(
+            "The copy of {} doesn't match the original",
+            from.display()
+        ));
+    }
+    Ok(())
+}
+
+fn copy_data(app_handle: &AppHandle, registry: &Registry, dest: &Path) -> Result<(), String> {
+    let config_dir = config_dir(app_handle)?;
+    let data_dir = data_dir(app_handle)?;
+    let databases = registry
+        .profiles
+        .iter()
+        .map(|profile| profile.database())
+        .filter(|database| config_dir.join(database).exists())
+        .collect::<Vec<_>>();
+    let skipped = databases
+        .iter()
+        .flat_map(|database| {
+            DATABASE_SUFFIXES
+                .iter()
+                .map(|suffix| config_dir.join(format!("{}{}", database, suffix)))
+        })
+        .collect::<Vec<_>>();
+    copy_tree(&config_dir, dest, &skipped)?;
+    if data_dir.exists() && !same_dir(&config_dir, &data_dir) {
+        copy_tree(&data_dir, dest, &skipped)?;
+    }
+    // Keyring keys are stored per vault and database path
+    vault::copy_machine_keys(&config_dir, dest)?;
+
+    for database in &databases {
+        let old_path = config_dir.join(database);
+        let new_path = dest.join(database);
+        if let Some(parent) = new_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        copy_database(&old_path, &new_path)?;
+        if db_encryption::is_encrypted(&old_path)? {
+            db_encryption::copy_keyring_keys(&old_path, &new_path)?;
+        }
+        rewrite_attachment_paths(&new_path, &data_dir, dest).map_err(|e| {
+            format!(
+                "Failed to update the attachment paths in {}: {}",
+                new_path.display(),
+                e
+            )
+        })?;
+    }
+    data_location::save_setting(&default_config_dir(app_handle)?, dest)
+}
+
+// Undoes a move that failed halfway: removes the keys stored for the copies,
+// then the copies themselves
+fn remove_copy(registry: &Registry, dest: &Path, existed: bool) {
+    if let Err(e) = vault::remove_machine_keys(dest) {
+        println!(
+            "Failed to remove the vault key of {}: {}",
+            dest.display(),
+            e
+        );
+    }
+    for profile in &registry.profiles {
+        let path = dest.join(profile.database());
+        for slot in [KeyringSlot::Current, KeyringSlot::Pending] {
+            if let Err(e) = db_encryption::store_keyring_key(&path, slot, None) {
+                println!("Failed to remove the key of {}: {}", path.display(), e);
+            }
+        }
+    }
+    let _ = fs::remove_dir_all(dest);
+    if existed {
+        let _ = fs::create_dir(dest);
+    }
+}
+
+/// Copies all the app's files into `dest`, which must be empty or not exist
+/// yet, and restarts the app using it. The webview has to close its database
+/// connection first.
+pub fn move_to(app_handle: &AppHandle, dest: &str) -> Result<(), String> {
+    let location = location(app_handle);
+    if !location.is_movable() {
+        let reason = match location.source {
+            Source::Argument => format!("the {} argument", data_location::DATA_DIR_ARG),
+            Source::Environment => data_location::DATA_DIR_ENV.to_string(),
+            _ => "portable mode".to_string(),
+        };
+        return Err(format!(
+            "The data directory is set by {}, so it can't be moved from here",
+            reason
+        ));
+    }
+    let dest = PathBuf::from(dest);
+    if !dest.is_absolute() {
+        return Err("The new data directory must be an absolute path".to_string());
+    }
+    for dir in [config_dir(app_handle)?, data_dir(app_handle)?] {
+        if dest.starts_with(&dir) || dir.starts_with(&dest) {
+            return Err(format!(
+                "The new data directory can't contain or be inside {}",
+                dir.display()
+            ));
+        }
+    }
+    let existed = match fs::read_dir(&dest) {
+        Ok(mut entries) => {
+            if entries.next().is_some() {
+                return Err(format!("{} isn't empty", dest.display()));
+            }
+            true
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => false,
+        Err(e) => return Err(format!("Failed to read {}: {}", dest.display(), e)),
+    };
+
+    // Don't copy the database in the middle of a migration or a backup
+    migrator::wait(app_handle)?;
+    let registry = profile_registry::load(&config_dir(app_handle)?)?;
+    backup::paused(|| {
+        let result = copy_data(app_handle, &registry, &dest);
+        if result.is_err() {
+            remove_copy(&registry, &dest, existed);
+        }
+        result
+    })?;
+    single_instance::restart(app_handle);
+}
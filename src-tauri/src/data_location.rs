@@ -0,0 +1,146 @@
+// Where the app keeps its files. By default that's Tauri's app config and app
+// data dirs, but it can be one directory chosen with --data-dir, with
+// CHORUS_DATA_DIR, by running portably or by moving the data from the
+// settings. Nothing in here depends on Tauri, so the command line tools can
+// include this file with `#[path]` and find the same chats.db as the app.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const DATA_DIR_ENV: &str = "CHORUS_DATA_DIR";
+pub const DATA_DIR_ARG: &str = "--data-dir";
+/// A directory with this name next to the app makes it portable: everything
+/// is kept in there instead of the user's app dirs
+pub const PORTABLE_DIR_NAME: &str = "ChorusData";
+/// Written to the default app config dir when the data is moved, since that's
+/// the only place the app can look before it knows where the data is
+pub const LOCATION_FILE_NAME: &str = "data-location.json";
+
+/// What decided where the data is, in order of precedence
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Argument,
+    Environment,
+    Portable,
+    Setting,
+    Default,
+}
+
+#[derive(Clone)]
+pub struct DataLocation {
+    /// Holds everything when set. Otherwise the app uses its default dirs.
+    pub root: Option<PathBuf>,
+    pub source: Source,
+}
+
+impl DataLocation {
+    /// Moving only changes the setting, which an argument, the environment
+    /// variable and portable mode take precedence over
+    pub fn is_movable(&self) -> bool {
+        matches!(self.source, Source::Setting | Source::Default)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LocationFile {
+    path: PathBuf,
+}
+
+fn absolute(path: &Path) -> Result<PathBuf, String> {
+    std::path::absolute(path)
+        .map_err(|e| format!("Invalid data directory {}: {}", path.display(), e))
+}
+
+/// The value of `--data-dir <path>` or `--data-dir=<path>` among the arguments
+pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == DATA_DIR_ARG {
+            return args.next();
+        }
+        if let Some(path) = arg.strip_prefix(&format!("{}=", DATA_DIR_ARG)) {
+            return Some(path.to_string());
+        }
+    }
+    None
+}
+
+/// The portable data directory, if there's one next to the executable. On
+/// macOS it's looked for next to Chorus.app as well, since the executable is
+/// inside the bundle.
+pub fn portable_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    let mut candidates = vec![exe_dir];
+    if cfg!(target_os = "macos") {
+        // Chorus.app/Contents/MacOS/Chorus
+        candidates.extend(exe_dir.ancestors().nth(3));
+    }
+    candidates
+        .into_iter()
+        .map(|dir| dir.join(PORTABLE_DIR_NAME))
+        .find(|dir| dir.is_dir())
+}
+
+/// The directory the data was moved to, read from the default app config dir
+pub fn read_setting(default_config_dir: &Path) -> Result<Option<PathBuf>, String> {
+    let path = default_config_dir.join(LOCATION_FILE_NAME);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let location: LocationFile = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok(Some(location.path))
+}
+
+/// Remembers where the data was moved to, replacing the old file only once
+/// the new one is complete
+pub fn save_setting(default_config_dir: &Path, root: &Path) -> Result<(), String> {
+    fs::create_dir_all(default_config_dir).map_err(|e| e.to_string())?;
+    let location = LocationFile {
+        path: root.to_path_buf(),
+    };
+    let contents = serde_json::to_string_pretty(&location).map_err(|e| e.to_string())?;
+    let path = default_config_dir.join(LOCATION_FILE_NAME);
+    let temp_path = default_config_dir.join(format!("{}.tmp", LOCATION_FILE_NAME));
+    fs::write(&temp_path, contents).map_err(|e| e.to_string())?;
+    fs::rename(&temp_path, &path).map_err(|e| e.to_string())
+}
+
+/// Finds where the data is. `default_config_dir` is where the setting is
+/// kept, and can be None if it isn't known, in which case the setting is
+/// skipped.
+pub fn resolve(
+    default_config_dir: Option<&Path>,
+    arg: Option<&str>,
+) -> Result<DataLocation, String> {
+    let custom = |path: &Path, source| {
+        Ok(DataLocation {
+            root: Some(absolute(path)?),
+            source,
+        })
+    };
+    if let Some(path) = arg.filter(|path| !path.is_empty()) {
+        return custom(Path::new(path), Source::Argument);
+    }
+    if let Some(path) = std::env::var_os(DATA_DIR_ENV).filter(|path| !path.is_empty()) {
+        return custom(Path::new(&path), Source::Environment);
+    }
+    if let Some(dir) = portable_dir() {
+        return custom(&dir, Source::Portable);
+    }
+    if let Some(dir) = default_config_dir {
+        if let Some(path) = read_setting(dir)? {
+            return custom(&path, Source::Setting);
+        }
+    }
+    Ok(DataLocation {
+        root: None,
+        source: Source::Default,
+    })
+}
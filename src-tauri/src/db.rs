@@ -0,0 +1,280 @@
+use rusqlite::auto_extension::{init_auto_extension, register_auto_extension};
+use rusqlite::{ffi, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::raw::{c_char, c_int};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::db_encryption::{self, DbKey, KeyringSlot};
+use crate::{backup, migrator, profiles, single_instance};
+
+// The webview holds its own connection pool through tauri_plugin_sql, so wait
+// for its locks instead of failing straight away.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub const UNLOCKED_EVENT: &str = "database-unlocked";
+
+struct ActiveKey {
+    // Canonical path of the database the key belongs to
+    path: PathBuf,
+    key: DbKey,
+}
+
+// Key of the chat database once it's been unlocked. `key_hook` hands it to
+// every new connection to the database, including the ones tauri_plugin_sql
+// opens for the webview, which we have no other way to configure.
+static ACTIVE_KEY: Mutex<Option<ActiveKey>> = Mutex::new(None);
+static UNLOCKED: Condvar = Condvar::new();
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySourceKind {
+    Keyring,
+    Passphrase,
+}
+
+#[derive(Serialize)]
+pub struct EncryptionStatus {
+    pub encrypted: bool,
+    pub locked: bool,
+    pub key_source: Option<KeySourceKind>,
+}
+
+/// Where the key of a newly encrypted database comes from.
+#[derive(Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum KeySource {
+    /// A random key stored in the OS keyring, so the database opens without
+    /// asking.
+    Keyring,
+    /// A passphrase the user types every time the app starts.
+    Passphrase { passphrase: String },
+}
+
+impl KeySource {
+    fn into_key(self) -> Result<DbKey, String> {
+        match self {
+            KeySource::Keyring => Ok(DbKey::generate()),
+            KeySource::Passphrase { passphrase } if passphrase.is_empty() => {
+                Err("The passphrase can't be empty".to_string())
+            }
+            KeySource::Passphrase { passphrase } => Ok(DbKey::Passphrase(passphrase)),
+        }
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn apply_active_key(conn: Connection) -> rusqlite::Result<()> {
+    let Ok(active) = ACTIVE_KEY.lock() else {
+        return Ok(());
+    };
+    if let (Some(active), Some(path)) = (active.as_ref(), conn.path()) {
+        if same_file(Path::new(path), &active.path) {
+            // A failure shows up as "file is not a database" on first use
+            let _ = db_encryption::set_key(&conn, &active.key);
+        }
+    }
+    Ok(())
+}
+
+unsafe extern "C" fn key_hook(
+    db: *mut ffi::sqlite3,
+    pz_err_msg: *mut *mut c_char,
+    _: *const ffi::sqlite3_api_routines,
+) -> c_int {
+    init_auto_extension(db, pz_err_msg, apply_active_key)
+}
+
+/// Makes every connection opened from now on use the chat database's key once
+/// it's unlocked. Must run before tauri_plugin_sql connects.
+pub fn install_key_hook() -> Result<(), String> {
+    // Safe because the hook never opens a connection itself
+    unsafe { register_auto_extension(key_hook) }.map_err(|e| e.to_string())
+}
+
+fn active_key() -> Option<DbKey> {
+    ACTIVE_KEY
+        .lock()
+        .ok()
+        .and_then(|active| active.as_ref().map(|active| active.key.clone()))
+}
+
+// Checks the key against the database, then hands it to new connections
+fn activate(path: &Path, key: Option<DbKey>) -> Result<(), String> {
+    let active = match key {
+        Some(key) => {
+            db_encryption::open(path, Some(&key), OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            let path = fs::canonicalize(path).map_err(|e| e.to_string())?;
+            Some(ActiveKey { path, key })
+        }
+        None => None,
+    };
+    *ACTIVE_KEY.lock().map_err(|e| e.to_string())? = active;
+    UNLOCKED.notify_all();
+    Ok(())
+}
+
+/// Unlocks the database with a stored key if it's encrypted and still
+/// locked. Returns whether it can be opened.
+fn try_unlock(app_handle: &AppHandle) -> Result<bool, String> {
+    if active_key().is_some() {
+        return Ok(true);
+    }
+    let path = db_path(app_handle)?;
+    if !db_encryption::is_encrypted(&path)? {
+        return Ok(true);
+    }
+    match db_encryption::find_key(&path)? {
+        Some(key) => {
+            activate(&path, Some(key))?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Drops the key of the database that was open before switching profiles.
+/// The next one is unlocked on its own, like at startup.
+pub fn forget_key() -> Result<(), String> {
+    *ACTIVE_KEY.lock().map_err(|e| e.to_string())? = None;
+    Ok(())
+}
+
+/// Blocks until the database can be opened, which for a passphrase protected
+/// database means waiting for `unlock`.
+pub fn wait_until_unlocked(app_handle: &AppHandle) -> Result<(), String> {
+    if try_unlock(app_handle)? {
+        return Ok(());
+    }
+    println!("The chat database is encrypted, waiting for its passphrase");
+    let mut active = ACTIVE_KEY.lock().map_err(|e| e.to_string())?;
+    while active.is_none() {
+        active = UNLOCKED.wait(active).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn unlock(app_handle: &AppHandle, passphrase: &str) -> Result<(), String> {
+    let path = db_path(app_handle)?;
+    if db_encryption::is_encrypted(&path)? && active_key().is_none() {
+        activate(&path, Some(DbKey::Passphrase(passphrase.to_string())))?;
+    }
+    let _ = app_handle.emit(UNLOCKED_EVENT, ());
+    Ok(())
+}
+
+pub fn encryption_status(app_handle: &AppHandle) -> Result<EncryptionStatus, String> {
+    let encrypted = db_encryption::is_encrypted(&db_path(app_handle)?)?;
+    let locked = !try_unlock(app_handle)?;
+    let key_source = match active_key() {
+        _ if !encrypted => None,
+        Some(DbKey::Raw(_)) => Some(KeySourceKind::Keyring),
+        Some(DbKey::Passphrase(_)) | None => Some(KeySourceKind::Passphrase),
+    };
+    Ok(EncryptionStatus {
+        encrypted,
+        locked,
+        key_source,
+    })
+}
+
+/// The active profile's chat database
+pub fn db_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    profiles::db_path(app_handle)
+}
+
+fn unlocked_db_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    if !try_unlock(app_handle)? {
+        return Err("The chat database is encrypted and hasn't been unlocked".to_string());
+    }
+    db_path(app_handle)
+}
+
+pub fn open(app_handle: &AppHandle) -> Result<Connection, String> {
+    let conn = Connection::open(unlocked_db_path(app_handle)?).map_err(|e| e.to_string())?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+pub fn open_read_only(app_handle: &AppHandle) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(
+        unlocked_db_path(app_handle)?,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| e.to_string())?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// Opens another copy of the chat database, like a backup, with the live
+/// database's key. New files are encrypted if the live database is.
+pub fn open_copy(path: &Path, flags: OpenFlags) -> Result<Connection, String> {
+    let key = match db_encryption::is_encrypted(path)? {
+        false if path.exists() => None,
+        _ => active_key(),
+    };
+    db_encryption::open(path, key.as_ref(), flags)
+}
+
+/// Encrypts, rekeys or decrypts the chat database, along with its backups,
+/// then restarts the app. The webview has to close its connection first.
+fn change_key(app_handle: &AppHandle, new_key: Option<DbKey>) -> Result<(), String> {
+    backup::paused(|| {
+        let path = db_path(app_handle)?;
+        if !try_unlock(app_handle)? {
+            return Err("Unlock the database first".to_string());
+        }
+        let old_key = active_key();
+
+        db_encryption::store_keyring_key(&path, KeyringSlot::Pending, new_key.as_ref())?;
+        db_encryption::convert(&path, old_key.as_ref(), new_key.as_ref())?;
+        db_encryption::store_keyring_key(&path, KeyringSlot::Current, new_key.as_ref())?;
+        db_encryption::store_keyring_key(&path, KeyringSlot::Pending, None)?;
+        activate(&path, new_key.clone())?;
+
+        // Copies that are left behind would keep the chats readable with the
+        // old key, or not at all
+        let mut copies = backup::list(app_handle)?
+            .into_iter()
+            .map(|backup| PathBuf::from(backup.path))
+            .collect::<Vec<_>>();
+        copies.push(migrator::snapshot_path(app_handle)?);
+        for copy in copies.iter().filter(|copy| copy.exists()) {
+            if let Err(e) = db_encryption::convert(copy, old_key.as_ref(), new_key.as_ref()) {
+                println!("Failed to convert {}: {}", copy.display(), e);
+            }
+        }
+        Ok(())
+    })?;
+    single_instance::restart(app_handle);
+}
+
+pub fn encrypt(app_handle: &AppHandle, source: KeySource) -> Result<(), String> {
+    if db_encryption::is_encrypted(&db_path(app_handle)?)? {
+        return Err("The database is already encrypted".to_string());
+    }
+    change_key(app_handle, Some(source.into_key()?))
+}
+
+pub fn rekey(app_handle: &AppHandle, source: KeySource) -> Result<(), String> {
+    if !db_encryption::is_encrypted(&db_path(app_handle)?)? {
+        return Err("The database isn't encrypted".to_string());
+    }
+    change_key(app_handle, Some(source.into_key()?))
+}
+
+pub fn decrypt(app_handle: &AppHandle) -> Result<(), String> {
+    if !db_encryption::is_encrypted(&db_path(app_handle)?)? {
+        return Err("The database isn't encrypted".to_string());
+    }
+    change_key(app_handle, None)
+}
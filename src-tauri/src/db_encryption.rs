@@ -0,0 +1,248 @@
+// SQLCipher helpers for the chat database. Nothing in here depends on Tauri,
+// so the command line tools can include this file with `#[path]` and open an
+// encrypted database the same way the app does.
+
+use rand::RngCore;
+use rusqlite::{params, Connection, OpenFlags};
+use std::fs;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Plaintext SQLite files start with this header. SQLCipher encrypts the whole
+// file, header included, which is how an encrypted database is recognized.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+const KEYRING_SERVICE: &str = "Chorus chat database";
+
+// How long `open_read_only` waits for the app's writes
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lets the command line tools open a passphrase protected database.
+pub const PASSPHRASE_ENV: &str = "CHORUS_DB_PASSPHRASE";
+
+#[derive(Clone)]
+pub enum DbKey {
+    /// A random 256-bit key, hex encoded. It's kept in the OS keyring.
+    Raw(String),
+    /// Typed by the user and never stored. SQLCipher derives the key from it.
+    Passphrase(String),
+}
+
+impl DbKey {
+    pub fn generate() -> Self {
+        let mut key = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut key);
+        DbKey::Raw(hex::encode(key))
+    }
+
+    // The value for `PRAGMA key` and `ATTACH ... KEY`. SQLCipher skips key
+    // derivation for values in the x'...' form.
+    fn sql_value(&self) -> String {
+        match self {
+            DbKey::Raw(hex) => format!("x'{}'", hex),
+            DbKey::Passphrase(passphrase) => passphrase.clone(),
+        }
+    }
+}
+
+/// Whether `path` holds a SQLCipher database. A missing or empty file isn't
+/// encrypted, since SQLite only writes the header once there's a schema.
+pub fn is_encrypted(path: &Path) -> Result<bool, String> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut header = [0u8; 16];
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header != SQLITE_HEADER),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Where a raw key lives in the keyring. While the key is being changed, the
+/// new one is kept in the pending slot, so a crash halfway through can't leave
+/// a database that no stored key opens.
+#[derive(Clone, Copy)]
+pub enum KeyringSlot {
+    Current,
+    Pending,
+}
+
+// Keys are stored per database file, so instances with their own data
+// directory don't share one
+fn keyring_entry(db_path: &Path, slot: KeyringSlot) -> Result<keyring::Entry, String> {
+    let path = fs::canonicalize(db_path).unwrap_or_else(|_| db_path.to_path_buf());
+    let account = match slot {
+        KeyringSlot::Current => path.to_string_lossy().to_string(),
+        KeyringSlot::Pending => format!("{} (pending)", path.to_string_lossy()),
+    };
+    keyring::Entry::new(KEYRING_SERVICE, &account).map_err(|e| e.to_string())
+}
+
+fn keyring_key(db_path: &Path, slot: KeyringSlot) -> Result<Option<DbKey>, String> {
+    match keyring_entry(db_path, slot)?.get_password() {
+        Ok(hex) => Ok(Some(DbKey::Raw(hex))),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!(
+            "Couldn't read the database key from the keyring: {}",
+            e
+        )),
+    }
+}
+
+/// Stores a raw key in the OS keyring. Passing a passphrase or None removes
+/// the stored key instead, since passphrases are never stored.
+pub fn store_keyring_key(
+    db_path: &Path,
+    slot: KeyringSlot,
+    key: Option<&DbKey>,
+) -> Result<(), String> {
+    let entry = keyring_entry(db_path, slot)?;
+    match key {
+        Some(DbKey::Raw(hex)) => entry
+            .set_password(hex)
+            .map_err(|e| format!("Couldn't save the database key to the keyring: {}", e)),
+        _ => match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!(
+                "Couldn't remove the database key from the keyring: {}",
+                e
+            )),
+        },
+    }
+}
+
+/// Stores the keys kept for the database at `from` for its copy at `to` too
+pub fn copy_keyring_keys(from: &Path, to: &Path) -> Result<(), String> {
+    for slot in [KeyringSlot::Current, KeyringSlot::Pending] {
+        if let Some(key) = keyring_key(from, slot)? {
+            store_keyring_key(to, slot, Some(&key))?;
+        }
+    }
+    Ok(())
+}
+
+/// Sets the key on a connection that hasn't read anything yet. A wrong key
+/// only shows up on the first query; use `apply_key` to check it.
+pub fn set_key(conn: &Connection, key: &DbKey) -> Result<(), String> {
+    conn.pragma_update(None, "key", key.sql_value())
+        .map_err(|e| e.to_string())
+}
+
+pub fn apply_key(conn: &Connection, key: &DbKey) -> Result<(), String> {
+    set_key(conn, key)?;
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        .map_err(|_| "Couldn't decrypt the database, the key or passphrase is wrong".to_string())
+}
+
+pub fn open(path: &Path, key: Option<&DbKey>, flags: OpenFlags) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(path, flags).map_err(|e| e.to_string())?;
+    if let Some(key) = key {
+        apply_key(&conn, key)?;
+    }
+    Ok(conn)
+}
+
+/// Finds the key of an encrypted database without asking the user. The
+/// passphrase in `CHORUS_DB_PASSPHRASE` is tried first, then the keys in the
+/// OS keyring. Returns None if the database isn't encrypted or none of them
+/// open it.
+pub fn find_key(path: &Path) -> Result<Option<DbKey>, String> {
+    if !is_encrypted(path)? {
+        return Ok(None);
+    }
+    let mut candidates = Vec::new();
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        candidates.push(DbKey::Passphrase(passphrase));
+    }
+    candidates.extend(keyring_key(path, KeyringSlot::Current)?);
+    candidates.extend(keyring_key(path, KeyringSlot::Pending)?);
+    Ok(candidates
+        .into_iter()
+        .find(|key| open(path, Some(key), OpenFlags::SQLITE_OPEN_READ_ONLY).is_ok()))
+}
+
+/// Opens an existing database read-only, finding its key with `find_key`. The
+/// command line tools use it, while the app may be writing to the database.
+#[allow(dead_code)]
+pub fn open_read_only(path: &Path) -> Result<Connection, String> {
+    if !path.is_file() {
+        return Err(format!("{} doesn't exist", path.display()));
+    }
+    let key = find_key(path)?;
+    if key.is_none() && is_encrypted(path)? {
+        return Err(format!(
+            "{} is encrypted and no key in the OS keyring opens it. Set {} to its passphrase.",
+            path.display(),
+            PASSPHRASE_ENV
+        ));
+    }
+    let conn = open(
+        path,
+        key.as_ref(),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Rewrites the database at `path` with a different key. `None` stands for
+/// plaintext on either side, so this encrypts, rekeys and decrypts. The new
+/// copy is written next to the original and checked before replacing it.
+/// Nothing else may have the database open.
+pub fn convert(path: &Path, from: Option<&DbKey>, to: Option<&DbKey>) -> Result<(), String> {
+    let converted_path = with_suffix(path, ".converting");
+    let _ = fs::remove_file(&converted_path);
+
+    let result = (|| {
+        let conn = open(path, from, OpenFlags::default())?;
+        // Fold the WAL into the main file, since it's deleted below
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .map_err(|e| e.to_string())?;
+        // An empty key attaches a plaintext database
+        let key = to.map(DbKey::sql_value).unwrap_or_default();
+        conn.execute(
+            "ATTACH DATABASE ?1 AS converted KEY ?2",
+            params![converted_path.to_string_lossy(), key],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.query_row("SELECT sqlcipher_export('converted')", [], |_| Ok(()))
+            .map_err(|e| format!("Couldn't copy the database: {}", e))?;
+        conn.execute("DETACH DATABASE converted", [])
+            .map_err(|e| e.to_string())?;
+        drop(conn);
+
+        let converted = open(&converted_path, to, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let result: String = converted
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        if result != "ok" {
+            return Err(format!("Integrity check failed: {}", result));
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&converted_path);
+        return Err(e);
+    }
+
+    // Leftover WAL files would be replayed against the new file
+    for suffix in ["-wal", "-shm"] {
+        match fs::remove_file(with_suffix(path, suffix)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    fs::rename(&converted_path, path).map_err(|e| e.to_string())
+}
@@ -0,0 +1,403 @@
+// Routes chorus:// links. Each link is checked here and turned into a typed
+// event for the window that acts on it, so the frontend never parses URLs
+// itself. Links that arrive before a window is listening are held until it
+// asks for them.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::{command, global_actions, SPOTLIGHT_LABEL};
+
+pub const SCHEME: &str = "chorus";
+pub const EVENT: &str = "deep-link";
+const MAIN_LABEL: &str = "main";
+
+const MAX_PROMPT_CHARS: usize = 20_000;
+const MAX_MODELS: usize = 8;
+const MAX_ID_CHARS: usize = 200;
+const MAX_TOKEN_CHARS: usize = 500;
+// How much of a JSON export is read to tell who exported it
+const SNIFF_BYTES: u64 = 1024 * 1024;
+
+/// What a link asks for. Serialized with an `action` tag, which is what the
+/// frontend switches on (see DeepLinks.ts).
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action")]
+pub enum DeepLink {
+    /// chorus://new?prompt=...&models=...&project=...
+    ///
+    /// The prompt is put in the composer rather than sent, so a link can't
+    /// spend the user's credits without them seeing what it says.
+    #[serde(rename = "new", rename_all = "camelCase")]
+    NewChat {
+        prompt: Option<String>,
+        model_config_ids: Vec<String>,
+        project_id: Option<String>,
+    },
+    /// chorus://chat/<id>
+    #[serde(rename = "chat", rename_all = "camelCase")]
+    OpenChat { chat_id: String },
+    /// chorus://quick?prompt=...
+    #[serde(rename = "quick")]
+    QuickChat { prompt: Option<String> },
+    /// chorus://import?file=...&source=openai|anthropic
+    ///
+    /// Opens the import dialog with the file picked; nothing is imported
+    /// until the user confirms.
+    #[serde(rename = "import")]
+    Import { file: String, source: ImportSource },
+    /// chorus://slack?access_token=...&team_id=..., the end of Slack's OAuth flow
+    #[serde(rename = "slack", rename_all = "camelCase")]
+    SlackConnected {
+        access_token: String,
+        team_id: String,
+    },
+    /// chorus://github_integration?access_token=..., the end of GitHub's OAuth flow
+    #[serde(rename = "github", rename_all = "camelCase")]
+    GithubConnected { access_token: String },
+    /// A link that was refused. The reason never repeats parameter values,
+    /// since some of them are tokens.
+    #[serde(rename = "rejected")]
+    Rejected { reason: String },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportSource {
+    OpenAI,
+    Anthropic,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Windows whose current page has asked for its pending links, and so is
+    /// listening
+    ready: HashSet<String>,
+    pending: Vec<(String, DeepLink)>,
+}
+
+#[derive(Default)]
+pub struct DeepLinkState {
+    inner: Mutex<Inner>,
+}
+
+// The query parameters of a link, each allowed once
+struct Query {
+    pairs: Vec<(String, String)>,
+}
+
+impl Query {
+    fn parse(url: &Url, allowed: &[&str]) -> Result<Query, String> {
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        for (name, value) in url.query_pairs() {
+            if !allowed.contains(&name.as_ref()) {
+                return Err(format!("Unknown parameter '{}'", name));
+            }
+            if pairs.iter().any(|(seen, _)| *seen == name) {
+                return Err(format!("'{}' is given more than once", name));
+            }
+            pairs.push((name.into_owned(), value.into_owned()));
+        }
+        Ok(Query { pairs })
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn require(&self, name: &str) -> Result<&str, String> {
+        self.get(name)
+            .ok_or_else(|| format!("'{}' is required", name))
+    }
+}
+
+/// Chat and project ids are generated by the app, so anything outside their
+/// alphabet is a malformed link
+fn check_id<'a>(name: &str, id: &'a str) -> Result<&'a str, String> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_ID_CHARS
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("'{}' isn't a valid id", name));
+    }
+    Ok(id)
+}
+
+/// Model config ids look like `anthropic::claude-sonnet-4` or
+/// `openrouter::meta-llama/llama-3.1-70b`
+fn check_model_id(id: &str) -> Result<String, String> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_ID_CHARS
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:/@".contains(c));
+    if !valid {
+        return Err("'models' has an invalid model id".to_string());
+    }
+    Ok(id.to_string())
+}
+
+fn check_token(name: &str, token: &str) -> Result<String, String> {
+    let valid = token.len() <= MAX_TOKEN_CHARS && token.chars().all(|c| c.is_ascii_graphic());
+    if !valid {
+        return Err(format!("'{}' isn't a valid token", name));
+    }
+    Ok(token.to_string())
+}
+
+/// Drops control characters other than newlines and tabs, and refuses
+/// prompts too long to have been meant for the composer
+fn clean_prompt(prompt: Option<&str>) -> Result<Option<String>, String> {
+    let Some(prompt) = prompt else {
+        return Ok(None);
+    };
+    if prompt.chars().count() > MAX_PROMPT_CHARS {
+        return Err(format!(
+            "'prompt' is longer than {} characters",
+            MAX_PROMPT_CHARS
+        ));
+    }
+    let prompt: String = prompt
+        .chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect();
+    Ok(Some(prompt).filter(|prompt| !prompt.trim().is_empty()))
+}
+
+/// Exports are only read from a full path to an existing .json or .zip file
+fn check_import_file(file: &str) -> Result<String, String> {
+    let path = Path::new(file);
+    if !path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+        return Err("'file' must be a full path".to_string());
+    }
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    if !matches!(extension.as_deref(), Some("json" | "zip")) {
+        return Err("'file' must be a .json or .zip export".to_string());
+    }
+    let path = path
+        .canonicalize()
+        .map_err(|_| "'file' doesn't exist".to_string())?;
+    if !path.is_file() {
+        return Err("'file' isn't a file".to_string());
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Tells OpenAI and Anthropic exports apart by the keys their conversations
+/// have. Zip archives can't be looked into here, so those need `source`.
+fn detect_source(file: &str) -> Result<ImportSource, String> {
+    let unknown = || "Couldn't tell who exported 'file'. Add source=openai or source=anthropic.";
+    if !file.to_lowercase().ends_with(".json") {
+        return Err(unknown().to_string());
+    }
+    let mut start = Vec::new();
+    File::open(file)
+        .and_then(|file| file.take(SNIFF_BYTES).read_to_end(&mut start))
+        .map_err(|_| "'file' can't be read".to_string())?;
+    let start = String::from_utf8_lossy(&start);
+    if start.contains("\"chat_messages\"") {
+        Ok(ImportSource::Anthropic)
+    } else if start.contains("\"mapping\"") {
+        Ok(ImportSource::OpenAI)
+    } else {
+        Err(unknown().to_string())
+    }
+}
+
+/// Checks a link and says what it asks for
+pub fn parse(url: &Url) -> Result<DeepLink, String> {
+    if url.scheme() != SCHEME {
+        return Err(format!("Not a {}:// link", SCHEME));
+    }
+    if !url.username().is_empty() || url.password().is_some() || url.port().is_some() {
+        return Err("Links can't have credentials or a port".to_string());
+    }
+    let action = url.host_str().unwrap_or_default();
+    let path: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default();
+    if action != "chat" && !path.is_empty() {
+        return Err(format!("chorus://{} doesn't take a path", action));
+    }
+
+    match action {
+        "new" => {
+            let query = Query::parse(url, &["prompt", "models", "project"])?;
+            let model_config_ids = query
+                .get("models")
+                .map(|models| {
+                    models
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty())
+                        .map(check_model_id)
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .transpose()?
+                .unwrap_or_default();
+            if model_config_ids.len() > MAX_MODELS {
+                return Err(format!("'models' can list at most {} models", MAX_MODELS));
+            }
+            Ok(DeepLink::NewChat {
+                prompt: clean_prompt(query.get("prompt"))?,
+                model_config_ids,
+                project_id: query
+                    .get("project")
+                    .map(|project| check_id("project", project).map(str::to_string))
+                    .transpose()?,
+            })
+        }
+        "chat" => {
+            Query::parse(url, &[])?;
+            let [chat_id] = path.as_slice() else {
+                return Err("Use chorus://chat/<id>".to_string());
+            };
+            Ok(DeepLink::OpenChat {
+                chat_id: check_id("chat", chat_id)?.to_string(),
+            })
+        }
+        "quick" => {
+            let query = Query::parse(url, &["prompt"])?;
+            Ok(DeepLink::QuickChat {
+                prompt: clean_prompt(query.get("prompt"))?,
+            })
+        }
+        "import" => {
+            let query = Query::parse(url, &["file", "source"])?;
+            let file = check_import_file(query.require("file")?)?;
+            let source = match query.get("source") {
+                Some("openai") => ImportSource::OpenAI,
+                Some("anthropic") => ImportSource::Anthropic,
+                Some(_) => return Err("'source' must be openai or anthropic".to_string()),
+                None => detect_source(&file)?,
+            };
+            Ok(DeepLink::Import { file, source })
+        }
+        "slack" => {
+            let query = Query::parse(url, &["access_token", "team_id"])?;
+            Ok(DeepLink::SlackConnected {
+                access_token: check_token("access_token", query.require("access_token")?)?,
+                team_id: check_token("team_id", query.require("team_id")?)?,
+            })
+        }
+        "github_integration" => {
+            let query = Query::parse(url, &["access_token"])?;
+            Ok(DeepLink::GithubConnected {
+                access_token: check_token("access_token", query.require("access_token")?)?,
+            })
+        }
+        "" => Err("The link has no action".to_string()),
+        _ => Err(format!("Unknown action '{}'", action)),
+    }
+}
+
+/// Shows the window the link is for and hands it the link, or holds the
+/// link if the window isn't listening yet
+pub fn deliver(app_handle: &AppHandle, link: DeepLink) {
+    let label = match link {
+        DeepLink::QuickChat { .. } if app_handle.get_webview_window(SPOTLIGHT_LABEL).is_some() => {
+            command::show(app_handle.clone());
+            SPOTLIGHT_LABEL
+        }
+        // Without a quick chat window the prompt starts a regular chat
+        DeepLink::QuickChat { prompt } => {
+            return deliver(
+                app_handle,
+                DeepLink::NewChat {
+                    prompt,
+                    model_config_ids: Vec::new(),
+                    project_id: None,
+                },
+            );
+        }
+        _ => {
+            global_actions::show_main_window(app_handle);
+            MAIN_LABEL
+        }
+    };
+
+    let state = app_handle.state::<DeepLinkState>();
+    let mut inner = state.inner.lock().unwrap();
+    if inner.ready.contains(label) {
+        drop(inner);
+        if let Err(e) = app_handle.emit_to(label, EVENT, link) {
+            println!("Failed to send a deep link to {}: {}", label, e);
+        }
+    } else {
+        inner.pending.push((label.to_string(), link));
+    }
+}
+
+/// Routes links, from the OS or from another launch of the app
+pub fn handle_urls(app_handle: &AppHandle, urls: Vec<Url>) {
+    for url in urls {
+        let link = parse(&url).unwrap_or_else(|reason| {
+            // Only the action is logged, since the query may hold tokens
+            println!(
+                "Rejected deep link chorus://{}: {}",
+                url.host_str().unwrap_or_default(),
+                reason
+            );
+            DeepLink::Rejected { reason }
+        });
+        deliver(app_handle, link);
+    }
+}
+
+/// Links for a window that were held until it started listening. From now on
+/// its links are sent to it as events.
+pub fn take_pending(app_handle: &AppHandle, label: &str) -> Vec<DeepLink> {
+    let state = app_handle.state::<DeepLinkState>();
+    let mut inner = state.inner.lock().unwrap();
+    inner.ready.insert(label.to_string());
+    let (taken, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut inner.pending)
+        .into_iter()
+        .partition(|(window, _)| window == label);
+    inner.pending = rest;
+    taken.into_iter().map(|(_, link)| link).collect()
+}
+
+/// Called when a window starts loading a page or is destroyed. Its links are
+/// held again until the new page asks for them.
+pub fn forget_window(app_handle: &AppHandle, label: &str) {
+    let state = app_handle.state::<DeepLinkState>();
+    state.inner.lock().unwrap().ready.remove(label);
+}
+
+/// Routes the link the app was launched with, if any, and every link that
+/// arrives while it runs
+pub fn start(app_handle: AppHandle) {
+    // Installed builds register the scheme when they're installed, but on
+    // Linux and Windows a dev build has to do it itself
+    #[cfg(all(debug_assertions, any(target_os = "linux", windows)))]
+    if let Err(e) = app_handle.deep_link().register_all() {
+        println!("Failed to register the {} scheme: {}", SCHEME, e);
+    }
+
+    match app_handle.deep_link().get_current() {
+        Ok(Some(urls)) => handle_urls(&app_handle, urls),
+        Ok(None) => {}
+        Err(e) => println!("Failed to read the launch deep link: {}", e),
+    }
+
+    let handle = app_handle.clone();
+    app_handle
+        .deep_link()
+        .on_open_url(move |event| handle_urls(&handle, event.urls()));
+}
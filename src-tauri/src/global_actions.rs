@@ -0,0 +1,339 @@
+// Global shortcuts, which work while other apps have focus. Each action has
+// its own binding in the settings: quick chat's in `settings.quickChat`, the
+// others in `settings.globalShortcuts`, where a missing or empty one means the
+// action has no shortcut. The actions run here and reuse the commands the
+// webview calls.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::quick_chat::{self, QuickChatDraft};
+use crate::{command, profiles, shortcut};
+
+/// Sent to the main window to start a new chat there
+pub const NEW_CHAT_EVENT: &str = "global_new_chat";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Action {
+    /// Shows or hides the quick chat window
+    QuickChat,
+    /// Takes a screenshot and starts a quick chat with it attached
+    Screenshot,
+    /// Starts a quick chat with the clipboard's text as the draft
+    Clipboard,
+    /// Opens the main window to a new chat
+    NewChat,
+    /// Starts a quick chat quoting the selected text. Linux only, since only
+    /// X11 and Wayland have a selection other apps can read.
+    Selection,
+}
+
+impl Action {
+    const ALL: [Action; 5] = [
+        Action::QuickChat,
+        Action::Screenshot,
+        Action::Clipboard,
+        Action::NewChat,
+        Action::Selection,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Action::QuickChat => "Quick chat",
+            Action::Screenshot => "Screenshot and ask",
+            Action::Clipboard => "New quick chat with clipboard",
+            Action::NewChat => "New chat in main window",
+            Action::Selection => "Ask about selected text",
+        }
+    }
+
+    fn is_supported(self) -> bool {
+        self != Action::Selection || cfg!(target_os = "linux")
+    }
+}
+
+/// The registered shortcut of each action that has one
+#[derive(Default)]
+pub struct GlobalActionsState {
+    bindings: Mutex<HashMap<Action, Shortcut>>,
+}
+
+fn bindings(app_handle: &AppHandle) -> HashMap<Action, Shortcut> {
+    let state = app_handle.state::<GlobalActionsState>();
+    let bindings = state.bindings.lock();
+    bindings
+        .map(|bindings| bindings.clone())
+        .unwrap_or_default()
+}
+
+fn update_bindings(
+    app_handle: &AppHandle,
+    f: impl FnOnce(&mut HashMap<Action, Shortcut>),
+) -> Result<(), String> {
+    let state = app_handle.state::<GlobalActionsState>();
+    let mut bindings = state.bindings.lock().map_err(|e| e.to_string())?;
+    f(&mut bindings);
+    Ok(())
+}
+
+// `settings.globalShortcuts` in the settings store
+fn saved_shortcut(app_handle: &AppHandle, action: Action) -> String {
+    let key = match action {
+        Action::QuickChat => return quick_chat::saved_shortcut(app_handle),
+        Action::Screenshot => "screenshot",
+        Action::Clipboard => "clipboard",
+        Action::NewChat => "newChat",
+        Action::Selection => "selection",
+    };
+    profiles::settings_store(app_handle)
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|settings| {
+            settings
+                .get("globalShortcuts")
+                .and_then(|shortcuts| shortcuts.get(key))
+                .and_then(|shortcut| shortcut.as_str().map(str::to_string))
+        })
+        .unwrap_or_default()
+}
+
+/// Brings the main window back. On macOS closing it may have hidden the whole
+/// app, so that's shown too.
+pub fn show_main_window(app_handle: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    let _ = app_handle.show();
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+// Starts a new chat in the quick chat window with `draft` and shows it
+fn ask_in_quick_chat(app_handle: &AppHandle, draft: QuickChatDraft) {
+    command::new_quick_chat(app_handle.clone(), Some(draft));
+    // The macOS panel can only be shown from the main thread
+    let handle = app_handle.clone();
+    if let Err(e) = app_handle.run_on_main_thread(move || command::show(handle)) {
+        println!("Failed to show the quick chat window: {}", e);
+    }
+}
+
+// Quoted, with room under it for the question
+fn quote(text: &str) -> String {
+    let quoted: Vec<String> = text.lines().map(|line| format!("> {}", line)).collect();
+    format!("{}\n\n", quoted.join("\n"))
+}
+
+#[cfg(target_os = "linux")]
+fn primary_selection() -> Result<String, String> {
+    use std::process::Command;
+
+    let readers: [(&str, &[&str]); 3] = [
+        ("wl-paste", &["--primary", "--no-newline"]),
+        ("xclip", &["-out", "-selection", "primary"]),
+        ("xsel", &["--primary", "--output"]),
+    ];
+    for (program, args) in readers {
+        // XWayland's selection isn't the one Wayland apps set
+        if program == "wl-paste" && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            continue;
+        }
+        if let Ok(output) = Command::new(program).args(args).output() {
+            if output.status.success() {
+                return Ok(String::from_utf8_lossy(&output.stdout).to_string());
+            }
+        }
+    }
+    Err("Couldn't read the selected text. Install xclip, xsel or wl-clipboard.".to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn primary_selection() -> Result<String, String> {
+    Err("Only Linux has a selection Chorus can read".to_string())
+}
+
+// Called by the shortcut handler on the main thread, so anything slow moves
+// off it
+pub fn run(app_handle: &AppHandle, action: Action) {
+    let app_handle = app_handle.clone();
+    match action {
+        Action::QuickChat => quick_chat::toggle(&app_handle),
+        Action::NewChat => {
+            quick_chat::hide(&app_handle);
+            show_main_window(&app_handle);
+            let _ = app_handle.emit_to("main", NEW_CHAT_EVENT, ());
+        }
+        // The rest ask in the quick chat window, so they're off along with it
+        Action::Screenshot | Action::Clipboard | Action::Selection
+            if !quick_chat::is_enabled(&app_handle) => {}
+        Action::Screenshot => {
+            // It would be in the screenshot otherwise
+            quick_chat::hide(&app_handle);
+            tauri::async_runtime::spawn_blocking(move || {
+                match command::capture_whole_screen(app_handle.clone()) {
+                    Ok(screenshot) => ask_in_quick_chat(
+                        &app_handle,
+                        QuickChatDraft {
+                            text: None,
+                            screenshot: Some(screenshot),
+                        },
+                    ),
+                    Err(e) => println!("Failed to take a screenshot: {}", e),
+                }
+            });
+        }
+        Action::Clipboard => {
+            tauri::async_runtime::spawn_blocking(move || {
+                let text = match app_handle.clipboard().read_text() {
+                    Ok(text) => Some(text),
+                    Err(e) => {
+                        println!("Failed to read the clipboard: {}", e);
+                        None
+                    }
+                };
+                ask_in_quick_chat(
+                    &app_handle,
+                    QuickChatDraft {
+                        text,
+                        screenshot: None,
+                    },
+                );
+            });
+        }
+        Action::Selection => {
+            tauri::async_runtime::spawn_blocking(move || match primary_selection() {
+                Ok(text) => ask_in_quick_chat(
+                    &app_handle,
+                    QuickChatDraft {
+                        text: (!text.trim().is_empty()).then(|| quote(&text)),
+                        screenshot: None,
+                    },
+                ),
+                Err(e) => println!("Failed to read the selection: {}", e),
+            });
+        }
+    }
+}
+
+/// Registers `text` as the action's shortcut in place of its current one, or
+/// removes its shortcut if `text` is empty. Quick chat always has one. The
+/// current shortcut stays registered if the new one is invalid, already used
+/// by the app menu or another action, or can't be registered.
+pub fn set_shortcut(app_handle: &AppHandle, action: Action, text: &str) -> Result<(), String> {
+    if !action.is_supported() {
+        return Err(format!(
+            "{} isn't available on this platform",
+            action.label()
+        ));
+    }
+    let bindings = bindings(app_handle);
+    let old = bindings.get(&action).copied();
+    let global_shortcut = app_handle.global_shortcut();
+    if text.trim().is_empty() && action != Action::QuickChat {
+        if let Some(old) = old {
+            update_bindings(app_handle, |bindings| {
+                bindings.remove(&action);
+            })?;
+            global_shortcut
+                .unregister(old)
+                .map_err(|e| format!("Failed to unregister {}: {}", action.label(), e))?;
+        }
+        return Ok(());
+    }
+
+    let new = shortcut::parse(text)?;
+    if let Some(item) = shortcut::menu_conflict(&new) {
+        return Err(format!(
+            "{} is already the shortcut for {} in Chorus",
+            text, item
+        ));
+    }
+    if old.is_some_and(|old| old.id() == new.id()) {
+        return Ok(());
+    }
+    if let Some((other, _)) = bindings.iter().find(|(_, bound)| bound.id() == new.id()) {
+        return Err(format!(
+            "{} is already the shortcut for {}",
+            text,
+            other.label()
+        ));
+    }
+    // Registered before the old one goes, so a failure leaves the old one.
+    // The bindings aren't locked meanwhile, since the handler locks them.
+    global_shortcut.register(new).map_err(|e| {
+        format!(
+            "Couldn't register {}, another app may be using it: {}",
+            text, e
+        )
+    })?;
+    update_bindings(app_handle, |bindings| {
+        bindings.insert(action, new);
+    })?;
+    if let Some(old) = old {
+        if let Err(e) = global_shortcut.unregister(old) {
+            println!(
+                "Failed to unregister the old {} shortcut: {}",
+                action.label(),
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Registers every action's shortcut from the active profile's settings.
+/// Quick chat falls back to the default when its shortcut is invalid, the
+/// others are left without one. Called at startup and after switching
+/// profiles.
+pub fn reload(app_handle: &AppHandle) {
+    // Cleared first, so the old profile's shortcuts can't clash with the new
+    for (_, old) in bindings(app_handle) {
+        let _ = app_handle.global_shortcut().unregister(old);
+    }
+    let _ = update_bindings(app_handle, HashMap::clear);
+
+    for action in Action::ALL
+        .into_iter()
+        .filter(|action| action.is_supported())
+    {
+        let text = saved_shortcut(app_handle, action);
+        if let Err(e) = set_shortcut(app_handle, action, &text) {
+            println!("Failed to set the {} shortcut: {}", action.label(), e);
+            if action == Action::QuickChat && text != shortcut::DEFAULT_QUICK_CHAT {
+                if let Err(e) = set_shortcut(app_handle, action, shortcut::DEFAULT_QUICK_CHAT) {
+                    println!("Failed to set the default quick chat shortcut: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Adds the global shortcut plugin and registers the shortcuts. One that
+/// can't be registered, because another app has it or the desktop doesn't
+/// allow global shortcuts, only leaves its action without a shortcut.
+pub fn setup(app_handle: &AppHandle) -> tauri::Result<()> {
+    app_handle.plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(|app, pressed, event| {
+                if event.state != ShortcutState::Pressed {
+                    return;
+                }
+                let action = bindings(app)
+                    .into_iter()
+                    .find(|(_, bound)| bound.id() == pressed.id())
+                    .map(|(action, _)| action);
+                if let Some(action) = action {
+                    run(app, action);
+                }
+            })
+            .build(),
+    )?;
+    reload(app_handle);
+    Ok(())
+}
@@ -0,0 +1,575 @@
+// Read-only queries over the chat history, for the tools that expose it
+// outside the app window. Nothing in here depends on Tauri, so the command
+// line tools can include this file with `#[path]`, along with search.rs.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::search::{self, SearchFilters, SearchHit};
+use crate::{data_location, profile_registry};
+
+pub const APP_IDENTIFIER: &str = "sh.chorus.app";
+pub const INSTANCE_NAME_ENV: &str = "CHORUS_INSTANCE_NAME";
+
+const DEFAULT_CHAT_LIMIT: u32 = 50;
+const MAX_CHAT_LIMIT: u32 = 500;
+
+/// The name given by CHORUS_INSTANCE_NAME, if any
+pub fn instance_name() -> Option<String> {
+    std::env::var(INSTANCE_NAME_ENV)
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+/// The app identifier for a named instance, made the same way as in
+/// script/dev-instance.sh
+pub fn instance_identifier(name: &str) -> String {
+    let safe_name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.dev.{}", APP_IDENTIFIER, safe_name)
+}
+
+/// Where the app keeps chats.db, found the way Tauri's app_config_dir is
+/// unless the data has been put somewhere else (see data_location.rs). Named
+/// instances have their own directory.
+pub fn default_data_dir() -> Result<PathBuf, String> {
+    let identifier = match instance_name() {
+        Some(name) => instance_identifier(&name),
+        None => APP_IDENTIFIER.to_string(),
+    };
+    let dir = config_dir()?.join(identifier);
+    let location = data_location::resolve(Some(&dir), None)?;
+    Ok(location.root.unwrap_or(dir))
+}
+
+/// The active profile's chats.db in the default data directory. The app knows
+/// its own, so only the command line tools look it up.
+#[allow(dead_code)]
+pub fn default_db_path() -> Result<PathBuf, String> {
+    profile_registry::active_db_path(&default_data_dir()?)
+}
+
+/// The directory Tauri puts each app's app_config_dir in
+pub fn config_dir() -> Result<PathBuf, String> {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    };
+    let home = || env_dir("HOME").ok_or_else(|| "HOME isn't set".to_string());
+    if cfg!(target_os = "macos") {
+        Ok(home()?.join("Library").join("Application Support"))
+    } else if cfg!(windows) {
+        env_dir("APPDATA").ok_or_else(|| "APPDATA isn't set".to_string())
+    } else {
+        match env_dir("XDG_CONFIG_HOME") {
+            Some(dir) => Ok(dir),
+            None => Ok(home()?.join(".config")),
+        }
+    }
+}
+
+/// Which projects a caller may see
+pub enum ProjectScope {
+    All,
+    Only(Vec<String>),
+}
+
+impl ProjectScope {
+    /// Builds a scope from project ids or names. An empty list allows every
+    /// project, and an entry that matches no project is an error.
+    pub fn resolve(conn: &Connection, entries: &[String]) -> Result<ProjectScope, String> {
+        if entries.is_empty() {
+            return Ok(ProjectScope::All);
+        }
+        let mut ids = Vec::new();
+        for entry in entries {
+            let id: Option<String> = conn
+                .query_row(
+                    "SELECT id FROM projects WHERE id = ?1 OR lower(name) = lower(?1)
+                     ORDER BY id = ?1 DESC LIMIT 1",
+                    params![entry],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?;
+            ids.push(id.ok_or_else(|| format!("No project matches '{}'", entry))?);
+        }
+        Ok(ProjectScope::Only(ids))
+    }
+
+    pub fn allows(&self, project_id: &str) -> bool {
+        match self {
+            ProjectScope::All => true,
+            ProjectScope::Only(ids) => ids.iter().any(|id| id == project_id),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub chat_count: i64,
+    pub has_context: bool,
+}
+
+#[derive(Serialize)]
+pub struct ChatSummary {
+    pub id: String,
+    pub title: Option<String>,
+    pub project_id: String,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    pub pinned: bool,
+    pub quick_chat: bool,
+    pub parent_chat_id: Option<String>,
+    pub summary: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TranscriptMessage {
+    pub id: String,
+    /// "user" or "assistant"
+    pub role: String,
+    pub model: Option<String>,
+    pub created_at: Option<String>,
+    pub text: String,
+}
+
+#[derive(Serialize)]
+pub struct Chat {
+    #[serde(flatten)]
+    pub summary: ChatSummary,
+    pub messages: Vec<TranscriptMessage>,
+}
+
+// One message set, with whether each of its messages is selected
+struct Turn {
+    set_id: String,
+    set_type: String,
+    messages: Vec<(bool, TranscriptMessage)>,
+}
+
+#[derive(Serialize)]
+pub struct ProjectAttachment {
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub path: String,
+}
+
+#[derive(Serialize)]
+pub struct ProjectChatSummary {
+    pub chat_id: String,
+    pub title: Option<String>,
+    pub summary: String,
+}
+
+#[derive(Serialize)]
+pub struct ProjectContext {
+    pub project: Project,
+    pub context_text: Option<String>,
+    pub attachments: Vec<ProjectAttachment>,
+    /// The summaries magic projects share between a project's chats
+    pub chat_summaries: Vec<ProjectChatSummary>,
+}
+
+pub fn list_projects(conn: &Connection, scope: &ProjectScope) -> Result<Vec<Project>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.id, p.name, p.created_at, p.updated_at,
+                (SELECT count(*) FROM chats c
+                 WHERE c.project_id = p.id AND c.reply_to_id IS NULL AND c.gc_prototype_chat = 0),
+                coalesce(p.context_text, '') != '' OR EXISTS (
+                    SELECT 1 FROM project_attachments pa WHERE pa.project_id = p.id)
+             FROM projects p
+             ORDER BY p.updated_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let projects = stmt
+        .query_map([], read_project)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(projects
+        .into_iter()
+        .filter(|project| scope.allows(&project.id))
+        .collect())
+}
+
+fn read_project(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at: row.get(2)?,
+        updated_at: row.get(3)?,
+        chat_count: row.get(4)?,
+        has_context: row.get(5)?,
+    })
+}
+
+const CHAT_COLUMNS: &str =
+    "id, title, project_id, created_at, updated_at, pinned, quick_chat, parent_chat_id, summary";
+
+fn read_chat_summary(row: &rusqlite::Row) -> rusqlite::Result<ChatSummary> {
+    Ok(ChatSummary {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        project_id: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+        pinned: row.get(5)?,
+        quick_chat: row.get(6)?,
+        parent_chat_id: row.get(7)?,
+        summary: row.get(8)?,
+    })
+}
+
+/// Chats, most recently updated first. Empty new chats and reply threads
+/// are left out, like in the sidebar.
+pub fn list_chats(
+    conn: &Connection,
+    scope: &ProjectScope,
+    project_id: Option<&str>,
+    limit: Option<u32>,
+    offset: u32,
+) -> Result<Vec<ChatSummary>, String> {
+    if project_id.is_some_and(|id| !scope.allows(id)) {
+        return Ok(Vec::new());
+    }
+    let allowed = match scope {
+        ProjectScope::All => None,
+        ProjectScope::Only(ids) => Some(serde_json::to_string(ids).map_err(|e| e.to_string())?),
+    };
+    let limit = limit.unwrap_or(DEFAULT_CHAT_LIMIT).clamp(1, MAX_CHAT_LIMIT);
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM chats
+             WHERE is_new_chat = 0 AND reply_to_id IS NULL AND gc_prototype_chat = 0
+                AND (?1 IS NULL OR project_id = ?1)
+                AND (?2 IS NULL OR project_id IN (SELECT value FROM json_each(?2)))
+             ORDER BY coalesce(updated_at, created_at) DESC
+             LIMIT ?3 OFFSET ?4",
+            CHAT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let chats = stmt
+        .query_map(
+            params![project_id, allowed, limit, offset],
+            read_chat_summary,
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(chats)
+}
+
+/// A chat's conversation as the user sees it: for each turn, the response
+/// they picked, or every response if they haven't picked one.
+pub fn get_chat(
+    conn: &Connection,
+    scope: &ProjectScope,
+    chat_id: &str,
+) -> Result<Option<Chat>, String> {
+    let summary = conn
+        .query_row(
+            &format!("SELECT {} FROM chats WHERE id = ?1", CHAT_COLUMNS),
+            params![chat_id],
+            read_chat_summary,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    // Chats outside the scope look the same as chats that don't exist
+    let Some(summary) = summary.filter(|chat| scope.allows(&chat.project_id)) else {
+        return Ok(None);
+    };
+
+    // Tool-using responses are stored in parts
+    let mut parts: HashMap<String, Vec<String>> = HashMap::new();
+    let mut stmt = conn
+        .prepare("SELECT message_id, content FROM message_parts WHERE chat_id = ?1 ORDER BY message_id, level")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![chat_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (message_id, content) = row.map_err(|e| e.to_string())?;
+        parts.entry(message_id).or_default().push(content);
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT ms.id, ms.type, m.id, m.model, m.created_at, m.text, coalesce(m.selected, 0)
+             FROM message_sets ms
+             JOIN messages m ON m.message_set_id = ms.id AND m.block_type = ms.selected_block_type
+             WHERE ms.chat_id = ?1 AND coalesce(m.is_review, 0) = 0
+             ORDER BY ms.level, ms.created_at, m.created_at",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![chat_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, bool>(6)?,
+                TranscriptMessage {
+                    id: row.get(2)?,
+                    role: String::new(),
+                    model: row.get(3)?,
+                    created_at: row.get(4)?,
+                    text: row.get(5)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // Group the messages by turn, keeping the order
+    let mut turns: Vec<Turn> = Vec::new();
+    for (set_id, set_type, selected, message) in rows {
+        match turns.last_mut() {
+            Some(turn) if turn.set_id == set_id => turn.messages.push((selected, message)),
+            _ => turns.push(Turn {
+                set_id,
+                set_type,
+                messages: vec![(selected, message)],
+            }),
+        }
+    }
+
+    let mut messages = Vec::new();
+    for Turn {
+        set_type,
+        messages: turn,
+        ..
+    } in turns
+    {
+        let any_selected = turn.iter().any(|(selected, _)| *selected);
+        for (selected, mut message) in turn {
+            if any_selected && !selected {
+                continue;
+            }
+            if let Some(parts) = parts.remove(&message.id) {
+                message.text = parts.join("\n\n");
+            }
+            if set_type == "user" {
+                message.role = "user".to_string();
+                message.model = None;
+            } else {
+                message.role = "assistant".to_string();
+            }
+            messages.push(message);
+        }
+    }
+    Ok(Some(Chat { summary, messages }))
+}
+
+pub fn project_context(
+    conn: &Connection,
+    scope: &ProjectScope,
+    project_id: &str,
+) -> Result<Option<ProjectContext>, String> {
+    if !scope.allows(project_id) {
+        return Ok(None);
+    }
+    let project = list_projects(conn, &ProjectScope::Only(vec![project_id.to_string()]))?
+        .into_iter()
+        .next();
+    let Some(project) = project else {
+        return Ok(None);
+    };
+    let context_text: Option<String> = conn
+        .query_row(
+            "SELECT context_text FROM projects WHERE id = ?1",
+            params![project_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT a.id, a.original_name, a.type, a.path
+             FROM project_attachments pa
+             JOIN attachments a ON pa.attachment_id = a.id
+             WHERE pa.project_id = ?1
+             ORDER BY a.created_at",
+        )
+        .map_err(|e| e.to_string())?;
+    let attachments = stmt
+        .query_map(params![project_id], |row| {
+            Ok(ProjectAttachment {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                kind: row.get(2)?,
+                path: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title, project_context_summary FROM chats
+             WHERE project_id = ?1 AND coalesce(project_context_summary, '') != ''
+             ORDER BY updated_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let chat_summaries = stmt
+        .query_map(params![project_id], |row| {
+            Ok(ProjectChatSummary {
+                chat_id: row.get(0)?,
+                title: row.get(1)?,
+                summary: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(Some(ProjectContext {
+        project,
+        context_text: context_text.filter(|text| !text.is_empty()),
+        attachments,
+        chat_summaries,
+    }))
+}
+
+/// Full-text search, limited to the projects in scope. Hits outside the
+/// scope are dropped after searching, so a page can hold fewer than `limit`.
+/// The app searches through search.rs directly.
+#[allow(dead_code)]
+pub fn search_chats(
+    conn: &Connection,
+    scope: &ProjectScope,
+    query: &str,
+    filters: SearchFilters,
+    limit: Option<u32>,
+) -> Result<Vec<SearchHit>, String> {
+    if filters
+        .project_id
+        .as_deref()
+        .is_some_and(|id| !scope.allows(id))
+    {
+        return Ok(Vec::new());
+    }
+    let page = search::search(conn, query, filters, None, limit)?;
+    Ok(page
+        .hits
+        .into_iter()
+        .filter(|hit| hit.project_id.as_deref().is_some_and(|id| scope.allows(id)))
+        .collect())
+}
+
+#[derive(Serialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub responses: i64,
+}
+
+#[derive(Serialize)]
+pub struct Stats {
+    pub projects: i64,
+    pub chats: i64,
+    pub user_messages: i64,
+    pub assistant_messages: i64,
+    pub attachments: i64,
+    pub first_chat_at: Option<String>,
+    pub last_chat_at: Option<String>,
+    /// Responses per model, most used first
+    pub models: Vec<ModelUsage>,
+}
+
+/// Totals over the chats the sidebar shows, for `chorus_cli stats`
+#[allow(dead_code)]
+pub fn stats(conn: &Connection) -> Result<Stats, String> {
+    let (chats, first_chat_at, last_chat_at) = conn
+        .query_row(
+            "SELECT count(*), min(created_at), max(coalesce(updated_at, created_at)) FROM chats
+             WHERE is_new_chat = 0 AND reply_to_id IS NULL AND gc_prototype_chat = 0",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let (projects, attachments) = conn
+        .query_row(
+            "SELECT (SELECT count(*) FROM projects), (SELECT count(*) FROM attachments)",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let (user_messages, assistant_messages) = conn
+        .query_row(
+            "SELECT coalesce(sum(ms.type = 'user'), 0), coalesce(sum(ms.type != 'user'), 0)
+             FROM messages m
+             JOIN message_sets ms ON m.message_set_id = ms.id
+             WHERE coalesce(m.is_review, 0) = 0",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT m.model, count(*) AS responses
+             FROM messages m
+             JOIN message_sets ms ON m.message_set_id = ms.id
+             WHERE ms.type != 'user' AND coalesce(m.is_review, 0) = 0
+             GROUP BY m.model
+             ORDER BY responses DESC, m.model",
+        )
+        .map_err(|e| e.to_string())?;
+    let models = stmt
+        .query_map([], |row| {
+            Ok(ModelUsage {
+                model: row.get(0)?,
+                responses: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(Stats {
+        projects,
+        chats,
+        user_messages,
+        assistant_messages,
+        attachments,
+        first_chat_at,
+        last_chat_at,
+        models,
+    })
+}
+
+/// Renders a chat as Markdown, one section per message
+pub fn chat_markdown(chat: &Chat) -> String {
+    let title = chat.summary.title.as_deref().unwrap_or("Untitled chat");
+    let mut markdown = format!("# {}\n", title);
+    for message in &chat.messages {
+        let heading = match (message.role.as_str(), &message.model) {
+            ("user", _) => "User".to_string(),
+            (_, Some(model)) => format!("Assistant ({})", model),
+            (_, None) => "Assistant".to_string(),
+        };
+        markdown.push_str(&format!("\n## {}\n\n{}\n", heading, message.text.trim()));
+    }
+    markdown
+}
@@ -0,0 +1,1082 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::mcp_http::{self, HttpConnection, HttpEvent, HttpOptions, HttpTransport};
+use crate::{db, vault};
+
+pub const STATUS_EVENT: &str = "mcp-server-status";
+pub const LOG_EVENT: &str = "mcp-server-log";
+
+// Launcher for custom servers. It runs the command with the user's shell
+// environment, so things like `npx` resolve the same way they do in a terminal.
+const RUN_MCP_BINARY: &str = "binaries/run-mcp";
+
+// Lines of stderr kept per server
+const LOG_CAPACITY: usize = 500;
+
+const MONITOR_TICK: Duration = Duration::from_secs(5);
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+// Restarts back off exponentially. A server that stayed up for STABLE_AFTER
+// starts over from the first delay; one that keeps crashing is given up on.
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+const MAX_RESTARTS: u32 = 5;
+
+// Lines queued for a process's stdin. The client waits for responses, so this
+// only fills up if the server stops reading.
+const STDIN_CAPACITY: usize = 256;
+
+// How long a server gets to exit after its stdin is closed
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+// Ids of requests the supervisor sends itself (health pings, replayed
+// handshakes). Responses to them aren't relayed to the frontend.
+const SUPERVISOR_ID_PREFIX: &str = "chorus-supervisor:";
+
+// Variables passed through from our own environment, on top of the server's
+// configured env. Same list as the MCP SDK's stdio transport.
+#[cfg(windows)]
+const INHERITED_ENV_VARS: &[&str] = &[
+    "APPDATA",
+    "HOMEDRIVE",
+    "HOMEPATH",
+    "LOCALAPPDATA",
+    "PATH",
+    "PROCESSOR_ARCHITECTURE",
+    "SYSTEMDRIVE",
+    "SYSTEMROOT",
+    "TEMP",
+    "USERNAME",
+    "USERPROFILE",
+];
+#[cfg(not(windows))]
+const INHERITED_ENV_VARS: &[&str] = &["HOME", "LOGNAME", "PATH", "SHELL", "TERM", "USER"];
+
+/// How to launch or reach a server. Mirrors MCPServerParameters in
+/// MCPStdioTauri.ts.
+#[derive(Clone, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum ServerParams {
+    Sidecar {
+        sidecar_binary: String,
+        #[serde(default)]
+        args: Vec<String>,
+        env: Option<HashMap<String, String>>,
+    },
+    Custom {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        env: Option<HashMap<String, String>>,
+    },
+    StreamableHttp {
+        url: String,
+        headers: Option<HashMap<String, String>>,
+        bearer_token: Option<String>,
+    },
+    Sse {
+        url: String,
+        headers: Option<HashMap<String, String>>,
+        bearer_token: Option<String>,
+    },
+}
+
+impl ServerParams {
+    fn describe(&self) -> String {
+        match self {
+            ServerParams::Sidecar { sidecar_binary, .. } => sidecar_binary.clone(),
+            ServerParams::Custom { command, .. } => command.clone(),
+            ServerParams::StreamableHttp { url, .. } | ServerParams::Sse { url, .. } => url.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ServerStatus {
+    Stopped,
+    /// Registered, and started by the next message sent to it
+    Idle,
+    Running,
+    Restarting {
+        attempt: u32,
+        delay_ms: u64,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+#[derive(Serialize)]
+pub struct ServerInfo {
+    /// Label of the window whose toolsets use the server. None for servers
+    /// that are defined but haven't been started.
+    pub window: Option<String>,
+    pub name: String,
+    pub command: String,
+    pub status: ServerStatus,
+    pub pid: Option<u32>,
+    pub restarts: u32,
+}
+
+#[derive(Clone, Serialize)]
+struct StatusEvent {
+    window: String,
+    name: String,
+    status: ServerStatus,
+}
+
+#[derive(Clone, Serialize)]
+struct LogEvent {
+    window: String,
+    name: String,
+    line: String,
+}
+
+// Each window runs its own MCP clients, so servers aren't shared between them
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ServerKey {
+    window: String,
+    name: String,
+}
+
+enum Transport {
+    Stdio {
+        child: Child,
+        // Lines for the thread that writes to the process's stdin
+        stdin: SyncSender<Vec<u8>>,
+    },
+    Http(HttpConnection),
+}
+
+struct Connection {
+    transport: Transport,
+    started_at: Instant,
+}
+
+impl Connection {
+    fn pid(&self) -> Option<u32> {
+        match &self.transport {
+            Transport::Stdio { child, .. } => Some(child.id()),
+            Transport::Http(_) => None,
+        }
+    }
+
+    /// Shuts the connection down right away. Returns the exit status of a
+    /// process.
+    fn kill(self) -> Option<String> {
+        match self.transport {
+            Transport::Stdio { mut child, .. } => {
+                let _ = child.kill();
+                Some(match child.wait() {
+                    Ok(status) => status.to_string(),
+                    Err(e) => e.to_string(),
+                })
+            }
+            Transport::Http(connection) => {
+                connection.close();
+                None
+            }
+        }
+    }
+}
+
+struct Server {
+    app_handle: AppHandle,
+    key: ServerKey,
+    params: ServerParams,
+    status: ServerStatus,
+    connection: Option<Connection>,
+    // Bumped whenever the connection is replaced, so threads reading from an
+    // old one know to stop
+    generation: u64,
+    channel: Channel<Value>,
+    logs: VecDeque<String>,
+    // The client's handshake, replayed when the process is restarted so the
+    // client doesn't notice
+    initialize_params: Option<Value>,
+    initialized: bool,
+    // Id of the replayed `initialize`. Until the server answers it, the
+    // client's messages are held back.
+    replaying: Option<String>,
+    held: Vec<Value>,
+    // Ids of client requests that haven't been answered yet
+    in_flight: HashSet<String>,
+    pending_pings: HashMap<String, Instant>,
+    last_ping: Instant,
+    last_activity: Instant,
+    failures: u32,
+    next_supervisor_id: u64,
+}
+
+type SharedServer = Arc<Mutex<Server>>;
+
+/// Runs MCP servers as child processes of the app, or connects to remote ones
+/// over HTTP, and relays their JSON-RPC messages to the webview.
+#[derive(Default)]
+pub struct McpSupervisor {
+    servers: Mutex<HashMap<ServerKey, SharedServer>>,
+}
+
+fn lock(server: &SharedServer) -> MutexGuard<'_, Server> {
+    server.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn request_id(frame: &Value) -> Option<String> {
+    frame
+        .get("id")
+        .filter(|id| !id.is_null())
+        .map(Value::to_string)
+}
+
+impl Server {
+    fn set_status(&mut self, status: ServerStatus) {
+        self.status = status.clone();
+        let _ = self.app_handle.emit(
+            STATUS_EVENT,
+            StatusEvent {
+                window: self.key.window.clone(),
+                name: self.key.name.clone(),
+                status,
+            },
+        );
+    }
+
+    fn log(&mut self, line: String) {
+        if self.logs.len() == LOG_CAPACITY {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(line.clone());
+        let _ = self.app_handle.emit(
+            LOG_EVENT,
+            LogEvent {
+                window: self.key.window.clone(),
+                name: self.key.name.clone(),
+                line,
+            },
+        );
+    }
+
+    fn supervisor_id(&mut self, kind: &str) -> String {
+        self.next_supervisor_id += 1;
+        format!(
+            "{}{}:{}",
+            SUPERVISOR_ID_PREFIX, kind, self.next_supervisor_id
+        )
+    }
+
+    fn write_frame(&mut self, frame: &Value) -> Result<(), String> {
+        let stdin = match self.connection.as_ref().map(|c| &c.transport) {
+            Some(Transport::Stdio { stdin, .. }) => stdin,
+            Some(Transport::Http(connection)) => return connection.send(frame.clone()),
+            None => return Err(format!("{} isn't running", self.key.name)),
+        };
+        let mut line = serde_json::to_vec(frame).map_err(|e| e.to_string())?;
+        line.push(b'\n');
+        // The writer thread does the actual write, so a server that stops
+        // reading can't block whoever holds the lock
+        stdin.try_send(line).map_err(|e| match e {
+            TrySendError::Full(_) => format!("{} isn't accepting messages", self.key.name),
+            TrySendError::Disconnected(_) => format!("Couldn't write to {}", self.key.name),
+        })
+    }
+
+    // Called with the server's answer to the replayed `initialize`. Only then
+    // is it told the client is initialized, and sent what the client sent in
+    // the meantime.
+    fn finish_replay(&mut self, response: &Value) {
+        self.replaying = None;
+        if let Some(error) = response.get("error") {
+            self.log(format!(
+                "[supervisor] the server rejected the replayed handshake: {}",
+                error
+            ));
+        }
+        let mut frames = std::mem::take(&mut self.held);
+        if self.initialized {
+            frames.insert(
+                0,
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/initialized",
+                }),
+            );
+        }
+        for frame in frames {
+            if let Err(e) = self.write_frame(&frame) {
+                self.log(format!("[supervisor] {}", e));
+            }
+        }
+    }
+
+    // Answers requests the server will never reply to, so the client doesn't
+    // wait for its timeout
+    fn fail_in_flight(&mut self, message: &str) {
+        for id in self.in_flight.drain() {
+            let Ok(id) = serde_json::from_str::<Value>(&id) else {
+                continue;
+            };
+            let _ = self.channel.send(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32000, "message": message },
+            }));
+        }
+    }
+
+    fn handle_server_frame(&mut self, frame: Value) {
+        if let Some(id) = frame.get("id").and_then(Value::as_str) {
+            if id.starts_with(SUPERVISOR_ID_PREFIX) {
+                self.pending_pings.remove(id);
+                if self.replaying.as_deref() == Some(id) {
+                    self.finish_replay(&frame);
+                }
+                return;
+            }
+        }
+        let is_response = frame.get("method").is_none();
+        if let (true, Some(id)) = (is_response, request_id(&frame)) {
+            self.in_flight.remove(&id);
+        }
+        self.last_activity = Instant::now();
+        if self.channel.send(frame).is_err() {
+            self.log("[supervisor] the window is gone, dropping message".to_string());
+        }
+    }
+
+    /// Takes the connection out of the server and shuts it down in the
+    /// background. A process has its stdin closed first, and is killed if it's
+    /// still around after the grace period.
+    fn disconnect(&mut self) -> Option<std::thread::JoinHandle<()>> {
+        self.generation += 1;
+        self.pending_pings.clear();
+        self.replaying = None;
+        self.held.clear();
+        self.fail_in_flight("The MCP server was stopped");
+        match self.connection.take()?.transport {
+            Transport::Stdio { child, stdin } => {
+                drop(stdin);
+                Some(std::thread::spawn(move || terminate(child)))
+            }
+            Transport::Http(connection) => {
+                connection.close();
+                None
+            }
+        }
+    }
+}
+
+fn terminate(mut child: Child) {
+    let deadline = Instant::now() + STOP_GRACE_PERIOD;
+    while Instant::now() < deadline {
+        if let Ok(Some(_)) = child.try_wait() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BACKOFF_BASE
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(BACKOFF_MAX)
+}
+
+fn sidecar_path(binary: &str) -> Result<PathBuf, String> {
+    // Tauri puts `externalBin` entries next to the main executable, without
+    // the target triple
+    let name = Path::new(binary)
+        .file_name()
+        .ok_or_else(|| format!("Invalid sidecar: {}", binary))?;
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let dir = exe
+        .parent()
+        .ok_or_else(|| "Couldn't find the app's directory".to_string())?;
+    Ok(dir.join(format!(
+        "{}{}",
+        name.to_string_lossy(),
+        std::env::consts::EXE_SUFFIX
+    )))
+}
+
+fn build_command(app_handle: &AppHandle, params: &ServerParams) -> Result<Command, String> {
+    let (program, args, env) = match params {
+        ServerParams::Sidecar {
+            sidecar_binary,
+            args,
+            env,
+        } => (sidecar_path(sidecar_binary)?, args.clone(), env),
+        ServerParams::Custom { command, args, env } => {
+            let mut launcher_args = vec![command.clone()];
+            launcher_args.extend(args.iter().cloned());
+            (sidecar_path(RUN_MCP_BINARY)?, launcher_args, env)
+        }
+        ServerParams::StreamableHttp { .. } | ServerParams::Sse { .. } => {
+            return Err("Remote servers don't run as a process".to_string())
+        }
+    };
+    // Env values may point into the vault
+    let env = vault::resolve(app_handle, env.clone().unwrap_or_default())?;
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .env_clear()
+        .envs(INHERITED_ENV_VARS.iter().filter_map(|key| {
+            std::env::var(key)
+                .ok()
+                // Skip exported shell functions
+                .filter(|value| !value.starts_with("()"))
+                .map(|value| (key.to_string(), value))
+        }))
+        .envs(env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    Ok(command)
+}
+
+fn http_options(
+    app_handle: &AppHandle,
+    params: &ServerParams,
+) -> Option<Result<HttpOptions, String>> {
+    let (transport, url, headers, bearer_token) = match params {
+        ServerParams::StreamableHttp {
+            url,
+            headers,
+            bearer_token,
+        } => (HttpTransport::StreamableHttp, url, headers, bearer_token),
+        ServerParams::Sse {
+            url,
+            headers,
+            bearer_token,
+        } => (HttpTransport::Sse, url, headers, bearer_token),
+        _ => return None,
+    };
+    // Header values and the token may point into the vault. Resolving them on
+    // every connect picks up a token that was replaced in the meantime.
+    let resolve = || {
+        let headers = vault::resolve(app_handle, headers.clone().unwrap_or_default())?;
+        let bearer_token = match bearer_token {
+            Some(token) => vault::resolve(
+                app_handle,
+                HashMap::from([("token".to_string(), token.clone())]),
+            )?
+            .remove("token"),
+            None => None,
+        };
+        Ok(HttpOptions {
+            transport,
+            url: url.clone(),
+            headers,
+            bearer_token,
+        })
+    };
+    Some(resolve())
+}
+
+fn spawn(shared: &SharedServer, server: &mut Server) -> Result<(), String> {
+    let generation = server.generation + 1;
+    let transport = match http_options(&server.app_handle, &server.params) {
+        Some(options) => connect_http(shared, options?, generation)?,
+        None => spawn_process(shared, server, generation)?,
+    };
+
+    server.generation = generation;
+    server.connection = Some(Connection {
+        transport,
+        started_at: Instant::now(),
+    });
+    server.last_activity = Instant::now();
+    server.last_ping = Instant::now();
+    server.log(format!("[supervisor] started {}", server.params.describe()));
+    server.set_status(ServerStatus::Running);
+
+    // Bring a restarted server back to where the client left it. The rest of
+    // the handshake waits for the server's answer, in `finish_replay`.
+    if let Some(params) = server.initialize_params.clone() {
+        let id = server.supervisor_id("initialize");
+        server.write_frame(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "initialize",
+            "params": params,
+        }))?;
+        server.replaying = Some(id);
+    }
+
+    Ok(())
+}
+
+fn spawn_process(
+    shared: &SharedServer,
+    server: &Server,
+    generation: u64,
+) -> Result<Transport, String> {
+    let mut child = build_command(&server.app_handle, &server.params)?
+        .spawn()
+        .map_err(|e| format!("Couldn't start {}: {}", server.params.describe(), e))?;
+    let (Some(mut input), Some(stdout), Some(stderr)) =
+        (child.stdin.take(), child.stdout.take(), child.stderr.take())
+    else {
+        let _ = child.kill();
+        return Err("Couldn't connect to the server's input and output".to_string());
+    };
+
+    let (stdin, lines) = mpsc::sync_channel::<Vec<u8>>(STDIN_CAPACITY);
+    let writer = shared.clone();
+    std::thread::spawn(move || {
+        // Runs without the lock, since the write blocks while the server's
+        // stdin is full. Ends when the connection is dropped, closing stdin.
+        for line in lines {
+            if let Err(e) = input.write_all(&line).and_then(|_| input.flush()) {
+                let mut server = lock(&writer);
+                if server.generation == generation {
+                    let message =
+                        format!("[supervisor] Couldn't write to {}: {}", server.key.name, e);
+                    server.log(message);
+                }
+                return;
+            }
+        }
+    });
+
+    let reader = shared.clone();
+    std::thread::spawn(move || {
+        // MCP's stdio transport sends one message per line
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut server = lock(&reader);
+            if server.generation != generation {
+                return;
+            }
+            match serde_json::from_str::<Value>(&line) {
+                Ok(frame) => server.handle_server_frame(frame),
+                Err(_) => server.log(format!("[stdout] {}", line)),
+            }
+        }
+        let mut server = lock(&reader);
+        if server.generation == generation {
+            handle_exit(&reader, &mut server, None);
+        }
+    });
+
+    let reader = shared.clone();
+    std::thread::spawn(move || {
+        let mut stderr = BufReader::new(stderr);
+        let mut line = Vec::new();
+        // Read raw bytes, since servers don't always write valid UTF-8
+        while stderr.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+            let mut server = lock(&reader);
+            if server.generation != generation {
+                return;
+            }
+            server.log(String::from_utf8_lossy(&line).trim_end().to_string());
+            line.clear();
+        }
+    });
+
+    Ok(Transport::Stdio { child, stdin })
+}
+
+fn connect_http(
+    shared: &SharedServer,
+    options: HttpOptions,
+    generation: u64,
+) -> Result<Transport, String> {
+    let reader = shared.clone();
+    let connection = mcp_http::connect(options, move |event| {
+        let mut server = lock(&reader);
+        if server.generation != generation {
+            return;
+        }
+        match event {
+            HttpEvent::Message(frame) => server.handle_server_frame(frame),
+            HttpEvent::Log(line) => server.log(line),
+            HttpEvent::Closed(error) => handle_exit(&reader, &mut server, Some(error)),
+        }
+    })?;
+    Ok(Transport::Http(connection))
+}
+
+// Called when the process exits or the remote session is lost, with the
+// reason if it's known
+fn handle_exit(shared: &SharedServer, server: &mut Server, error: Option<String>) {
+    let Some(connection) = server.connection.take() else {
+        return;
+    };
+    let stable = connection.started_at.elapsed() >= STABLE_AFTER;
+    let exit_status = connection.kill();
+    let error = error.unwrap_or_else(|| {
+        format!(
+            "The MCP server exited ({})",
+            exit_status.unwrap_or_default()
+        )
+    });
+    server.generation += 1;
+    server.pending_pings.clear();
+    server.replaying = None;
+    server.held.clear();
+    server.fail_in_flight(&error);
+    if stable {
+        server.failures = 0;
+    }
+    schedule_restart(shared, server, error);
+}
+
+fn schedule_restart(shared: &SharedServer, server: &mut Server, error: String) {
+    server.log(format!("[supervisor] {}", error));
+    server.failures += 1;
+    if server.failures > MAX_RESTARTS {
+        server.set_status(ServerStatus::Failed { error });
+        return;
+    }
+
+    let delay = backoff(server.failures);
+    server.set_status(ServerStatus::Restarting {
+        attempt: server.failures,
+        delay_ms: delay.as_millis() as u64,
+    });
+    let generation = server.generation;
+    let shared = shared.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(delay);
+        let mut server = lock(&shared);
+        // Stopped, restarted or woken up by a message in the meantime
+        if server.generation != generation
+            || !matches!(server.status, ServerStatus::Restarting { .. })
+        {
+            return;
+        }
+        if let Err(e) = spawn(&shared, &mut server) {
+            schedule_restart(&shared, &mut server, e);
+        }
+    });
+}
+
+// Splits an argument string the way string-argv does on the frontend:
+// whitespace separates arguments and quotes group them
+pub fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+// Parses one of the JSON maps stored in custom_toolsets
+fn json_map(
+    name: &str,
+    column: &str,
+    value: Option<String>,
+) -> Result<Option<HashMap<String, String>>, String> {
+    value
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| serde_json::from_str::<HashMap<String, String>>(&value))
+        .transpose()
+        .map_err(|e| format!("{} has invalid {}: {}", name, column, e))
+}
+
+fn custom_toolset_params(app_handle: &AppHandle, name: &str) -> Result<ServerParams, String> {
+    let conn = db::open_read_only(app_handle)?;
+    let (transport, command, args, env, url, headers, bearer_token) = conn
+        .query_row(
+            "SELECT transport, command, args, env, url, headers, bearer_token
+             FROM custom_toolsets WHERE name = ?1",
+            [name],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("There's no MCP server called {}", name))?;
+
+    if transport == "stdio" {
+        let command = command
+            .filter(|command| !command.trim().is_empty())
+            .ok_or_else(|| format!("{} has no command", name))?;
+        return Ok(ServerParams::Custom {
+            command,
+            args: split_args(&args.unwrap_or_default()),
+            env: json_map(name, "environment", env)?,
+        });
+    }
+
+    let url = url
+        .filter(|url| !url.trim().is_empty())
+        .ok_or_else(|| format!("{} has no URL", name))?;
+    let headers = json_map(name, "headers", headers)?;
+    let bearer_token = bearer_token.filter(|token| !token.is_empty());
+    match transport.as_str() {
+        "streamable_http" => Ok(ServerParams::StreamableHttp {
+            url,
+            headers,
+            bearer_token,
+        }),
+        "sse" => Ok(ServerParams::Sse {
+            url,
+            headers,
+            bearer_token,
+        }),
+        _ => Err(format!("{} has an unknown transport: {}", name, transport)),
+    }
+}
+
+impl McpSupervisor {
+    fn get(&self, window: &str, name: &str) -> Result<SharedServer, String> {
+        let key = ServerKey {
+            window: window.to_string(),
+            name: name.to_string(),
+        };
+        self.servers
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| format!("{} hasn't been started", name))
+    }
+
+    fn all(&self) -> Vec<SharedServer> {
+        self.servers
+            .lock()
+            .map(|servers| servers.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Registers a server for `window`, replacing any earlier one with the
+    /// same name. The process starts with the first message sent to it.
+    /// Without `params`, the server is looked up in `custom_toolsets`.
+    pub fn start(
+        &self,
+        app_handle: &AppHandle,
+        window: &str,
+        name: &str,
+        params: Option<ServerParams>,
+        channel: Channel<Value>,
+    ) -> Result<(), String> {
+        let params = match params {
+            Some(params) => params,
+            None => custom_toolset_params(app_handle, name)?,
+        };
+        let key = ServerKey {
+            window: window.to_string(),
+            name: name.to_string(),
+        };
+        let server = Arc::new(Mutex::new(Server {
+            app_handle: app_handle.clone(),
+            key: key.clone(),
+            params,
+            status: ServerStatus::Idle,
+            connection: None,
+            generation: 0,
+            channel,
+            logs: VecDeque::new(),
+            initialize_params: None,
+            initialized: false,
+            replaying: None,
+            held: Vec::new(),
+            in_flight: HashSet::new(),
+            pending_pings: HashMap::new(),
+            last_ping: Instant::now(),
+            last_activity: Instant::now(),
+            failures: 0,
+            next_supervisor_id: 0,
+        }));
+        let previous = self
+            .servers
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(key, server.clone());
+        if let Some(previous) = previous {
+            lock(&previous).disconnect();
+        }
+        lock(&server).set_status(ServerStatus::Idle);
+        Ok(())
+    }
+
+    /// Relays a JSON-RPC message from the client, starting the process first
+    /// if it isn't running.
+    pub fn send(&self, window: &str, name: &str, frame: Value) -> Result<(), String> {
+        let shared = self.get(window, name)?;
+        let mut server = lock(&shared);
+        let method = frame.get("method").and_then(Value::as_str);
+
+        // A new handshake replaces the one we'd replay
+        if method == Some("initialize") {
+            server.initialize_params = None;
+            server.initialized = false;
+        }
+        if server.connection.is_none() {
+            match &server.status {
+                ServerStatus::Stopped => return Err(format!("{} was stopped", name)),
+                ServerStatus::Failed { error } => return Err(error.clone()),
+                ServerStatus::Idle | ServerStatus::Restarting { .. } | ServerStatus::Running => {
+                    spawn(&shared, &mut server)?
+                }
+            }
+        }
+
+        match method {
+            Some("initialize") => {
+                server.initialize_params = frame.get("params").cloned();
+            }
+            Some("notifications/initialized") => server.initialized = true,
+            _ => {}
+        }
+        if let (Some(_), Some(id)) = (method, request_id(&frame)) {
+            server.in_flight.insert(id);
+        }
+        server.last_activity = Instant::now();
+        if server.replaying.is_some() {
+            server.held.push(frame);
+            return Ok(());
+        }
+        server.write_frame(&frame)
+    }
+
+    pub fn stop(&self, window: &str, name: &str) -> Result<(), String> {
+        let shared = self.get(window, name)?;
+        let mut server = lock(&shared);
+        server.disconnect();
+        server.log("[supervisor] stopped".to_string());
+        server.set_status(ServerStatus::Stopped);
+        Ok(())
+    }
+
+    /// Starts a fresh process right away, even if the server failed or was
+    /// stopped.
+    pub fn restart(&self, window: &str, name: &str) -> Result<(), String> {
+        let shared = self.get(window, name)?;
+        let mut server = lock(&shared);
+        server.disconnect();
+        server.failures = 0;
+        server.log("[supervisor] restarting".to_string());
+        if let Err(error) = spawn(&shared, &mut server) {
+            server.set_status(ServerStatus::Failed {
+                error: error.clone(),
+            });
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    pub fn logs(&self, window: &str, name: &str) -> Result<Vec<String>, String> {
+        let shared = self.get(window, name)?;
+        let server = lock(&shared);
+        Ok(server.logs.iter().cloned().collect())
+    }
+
+    /// Lists running and registered servers, followed by the ones defined in
+    /// `custom_toolsets` and the bundled sidecars that no window has started.
+    pub fn list(&self, app_handle: &AppHandle) -> Result<Vec<ServerInfo>, String> {
+        let mut servers = self
+            .all()
+            .iter()
+            .map(|shared| {
+                let server = lock(shared);
+                ServerInfo {
+                    window: Some(server.key.window.clone()),
+                    name: server.key.name.clone(),
+                    command: server.params.describe(),
+                    status: server.status.clone(),
+                    pid: server.connection.as_ref().and_then(Connection::pid),
+                    restarts: server.failures,
+                }
+            })
+            .collect::<Vec<_>>();
+        servers.sort_by(|a, b| (&a.window, &a.name).cmp(&(&b.window, &b.name)));
+
+        let mut defined = Vec::new();
+        let conn = db::open_read_only(app_handle)?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT name, CASE transport WHEN 'stdio' THEN command ELSE url END
+                 FROM custom_toolsets ORDER BY name",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (name, command) = row.map_err(|e| e.to_string())?;
+            defined.push((name, command.unwrap_or_default()));
+        }
+        let sidecars = app_handle
+            .config()
+            .bundle
+            .external_bin
+            .clone()
+            .unwrap_or_default();
+        for binary in sidecars
+            .into_iter()
+            .filter(|binary| binary != RUN_MCP_BINARY)
+        {
+            let name = Path::new(&binary)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| binary.clone());
+            defined.push((name, binary));
+        }
+
+        for (name, command) in defined {
+            let started = servers
+                .iter()
+                .any(|server| server.name == name || server.command == command);
+            if !started {
+                servers.push(ServerInfo {
+                    window: None,
+                    name,
+                    command,
+                    status: ServerStatus::Stopped,
+                    pid: None,
+                    restarts: 0,
+                });
+            }
+        }
+        Ok(servers)
+    }
+
+    /// Stops the servers a window started, once the window is gone.
+    pub fn stop_window(&self, window: &str) {
+        let Ok(mut servers) = self.servers.lock() else {
+            return;
+        };
+        servers.retain(|key, server| {
+            if key.window == window {
+                lock(server).disconnect();
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Stops every server and waits for the processes to exit. Called when
+    /// the app quits, so no server outlives it.
+    pub fn stop_all(&self) {
+        let handles = self
+            .all()
+            .iter()
+            .filter_map(|server| lock(server).disconnect())
+            .collect::<Vec<_>>();
+        for handle in handles {
+            let _ = handle.join();
+        }
+    }
+
+    fn check(&self) {
+        for shared in self.all() {
+            let mut server = lock(&shared);
+            if server.connection.is_none() {
+                continue;
+            }
+
+            let ping_timed_out = server
+                .pending_pings
+                .values()
+                .any(|sent| sent.elapsed() >= PING_TIMEOUT);
+            if ping_timed_out {
+                let error = "The MCP server didn't answer a health check".to_string();
+                handle_exit(&shared, &mut server, Some(error));
+                continue;
+            }
+
+            if server.in_flight.is_empty() && server.last_activity.elapsed() >= IDLE_TIMEOUT {
+                server.disconnect();
+                server.log("[supervisor] stopped after being idle".to_string());
+                server.set_status(ServerStatus::Idle);
+                continue;
+            }
+
+            // Servers only have to answer pings once they're initialized
+            if server.initialized
+                && server.pending_pings.is_empty()
+                && server.last_ping.elapsed() >= PING_INTERVAL
+            {
+                let id = server.supervisor_id("ping");
+                let ping = json!({ "jsonrpc": "2.0", "id": id, "method": "ping" });
+                match server.write_frame(&ping) {
+                    Ok(()) => {
+                        server.pending_pings.insert(id, Instant::now());
+                    }
+                    Err(e) => server.log(format!("[supervisor] {}", e)),
+                }
+                server.last_ping = Instant::now();
+            }
+        }
+    }
+}
+
+/// Starts the background thread that health checks servers and stops idle
+/// ones.
+pub fn start_monitor(app_handle: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(MONITOR_TICK);
+        app_handle.state::<McpSupervisor>().check();
+    });
+}
@@ -0,0 +1,713 @@
+// Moves MCP server definitions between custom_toolsets and the config files
+// other MCP clients use: Claude Desktop's claude_desktop_config.json, Cursor
+// and Windsurf's mcp.json, and VS Code's mcp.json or settings.json.
+
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::db;
+use crate::mcp::split_args;
+use crate::vault;
+
+/// Written in place of secrets when exporting
+pub const REDACTED: &str = "<redacted>";
+
+// Arguments like --api-key=... carry secrets as often as env vars do
+const SECRET_ARG_HINTS: &[&str] = &["key", "token", "secret", "password", "auth"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFormat {
+    ClaudeDesktop,
+    Cursor,
+    VsCode,
+    Windsurf,
+}
+
+impl ConfigFormat {
+    // The key the servers live under
+    fn servers_key(self) -> &'static str {
+        match self {
+            ConfigFormat::VsCode => "servers",
+            _ => "mcpServers",
+        }
+    }
+
+    // Where each client keeps its user-wide config
+    fn default_path(self, app_handle: &AppHandle) -> Result<PathBuf, String> {
+        let path = app_handle.path();
+        let home = path.home_dir().map_err(|e| e.to_string())?;
+        let config = path.config_dir().map_err(|e| e.to_string())?;
+        Ok(match self {
+            ConfigFormat::ClaudeDesktop => config.join("Claude").join("claude_desktop_config.json"),
+            ConfigFormat::Cursor => home.join(".cursor").join("mcp.json"),
+            ConfigFormat::VsCode => config.join("Code").join("User").join("mcp.json"),
+            ConfigFormat::Windsurf => home
+                .join(".codeium")
+                .join("windsurf")
+                .join("mcp_config.json"),
+        })
+    }
+}
+
+const ALL_FORMATS: [ConfigFormat; 4] = [
+    ConfigFormat::ClaudeDesktop,
+    ConfigFormat::Cursor,
+    ConfigFormat::VsCode,
+    ConfigFormat::Windsurf,
+];
+
+#[derive(Serialize)]
+pub struct ConfigLocation {
+    pub format: ConfigFormat,
+    pub path: String,
+}
+
+/// One server found in a config file
+#[derive(Serialize)]
+pub struct ImportCandidate {
+    /// The server's name in the config file
+    pub source_name: String,
+    /// A valid toolset name derived from it
+    pub name: String,
+    pub transport: String,
+    pub command: String,
+    pub args: String,
+    pub url: Option<String>,
+    /// Only the names, the values are usually secrets
+    pub env_keys: Vec<String>,
+    pub header_names: Vec<String>,
+    /// Whether a toolset called `name` already exists
+    pub conflict: bool,
+    /// A name that's free, for renaming on conflict
+    pub suggested_name: String,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportPreview {
+    pub path: String,
+    pub candidates: Vec<ImportCandidate>,
+    /// Entries that couldn't be read, with the reason
+    pub skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ExportResult {
+    pub exported: usize,
+    /// Servers left out because the file already has one by that name
+    pub skipped: Vec<String>,
+}
+
+/// A server to import, identified by its name in the config file
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSelection {
+    pub source_name: String,
+    pub name: String,
+    /// Overwrite a toolset that already has this name
+    #[serde(default)]
+    pub replace: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerEntry {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    command: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    url: Option<String>,
+    // Windsurf's name for url
+    server_url: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+}
+
+// A server as it would be stored in custom_toolsets
+struct ParsedServer {
+    transport: &'static str,
+    command: String,
+    args: String,
+    env: BTreeMap<String, String>,
+    url: Option<String>,
+    headers: BTreeMap<String, String>,
+    warnings: Vec<String>,
+}
+
+/// Config files of other clients that exist on this machine
+pub fn locations(app_handle: &AppHandle) -> Result<Vec<ConfigLocation>, String> {
+    let mut found = Vec::new();
+    for format in ALL_FORMATS {
+        let path = format.default_path(app_handle)?;
+        if path.is_file() {
+            found.push(ConfigLocation {
+                format,
+                path: path.to_string_lossy().to_string(),
+            });
+        }
+    }
+    Ok(found)
+}
+
+pub fn preview(app_handle: &AppHandle, path: &Path) -> Result<ImportPreview, String> {
+    let (servers, skipped) = read_servers(path)?;
+    let existing = existing_names(app_handle)?;
+    let mut taken = existing.clone();
+    let candidates = servers
+        .into_iter()
+        .map(|(source_name, server)| {
+            let name = toolset_name(&source_name);
+            let suggested_name = free_name(&name, &taken);
+            taken.insert(suggested_name.clone());
+            ImportCandidate {
+                conflict: existing.contains(&name),
+                source_name,
+                name,
+                transport: server.transport.to_string(),
+                command: server.command,
+                args: server.args,
+                url: server.url,
+                env_keys: server.env.into_keys().collect(),
+                header_names: server.headers.into_keys().collect(),
+                suggested_name,
+                warnings: server.warnings,
+            }
+        })
+        .collect();
+    Ok(ImportPreview {
+        path: path.to_string_lossy().to_string(),
+        candidates,
+        skipped,
+    })
+}
+
+/// Imports the selected servers, moving env and header values into the vault
+/// the way the settings page does. Returns the names of the new toolsets.
+pub fn import(
+    app_handle: &AppHandle,
+    path: &Path,
+    selections: Vec<ImportSelection>,
+) -> Result<Vec<String>, String> {
+    // The file is read again rather than trusting the preview, which may be stale
+    let (mut servers, _) = read_servers(path)?;
+    let existing = existing_names(app_handle)?;
+
+    let mut names = HashSet::new();
+    let mut rows = Vec::new();
+    for selection in selections {
+        if !is_valid_name(&selection.name) {
+            return Err(format!(
+                "'{}' isn't a valid name. Use lowercase letters, numbers and dashes.",
+                selection.name
+            ));
+        }
+        if !names.insert(selection.name.clone()) {
+            return Err(format!(
+                "'{}' is used for more than one server",
+                selection.name
+            ));
+        }
+        if existing.contains(&selection.name) && !selection.replace {
+            return Err(format!(
+                "A connection named '{}' already exists",
+                selection.name
+            ));
+        }
+        let server = servers.remove(&selection.source_name).ok_or_else(|| {
+            format!(
+                "'{}' is no longer in {}",
+                selection.source_name,
+                path.display()
+            )
+        })?;
+        rows.push((selection, server));
+    }
+
+    // The vault can't be rolled back, so it's written before the database
+    for (selection, server) in &mut rows {
+        if selection.replace {
+            delete_secrets(app_handle, &format!("toolset/{}/", selection.name))?;
+        }
+        let prefix = format!("toolset/{}", selection.name);
+        server.env = store_secrets(app_handle, &prefix, std::mem::take(&mut server.env))?;
+        server.headers = store_secrets(
+            app_handle,
+            &format!("{}/headers", prefix),
+            std::mem::take(&mut server.headers),
+        )?;
+    }
+
+    let mut conn = db::open(app_handle)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let mut imported = Vec::new();
+    for (ImportSelection { name, .. }, server) in rows {
+        // An upsert rather than INSERT OR REPLACE keeps the permission the user
+        // picked for a toolset that's being replaced
+        tx.execute(
+            "INSERT INTO custom_toolsets (name, transport, command, args, env, url, headers, bearer_token, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, NULL, ?8)
+             ON CONFLICT(name) DO UPDATE SET
+                transport = excluded.transport, command = excluded.command, args = excluded.args,
+                env = excluded.env, url = excluded.url, headers = excluded.headers,
+                bearer_token = NULL, updated_at = excluded.updated_at",
+            params![
+                name,
+                server.transport,
+                server.command,
+                server.args,
+                to_json(&server.env)?,
+                server.url,
+                (!server.headers.is_empty())
+                    .then(|| to_json(&server.headers))
+                    .transpose()?,
+                now,
+            ],
+        )
+        .map_err(|e| format!("Couldn't save {}: {}", name, e))?;
+        imported.push(name);
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(imported)
+}
+
+/// Builds a config file in `format` from the given toolsets, or all of them.
+/// Env values, header values, bearer tokens and secret looking arguments are
+/// replaced with a placeholder. If `path` already holds a config, the servers
+/// are added to it and everything else in the file is kept. A server the file
+/// already has is never replaced, since it holds the real secrets, so it's
+/// skipped instead.
+pub fn export(
+    app_handle: &AppHandle,
+    format: ConfigFormat,
+    names: Option<Vec<String>>,
+    path: &Path,
+) -> Result<ExportResult, String> {
+    let servers = exported_servers(app_handle, format, names)?;
+
+    let mut config = match fs::read_to_string(path) {
+        Ok(contents) if !contents.trim().is_empty() => {
+            match serde_json::from_str::<Value>(&contents) {
+                Ok(Value::Object(config)) => config,
+                _ => {
+                    return Err(format!(
+                        "{} isn't a JSON config file, so it wasn't changed. Pick a new file to export to.",
+                        path.display()
+                    ))
+                }
+            }
+        }
+        Ok(_) => Map::new(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Map::new(),
+        Err(e) => return Err(e.to_string()),
+    };
+    let entry = config
+        .entry(format.servers_key())
+        .or_insert_with(|| Value::Object(Map::new()));
+    let Value::Object(existing) = entry else {
+        return Err(format!(
+            "\"{}\" in {} isn't an object",
+            format.servers_key(),
+            path.display()
+        ));
+    };
+    let mut result = ExportResult {
+        exported: 0,
+        skipped: Vec::new(),
+    };
+    for (name, server) in servers {
+        if existing.contains_key(&name) {
+            result.skipped.push(name);
+        } else {
+            existing.insert(name, server);
+            result.exported += 1;
+        }
+    }
+    if result.exported == 0 {
+        return Ok(result);
+    }
+
+    let contents = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, contents + "\n").map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+fn exported_servers(
+    app_handle: &AppHandle,
+    format: ConfigFormat,
+    names: Option<Vec<String>>,
+) -> Result<Map<String, Value>, String> {
+    let conn = db::open_read_only(app_handle)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT name, transport, command, args, env, url, headers, bearer_token
+             FROM custom_toolsets ORDER BY name",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, Option<String>>(7)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut servers = Map::new();
+    for (name, transport, command, args, env, url, headers, bearer_token) in rows {
+        if names.as_ref().is_some_and(|names| !names.contains(&name)) {
+            continue;
+        }
+        let mut headers = redacted(json_keys(headers.as_deref()));
+        if bearer_token.is_some_and(|token| !token.is_empty()) {
+            headers.insert("Authorization".to_string(), format!("Bearer {}", REDACTED));
+        }
+        let server = match (transport.as_str(), url) {
+            ("stdio", _) | (_, None) => stdio_entry(
+                format,
+                &command,
+                split_args(&args).into_iter().map(redact_arg).collect(),
+                redacted(json_keys(env.as_deref())),
+            ),
+            (transport, Some(url)) => remote_entry(format, transport, url, headers),
+        };
+        servers.insert(name, server);
+    }
+    Ok(servers)
+}
+
+fn stdio_entry(
+    format: ConfigFormat,
+    command: &str,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+) -> Value {
+    let mut entry = Map::new();
+    if format == ConfigFormat::VsCode {
+        entry.insert("type".to_string(), "stdio".into());
+    }
+    entry.insert("command".to_string(), command.into());
+    entry.insert("args".to_string(), args.into());
+    if !env.is_empty() {
+        entry.insert("env".to_string(), serde_json::json!(env));
+    }
+    Value::Object(entry)
+}
+
+fn remote_entry(
+    format: ConfigFormat,
+    transport: &str,
+    url: String,
+    headers: BTreeMap<String, String>,
+) -> Value {
+    let mut entry = Map::new();
+    match format {
+        // Claude Desktop only runs local servers, so remote ones go through
+        // the mcp-remote proxy
+        ConfigFormat::ClaudeDesktop => {
+            let mut args = vec!["-y".to_string(), "mcp-remote".to_string(), url];
+            if transport == "sse" {
+                args.extend(["--transport".to_string(), "sse-only".to_string()]);
+            }
+            for (name, value) in headers {
+                args.extend(["--header".to_string(), format!("{}:{}", name, value)]);
+            }
+            return stdio_entry(format, "npx", args, BTreeMap::new());
+        }
+        ConfigFormat::VsCode => {
+            let kind = if transport == "sse" { "sse" } else { "http" };
+            entry.insert("type".to_string(), kind.into());
+            entry.insert("url".to_string(), url.into());
+        }
+        ConfigFormat::Windsurf => {
+            entry.insert("serverUrl".to_string(), url.into());
+        }
+        ConfigFormat::Cursor => {
+            entry.insert("url".to_string(), url.into());
+        }
+    }
+    if !headers.is_empty() {
+        entry.insert("headers".to_string(), serde_json::json!(headers));
+    }
+    Value::Object(entry)
+}
+
+fn redacted(keys: Vec<String>) -> BTreeMap<String, String> {
+    keys.into_iter()
+        .map(|key| (key, REDACTED.to_string()))
+        .collect()
+}
+
+// Redacts the value of --api-key=... and similar
+fn redact_arg(arg: String) -> String {
+    match arg.split_once('=') {
+        Some((flag, _))
+            if flag.starts_with('-')
+                && SECRET_ARG_HINTS
+                    .iter()
+                    .any(|hint| flag.to_lowercase().contains(hint)) =>
+        {
+            format!("{}={}", flag, REDACTED)
+        }
+        _ => arg,
+    }
+}
+
+// Keys of one of the JSON maps stored in custom_toolsets
+fn json_keys(value: Option<&str>) -> Vec<String> {
+    value
+        .and_then(|value| serde_json::from_str::<BTreeMap<String, Value>>(value).ok())
+        .map(|map| map.into_keys().collect())
+        .unwrap_or_default()
+}
+
+fn to_json(map: &BTreeMap<String, String>) -> Result<String, String> {
+    serde_json::to_string(map).map_err(|e| e.to_string())
+}
+
+fn read_servers(path: &Path) -> Result<(BTreeMap<String, ParsedServer>, Vec<String>), String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+    let config: Value = serde_json::from_str(&strip_jsonc(&contents))
+        .map_err(|e| format!("{} isn't valid JSON: {}", path.display(), e))?;
+
+    // VS Code also accepts servers under "mcp" in settings.json
+    let servers = ["mcpServers", "servers"]
+        .iter()
+        .find_map(|key| config.get(key))
+        .or_else(|| config.get("mcp").and_then(|mcp| mcp.get("servers")))
+        .and_then(Value::as_object)
+        .ok_or_else(|| format!("No MCP servers found in {}", path.display()))?;
+
+    let mut parsed = BTreeMap::new();
+    let mut skipped = Vec::new();
+    for (name, entry) in servers {
+        match serde_json::from_value::<ServerEntry>(entry.clone())
+            .map_err(|e| e.to_string())
+            .and_then(parse_entry)
+        {
+            Ok(server) => {
+                parsed.insert(name.clone(), server);
+            }
+            Err(e) => skipped.push(format!("{}: {}", name, e)),
+        }
+    }
+    Ok((parsed, skipped))
+}
+
+fn parse_entry(entry: ServerEntry) -> Result<ParsedServer, String> {
+    let mut warnings = Vec::new();
+    let uses_inputs = entry
+        .env
+        .values()
+        .chain(entry.headers.values())
+        .chain(&entry.args)
+        .any(|value| value.contains("${input:"));
+    if uses_inputs {
+        warnings.push("Uses VS Code input variables, fill them in after importing".to_string());
+    }
+
+    if let Some(url) = entry.url.or(entry.server_url) {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("unsupported URL {}", url));
+        }
+        // Only VS Code says which transport it is. Elsewhere, /sse is the
+        // usual sign of the older transport.
+        let transport = match entry.kind.as_deref() {
+            Some("sse") => "sse",
+            Some(_) => "streamable_http",
+            None if url.trim_end_matches('/').ends_with("/sse") => "sse",
+            None => "streamable_http",
+        };
+        return Ok(ParsedServer {
+            transport,
+            command: String::new(),
+            args: String::new(),
+            env: BTreeMap::new(),
+            url: Some(url),
+            headers: entry.headers,
+            warnings,
+        });
+    }
+
+    let command = entry
+        .command
+        .filter(|command| !command.trim().is_empty())
+        .ok_or("no command or URL")?;
+    Ok(ParsedServer {
+        transport: "stdio",
+        command,
+        args: join_args(&entry.args)?,
+        env: entry.env,
+        url: None,
+        headers: BTreeMap::new(),
+        warnings,
+    })
+}
+
+/// Inverse of `split_args`, which has no escapes: arguments are quoted with
+/// whichever quote they don't contain.
+fn join_args(args: &[String]) -> Result<String, String> {
+    args.iter()
+        .map(|arg| {
+            if !arg.is_empty()
+                && !arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'')
+            {
+                Ok(arg.clone())
+            } else if !arg.contains('"') {
+                Ok(format!("\"{}\"", arg))
+            } else if !arg.contains('\'') {
+                Ok(format!("'{}'", arg))
+            } else {
+                Err(format!("argument {} has both kinds of quotes", arg))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|args| args.join(" "))
+}
+
+/// Removes the comments and trailing commas VS Code allows in its JSON files
+fn strip_jsonc(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            if c == '\\' {
+                output.extend(chars.next());
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                output.push(' ');
+            }
+            (']' | '}', _) => {
+                let trimmed = output.trim_end().len();
+                if output[..trimmed].ends_with(',') {
+                    output.truncate(trimmed - 1);
+                }
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+// Lowercase letters, numbers and dashes, like the settings page requires
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+fn toolset_name(source_name: &str) -> String {
+    let mut name = String::new();
+    for c in source_name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let name = name.trim_end_matches('-');
+    if name.is_empty() {
+        "mcp".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+fn free_name(name: &str, taken: &HashSet<String>) -> String {
+    if !taken.contains(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}-{}", name, n))
+        .find(|candidate| !taken.contains(candidate))
+        .expect("some suffix is free")
+}
+
+fn existing_names(app_handle: &AppHandle) -> Result<HashSet<String>, String> {
+    let conn = db::open_read_only(app_handle)?;
+    let mut stmt = conn
+        .prepare("SELECT name FROM custom_toolsets")
+        .map_err(|e| e.to_string())?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(names)
+}
+
+// Same layout as storeSecrets in Vault.ts: the value goes to `prefix/<key>`
+// and a reference to it is kept in its place
+fn store_secrets(
+    app_handle: &AppHandle,
+    prefix: &str,
+    values: BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, String> {
+    values
+        .into_iter()
+        .map(|(key, value)| {
+            if value.is_empty() || value.starts_with(vault::REF_PREFIX) {
+                return Ok((key, value));
+            }
+            let name = format!("{}/{}", prefix, key);
+            vault::put(app_handle, &name, &value)?;
+            Ok((key, vault::to_ref(&name)))
+        })
+        .collect()
+}
+
+fn delete_secrets(app_handle: &AppHandle, prefix: &str) -> Result<(), String> {
+    for name in vault::list(app_handle)? {
+        if name.starts_with(prefix) {
+            vault::delete(app_handle, &name)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,512 @@
+// MCP over HTTP: the Streamable HTTP transport, and the older HTTP+SSE
+// transport that many remote servers still use. A connection is driven by the
+// supervisor in mcp.rs, which treats a lost connection like a crashed process:
+// requests in flight fail, and it reconnects with backoff and replays the
+// client's handshake.
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::async_runtime::{self, JoinHandle};
+use tokio::sync::mpsc;
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Messages queued for posting. The client waits for responses, so this only
+// fills up if the server stops answering.
+const OUTGOING_CAPACITY: usize = 256;
+
+// Streamable HTTP servers may close the stream for server-initiated messages
+// at any time. It's reopened with this backoff, resuming from the last event.
+const STREAM_RETRY_BASE: Duration = Duration::from_secs(1);
+const STREAM_RETRY_MAX: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HttpTransport {
+    /// A single endpoint that's posted to, answering with JSON or an event
+    /// stream (MCP 2025-03-26 and later)
+    StreamableHttp,
+    /// An event stream that announces where to post messages (MCP 2024-11-05)
+    Sse,
+}
+
+pub struct HttpOptions {
+    pub transport: HttpTransport,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    /// Sent as `Authorization: Bearer <token>`
+    pub bearer_token: Option<String>,
+}
+
+pub enum HttpEvent {
+    Message(Value),
+    Log(String),
+    /// The session can't be used anymore. Nothing else is delivered after this.
+    Closed(String),
+}
+
+enum Failure {
+    /// The session or the credentials are no good, so the supervisor has to
+    /// start over
+    Connection(String),
+    /// Only this message failed
+    Message(String),
+}
+
+struct Shared {
+    client: Client,
+    transport: HttpTransport,
+    url: Url,
+    headers: HeaderMap,
+    session_id: Mutex<Option<String>>,
+    protocol_version: Mutex<Option<String>>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+    on_event: Box<dyn Fn(HttpEvent) + Send + Sync>,
+}
+
+pub struct HttpConnection {
+    outgoing: mpsc::Sender<Value>,
+    shared: Arc<Shared>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn header_map(
+    headers: &HashMap<String, String>,
+    bearer_token: Option<&str>,
+) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("Invalid header name: {}", name))?;
+        let mut value = HeaderValue::from_str(value)
+            .map_err(|_| format!("Invalid value for header {}", name))?;
+        value.set_sensitive(true);
+        map.insert(name, value);
+    }
+    if let Some(token) = bearer_token.filter(|token| !token.is_empty()) {
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| "The bearer token contains invalid characters".to_string())?;
+        value.set_sensitive(true);
+        map.insert(AUTHORIZATION, value);
+    }
+    Ok(map)
+}
+
+/// Opens a connection. Nothing is sent until the first message, except that
+/// the SSE transport connects to its event stream right away.
+pub fn connect(
+    options: HttpOptions,
+    on_event: impl Fn(HttpEvent) + Send + Sync + 'static,
+) -> Result<HttpConnection, String> {
+    let url =
+        Url::parse(&options.url).map_err(|e| format!("Invalid URL {}: {}", options.url, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("{} isn't an HTTP URL", url));
+    }
+    let client = Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let shared = Arc::new(Shared {
+        client,
+        transport: options.transport,
+        url: url.clone(),
+        headers: header_map(&options.headers, options.bearer_token.as_deref())?,
+        session_id: Mutex::new(None),
+        protocol_version: Mutex::new(None),
+        tasks: Mutex::new(Vec::new()),
+        on_event: Box::new(on_event),
+    });
+
+    let (outgoing, frames) = mpsc::channel(OUTGOING_CAPACITY);
+    match options.transport {
+        HttpTransport::StreamableHttp => shared.spawn(post_loop(shared.clone(), frames, url)),
+        HttpTransport::Sse => shared.spawn(sse_session(shared.clone(), frames)),
+    }
+    Ok(HttpConnection { outgoing, shared })
+}
+
+impl HttpConnection {
+    /// Queues a message. Responses arrive through the event handler.
+    pub fn send(&self, frame: Value) -> Result<(), String> {
+        self.outgoing.try_send(frame).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(_) => {
+                "The MCP server isn't accepting messages".to_string()
+            }
+            mpsc::error::TrySendError::Closed(_) => "The connection is closed".to_string(),
+        })
+    }
+
+    /// Ends the session. Streamable HTTP servers are told about it, so they
+    /// can free it right away.
+    pub fn close(self) {
+        let session_id = lock(&self.shared.session_id).clone();
+        if let (HttpTransport::StreamableHttp, Some(_)) = (self.shared.transport, session_id) {
+            let request = self
+                .shared
+                .request(self.shared.client.delete(self.shared.url.clone()));
+            // Not tracked in `tasks`, so dropping the connection doesn't cancel it
+            async_runtime::spawn(async move {
+                let _ = request.send().await;
+            });
+        }
+    }
+}
+
+impl Drop for HttpConnection {
+    fn drop(&mut self) {
+        for task in lock(&self.shared.tasks).drain(..) {
+            task.abort();
+        }
+    }
+}
+
+impl Shared {
+    fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) {
+        let mut tasks = lock(&self.tasks);
+        tasks.retain(|task| !task.inner().is_finished());
+        tasks.push(async_runtime::spawn(task));
+    }
+
+    fn emit(&self, event: HttpEvent) {
+        (self.on_event)(event);
+    }
+
+    fn request(&self, builder: RequestBuilder) -> RequestBuilder {
+        let mut builder = builder.headers(self.headers.clone());
+        if let Some(session_id) = lock(&self.session_id).as_deref() {
+            builder = builder.header(SESSION_ID_HEADER, session_id);
+        }
+        if let Some(version) = lock(&self.protocol_version).as_deref() {
+            builder = builder.header(PROTOCOL_VERSION_HEADER, version);
+        }
+        builder
+    }
+
+    fn check_status(&self, response: &Response) -> Result<(), Failure> {
+        let status = response.status();
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(Failure::Connection(format!(
+                "The MCP server rejected our credentials ({})",
+                status
+            ))),
+            // Servers answer 404 to requests for a session they've dropped
+            StatusCode::NOT_FOUND if lock(&self.session_id).is_some() => Err(Failure::Connection(
+                "The MCP server ended the session".to_string(),
+            )),
+            _ if status.is_success() => Ok(()),
+            _ => Err(Failure::Message(format!(
+                "The MCP server responded with {}",
+                status
+            ))),
+        }
+    }
+
+    async fn post(&self, endpoint: &Url, frame: &Value) -> Result<Response, Failure> {
+        let response = self
+            .request(self.client.post(endpoint.clone()))
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT, "application/json, text/event-stream")
+            .json(frame)
+            .send()
+            .await
+            .map_err(|e| Failure::Connection(format!("Couldn't reach {}: {}", endpoint, e)))?;
+        self.check_status(&response)?;
+        if self.transport == HttpTransport::StreamableHttp {
+            if let Some(session_id) = response
+                .headers()
+                .get(SESSION_ID_HEADER)
+                .and_then(|value| value.to_str().ok())
+            {
+                *lock(&self.session_id) = Some(session_id.to_string());
+            }
+        }
+        Ok(response)
+    }
+
+    /// Opens an event stream. Returns None if the server doesn't offer one.
+    async fn open_stream(&self, last_event_id: Option<&str>) -> Result<Option<Response>, Failure> {
+        let mut request = self
+            .request(self.client.get(self.url.clone()))
+            .header(ACCEPT, "text/event-stream");
+        if let Some(id) = last_event_id {
+            request = request.header(LAST_EVENT_ID_HEADER, id);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| Failure::Message(format!("Couldn't reach {}: {}", self.url, e)))?;
+        if response.status() == StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(None);
+        }
+        self.check_status(&response)?;
+        Ok(Some(response))
+    }
+
+    // Reads an event stream until it ends
+    async fn read_stream(
+        &self,
+        mut response: Response,
+        mut on_event: impl FnMut(SseEvent),
+    ) -> Result<(), String> {
+        let mut parser = SseParser::default();
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            for event in parser.push(&chunk) {
+                on_event(event);
+            }
+        }
+        Ok(())
+    }
+
+    fn deliver(&self, frame: Value) {
+        // Later requests have to carry the negotiated version
+        if self.transport == HttpTransport::StreamableHttp {
+            if let Some(version) = frame
+                .pointer("/result/protocolVersion")
+                .and_then(Value::as_str)
+            {
+                *lock(&self.protocol_version) = Some(version.to_string());
+            }
+        }
+        self.emit(HttpEvent::Message(frame));
+    }
+
+    // Bodies and events hold a single message or a batch of them
+    fn deliver_json(&self, json: &[u8]) {
+        match serde_json::from_slice::<Value>(json) {
+            Ok(Value::Array(frames)) => frames.into_iter().for_each(|frame| self.deliver(frame)),
+            Ok(frame) => self.deliver(frame),
+            Err(e) => self.emit(HttpEvent::Log(format!(
+                "[http] invalid message from the server: {}",
+                e
+            ))),
+        }
+    }
+
+    fn deliver_event(&self, event: SseEvent) {
+        if event.event == "message" {
+            self.deliver_json(event.data.as_bytes());
+        }
+    }
+
+    // Answers a request that couldn't be sent, so the client isn't left waiting
+    fn fail_message(&self, frame: &Value, error: String) {
+        match (frame.get("method"), frame.get("id")) {
+            (Some(_), Some(id)) => self.emit(HttpEvent::Message(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32000, "message": error },
+            }))),
+            _ => self.emit(HttpEvent::Log(format!("[http] {}", error))),
+        }
+    }
+}
+
+// Posts messages in order. Only the response headers are awaited, so a slow
+// request doesn't hold up the ones after it.
+async fn post_loop(shared: Arc<Shared>, mut frames: mpsc::Receiver<Value>, endpoint: Url) {
+    while let Some(frame) = frames.recv().await {
+        let response = match shared.post(&endpoint, &frame).await {
+            Ok(response) => response,
+            Err(Failure::Connection(error)) => return shared.emit(HttpEvent::Closed(error)),
+            Err(Failure::Message(error)) => {
+                shared.fail_message(&frame, error);
+                continue;
+            }
+        };
+        // The SSE transport answers on its event stream instead
+        if shared.transport == HttpTransport::Sse {
+            continue;
+        }
+        if frame.get("method").and_then(Value::as_str) == Some("notifications/initialized") {
+            shared.spawn(listen(shared.clone()));
+        }
+        shared.spawn(read_response(shared.clone(), response));
+    }
+}
+
+async fn read_response(shared: Arc<Shared>, response: Response) {
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let result = if content_type.starts_with("text/event-stream") {
+        shared
+            .read_stream(response, |event| shared.deliver_event(event))
+            .await
+    } else if content_type.starts_with("application/json") {
+        response
+            .bytes()
+            .await
+            .map(|body| {
+                if !body.is_empty() {
+                    shared.deliver_json(&body);
+                }
+            })
+            .map_err(|e| e.to_string())
+    } else {
+        // 202 Accepted for notifications and responses
+        Ok(())
+    };
+    if let Err(e) = result {
+        shared.emit(HttpEvent::Log(format!("[http] response broke off: {}", e)));
+    }
+}
+
+// Streamable HTTP: the stream for messages the server sends on its own, like
+// notifications/tools/list_changed
+async fn listen(shared: Arc<Shared>) {
+    let mut last_event_id: Option<String> = None;
+    let mut attempt = 0;
+    loop {
+        match shared.open_stream(last_event_id.as_deref()).await {
+            Ok(None) => return,
+            Ok(Some(response)) => {
+                attempt = 0;
+                let result = shared
+                    .read_stream(response, |event| {
+                        if event.id.is_some() {
+                            last_event_id = event.id.clone();
+                        }
+                        shared.deliver_event(event);
+                    })
+                    .await;
+                if let Err(e) = result {
+                    shared.emit(HttpEvent::Log(format!(
+                        "[http] event stream broke off: {}",
+                        e
+                    )));
+                }
+            }
+            Err(Failure::Connection(error)) => return shared.emit(HttpEvent::Closed(error)),
+            Err(Failure::Message(error)) => {
+                shared.emit(HttpEvent::Log(format!("[http] {}", error)))
+            }
+        }
+        attempt += 1;
+        let delay = STREAM_RETRY_BASE
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(STREAM_RETRY_MAX);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+// The SSE transport: the server's first event says where to post messages,
+// and responses arrive on the stream. The session ends with the stream.
+async fn sse_session(shared: Arc<Shared>, frames: mpsc::Receiver<Value>) {
+    let mut frames = Some(frames);
+    let result = async {
+        let response = match shared.open_stream(None).await {
+            Ok(Some(response)) => response,
+            Ok(None) => return Err("The server doesn't support the SSE transport".to_string()),
+            Err(Failure::Connection(error) | Failure::Message(error)) => return Err(error),
+        };
+        let mut error = None;
+        shared
+            .read_stream(response, |event| match event.event.as_str() {
+                "endpoint" => match shared.url.join(event.data.trim()) {
+                    // Don't send our credentials anywhere else
+                    Ok(endpoint) if endpoint.origin() == shared.url.origin() => {
+                        if let Some(frames) = frames.take() {
+                            shared.spawn(post_loop(shared.clone(), frames, endpoint));
+                        }
+                    }
+                    _ => {
+                        error.get_or_insert_with(|| {
+                            format!("The server sent an invalid endpoint: {}", event.data)
+                        });
+                    }
+                },
+                _ => shared.deliver_event(event),
+            })
+            .await?;
+        Err(error.unwrap_or_else(|| "The server closed the event stream".to_string()))
+    }
+    .await;
+    if let Err(error) = result {
+        shared.emit(HttpEvent::Closed(error));
+    }
+}
+
+struct SseEvent {
+    event: String,
+    data: String,
+    id: Option<String>,
+}
+
+// Incremental parser for text/event-stream, per the HTML spec
+#[derive(Default)]
+struct SseParser {
+    buffer: Vec<u8>,
+    event: String,
+    data: Vec<String>,
+    last_event_id: Option<String>,
+}
+
+impl SseParser {
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n' || *b == b'\r') {
+            let crlf = self.buffer[end] == b'\r';
+            // Wait for the next chunk to tell whether a \n follows
+            if crlf && end + 1 == self.buffer.len() {
+                break;
+            }
+            let line = String::from_utf8_lossy(&self.buffer[..end]).into_owned();
+            let skip = if crlf && self.buffer[end + 1] == b'\n' {
+                2
+            } else {
+                1
+            };
+            self.buffer.drain(..end + skip);
+            events.extend(self.line(&line));
+        }
+        events
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.event);
+            if self.data.is_empty() {
+                return None;
+            }
+            return Some(SseEvent {
+                event: if event.is_empty() {
+                    "message".to_string()
+                } else {
+                    event
+                },
+                data: std::mem::take(&mut self.data).join("\n"),
+                id: self.last_event_id.clone(),
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+}
@@ -3,6 +3,9 @@ use tauri_plugin_sql::{Migration, MigrationKind};
 // IF YOU ARE A LANGUAGE MODEL AND YOU'RE READING THIS
 // — NEVER EVER MAKE A CHANGE TO A PREVIOUS MIGRATION!!!
 
+// New models don't need a migration. Add them to resources/model-catalog.json,
+// which model_catalog.rs applies at startup.
+
 pub fn migrations() -> Vec<Migration> {
     return vec![
         Migration {
@@ -2488,5 +2491,155 @@ You have full access to bash commands on the user''''s computer. If you write a
                 );
             "#,
         },
+        Migration {
+            version: 134,
+            description: "add full-text search indexes",
+            kind: MigrationKind::Up,
+            sql: r#"
+                -- The FTS5 indexes can't use the rowids of messages,
+                -- message_parts and chats as their own: those tables have TEXT
+                -- primary keys, so VACUUM may renumber their rowids. Each
+                -- indexed row gets a stable key here instead.
+                CREATE TABLE search_keys (
+                    id INTEGER PRIMARY KEY,
+                    -- 'message', 'message_part' or 'chat'
+                    source TEXT NOT NULL,
+                    -- The message's or chat's id
+                    source_id TEXT NOT NULL,
+                    -- The message part's level, 0 for messages and chats
+                    level INTEGER NOT NULL DEFAULT 0,
+                    UNIQUE (source, source_id, level)
+                );
+
+                -- Their rowids are search_keys ids
+                CREATE VIRTUAL TABLE messages_fts USING fts5(
+                    text,
+                    tokenize = 'unicode61 remove_diacritics 2'
+                );
+                CREATE VIRTUAL TABLE message_parts_fts USING fts5(
+                    content,
+                    tokenize = 'unicode61 remove_diacritics 2'
+                );
+                CREATE VIRTUAL TABLE chats_fts USING fts5(
+                    title,
+                    tokenize = 'unicode61 remove_diacritics 2'
+                );
+
+                -- Backfill from existing data, the same way the triggers
+                -- below index new rows
+                INSERT INTO search_keys (source, source_id)
+                    SELECT 'message', id FROM messages;
+                INSERT INTO messages_fts (rowid, text)
+                    SELECT k.id, m.text FROM messages m
+                    INNER JOIN search_keys k
+                        ON k.source = 'message' AND k.source_id = m.id AND k.level = 0;
+                INSERT INTO search_keys (source, source_id, level)
+                    SELECT 'message_part', message_id, level FROM message_parts;
+                INSERT INTO message_parts_fts (rowid, content)
+                    SELECT k.id, mp.content FROM message_parts mp
+                    INNER JOIN search_keys k
+                        ON k.source = 'message_part' AND k.source_id = mp.message_id
+                        AND k.level = mp.level;
+                INSERT INTO search_keys (source, source_id)
+                    SELECT 'chat', id FROM chats;
+                INSERT INTO chats_fts (rowid, title)
+                    SELECT k.id, COALESCE(c.title, '') FROM chats c
+                    INNER JOIN search_keys k
+                        ON k.source = 'chat' AND k.source_id = c.id AND k.level = 0;
+
+                -- Keep messages_fts in sync
+                CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+                    INSERT OR IGNORE INTO search_keys (source, source_id) VALUES ('message', new.id);
+                    INSERT OR REPLACE INTO messages_fts (rowid, text)
+                        SELECT id, new.text FROM search_keys
+                        WHERE source = 'message' AND source_id = new.id AND level = 0;
+                END;
+                CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+                    DELETE FROM messages_fts WHERE rowid = (
+                        SELECT id FROM search_keys
+                        WHERE source = 'message' AND source_id = old.id AND level = 0
+                    );
+                    DELETE FROM search_keys
+                        WHERE source = 'message' AND source_id = old.id AND level = 0;
+                END;
+                CREATE TRIGGER messages_fts_update AFTER UPDATE OF text ON messages
+                WHEN old.text IS NOT new.text BEGIN
+                    INSERT OR IGNORE INTO search_keys (source, source_id) VALUES ('message', new.id);
+                    INSERT OR REPLACE INTO messages_fts (rowid, text)
+                        SELECT id, new.text FROM search_keys
+                        WHERE source = 'message' AND source_id = new.id AND level = 0;
+                END;
+
+                -- Keep message_parts_fts in sync
+                CREATE TRIGGER message_parts_fts_insert AFTER INSERT ON message_parts BEGIN
+                    INSERT OR IGNORE INTO search_keys (source, source_id, level)
+                        VALUES ('message_part', new.message_id, new.level);
+                    INSERT OR REPLACE INTO message_parts_fts (rowid, content)
+                        SELECT id, new.content FROM search_keys
+                        WHERE source = 'message_part' AND source_id = new.message_id
+                            AND level = new.level;
+                END;
+                CREATE TRIGGER message_parts_fts_delete AFTER DELETE ON message_parts BEGIN
+                    DELETE FROM message_parts_fts WHERE rowid = (
+                        SELECT id FROM search_keys
+                        WHERE source = 'message_part' AND source_id = old.message_id
+                            AND level = old.level
+                    );
+                    DELETE FROM search_keys
+                        WHERE source = 'message_part' AND source_id = old.message_id
+                            AND level = old.level;
+                END;
+                CREATE TRIGGER message_parts_fts_update AFTER UPDATE OF content ON message_parts
+                WHEN old.content IS NOT new.content BEGIN
+                    INSERT OR IGNORE INTO search_keys (source, source_id, level)
+                        VALUES ('message_part', new.message_id, new.level);
+                    INSERT OR REPLACE INTO message_parts_fts (rowid, content)
+                        SELECT id, new.content FROM search_keys
+                        WHERE source = 'message_part' AND source_id = new.message_id
+                            AND level = new.level;
+                END;
+
+                -- Keep chats_fts in sync
+                CREATE TRIGGER chats_fts_insert AFTER INSERT ON chats BEGIN
+                    INSERT OR IGNORE INTO search_keys (source, source_id) VALUES ('chat', new.id);
+                    INSERT OR REPLACE INTO chats_fts (rowid, title)
+                        SELECT id, COALESCE(new.title, '') FROM search_keys
+                        WHERE source = 'chat' AND source_id = new.id AND level = 0;
+                END;
+                CREATE TRIGGER chats_fts_delete AFTER DELETE ON chats BEGIN
+                    DELETE FROM chats_fts WHERE rowid = (
+                        SELECT id FROM search_keys
+                        WHERE source = 'chat' AND source_id = old.id AND level = 0
+                    );
+                    DELETE FROM search_keys
+                        WHERE source = 'chat' AND source_id = old.id AND level = 0;
+                END;
+                CREATE TRIGGER chats_fts_update AFTER UPDATE OF title ON chats
+                WHEN old.title IS NOT new.title BEGIN
+                    INSERT OR IGNORE INTO search_keys (source, source_id) VALUES ('chat', new.id);
+                    INSERT OR REPLACE INTO chats_fts (rowid, title)
+                        SELECT id, COALESCE(new.title, '') FROM search_keys
+                        WHERE source = 'chat' AND source_id = new.id AND level = 0;
+                END;
+            "#,
+        },
+        Migration {
+            version: 135,
+            description: "add remote transports to custom toolsets",
+            kind: MigrationKind::Up,
+            sql: r#"
+                -- Remote MCP servers are connected to over HTTP instead of being run
+                ALTER TABLE custom_toolsets ADD COLUMN transport TEXT NOT NULL DEFAULT 'stdio'
+                    CHECK (transport IN ('stdio', 'streamable_http', 'sse'));
+                ALTER TABLE custom_toolsets ADD COLUMN url TEXT;
+                -- Header names to values. Values may be vault references.
+                ALTER TABLE custom_toolsets ADD COLUMN headers JSON
+                    CHECK (headers IS NULL OR json_valid(headers));
+                -- Vault reference to the token sent as `Authorization: Bearer`
+                ALTER TABLE custom_toolsets ADD COLUMN bearer_token TEXT;
+                -- Servers added as `npx -y mcp-remote <url>` stay on stdio, since
+                -- mcp-remote runs their OAuth flow
+            "#,
+        },
     ];
 }
@@ -0,0 +1,381 @@
+use rusqlite::backup::Backup;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha384};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_sql::{Migration, MigrationKind};
+
+use crate::{db, migrations, model_catalog};
+
+// Migrations used to be applied by tauri_plugin_sql, which goes through sqlx.
+// We keep writing sqlx's bookkeeping table in the same format, so databases
+// stay compatible with older builds of the app.
+const MIGRATIONS_TABLE_SQL: &str = r#"
+    CREATE TABLE IF NOT EXISTS _sqlx_migrations (
+        version BIGINT PRIMARY KEY,
+        description TEXT NOT NULL,
+        installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        success BOOLEAN NOT NULL,
+        checksum BLOB NOT NULL,
+        execution_time BIGINT NOT NULL
+    );
+"#;
+
+const SNAPSHOT_FILE_NAME: &str = "pre-migration.db";
+
+pub const PROGRESS_EVENT: &str = "migration-progress";
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum MigrationProgress {
+    Snapshot,
+    Applying {
+        version: i64,
+        description: String,
+        index: usize,
+        total: usize,
+    },
+    Verifying,
+    RollingBack {
+        error: String,
+    },
+    Done {
+        applied: usize,
+    },
+}
+
+enum Status {
+    Running,
+    Done,
+    Failed(String),
+}
+
+/// Tracks whether startup migrations have finished, so the frontend can wait
+/// for them before opening the database.
+pub struct MigrationState {
+    status: Mutex<Status>,
+    finished: Condvar,
+}
+
+impl Default for MigrationState {
+    fn default() -> Self {
+        Self {
+            status: Mutex::new(Status::Running),
+            finished: Condvar::new(),
+        }
+    }
+}
+
+impl MigrationState {
+    fn finish(&self, status: Status) {
+        *self.status.lock().unwrap() = status;
+        self.finished.notify_all();
+    }
+}
+
+fn emit_progress(app_handle: &AppHandle, progress: MigrationProgress) {
+    let _ = app_handle.emit(PROGRESS_EVENT, progress);
+}
+
+fn checksum(migration: &Migration) -> Vec<u8> {
+    Sha384::digest(migration.sql.as_bytes()).to_vec()
+}
+
+/// Returns the migrations that haven't been applied yet, after checking that
+/// the applied ones match the current list.
+fn pending_migrations<'a>(
+    conn: &Connection,
+    migrations: &'a [Migration],
+) -> Result<Vec<&'a Migration>, String> {
+    conn.execute_batch(MIGRATIONS_TABLE_SQL)
+        .map_err(|e| e.to_string())?;
+
+    let dirty: Option<i64> = conn
+        .query_row(
+            "SELECT version FROM _sqlx_migrations WHERE success = false ORDER BY version LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(version) = dirty {
+        return Err(format!("Migration {} was left partially applied", version));
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT version, checksum FROM _sqlx_migrations ORDER BY version")
+        .map_err(|e| e.to_string())?;
+    let applied: HashMap<i64, Vec<u8>> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    for version in applied.keys() {
+        if !migrations
+            .iter()
+            .any(|migration| migration.version == *version)
+        {
+            return Err(format!(
+                "The database was upgraded by a newer version of Chorus (migration {} is unknown)",
+                version
+            ));
+        }
+    }
+
+    let mut pending = Vec::new();
+    for migration in migrations {
+        if !matches!(migration.kind, MigrationKind::Up) {
+            continue;
+        }
+        match applied.get(&migration.version) {
+            Some(applied_checksum) if *applied_checksum != checksum(migration) => {
+                return Err(format!(
+                    "Migration {} was modified after it was applied",
+                    migration.version
+                ));
+            }
+            Some(_) => {}
+            None => pending.push(migration),
+        }
+    }
+    Ok(pending)
+}
+
+/// Applies one migration and records it, in a single transaction.
+fn apply(conn: &mut Connection, migration: &Migration) -> Result<(), String> {
+    let start = Instant::now();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute_batch(migration.sql).map_err(|e| {
+        format!(
+            "Migration {} ({}) failed: {}",
+            migration.version, migration.description, e
+        )
+    })?;
+    tx.execute(
+        "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+         VALUES (?1, ?2, TRUE, ?3, -1)",
+        params![
+            migration.version,
+            migration.description,
+            checksum(migration)
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE _sqlx_migrations SET execution_time = ?1 WHERE version = ?2",
+        params![start.elapsed().as_nanos() as i64, migration.version],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn integrity_errors(conn: &Connection) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows.into_iter().filter(|row| row != "ok").collect())
+}
+
+fn foreign_key_violations(conn: &Connection) -> Result<usize, String> {
+    let mut stmt = conn
+        .prepare("PRAGMA foreign_key_check")
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    let mut count = 0;
+    while rows.next().map_err(|e| e.to_string())?.is_some() {
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Where the database is copied before migrating, so it can be rolled back.
+pub fn snapshot_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(db::db_path(app_handle)?.with_file_name(SNAPSHOT_FILE_NAME))
+}
+
+fn copy_database(from: &Connection, to: &mut Connection) -> Result<(), String> {
+    Backup::new(from, to)
+        .and_then(|backup| backup.run_to_completion(256, Duration::from_millis(0), None))
+        .map_err(|e| e.to_string())
+}
+
+/// Applies pending migrations, then checks the result. If anything goes wrong
+/// the database is put back the way it was before the first pending migration.
+fn run(app_handle: &AppHandle) -> Result<usize, String> {
+    db::wait_until_unlocked(app_handle)?;
+    let mut conn = db::open(app_handle)?;
+    // sqlx turns foreign key enforcement on for every connection, so all past
+    // migrations were written against it
+    conn.pragma_update(None, "foreign_keys", "ON")
+        .map_err(|e| e.to_string())?;
+
+    let migrations = migrations::migrations();
+    let pending = pending_migrations(&conn, &migrations)?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+    println!("Applying {} pending migrations", pending.len());
+
+    emit_progress(app_handle, MigrationProgress::Snapshot);
+    let snapshot_path = snapshot_path(app_handle)?;
+    let _ = fs::remove_file(&snapshot_path);
+    let mut snapshot = db::open_copy(&snapshot_path, OpenFlags::default())?;
+    copy_database(&conn, &mut snapshot)
+        .map_err(|e| format!("Couldn't snapshot the database before migrating: {}", e))?;
+    drop(snapshot);
+    let fk_violations_before = foreign_key_violations(&conn)?;
+
+    let result = (|| {
+        let total = pending.len();
+        for (index, migration) in pending.iter().enumerate() {
+            emit_progress(
+                app_handle,
+                MigrationProgress::Applying {
+                    version: migration.version,
+                    description: migration.description.to_string(),
+                    index,
+                    total,
+                },
+            );
+            apply(&mut conn, migration)?;
+        }
+
+        emit_progress(app_handle, MigrationProgress::Verifying);
+        let errors = integrity_errors(&conn)?;
+        if !errors.is_empty() {
+            return Err(format!(
+                "Integrity check failed after migrating: {}",
+                errors.join("; ")
+            ));
+        }
+        // Older databases may already contain dangling references, so only
+        // fail on violations introduced by the migrations themselves
+        let fk_violations_after = foreign_key_violations(&conn)?;
+        if fk_violations_after > fk_violations_before {
+            return Err(format!(
+                "Migrations introduced {} foreign key violations",
+                fk_violations_after - fk_violations_before
+            ));
+        }
+        Ok(total)
+    })();
+
+    match result {
+        Ok(applied) => {
+            emit_progress(app_handle, MigrationProgress::Done { applied });
+            Ok(applied)
+        }
+        Err(e) => {
+            emit_progress(
+                app_handle,
+                MigrationProgress::RollingBack { error: e.clone() },
+            );
+            rollback(&mut conn, &snapshot_path).map_err(|rollback_error| {
+                format!(
+                    "{}\n\nRestoring the database also failed: {}. A copy of your data from before the upgrade is at {}",
+                    e,
+                    rollback_error,
+                    snapshot_path.display()
+                )
+            })?;
+            Err(e)
+        }
+    }
+}
+
+fn rollback(conn: &mut Connection, snapshot_path: &Path) -> Result<(), String> {
+    let snapshot = db::open_copy(snapshot_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    copy_database(&snapshot, conn)?;
+    let errors = integrity_errors(conn)?;
+    if !errors.is_empty() {
+        return Err(format!("integrity check failed: {}", errors.join("; ")));
+    }
+    Ok(())
+}
+
+/// Runs startup migrations on a background thread, then brings the built-in
+/// models up to date with the model catalog. If migrating fails the user is
+/// told what happened and the app quits, since the frontend can't run against
+/// a schema it doesn't expect.
+pub fn start(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        let result = run(&app_handle);
+        let state = app_handle.state::<MigrationState>();
+        match result {
+            Ok(applied) => {
+                if applied > 0 {
+                    println!("Applied {} migrations", applied);
+                }
+                // The catalog only adds and updates rows, so a bad one is
+                // logged rather than blocking startup
+                match model_catalog::reconcile(&app_handle) {
+                    Ok(Some(report)) => println!(
+                        "Model catalog applied: added {} models and {} configs ({} deprecated, {} selections moved to successors)",
+                        report.models_added,
+                        report.configs_added,
+                        report.deprecated,
+                        report.selections_migrated
+                    ),
+                    Ok(None) => {}
+                    Err(e) => println!("Failed to apply model catalog: {}", e),
+                }
+                state.finish(Status::Done);
+            }
+            Err(e) => {
+                println!("Migrations failed: {}", e);
+                state.finish(Status::Failed(e.clone()));
+                app_handle
+                    .dialog()
+                    .message(format!(
+                        "Chorus couldn't upgrade its database, so your data has been left as it was before the upgrade.\n\n{}",
+                        e
+                    ))
+                    .title("Database upgrade failed")
+                    .kind(MessageDialogKind::Error)
+                    .blocking_show();
+                app_handle.exit(1);
+            }
+        }
+    });
+}
+
+/// Migrates again once the database has been swapped for another profile's.
+pub fn restart(app_handle: AppHandle) {
+    app_handle.state::<MigrationState>().finish(Status::Running);
+    start(app_handle);
+}
+
+/// Whether startup migrations have finished without an error, after which the
+/// webviews open the database.
+pub fn is_done(app_handle: &AppHandle) -> bool {
+    let state = app_handle.state::<MigrationState>();
+    let status = state.status.lock().unwrap();
+    matches!(*status, Status::Done)
+}
+
+/// Blocks until startup migrations have finished.
+pub fn wait(app_handle: &AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<MigrationState>();
+    let mut status = state.status.lock().unwrap();
+    while matches!(*status, Status::Running) {
+        status = state.finished.wait(status).unwrap();
+    }
+    match &*status {
+        Status::Failed(e) => Err(e.clone()),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,428 @@
+use chrono::NaiveDateTime;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::{data_dir, db};
+
+// Built-in models ship in this file rather than as migrations, so adding a
+// model doesn't need a schema change
+const BUNDLED_CATALOG: &str = include_str!("../resources/model-catalog.json");
+
+// A catalog in the app config dir (or at $CHORUS_MODEL_CATALOG) is applied on
+// top of the bundled one, so models can be added without a release
+const LOCAL_CATALOG_FILE_NAME: &str = "model-catalog.json";
+const LOCAL_CATALOG_ENV: &str = "CHORUS_MODEL_CATALOG";
+
+const VERSION_KEY: &str = "model_catalog_version";
+const CHECKSUM_KEY: &str = "model_catalog_checksum";
+
+// Same format the migrations used for new_until
+const NEW_UNTIL_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+const ATTACHMENT_TYPES: &[&str] = &["text", "image", "webpage", "pdf"];
+
+// app_metadata keys holding a single model config id picked by the user
+const SELECTED_CONFIG_KEYS: &[&str] = &[
+    "selected_model_config_chat",
+    "quick_chat_model_config_id",
+    "internal_task_model_config_id",
+];
+
+// app_metadata keys holding a JSON array of model config ids
+const SELECTED_CONFIG_LIST_KEYS: &[&str] = &["selected_model_configs_compare"];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Catalog {
+    version: i64,
+    models: Vec<CatalogModel>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogModel {
+    id: String,
+    display_name: String,
+    #[serde(default)]
+    supported_attachment_types: Vec<String>,
+    #[serde(default)]
+    is_internal: bool,
+    #[serde(default)]
+    deprecated: bool,
+    /// Model that replaces this one in the user's selections once deprecated
+    successor: Option<String>,
+    new_until: Option<String>,
+    /// Configs to create for this model. Defaults to a single config with the
+    /// model's id and name, which is what the old migrations did.
+    #[serde(default)]
+    configs: Vec<CatalogConfig>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CatalogConfig {
+    id: String,
+    display_name: String,
+    #[serde(default)]
+    system_prompt: String,
+    budget_tokens: Option<i64>,
+    reasoning_effort: Option<String>,
+    new_until: Option<String>,
+}
+
+impl CatalogModel {
+    fn configs(&self) -> Vec<CatalogConfig> {
+        if !self.configs.is_empty() {
+            return self.configs.clone();
+        }
+        vec![CatalogConfig {
+            id: self.id.clone(),
+            display_name: self.display_name.clone(),
+            system_prompt: String::new(),
+            budget_tokens: None,
+            reasoning_effort: None,
+            new_until: self.new_until.clone(),
+        }]
+    }
+}
+
+#[derive(Default)]
+pub struct ReconcileReport {
+    pub models_added: usize,
+    pub configs_added: usize,
+    pub deprecated: usize,
+    pub selections_migrated: usize,
+}
+
+fn parse(contents: &str, source: &str) -> Result<Catalog, String> {
+    let catalog: Catalog = serde_json::from_str(contents)
+        .map_err(|e| format!("Invalid model catalog {}: {}", source, e))?;
+    validate(&catalog).map_err(|e| format!("Invalid model catalog {}: {}", source, e))?;
+    Ok(catalog)
+}
+
+fn validate(catalog: &Catalog) -> Result<(), String> {
+    let mut model_ids = HashSet::new();
+    let mut config_ids = HashSet::new();
+    for model in &catalog.models {
+        if !model.id.contains("::") {
+            return Err(format!(
+                "model id '{}' must be of the form provider::model",
+                model.id
+            ));
+        }
+        if !model_ids.insert(&model.id) {
+            return Err(format!("model '{}' is listed twice", model.id));
+        }
+        for attachment_type in &model.supported_attachment_types {
+            if !ATTACHMENT_TYPES.contains(&attachment_type.as_str()) {
+                return Err(format!(
+                    "model '{}' has unknown attachment type '{}'",
+                    model.id, attachment_type
+                ));
+            }
+        }
+        if model.successor.is_some() && !model.deprecated {
+            return Err(format!(
+                "model '{}' has a successor but isn't deprecated",
+                model.id
+            ));
+        }
+        if model.successor.as_ref() == Some(&model.id) {
+            return Err(format!("model '{}' is its own successor", model.id));
+        }
+        for config in model.configs() {
+            if !config_ids.insert(config.id.clone()) {
+                return Err(format!("config '{}' is listed twice", config.id));
+            }
+            if let Some(new_until) = &config.new_until {
+                NaiveDateTime::parse_from_str(new_until, NEW_UNTIL_FORMAT).map_err(|_| {
+                    format!(
+                        "config '{}' has new_until '{}', expected YYYY-MM-DD HH:MM:SS",
+                        config.id, new_until
+                    )
+                })?;
+            }
+            if let Some(effort) = &config.reasoning_effort {
+                if !["low", "medium", "high"].contains(&effort.as_str()) {
+                    return Err(format!(
+                        "config '{}' has unknown reasoning effort '{}'",
+                        config.id, effort
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn local_catalog_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    if let Ok(path) = std::env::var(LOCAL_CATALOG_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+    let app_dir = data_dir::config_dir(app_handle)?;
+    Ok(app_dir.join(LOCAL_CATALOG_FILE_NAME))
+}
+
+fn get_metadata(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT value FROM app_metadata WHERE key = ?1",
+        [key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn set_metadata(tx: &Transaction, key: &str, value: &str) -> Result<(), String> {
+    tx.execute(
+        "INSERT OR REPLACE INTO app_metadata (key, value) VALUES (?1, ?2)",
+        params![key, value],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn exists(tx: &Transaction, table: &str, id: &str) -> Result<bool, String> {
+    tx.query_row(
+        &format!("SELECT 1 FROM {} WHERE id = ?1", table),
+        [id],
+        |_| Ok(()),
+    )
+    .optional()
+    .map(|row| row.is_some())
+    .map_err(|e| e.to_string())
+}
+
+fn upsert_model(
+    tx: &Transaction,
+    model: &CatalogModel,
+    report: &mut ReconcileReport,
+) -> Result<(), String> {
+    if !exists(tx, "models", &model.id)? {
+        report.models_added += 1;
+    }
+    let attachment_types =
+        serde_json::to_string(&model.supported_attachment_types).map_err(|e| e.to_string())?;
+    // is_enabled is left alone on existing rows, since the user may have
+    // turned the model off
+    tx.execute(
+        "INSERT INTO models (id, display_name, is_enabled, supported_attachment_types, is_internal, is_deprecated)
+         VALUES (?1, ?2, 1, ?3, ?4, ?5)
+         ON CONFLICT(id) DO UPDATE SET
+            display_name = excluded.display_name,
+            supported_attachment_types = excluded.supported_attachment_types,
+            is_internal = excluded.is_internal,
+            is_deprecated = excluded.is_deprecated",
+        params![
+            model.id,
+            model.display_name,
+            attachment_types,
+            model.is_internal,
+            model.deprecated
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    for config in model.configs() {
+        if !exists(tx, "model_configs", &config.id)? {
+            report.configs_added += 1;
+        }
+        // Configs the user wrote are never touched, even if a catalog entry
+        // happens to share their id. is_pinned is the user's to change, too.
+        tx.execute(
+            "INSERT INTO model_configs (id, model_id, display_name, author, system_prompt, is_default, budget_tokens, reasoning_effort, new_until)
+             VALUES (?1, ?2, ?3, 'system', ?4, 0, ?5, ?6, ?7)
+             ON CONFLICT(id) DO UPDATE SET
+                model_id = excluded.model_id,
+                display_name = excluded.display_name,
+                system_prompt = excluded.system_prompt,
+                budget_tokens = excluded.budget_tokens,
+                reasoning_effort = excluded.reasoning_effort,
+                new_until = excluded.new_until
+             WHERE model_configs.author = 'system'",
+            params![
+                config.id,
+                model.id,
+                config.display_name,
+                config.system_prompt,
+                config.budget_tokens,
+                config.reasoning_effort,
+                config.new_until
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Follows successor links until it reaches a model that isn't deprecated.
+fn final_successor(models: &HashMap<&str, &CatalogModel>, id: &str) -> Option<String> {
+    let mut current = models.get(id)?.successor.clone()?;
+    let mut seen = HashSet::from([id.to_string()]);
+    while let Some(next) = models
+        .get(current.as_str())
+        .filter(|model| model.deprecated)
+        .and_then(|model| model.successor.clone())
+    {
+        if !seen.insert(current.clone()) {
+            return None;
+        }
+        current = next;
+    }
+    Some(current)
+}
+
+/// Points the user's model selections at the successor of any deprecated
+/// model, so they don't end up with a model that's hidden from the picker.
+fn migrate_selections(
+    tx: &Transaction,
+    models: &[CatalogModel],
+    report: &mut ReconcileReport,
+) -> Result<(), String> {
+    let by_id: HashMap<&str, &CatalogModel> = models
+        .iter()
+        .map(|model| (model.id.as_str(), model))
+        .collect();
+
+    let mut replacements: HashMap<String, String> = HashMap::new();
+    for model in models.iter().filter(|model| model.deprecated) {
+        let Some(successor) = final_successor(&by_id, &model.id) else {
+            continue;
+        };
+        // Use the successor's default config, which shares its id
+        let successor_config = match by_id.get(successor.as_str()) {
+            Some(successor) => successor.configs()[0].id.clone(),
+            None => successor.clone(),
+        };
+        if !exists(tx, "model_configs", &successor_config)? {
+            println!(
+                "Successor {} of {} has no config, leaving selections alone",
+                successor, model.id
+            );
+            continue;
+        }
+
+        let mut stmt = tx
+            .prepare("SELECT id FROM model_configs WHERE model_id = ?1 AND author = 'system'")
+            .map_err(|e| e.to_string())?;
+        let config_ids = stmt
+            .query_map([&model.id], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        for config_id in config_ids {
+            replacements.insert(config_id, successor_config.clone());
+        }
+    }
+    if replacements.is_empty() {
+        return Ok(());
+    }
+
+    for key in SELECTED_CONFIG_KEYS {
+        let Some(value) = get_metadata(tx, key)? else {
+            continue;
+        };
+        if let Some(replacement) = replacements.get(&value) {
+            set_metadata(tx, key, replacement)?;
+            report.selections_migrated += 1;
+        }
+    }
+
+    for key in SELECTED_CONFIG_LIST_KEYS {
+        let Some(value) = get_metadata(tx, key)? else {
+            continue;
+        };
+        let Ok(selected) = serde_json::from_str::<Vec<String>>(&value) else {
+            continue;
+        };
+        let mut migrated: Vec<String> = Vec::new();
+        for id in &selected {
+            let id = replacements.get(id).unwrap_or(id);
+            if !migrated.contains(id) {
+                migrated.push(id.clone());
+            }
+        }
+        if migrated != selected {
+            let migrated = serde_json::to_string(&migrated).map_err(|e| e.to_string())?;
+            set_metadata(tx, key, &migrated)?;
+            report.selections_migrated += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Applies the bundled catalog, and the local one if there is one, to the
+/// models and model_configs tables. Returns None if neither has changed since
+/// the last run.
+pub fn reconcile(app_handle: &AppHandle) -> Result<Option<ReconcileReport>, String> {
+    let bundled = parse(BUNDLED_CATALOG, "(bundled)")?;
+
+    let local_path = local_catalog_path(app_handle)?;
+    let local_contents = match fs::read_to_string(&local_path) {
+        Ok(contents) => Some(contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(format!("Failed to read {}: {}", local_path.display(), e)),
+    };
+    let local = local_contents
+        .as_deref()
+        .map(|contents| parse(contents, &local_path.display().to_string()))
+        .transpose()?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(BUNDLED_CATALOG);
+    if let Some(contents) = &local_contents {
+        hasher.update(contents);
+    }
+    let checksum = hex::encode(hasher.finalize());
+
+    let mut conn = db::open(app_handle)?;
+    let applied_version = get_metadata(&conn, VERSION_KEY)?
+        .and_then(|version| version.parse::<i64>().ok())
+        .unwrap_or(0);
+    if get_metadata(&conn, CHECKSUM_KEY)?.as_deref() == Some(checksum.as_str()) {
+        return Ok(None);
+    }
+
+    // A newer build of the app has already applied a newer catalog, so don't
+    // roll its models back
+    let mut models = if bundled.version >= applied_version {
+        bundled.models
+    } else {
+        println!(
+            "Skipping bundled model catalog version {}, version {} is already applied",
+            bundled.version, applied_version
+        );
+        Vec::new()
+    };
+    for model in local.map(|local| local.models).unwrap_or_default() {
+        match models.iter_mut().find(|existing| existing.id == model.id) {
+            Some(existing) => *existing = model,
+            None => models.push(model),
+        }
+    }
+
+    let mut report = ReconcileReport::default();
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for model in &models {
+        upsert_model(&tx, model, &mut report)?;
+        if model.deprecated {
+            report.deprecated += 1;
+        }
+    }
+    migrate_selections(&tx, &models, &mut report)?;
+    set_metadata(
+        &tx,
+        VERSION_KEY,
+        &bundled.version.max(applied_version).to_string(),
+    )?;
+    set_metadata(&tx, CHECKSUM_KEY, &checksum)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(Some(report))
+}
@@ -0,0 +1,253 @@
+// The list of profiles and which one is active. It's kept in profiles.json in
+// the app config dir, outside every profile's database, so it can be read
+// before any database is opened. Nothing in here depends on Tauri, so the
+// command line tools can include this file with `#[path]` to find the active
+// profile's chats.db.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const REGISTRY_FILE_NAME: &str = "profiles.json";
+pub const DEFAULT_PROFILE_ID: &str = "default";
+pub const DB_FILE_NAME: &str = "chats.db";
+
+/// Every profile but the default one has a directory in here
+pub const PROFILES_DIR: &str = "profiles";
+
+const DEFAULT_PROFILE_NAME: &str = "Default";
+const SETTINGS_STORE_NAME: &str = "settings";
+const MAX_NAME_CHARS: usize = 64;
+const MAX_ID_CHARS: usize = 32;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Names the profile's directories, so it never changes
+    pub id: String,
+    pub name: String,
+}
+
+impl Profile {
+    pub fn is_default(&self) -> bool {
+        self.id == DEFAULT_PROFILE_ID
+    }
+
+    /// The profile's directory, relative to the app config and data dirs.
+    /// The default profile uses the app dirs themselves, which is where
+    /// everything was kept before there were profiles.
+    pub fn dir(&self) -> Option<String> {
+        if self.is_default() {
+            None
+        } else {
+            Some(format!("{}/{}", PROFILES_DIR, self.id))
+        }
+    }
+
+    fn join(&self, file_name: &str) -> String {
+        match self.dir() {
+            Some(dir) => format!("{}/{}", dir, file_name),
+            None => file_name.to_string(),
+        }
+    }
+
+    /// The chat database, relative to the app config dir
+    pub fn database(&self) -> String {
+        self.join(DB_FILE_NAME)
+    }
+
+    /// The settings store, relative to the app data dir
+    pub fn settings_store(&self) -> String {
+        self.join(SETTINGS_STORE_NAME)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Registry {
+    pub active: String,
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE_ID.to_string(),
+            profiles: vec![default_profile()],
+        }
+    }
+}
+
+fn default_profile() -> Profile {
+    Profile {
+        id: DEFAULT_PROFILE_ID.to_string(),
+        name: DEFAULT_PROFILE_NAME.to_string(),
+    }
+}
+
+impl Registry {
+    pub fn active(&self) -> &Profile {
+        self.profiles
+            .iter()
+            .find(|profile| profile.id == self.active)
+            .unwrap_or(&self.profiles[0])
+    }
+
+    pub fn get(&self, id: &str) -> Result<&Profile, String> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.id == id)
+            .ok_or_else(|| format!("No profile with id {}", id))
+    }
+
+    fn get_mut(&mut self, id: &str) -> Result<&mut Profile, String> {
+        self.profiles
+            .iter_mut()
+            .find(|profile| profile.id == id)
+            .ok_or_else(|| format!("No profile with id {}", id))
+    }
+
+    pub fn create(&mut self, name: &str) -> Result<Profile, String> {
+        let name = self.check_name(name, None)?;
+        let profile = Profile {
+            id: self.new_id(&name),
+            name,
+        };
+        self.profiles.push(profile.clone());
+        Ok(profile)
+    }
+
+    pub fn rename(&mut self, id: &str, name: &str) -> Result<Profile, String> {
+        let name = self.check_name(name, Some(id))?;
+        let profile = self.get_mut(id)?;
+        profile.name = name;
+        Ok(profile.clone())
+    }
+
+    /// Takes a profile out of the list. Its files are left for the caller.
+    pub fn remove(&mut self, id: &str) -> Result<Profile, String> {
+        let profile = self.get(id)?;
+        if profile.is_default() {
+            return Err("The default profile can't be deleted".to_string());
+        }
+        if profile.id == self.active {
+            return Err("Switch to another profile before deleting this one".to_string());
+        }
+        let index = self
+            .profiles
+            .iter()
+            .position(|profile| profile.id == id)
+            .ok_or_else(|| format!("No profile with id {}", id))?;
+        Ok(self.profiles.remove(index))
+    }
+
+    pub fn set_active(&mut self, id: &str) -> Result<Profile, String> {
+        let profile = self.get(id)?.clone();
+        self.active = profile.id.clone();
+        Ok(profile)
+    }
+
+    // Trims the name and checks it's usable and not taken by another profile
+    fn check_name(&self, name: &str, except_id: Option<&str>) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("The profile name can't be empty".to_string());
+        }
+        if name.chars().count() > MAX_NAME_CHARS {
+            return Err(format!(
+                "Profile names can be at most {} characters",
+                MAX_NAME_CHARS
+            ));
+        }
+        if name.chars().any(char::is_control) {
+            return Err("The profile name can't contain control characters".to_string());
+        }
+        let taken = self.profiles.iter().any(|profile| {
+            Some(profile.id.as_str()) != except_id
+                && profile.name.to_lowercase() == name.to_lowercase()
+        });
+        if taken {
+            return Err(format!("There's already a profile called {}", name));
+        }
+        Ok(name.to_string())
+    }
+
+    // A directory name made from the profile's name, like client-x. It only
+    // has the characters `is_valid_id` allows.
+    fn new_id(&self, name: &str) -> String {
+        let mut slug = String::new();
+        for c in name.chars().flat_map(char::to_lowercase) {
+            if c.is_ascii_alphanumeric() {
+                slug.push(c);
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+        let slug: String = slug.chars().take(MAX_ID_CHARS).collect();
+        let mut slug = slug.trim_end_matches('-').to_string();
+        if slug.is_empty() || slug == DEFAULT_PROFILE_ID {
+            slug = "profile".to_string();
+        }
+        let taken = |id: &str| self.profiles.iter().any(|profile| profile.id == id);
+        let mut id = slug.clone();
+        let mut suffix = 2;
+        while taken(&id) {
+            id = format!("{}-{}", slug, suffix);
+            suffix += 1;
+        }
+        id
+    }
+}
+
+/// Ids name directories, so one edited into profiles.json by hand mustn't
+/// lead anywhere else
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('-')
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Reads the registry from the app config dir. Without one there's only the
+/// default profile.
+pub fn load(config_dir: &Path) -> Result<Registry, String> {
+    let path = config_dir.join(REGISTRY_FILE_NAME);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Registry::default()),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    let mut registry: Registry = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    if let Some(profile) = registry
+        .profiles
+        .iter()
+        .find(|profile| !is_valid_id(&profile.id))
+    {
+        return Err(format!(
+            "{} has a profile with an invalid id: {:?}",
+            path.display(),
+            profile.id
+        ));
+    }
+    if !registry.profiles.iter().any(Profile::is_default) {
+        registry.profiles.insert(0, default_profile());
+    }
+    Ok(registry)
+}
+
+/// Writes the registry, replacing the old file only once the new one is
+/// complete.
+pub fn save(config_dir: &Path, registry: &Registry) -> Result<(), String> {
+    fs::create_dir_all(config_dir).map_err(|e| e.to_string())?;
+    let contents = serde_json::to_string_pretty(registry).map_err(|e| e.to_string())?;
+    let path = config_dir.join(REGISTRY_FILE_NAME);
+    let temp_path = config_dir.join(format!("{}.tmp", REGISTRY_FILE_NAME));
+    fs::write(&temp_path, contents).map_err(|e| e.to_string())?;
+    fs::rename(&temp_path, &path).map_err(|e| e.to_string())
+}
+
+/// The active profile's chat database
+pub fn active_db_path(config_dir: &Path) -> Result<PathBuf, String> {
+    Ok(config_dir.join(load(config_dir)?.active().database()))
+}
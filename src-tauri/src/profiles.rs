@@ -0,0 +1,243 @@
+// Named profiles, each with its own chat database, attachments, backups and
+// settings store, listed in the registry from profile_registry.rs. Switching
+// doesn't restart the app: the backend points at the new profile's files,
+// migrates its database and reloads every window, which then connect to it.
+
+use serde::Serialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+use crate::db_encryption::{self, KeyringSlot};
+use crate::mcp::McpSupervisor;
+use crate::profile_registry::{self, Profile, Registry};
+use crate::{app_menu, backup, data_dir, db, global_actions, migrator, tray, vault};
+
+pub const CHANGED_EVENT: &str = "profiles-changed";
+
+/// The registry, read from profiles.json the first time it's needed
+#[derive(Default)]
+pub struct ProfileState {
+    registry: Mutex<Option<Registry>>,
+}
+
+/// What a window needs to open the active profile's files
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveProfile {
+    pub id: String,
+    pub name: String,
+    /// Absolute, for the `sqlite:` URL, since the data may not be in the app
+    /// config dir
+    pub database: String,
+    pub settings_store: String,
+    /// Where uploads and generated images go
+    pub data_dir: String,
+}
+
+#[derive(Serialize)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<Profile>,
+}
+
+fn app_config_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    data_dir::config_dir(app_handle)
+}
+
+fn app_data_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    data_dir::data_dir(app_handle)
+}
+
+fn in_dir(base: PathBuf, profile: &Profile) -> PathBuf {
+    match profile.dir() {
+        Some(dir) => base.join(dir),
+        None => base,
+    }
+}
+
+fn registry(app_handle: &AppHandle) -> Result<Registry, String> {
+    let state = app_handle.state::<ProfileState>();
+    let mut registry = state.registry.lock().map_err(|e| e.to_string())?;
+    if registry.is_none() {
+        *registry = Some(profile_registry::load(&app_config_dir(app_handle)?)?);
+    }
+    Ok(registry.clone().unwrap_or_default())
+}
+
+// Applies a change to the registry and saves it. Nothing changes if `f` or
+// saving fails.
+fn update<T>(
+    app_handle: &AppHandle,
+    f: impl FnOnce(&mut Registry) -> Result<T, String>,
+) -> Result<T, String> {
+    let config_dir = app_config_dir(app_handle)?;
+    let state = app_handle.state::<ProfileState>();
+    let mut registry = state.registry.lock().map_err(|e| e.to_string())?;
+    let mut updated = match registry.as_ref() {
+        Some(registry) => registry.clone(),
+        None => profile_registry::load(&config_dir)?,
+    };
+    let result = f(&mut updated)?;
+    profile_registry::save(&config_dir, &updated)?;
+    *registry = Some(updated);
+    Ok(result)
+}
+
+pub fn active(app_handle: &AppHandle) -> Result<Profile, String> {
+    Ok(registry(app_handle)?.active().clone())
+}
+
+/// The active profile's directory in the app config dir, which holds its
+/// database and backups
+pub fn config_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(in_dir(app_config_dir(app_handle)?, &active(app_handle)?))
+}
+
+/// The active profile's directory in the app data dir, which holds its
+/// uploads and generated images
+pub fn data_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(in_dir(app_data_dir(app_handle)?, &active(app_handle)?))
+}
+
+pub fn db_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_config_dir(app_handle)?.join(active(app_handle)?.database()))
+}
+
+fn settings_store_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app_handle)?.join(active(app_handle)?.settings_store()))
+}
+
+/// The active profile's settings, which SettingsManager keeps in the frontend
+pub fn settings_store(app_handle: &AppHandle) -> Result<Arc<Store<Wry>>, String> {
+    app_handle
+        .store(settings_store_path(app_handle)?)
+        .map_err(|e| e.to_string())
+}
+
+pub fn active_profile(app_handle: &AppHandle) -> Result<ActiveProfile, String> {
+    let profile = active(app_handle)?;
+    Ok(ActiveProfile {
+        database: db_path(app_handle)?.to_string_lossy().to_string(),
+        settings_store: settings_store_path(app_handle)?
+            .to_string_lossy()
+            .to_string(),
+        data_dir: data_dir(app_handle)?.to_string_lossy().to_string(),
+        id: profile.id,
+        name: profile.name,
+    })
+}
+
+pub fn list(app_handle: &AppHandle) -> Result<ProfileList, String> {
+    let registry = registry(app_handle)?;
+    Ok(ProfileList {
+        active: registry.active,
+        profiles: registry.profiles,
+    })
+}
+
+/// Lets the webview show every profile's attachments
+pub fn allow_assets(app_handle: &AppHandle) {
+    let Ok(base) = app_data_dir(app_handle) else {
+        return;
+    };
+    if let Ok(registry) = registry(app_handle) {
+        for profile in &registry.profiles {
+            data_dir::allow_assets(app_handle, &in_dir(base.clone(), profile));
+        }
+    }
+}
+
+fn emit_changed(app_handle: &AppHandle) {
+    if let Ok(profiles) = list(app_handle) {
+        let _ = app_handle.emit(CHANGED_EVENT, profiles);
+    }
+}
+
+pub fn create(app_handle: &AppHandle, name: &str) -> Result<Profile, String> {
+    let profile = update(app_handle, |registry| registry.create(name))?;
+    data_dir::allow_assets(app_handle, &in_dir(app_data_dir(app_handle)?, &profile));
+    emit_changed(app_handle);
+    Ok(profile)
+}
+
+pub fn rename(app_handle: &AppHandle, id: &str, name: &str) -> Result<Profile, String> {
+    let profile = update(app_handle, |registry| registry.rename(id, name))?;
+    emit_changed(app_handle);
+    Ok(profile)
+}
+
+// Deletes the profile's directory in `base`, which has to be right inside the
+// profiles directory there
+fn remove_profile_dir(base: PathBuf, profile: &Profile) -> Result<(), String> {
+    let dir = in_dir(base.clone(), profile);
+    let dir = match dir.canonicalize() {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Failed to find {}: {}", dir.display(), e)),
+    };
+    let root = base
+        .join(profile_registry::PROFILES_DIR)
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+    if dir.parent() != Some(root.as_path()) {
+        return Err(format!(
+            "Refusing to delete {}, which isn't a profile's directory",
+            dir.display()
+        ));
+    }
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete {}: {}", dir.display(), e))
+}
+
+/// Deletes a profile that isn't active, with its chats, attachments and
+/// settings, and any database key it kept in the keyring.
+pub fn delete(app_handle: &AppHandle, id: &str) -> Result<(), String> {
+    let profile = update(app_handle, |registry| registry.remove(id))?;
+    emit_changed(app_handle);
+
+    let db_path = app_config_dir(app_handle)?.join(profile.database());
+    for slot in [KeyringSlot::Current, KeyringSlot::Pending] {
+        if let Err(e) = db_encryption::store_keyring_key(&db_path, slot, None) {
+            println!("Failed to remove the key of {}: {}", db_path.display(), e);
+        }
+    }
+    // Its vault entries. They're left behind if the vault is locked.
+    if let Err(e) = vault::delete_profile(app_handle, &profile.id) {
+        println!("Failed to remove the secrets of {}: {}", profile.id, e);
+    }
+    remove_profile_dir(app_config_dir(app_handle)?, &profile)?;
+    remove_profile_dir(app_data_dir(app_handle)?, &profile)
+}
+
+/// Makes another profile active and reloads every window. The windows have to
+/// close their database connections first.
+pub fn switch(app_handle: &AppHandle, id: &str) -> Result<(), String> {
+    if active(app_handle)?.id == id {
+        return Ok(());
+    }
+    // Don't swap the database out from under a migration or a backup
+    migrator::wait(app_handle)?;
+    backup::paused(|| {
+        update(app_handle, |registry| registry.set_active(id))?;
+        db::forget_key()
+    })?;
+    migrator::restart(app_handle.clone());
+
+    let supervisor = app_handle.state::<McpSupervisor>();
+    for (label, window) in app_handle.webview_windows() {
+        // Their MCP servers were set up for the old profile's toolsets
+        supervisor.stop_window(&label);
+        if let Err(e) = window.reload() {
+            println!("Failed to reload {}: {}", label, e);
+        }
+    }
+    // Each profile has its own settings, so its own global shortcuts
+    global_actions::reload(app_handle);
+    tray::refresh_when_ready(app_handle);
+    app_menu::refresh_when_ready(app_handle);
+    emit_changed(app_handle);
+    Ok(())
+}
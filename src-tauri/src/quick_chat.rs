@@ -0,0 +1,211 @@
+// The quick chat window, which a global shortcut shows over other apps. On
+// macOS it's turned into a non-activating panel (see window.rs). Elsewhere
+// it stays a window, kept on top without decorations or a taskbar entry, and
+// moved to the monitor the cursor is on each time it's shown.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+#[cfg(target_os = "macos")]
+use crate::window::WebviewWindowExt;
+#[cfg(target_os = "macos")]
+use tauri::Listener;
+#[cfg(not(target_os = "macos"))]
+use tauri::{PhysicalPosition, WebviewWindow, WindowEvent};
+#[cfg(target_os = "macos")]
+use tauri_nspanel::ManagerExt;
+
+use crate::{profiles, shortcut, SPOTLIGHT_LABEL};
+
+/// Sent when the shortcut shows the window
+pub const SHOWN_EVENT: &str = "show_quick_chat";
+/// Sent whenever the window gets focus, so the input can take it
+pub const FOCUSED_EVENT: &str = "quick-chat-focused";
+/// Sent to start a new quick chat, with a `QuickChatDraft`
+pub const NEW_CHAT_EVENT: &str = "new_quick_chat";
+
+/// What a new quick chat starts with
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickChatDraft {
+    /// Put in the input, but not sent
+    pub text: Option<String>,
+    /// A base64 PNG, as returned by capture_whole_screen, to attach
+    pub screenshot: Option<String>,
+}
+
+/// `settings.quickChat` in the settings store
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct QuickChatSettings {
+    enabled: bool,
+    shortcut: String,
+}
+
+impl Default for QuickChatSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            shortcut: shortcut::DEFAULT_QUICK_CHAT.to_string(),
+        }
+    }
+}
+
+fn read_settings(app_handle: &AppHandle) -> QuickChatSettings {
+    profiles::settings_store(app_handle)
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|settings| settings.get("quickChat").cloned())
+        .and_then(|quick_chat| serde_json::from_value(quick_chat).ok())
+        .unwrap_or_default()
+}
+
+/// The shortcut in the settings, which global_actions.rs registers
+pub fn saved_shortcut(app_handle: &AppHandle) -> String {
+    read_settings(app_handle).shortcut
+}
+
+#[cfg(target_os = "macos")]
+fn is_dark_mode(app_handle: &AppHandle) -> bool {
+    profiles::settings_store(app_handle)
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|settings| {
+            settings
+                .get("theme")
+                .and_then(|theme| theme.get("mode"))
+                .and_then(|mode| mode.as_str().map(|mode| mode == "dark"))
+        })
+        .unwrap_or(false)
+}
+
+// Centered, a quarter of the way down, like Spotlight. Falls back to the
+// primary monitor when the cursor can't be found, as on Wayland.
+#[cfg(not(target_os = "macos"))]
+fn move_to_active_monitor(window: &WebviewWindow) -> tauri::Result<()> {
+    let monitor = match window.cursor_position() {
+        Ok(cursor) => window.monitor_from_point(cursor.x, cursor.y)?,
+        Err(_) => None,
+    };
+    let Some(monitor) = monitor.or(window.primary_monitor()?) else {
+        return Ok(());
+    };
+    let size = window.outer_size()?;
+    let free_width = monitor.size().width.saturating_sub(size.width) as i32;
+    let free_height = monitor.size().height.saturating_sub(size.height) as i32;
+    window.set_position(PhysicalPosition::new(
+        monitor.position().x + free_width / 2,
+        monitor.position().y + free_height / 4,
+    ))
+}
+
+pub fn show(app_handle: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(panel) = app_handle.get_webview_panel(SPOTLIGHT_LABEL) {
+            panel.show();
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        if let Some(window) = app_handle.get_webview_window(SPOTLIGHT_LABEL) {
+            if let Err(e) = move_to_active_monitor(&window) {
+                println!("Failed to position the quick chat window: {}", e);
+            }
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+    }
+}
+
+pub fn hide(app_handle: &AppHandle) {
+    #[cfg(target_os = "macos")]
+    {
+        if let Ok(panel) = app_handle.get_webview_panel(SPOTLIGHT_LABEL) {
+            if panel.is_visible() {
+                panel.order_out(None);
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        if let Some(window) = app_handle.get_webview_window(SPOTLIGHT_LABEL) {
+            let _ = window.hide();
+        }
+    }
+}
+
+pub fn is_visible(app_handle: &AppHandle) -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        app_handle
+            .get_webview_panel(SPOTLIGHT_LABEL)
+            .is_ok_and(|panel| panel.is_visible())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        app_handle
+            .get_webview_window(SPOTLIGHT_LABEL)
+            .and_then(|window| window.is_visible().ok())
+            .unwrap_or(false)
+    }
+}
+
+/// Whether quick chat is turned on in the settings
+pub fn is_enabled(app_handle: &AppHandle) -> bool {
+    read_settings(app_handle).enabled
+}
+
+/// What the shortcut does: shows the window, or hides it if it's showing.
+/// Nothing happens while quick chat is turned off in the settings.
+pub fn toggle(app_handle: &AppHandle) {
+    if !is_enabled(app_handle) {
+        return;
+    }
+    if is_visible(app_handle) {
+        hide(app_handle);
+    } else {
+        let _ = app_handle.emit(SHOWN_EVENT, ());
+        show(app_handle);
+    }
+}
+
+/// Sets up the window. Its shortcut is registered by global_actions.rs.
+pub fn setup(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    let window = app_handle
+        .get_webview_window(SPOTLIGHT_LABEL)
+        .ok_or("The quick chat window is missing")?;
+
+    #[cfg(target_os = "macos")]
+    {
+        window.to_spotlight_panel(is_dark_mode(app_handle))?;
+        let handle = app_handle.clone();
+        app_handle.listen(
+            format!("{}_panel_did_become_key", SPOTLIGHT_LABEL),
+            move |_| {
+                let _ = handle.emit(FOCUSED_EVENT, ());
+            },
+        );
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        window.set_always_on_top(true)?;
+        window.set_decorations(false)?;
+        window.set_skip_taskbar(true)?;
+        let handle = app_handle.clone();
+        window.on_window_event(move |event| match event {
+            WindowEvent::Focused(true) => {
+                let _ = handle.emit(FOCUSED_EVENT, ());
+            }
+            // The window is reused, so closing it only hides it
+            WindowEvent::CloseRequested { .. } => hide(&handle),
+            _ => {}
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,946 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{types::ToSql, Connection};
+use serde::{Deserialize, Serialize};
+
+// Markers wrapped around matched terms in snippets. The snippet text itself is
+// not HTML-escaped, so the frontend should split on these rather than render
+// the snippet as HTML.
+pub const HIGHLIGHT_OPEN: &str = "<mark>";
+pub const HIGHLIGHT_CLOSE: &str = "</mark>";
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+// Number of tokens FTS5 includes in each snippet
+const SNIPPET_TOKENS: i32 = 24;
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct SearchFilters {
+    pub model: Option<String>,
+    pub project_id: Option<String>,
+    /// Inclusive lower bound, as `YYYY-MM-DD` or RFC 3339
+    pub after: Option<String>,
+    /// Exclusive upper bound, as `YYYY-MM-DD` or RFC 3339
+    pub before: Option<String>,
+    pub has_attachment: Option<bool>,
+    pub quick_chat: Option<bool>,
+}
+
+impl SearchFilters {
+    /// Fills any unset filter from `other`.
+    fn or(self, other: SearchFilters) -> SearchFilters {
+        SearchFilters {
+            model: self.model.or(other.model),
+            project_id: self.project_id.or(other.project_id),
+            after: self.after.or(other.after),
+            before: self.before.or(other.before),
+            has_attachment: self.has_attachment.or(other.has_attachment),
+            quick_chat: self.quick_chat.or(other.quick_chat),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    /// Where the match was found: "message", "message_part" or "title"
+    pub source: String,
+    pub chat_id: String,
+    pub message_id: Option<String>,
+    pub snippet: String,
+    pub score: f64,
+    pub model: Option<String>,
+    pub created_at: Option<String>,
+    pub title: Option<String>,
+    pub project_id: Option<String>,
+    pub parent_chat_id: Option<String>,
+    pub reply_to_id: Option<String>,
+    pub message_set_type: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchPage {
+    pub hits: Vec<SearchHit>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    score: f64,
+    key: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Term { text: String, prefix: bool },
+    Phrase { text: String, prefix: bool },
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+#[derive(Debug)]
+pub struct ParsedQuery {
+    /// FTS5 MATCH expression
+    pub match_expr: String,
+    /// Filters given inline, e.g. `model:openai::gpt-4o` or `has:attachment`
+    pub filters: SearchFilters,
+}
+
+/// Parses the user-facing query language into an FTS5 expression.
+///
+/// Supported syntax:
+/// - bare words, matched case- and diacritic-insensitively
+/// - `"exact phrase"`
+/// - `prefix*`
+/// - `AND`, `OR`, `NOT`, `-word` and parentheses
+/// - filters: `model:`, `project:`, `after:`, `before:`, `has:attachment`, `is:quick`
+pub fn parse_query(input: &str) -> Result<ParsedQuery, String> {
+    let mut tokens = Vec::new();
+    let mut filters = SearchFilters::default();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+    let mut negate_next = false;
+
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch.is_whitespace() {
+            if negate_next {
+                return Err(format!("Expected a term after '-' at position {}", i));
+            }
+            i += 1;
+            continue;
+        }
+
+        match ch {
+            '(' | ')' => {
+                if negate_next {
+                    return Err(format!("Cannot negate '{}' at position {}", ch, i));
+                }
+                tokens.push(if ch == '(' { Token::Open } else { Token::Close });
+                i += 1;
+            }
+            '-' if !negate_next && i + 1 < chars.len() && !chars[i + 1].is_whitespace() => {
+                negate_next = true;
+                i += 1;
+            }
+            '"' => {
+                let start = i;
+                i += 1;
+                let mut text = String::new();
+                while i < chars.len() && chars[i] != '"' {
+                    text.push(chars[i]);
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(format!("Unterminated quote at position {}", start));
+                }
+                i += 1;
+                let prefix = i < chars.len() && chars[i] == '*';
+                if prefix {
+                    i += 1;
+                }
+                if text.trim().is_empty() {
+                    negate_next = false;
+                    continue;
+                }
+                if std::mem::take(&mut negate_next) {
+                    tokens.push(Token::Not);
+                }
+                tokens.push(Token::Phrase { text, prefix });
+            }
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !matches!(chars[i], '(' | ')' | '"')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let negated = std::mem::take(&mut negate_next);
+
+                if let Some((key, value)) = word.split_once(':') {
+                    if apply_filter(&mut filters, key, value, negated)? {
+                        continue;
+                    }
+                }
+
+                match word.as_str() {
+                    "AND" | "OR" | "NOT" if negated => {
+                        return Err(format!("Cannot negate '{}' at position {}", word, start));
+                    }
+                    "AND" => tokens.push(Token::And),
+                    "OR" => tokens.push(Token::Or),
+                    "NOT" => tokens.push(Token::Not),
+                    _ => {
+                        let (text, prefix) = match word.strip_suffix('*') {
+                            Some(stem) => (stem.to_string(), true),
+                            None => (word.clone(), false),
+                        };
+                        if text.is_empty() {
+                            return Err(format!(
+                                "Expected a term before '*' at position {}",
+                                start
+                            ));
+                        }
+                        if negated {
+                            tokens.push(Token::Not);
+                        }
+                        tokens.push(Token::Term { text, prefix });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(ParsedQuery {
+        match_expr: build_match_expr(&tokens)?,
+        filters,
+    })
+}
+
+/// Applies an inline `key:value` filter. Returns false if `key` is not a known
+/// filter, in which case the word is searched for as-is.
+fn apply_filter(
+    filters: &mut SearchFilters,
+    key: &str,
+    value: &str,
+    negated: bool,
+) -> Result<bool, String> {
+    let key = key.to_lowercase();
+    let is_known = matches!(
+        key.as_str(),
+        "model" | "project" | "after" | "before" | "has" | "is"
+    );
+    if !is_known {
+        return Ok(false);
+    }
+    if value.is_empty() {
+        return Err(format!("Missing value for '{}:' filter", key));
+    }
+    if negated && !matches!(key.as_str(), "has" | "is") {
+        return Err(format!("The '{}:' filter cannot be negated", key));
+    }
+
+    match key.as_str() {
+        "model" => filters.model = Some(value.to_string()),
+        "project" => filters.project_id = Some(value.to_string()),
+        "after" => filters.after = Some(value.to_string()),
+        "before" => filters.before = Some(value.to_string()),
+        "has" if value.eq_ignore_ascii_case("attachment") => {
+            filters.has_attachment = Some(!negated)
+        }
+        "is" if value.eq_ignore_ascii_case("quick") => filters.quick_chat = Some(!negated),
+        _ => return Err(format!("Unknown filter '{}:{}'", key, value)),
+    }
+    Ok(true)
+}
+
+fn quote_fts(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+fn build_match_expr(tokens: &[Token]) -> Result<String, String> {
+    let mut expr = String::new();
+    let mut depth = 0usize;
+    // Whether the previous token completes an operand, i.e. a term, a phrase
+    // or a closing parenthesis
+    let mut after_operand = false;
+    let mut has_positive_term = false;
+
+    for (index, token) in tokens.iter().enumerate() {
+        let negated = index > 0 && tokens[index - 1] == Token::Not;
+        match token {
+            Token::Term { text, prefix } | Token::Phrase { text, prefix } => {
+                if after_operand {
+                    expr.push_str(" AND ");
+                }
+                expr.push_str(&quote_fts(text));
+                if *prefix {
+                    expr.push('*');
+                }
+                has_positive_term |= !negated;
+                after_operand = true;
+            }
+            Token::Open => {
+                if after_operand {
+                    expr.push_str(" AND ");
+                }
+                expr.push('(');
+                depth += 1;
+                after_operand = false;
+            }
+            Token::Close => {
+                if depth == 0 {
+                    return Err("Unbalanced ')' in search query".to_string());
+                }
+                if !after_operand {
+                    return Err("Empty group or dangling operator before ')'".to_string());
+                }
+                expr.push(')');
+                depth -= 1;
+            }
+            Token::And | Token::Or | Token::Not => {
+                let name = match token {
+                    Token::And => "AND",
+                    Token::Or => "OR",
+                    _ => "NOT",
+                };
+                if !after_operand {
+                    return Err(match token {
+                        Token::Not => "NOT and '-' must follow another search term".to_string(),
+                        _ => format!("{} must be placed between two search terms", name),
+                    });
+                }
+                expr.push(' ');
+                expr.push_str(name);
+                expr.push(' ');
+                after_operand = false;
+            }
+        }
+    }
+
+    if depth > 0 {
+        return Err("Unbalanced '(' in search query".to_string());
+    }
+    if !expr.is_empty() && !after_operand {
+        return Err("Search query ends with an operator".to_string());
+    }
+    if !has_positive_term {
+        return Err("Search query needs at least one term to match".to_string());
+    }
+    Ok(expr)
+}
+
+/// Normalizes a date filter to the `YYYY-MM-DD HH:MM:SS` UTC format SQLite's
+/// CURRENT_TIMESTAMP produces, so it can be compared against `created_at`.
+fn normalize_date(value: &str) -> Result<String, String> {
+    const SQLITE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .format(SQLITE_FORMAT)
+            .to_string());
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime
+            .with_timezone(&Utc)
+            .format(SQLITE_FORMAT)
+            .to_string());
+    }
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, SQLITE_FORMAT) {
+        return Ok(datetime.format(SQLITE_FORMAT).to_string());
+    }
+    Err(format!(
+        "Invalid date '{}'. Use YYYY-MM-DD or an RFC 3339 timestamp",
+        value
+    ))
+}
+
+fn encode_cursor(score: f64, key: &str) -> String {
+    let cursor = Cursor {
+        score,
+        key: key.to_string(),
+    };
+    URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+}
+
+fn decode_cursor(cursor: &str) -> Result<Cursor, String> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(|| "Invalid search cursor".to_string())
+}
+
+/// Runs a ranked full-text search over message text, message parts and chat
+/// titles. Filters passed in `filters` take precedence over inline ones.
+pub fn search(
+    conn: &Connection,
+    query: &str,
+    filters: SearchFilters,
+    cursor: Option<&str>,
+    limit: Option<u32>,
+) -> Result<SearchPage, String> {
+    let parsed = parse_query(query)?;
+    let filters = filters.or(parsed.filters);
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    // Conditions on `m` (messages) and `c` (chats) for message and part hits
+    let mut message_filters = String::new();
+    // Conditions on `c` (chats) for title hits
+    let mut chat_filters = String::new();
+    let mut params: Vec<(&str, Box<dyn ToSql>)> = vec![
+        (":query", Box::new(parsed.match_expr)),
+        (":open", Box::new(HIGHLIGHT_OPEN)),
+        (":close", Box::new(HIGHLIGHT_CLOSE)),
+        (":snippet_tokens", Box::new(SNIPPET_TOKENS)),
+        (":limit", Box::new(limit + 1)),
+    ];
+
+    if let Some(model) = filters.model {
+        message_filters.push_str(" AND m.model = :model");
+        chat_filters.push_str(
+            " AND EXISTS (SELECT 1 FROM messages fm WHERE fm.chat_id = c.id AND fm.model = :model)",
+        );
+        params.push((":model", Box::new(model)));
+    }
+    if let Some(project_id) = filters.project_id {
+        message_filters.push_str(" AND c.project_id = :project_id");
+        chat_filters.push_str(" AND c.project_id = :project_id");
+        params.push((":project_id", Box::new(project_id)));
+    }
+    if let Some(after) = filters.after {
+        message_filters.push_str(" AND m.created_at >= :after");
+        chat_filters.push_str(" AND c.created_at >= :after");
+        params.push((":after", Box::new(normalize_date(&after)?)));
+    }
+    if let Some(before) = filters.before {
+        message_filters.push_str(" AND m.created_at < :before");
+        chat_filters.push_str(" AND c.created_at < :before");
+        params.push((":before", Box::new(normalize_date(&before)?)));
+    }
+    if let Some(has_attachment) = filters.has_attachment {
+        let negation = if has_attachment { "" } else { "NOT " };
+        message_filters.push_str(&format!(
+            " AND {}EXISTS (SELECT 1 FROM message_attachments ma WHERE ma.message_id = m.id)",
+            negation
+        ));
+        chat_filters.push_str(&format!(
+            " AND {}EXISTS (SELECT 1 FROM messages fm \
+               INNER JOIN message_attachments ma ON ma.message_id = fm.id \
+               WHERE fm.chat_id = c.id)",
+            negation
+        ));
+    }
+    if let Some(quick_chat) = filters.quick_chat {
+        message_filters.push_str(" AND c.quick_chat = :quick_chat");
+        chat_filters.push_str(" AND c.quick_chat = :quick_chat");
+        params.push((":quick_chat", Box::new(quick_chat)));
+    }
+
+    let mut cursor_clause = "";
+    if let Some(cursor) = cursor {
+        let cursor = decode_cursor(cursor)?;
+        cursor_clause = "WHERE (h.score, h.hit_key) > (:cursor_score, :cursor_key)";
+        params.push((":cursor_score", Box::new(cursor.score)));
+        params.push((":cursor_key", Box::new(cursor.key)));
+    }
+
+    // bm25() returns lower (more negative) scores for better matches. Title
+    // matches are short, so they are weighted up to compete with long messages.
+    let sql = format!(
+        r#"
+        WITH hits AS (
+            SELECT
+                'message' AS source,
+                m.chat_id AS chat_id,
+                m.id AS message_id,
+                snippet(messages_fts, 0, :open, :close, '…', :snippet_tokens) AS snippet,
+                bm25(messages_fts) AS score,
+                m.model AS model,
+                m.created_at AS created_at,
+                m.message_set_id AS message_set_id,
+                'm' || messages_fts.rowid AS hit_key
+            FROM messages_fts
+            INNER JOIN search_keys k ON k.id = messages_fts.rowid
+            INNER JOIN messages m ON m.id = k.source_id
+            INNER JOIN chats c ON c.id = m.chat_id
+            WHERE messages_fts MATCH :query{message_filters}
+
+            UNION ALL
+
+            SELECT
+                'message_part',
+                mp.chat_id,
+                mp.message_id,
+                snippet(message_parts_fts, 0, :open, :close, '…', :snippet_tokens),
+                bm25(message_parts_fts),
+                m.model,
+                m.created_at,
+                m.message_set_id,
+                'p' || message_parts_fts.rowid
+            FROM message_parts_fts
+            INNER JOIN search_keys k ON k.id = message_parts_fts.rowid
+            INNER JOIN message_parts mp ON mp.message_id = k.source_id AND mp.level = k.level
+            INNER JOIN messages m ON m.id = mp.message_id AND m.chat_id = mp.chat_id
+            INNER JOIN chats c ON c.id = mp.chat_id
+            WHERE message_parts_fts MATCH :query{message_filters}
+
+            UNION ALL
+
+            SELECT
+                'title',
+                c.id,
+                NULL,
+                snippet(chats_fts, 0, :open, :close, '…', :snippet_tokens),
+                bm25(chats_fts) * 2.0,
+                NULL,
+                c.created_at,
+                NULL,
+                'c' || chats_fts.rowid
+            FROM chats_fts
+            INNER JOIN search_keys k ON k.id = chats_fts.rowid
+            INNER JOIN chats c ON c.id = k.source_id
+            WHERE chats_fts MATCH :query
+                AND c.title IS NOT NULL
+                AND c.title != 'Untitled Chat'{chat_filters}
+        )
+        SELECT
+            h.source,
+            h.chat_id,
+            h.message_id,
+            h.snippet,
+            h.score,
+            h.model,
+            h.created_at,
+            c.title,
+            c.project_id,
+            c.parent_chat_id,
+            c.reply_to_id,
+            ms.type,
+            h.hit_key
+        FROM hits h
+        INNER JOIN chats c ON c.id = h.chat_id
+        LEFT JOIN message_sets ms ON ms.id = h.message_set_id
+        {cursor_clause}
+        ORDER BY h.score, h.hit_key
+        LIMIT :limit
+        "#
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let params: Vec<(&str, &dyn ToSql)> = params
+        .iter()
+        .map(|(name, value)| (*name, value.as_ref()))
+        .collect();
+    let rows = stmt
+        .query_map(params.as_slice(), |row| {
+            Ok((
+                SearchHit {
+                    source: row.get(0)?,
+                    chat_id: row.get(1)?,
+                    message_id: row.get(2)?,
+                    snippet: row.get(3)?,
+                    score: row.get(4)?,
+                    model: row.get(5)?,
+                    created_at: row.get(6)?,
+                    title: row.get(7)?,
+                    project_id: row.get(8)?,
+                    parent_chat_id: row.get(9)?,
+                    reply_to_id: row.get(10)?,
+                    message_set_type: row.get(11)?,
+                },
+                row.get::<_, String>(12)?,
+            ))
+        })
+        .map_err(|e| format!("Search failed: {}", e))?;
+
+    // One extra row is requested to find out whether there is another page
+    let mut hits = Vec::new();
+    let mut last_key = None;
+    let mut has_more = false;
+    for row in rows {
+        let (hit, key) = row.map_err(|e| format!("Search failed: {}", e))?;
+        if hits.len() == limit as usize {
+            has_more = true;
+            break;
+        }
+        last_key = Some(key);
+        hits.push(hit);
+    }
+
+    let next_cursor = match (has_more, hits.last(), last_key) {
+        (true, Some(hit), Some(key)) => Some(encode_cursor(hit.score, &key)),
+        _ => None,
+    };
+
+    Ok(SearchPage { hits, next_cursor })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn match_expr(input: &str) -> String {
+        parse_query(input).unwrap().match_expr
+    }
+
+    fn parse_error(input: &str) -> String {
+        parse_query(input).unwrap_err()
+    }
+
+    #[test]
+    fn words_are_quoted_and_joined_with_and() {
+        assert_eq!(match_expr("apple"), r#""apple""#);
+        assert_eq!(match_expr("  apple   pie "), r#""apple" AND "pie""#);
+    }
+
+    #[test]
+    fn phrases() {
+        assert_eq!(match_expr(r#""apple pie""#), r#""apple pie""#);
+        assert_eq!(
+            match_expr(r#"recipe "apple pie" "#),
+            r#""recipe" AND "apple pie""#
+        );
+        // An empty phrase matches nothing, so it's left out
+        assert_eq!(match_expr(r#"apple """#), r#""apple""#);
+    }
+
+    #[test]
+    fn prefix_terms() {
+        assert_eq!(match_expr("app*"), r#""app"*"#);
+        assert_eq!(match_expr(r#""apple p"*"#), r#""apple p"*"#);
+    }
+
+    #[test]
+    fn operators_and_groups() {
+        assert_eq!(match_expr("apple OR pear"), r#""apple" OR "pear""#);
+        assert_eq!(match_expr("apple AND pear"), r#""apple" AND "pear""#);
+        assert_eq!(
+            match_expr("(apple OR pear) pie"),
+            r#"("apple" OR "pear") AND "pie""#
+        );
+        assert_eq!(
+            match_expr("pie (apple OR pear)"),
+            r#""pie" AND ("apple" OR "pear")"#
+        );
+        // Only uppercase words are operators
+        assert_eq!(
+            match_expr("apple or pear"),
+            r#""apple" AND "or" AND "pear""#
+        );
+    }
+
+    #[test]
+    fn negation() {
+        assert_eq!(match_expr("apple -pie"), r#""apple" NOT "pie""#);
+        assert_eq!(match_expr("apple NOT pie"), r#""apple" NOT "pie""#);
+        assert_eq!(
+            match_expr(r#"apple -"pie crust""#),
+            r#""apple" NOT "pie crust""#
+        );
+        // A lone dash is a word, not a negation
+        assert_eq!(match_expr("apple - pie"), r#""apple" AND "-" AND "pie""#);
+    }
+
+    #[test]
+    fn fts_syntax_is_escaped() {
+        assert_eq!(quote_fts(r#"say "hi""#), r#""say ""hi""""#);
+        // FTS5 operators and column filters are searched for as text
+        assert_eq!(
+            match_expr("NEAR(apple pie)"),
+            r#""NEAR" AND ("apple" AND "pie")"#
+        );
+        assert_eq!(match_expr("title:apple"), r#""title:apple""#);
+        assert_eq!(match_expr("^apple +pie"), r#""^apple" AND "+pie""#);
+        assert_eq!(match_expr("a*b"), r#""a*b""#);
+    }
+
+    #[test]
+    fn inline_filters() {
+        let parsed = parse_query(
+            "model:openai::gpt-4o project:work after:2024-01-01 before:2024-02-01 \
+             -has:attachment is:quick apple",
+        )
+        .unwrap();
+        assert_eq!(parsed.match_expr, r#""apple""#);
+        let filters = parsed.filters;
+        assert_eq!(filters.model.as_deref(), Some("openai::gpt-4o"));
+        assert_eq!(filters.project_id.as_deref(), Some("work"));
+        assert_eq!(filters.after.as_deref(), Some("2024-01-01"));
+        assert_eq!(filters.before.as_deref(), Some("2024-02-01"));
+        assert_eq!(filters.has_attachment, Some(false));
+        assert_eq!(filters.quick_chat, Some(true));
+
+        let filters = parse_query("HAS:Attachment -is:quick apple")
+            .unwrap()
+            .filters;
+        assert_eq!(filters.has_attachment, Some(true));
+        assert_eq!(filters.quick_chat, Some(false));
+    }
+
+    #[test]
+    fn passed_filters_take_precedence() {
+        let passed = SearchFilters {
+            model: Some("anthropic::claude".to_string()),
+            ..Default::default()
+        };
+        let inline = parse_query("model:openai::gpt-4o project:work apple")
+            .unwrap()
+            .filters;
+        let filters = passed.or(inline);
+        assert_eq!(filters.model.as_deref(), Some("anthropic::claude"));
+        assert_eq!(filters.project_id.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn malformed_queries() {
+        assert_eq!(
+            parse_error(r#"apple "pie"#),
+            "Unterminated quote at position 6"
+        );
+        assert_eq!(parse_error("(apple"), "Unbalanced '(' in search query");
+        assert_eq!(parse_error("apple)"), "Unbalanced ')' in search query");
+        assert_eq!(
+            parse_error("apple ()"),
+            "Empty group or dangling operator before ')'"
+        );
+        assert_eq!(
+            parse_error("(apple OR)"),
+            "Empty group or dangling operator before ')'"
+        );
+        assert_eq!(
+            parse_error("apple AND"),
+            "Search query ends with an operator"
+        );
+        assert_eq!(
+            parse_error("OR apple"),
+            "OR must be placed between two search terms"
+        );
+        assert_eq!(
+            parse_error("-apple"),
+            "NOT and '-' must follow another search term"
+        );
+        assert_eq!(
+            parse_error("apple -OR pie"),
+            "Cannot negate 'OR' at position 7"
+        );
+        assert_eq!(
+            parse_error("apple -(pie)"),
+            "Cannot negate '(' at position 7"
+        );
+        assert_eq!(parse_error("*"), "Expected a term before '*' at position 0");
+        assert_eq!(
+            parse_error("apple model:"),
+            "Missing value for 'model:' filter"
+        );
+        assert_eq!(
+            parse_error("apple -model:gpt"),
+            "The 'model:' filter cannot be negated"
+        );
+        assert_eq!(parse_error("apple has:image"), "Unknown filter 'has:image'");
+        assert_eq!(
+            parse_error("has:attachment"),
+            "Search query needs at least one term to match"
+        );
+        assert_eq!(
+            parse_error(""),
+            "Search query needs at least one term to match"
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(normalize_date("2024-03-01").unwrap(), "2024-03-01 00:00:00");
+        assert_eq!(
+            normalize_date("2024-03-01T12:30:00+02:00").unwrap(),
+            "2024-03-01 10:30:00"
+        );
+        assert_eq!(
+            normalize_date("2024-03-01 12:30:00").unwrap(),
+            "2024-03-01 12:30:00"
+        );
+        assert!(normalize_date("yesterday")
+            .unwrap_err()
+            .starts_with("Invalid date 'yesterday'"));
+    }
+
+    // The tables and columns search() reads, indexed the way migration 134's
+    // triggers index them
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE chats (
+                 id TEXT PRIMARY KEY,
+                 title TEXT,
+                 project_id TEXT NOT NULL DEFAULT 'default',
+                 parent_chat_id TEXT,
+                 reply_to_id TEXT,
+                 quick_chat BOOLEAN NOT NULL DEFAULT 0,
+                 created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+             );
+             CREATE TABLE message_sets (id TEXT PRIMARY KEY, type TEXT NOT NULL);
+             CREATE TABLE messages (
+                 id TEXT PRIMARY KEY,
+                 chat_id TEXT NOT NULL,
+                 message_set_id TEXT,
+                 text TEXT NOT NULL,
+                 model TEXT,
+                 created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+             );
+             CREATE TABLE message_parts (
+                 chat_id TEXT NOT NULL,
+                 message_id TEXT NOT NULL,
+                 level INTEGER NOT NULL,
+                 content TEXT NOT NULL
+             );
+             CREATE TABLE message_attachments (message_id TEXT NOT NULL);
+             CREATE TABLE search_keys (
+                 id INTEGER PRIMARY KEY,
+                 source TEXT NOT NULL,
+                 source_id TEXT NOT NULL,
+                 level INTEGER NOT NULL DEFAULT 0
+             );
+             CREATE VIRTUAL TABLE messages_fts USING fts5(
+                 text, tokenize = 'unicode61 remove_diacritics 2'
+             );
+             CREATE VIRTUAL TABLE message_parts_fts USING fts5(
+                 content, tokenize = 'unicode61 remove_diacritics 2'
+             );
+             CREATE VIRTUAL TABLE chats_fts USING fts5(
+                 title, tokenize = 'unicode61 remove_diacritics 2'
+             );",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn add_chat(conn: &Connection, id: &str, title: &str) {
+        conn.execute("INSERT INTO chats (id, title) VALUES (?1, ?2)", [id, title])
+            .unwrap();
+        conn.execute(
+            "INSERT INTO search_keys (source, source_id) VALUES ('chat', ?1)",
+            [id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO chats_fts (rowid, title) VALUES (last_insert_rowid(), ?1)",
+            [title],
+        )
+        .unwrap();
+    }
+
+    fn add_message(conn: &Connection, chat_id: &str, id: &str, model: &str, text: &str) {
+        conn.execute(
+            "INSERT INTO messages (id, chat_id, text, model) VALUES (?1, ?2, ?3, ?4)",
+            [id, chat_id, text, model],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO search_keys (source, source_id) VALUES ('message', ?1)",
+            [id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO messages_fts (rowid, text) VALUES (last_insert_rowid(), ?1)",
+            [text],
+        )
+        .unwrap();
+    }
+
+    fn message_ids(page: &SearchPage) -> Vec<&str> {
+        page.hits
+            .iter()
+            .filter_map(|hit| hit.message_id.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn search_matches_messages_and_titles() {
+        let conn = test_db();
+        add_chat(&conn, "c1", "Baking a café cake");
+        add_message(&conn, "c1", "m1", "openai::gpt-4o", "An apple pie recipe");
+        add_message(&conn, "c1", "m2", "anthropic::claude", "A pear tart recipe");
+
+        let page = search(&conn, "apple", SearchFilters::default(), None, None).unwrap();
+        assert_eq!(message_ids(&page), ["m1"]);
+        assert_eq!(
+            page.hits[0].snippet,
+            format!("An {}apple{} pie recipe", HIGHLIGHT_OPEN, HIGHLIGHT_CLOSE)
+        );
+        assert_eq!(page.next_cursor, None);
+
+        let page = search(&conn, "cafe", SearchFilters::default(), None, None).unwrap();
+        assert_eq!(page.hits.len(), 1);
+        assert_eq!(page.hits[0].source, "title");
+        assert_eq!(page.hits[0].chat_id, "c1");
+
+        let page = search(
+            &conn,
+            "recipe model:anthropic::claude",
+            SearchFilters::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(message_ids(&page), ["m2"]);
+
+        let page = search(&conn, "recipe -tart", SearchFilters::default(), None, None).unwrap();
+        assert_eq!(message_ids(&page), ["m1"]);
+    }
+
+    #[test]
+    fn cursor_pages_through_every_hit_once() {
+        let conn = test_db();
+        add_chat(&conn, "c1", "Fruit");
+        // Equal scores, so the pages are ordered by the hits' keys alone
+        for i in 0..12 {
+            add_message(&conn, "c1", &format!("m{}", i), "openai::gpt-4o", "apple");
+        }
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let page = search(
+                &conn,
+                "apple",
+                SearchFilters::default(),
+                cursor.as_deref(),
+                Some(5),
+            )
+            .unwrap();
+            assert!(page.hits.len() <= 5);
+            seen.extend(message_ids(&page).into_iter().map(str::to_string));
+            pages += 1;
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(pages, 3);
+        seen.sort();
+        let mut expected: Vec<String> = (0..12).map(|i| format!("m{}", i)).collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn cursor_is_not_offered_on_the_last_page() {
+        let conn = test_db();
+        add_chat(&conn, "c1", "Fruit");
+        for i in 0..5 {
+            add_message(&conn, "c1", &format!("m{}", i), "openai::gpt-4o", "apple");
+        }
+        let page = search(&conn, "apple", SearchFilters::default(), None, Some(5)).unwrap();
+        assert_eq!(page.hits.len(), 5);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn malformed_cursor() {
+        let conn = test_db();
+        for cursor in ["not a cursor", "e30"] {
+            let error =
+                search(&conn, "apple", SearchFilters::default(), Some(cursor), None).unwrap_err();
+            assert_eq!(error, "Invalid search cursor");
+        }
+    }
+
+    #[test]
+    fn limit_is_clamped() {
+        let conn = test_db();
+        add_chat(&conn, "c1", "Fruit");
+        for i in 0..3 {
+            add_message(&conn, "c1", &format!("m{}", i), "openai::gpt-4o", "apple");
+        }
+        let page = search(&conn, "apple", SearchFilters::default(), None, Some(0)).unwrap();
+        assert_eq!(page.hits.len(), 1);
+        assert!(page.next_cursor.is_some());
+    }
+}
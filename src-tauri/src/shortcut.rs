@@ -0,0 +1,183 @@
+// Shortcuts written the way the settings store them, like "CmdOrCtrl+Shift+K".
+// Modifiers can come in any order. Keys can be given by name ("Slash", "F12",
+// "Num1", "Up"), by their W3C code ("NumpadAdd"), or by the character they
+// type without Shift ("/").
+
+use std::str::FromStr;
+use tauri_plugin_global_shortcut::{Code, Modifiers, Shortcut};
+
+pub const DEFAULT_QUICK_CHAT: &str = "Alt+Space";
+
+// The app menu's accelerators. A global shortcut with the same keys would
+// take them over.
+pub const NEW_CHAT: &str = "CmdOrCtrl+N";
+pub const NEW_PROJECT: &str = "CmdOrCtrl+Shift+N";
+pub const SETTINGS: &str = "CmdOrCtrl+,";
+pub const QUIT: &str = "CmdOrCtrl+Q";
+const MENU_SHORTCUTS: [(&str, &str); 4] = [
+    (NEW_CHAT, "New chat"),
+    (NEW_PROJECT, "New project"),
+    (SETTINGS, "Settings"),
+    (QUIT, "Quit"),
+];
+
+fn parse_modifier(part: &str) -> Option<Modifiers> {
+    match part.to_ascii_lowercase().as_str() {
+        "alt" | "option" => Some(Modifiers::ALT),
+        "ctrl" | "control" => Some(Modifiers::CONTROL),
+        "shift" => Some(Modifiers::SHIFT),
+        "super" | "cmd" | "command" | "meta" | "win" => Some(Modifiers::SUPER),
+        "cmdorctrl" | "cmdorcontrol" | "commandorctrl" | "commandorcontrol" => {
+            if cfg!(target_os = "macos") {
+                Some(Modifiers::SUPER)
+            } else {
+                Some(Modifiers::CONTROL)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn parse_key(part: &str) -> Option<Code> {
+    let lower = part.to_ascii_lowercase();
+    let code = match lower.as_str() {
+        "space" => Code::Space,
+        "enter" | "return" => Code::Enter,
+        "tab" => Code::Tab,
+        "escape" | "esc" => Code::Escape,
+        "backspace" => Code::Backspace,
+        "delete" | "del" => Code::Delete,
+        "insert" | "ins" => Code::Insert,
+        "home" => Code::Home,
+        "end" => Code::End,
+        "pageup" => Code::PageUp,
+        "pagedown" => Code::PageDown,
+        "up" | "arrowup" => Code::ArrowUp,
+        "down" | "arrowdown" => Code::ArrowDown,
+        "left" | "arrowleft" => Code::ArrowLeft,
+        "right" | "arrowright" => Code::ArrowRight,
+        "-" | "minus" => Code::Minus,
+        // Plus is on the same key as = in most layouts
+        "=" | "equal" | "plus" => Code::Equal,
+        "[" | "bracketleft" => Code::BracketLeft,
+        "]" | "bracketright" => Code::BracketRight,
+        "\\" | "backslash" => Code::Backslash,
+        ";" | "semicolon" => Code::Semicolon,
+        "'" | "quote" => Code::Quote,
+        "," | "comma" => Code::Comma,
+        "." | "period" => Code::Period,
+        "/" | "slash" => Code::Slash,
+        "`" | "backquote" => Code::Backquote,
+        "numadd" | "numpadadd" => Code::NumpadAdd,
+        "numsub" | "numpadsubtract" => Code::NumpadSubtract,
+        "nummult" | "numpadmultiply" => Code::NumpadMultiply,
+        "numdiv" | "numpaddivide" => Code::NumpadDivide,
+        "numdec" | "numpaddecimal" => Code::NumpadDecimal,
+        "numenter" | "numpadenter" => Code::NumpadEnter,
+        _ => {
+            let mut chars = lower.chars();
+            let name = match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphabetic() => {
+                    format!("Key{}", c.to_ascii_uppercase())
+                }
+                (Some(c), None) if c.is_ascii_digit() => format!("Digit{}", c),
+                _ => match lower
+                    .strip_prefix("numpad")
+                    .or_else(|| lower.strip_prefix("num"))
+                {
+                    Some(digit) if digit.len() == 1 => format!("Numpad{}", digit),
+                    _ => match lower.strip_prefix('f') {
+                        Some(number) if number.parse::<u8>().is_ok() => {
+                            format!("F{}", number)
+                        }
+                        _ => part.to_string(),
+                    },
+                },
+            };
+            return Code::from_str(&name)
+                .ok()
+                .filter(|code| !is_modifier_key(*code));
+        }
+    };
+    Some(code)
+}
+
+fn is_modifier_key(code: Code) -> bool {
+    matches!(
+        code,
+        Code::AltLeft
+            | Code::AltRight
+            | Code::ControlLeft
+            | Code::ControlRight
+            | Code::ShiftLeft
+            | Code::ShiftRight
+            | Code::MetaLeft
+            | Code::MetaRight
+            | Code::Hyper
+            | Code::Super
+            | Code::Fn
+            | Code::FnLock
+    )
+}
+
+fn is_function_key(code: Code) -> bool {
+    let name = code.to_string();
+    name.len() > 1 && name.starts_with('F') && name[1..].parse::<u8>().is_ok()
+}
+
+/// Reads a shortcut. The error says which part of it is wrong.
+pub fn parse(text: &str) -> Result<Shortcut, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("The shortcut is empty".to_string());
+    }
+    let mut modifiers = Modifiers::empty();
+    let mut key: Option<(&str, Code)> = None;
+    for (index, part) in text.split('+').map(str::trim).enumerate() {
+        if part.is_empty() {
+            return Err(format!(
+                "Part {} of \"{}\" is empty. Write Plus for the + key.",
+                index + 1,
+                text
+            ));
+        }
+        if let Some(modifier) = parse_modifier(part) {
+            if modifiers.contains(modifier) {
+                return Err(format!("\"{}\" has the {} modifier twice", text, part));
+            }
+            modifiers |= modifier;
+        } else if let Some(code) = parse_key(part) {
+            if let Some((first, _)) = key {
+                return Err(format!(
+                    "\"{}\" has two keys, {} and {}. Shortcuts have one key and any modifiers.",
+                    text, first, part
+                ));
+            }
+            key = Some((part, code));
+        } else {
+            return Err(format!(
+                "\"{}\" in \"{}\" isn't a key or modifier",
+                part, text
+            ));
+        }
+    }
+    let Some((name, code)) = key else {
+        return Err(format!("\"{}\" only has modifiers, it needs a key", text));
+    };
+    // Anything else would stop the key from working in every other app
+    if modifiers.is_empty() && !is_function_key(code) {
+        return Err(format!(
+            "{} needs a modifier like Alt or Ctrl, unless it's a function key",
+            name
+        ));
+    }
+    Ok(Shortcut::new(Some(modifiers), code))
+}
+
+/// The app menu item that already uses these keys, if any
+pub fn menu_conflict(shortcut: &Shortcut) -> Option<&'static str> {
+    MENU_SHORTCUTS
+        .iter()
+        .find(|(keys, _)| parse(keys).is_ok_and(|keys| keys.id() == shortcut.id()))
+        .map(|(_, item)| *item)
+}
@@ -0,0 +1,151 @@
+// Closing the main window, and quitting. What the close button does is the
+// `closeBehavior` setting: hide to the tray, minimize, or quit. The quick chat
+// window always just hides. Quitting gives the webviews a few seconds to
+// finish their database writes and close their connections, then stops the
+// child processes and folds the WAL into the database before exiting.
+
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, Window};
+
+use crate::{api, backup, db, mcp, migrator, profiles, quick_chat, SPOTLIGHT_LABEL};
+
+/// Sent to every webview, which answers with `ready_to_quit`
+pub const QUIT_REQUESTED_EVENT: &str = "quit_requested";
+// A webview that hasn't answered by then is quit anyway
+const WEBVIEW_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CloseBehavior {
+    /// Hides the window, or the whole app on macOS. The tray menu brings it
+    /// back.
+    Tray,
+    Minimize,
+    Quit,
+}
+
+impl CloseBehavior {
+    // Not every Linux desktop shows tray icons, so a hidden window could be
+    // lost there. Keep in sync with Shutdown.ts.
+    fn platform_default() -> Self {
+        if cfg!(target_os = "linux") {
+            CloseBehavior::Quit
+        } else {
+            CloseBehavior::Tray
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ShutdownState {
+    quitting: AtomicBool,
+    /// The webviews that are done with the database
+    ready: Mutex<HashSet<String>>,
+    ready_changed: Condvar,
+}
+
+// `settings.closeBehavior` in the settings store
+fn saved_close_behavior(app_handle: &AppHandle) -> CloseBehavior {
+    profiles::settings_store(app_handle)
+        .ok()
+        .and_then(|store| store.get("settings"))
+        .and_then(|settings| settings.get("closeBehavior").cloned())
+        .and_then(|behavior| serde_json::from_value(behavior).ok())
+        .unwrap_or_else(CloseBehavior::platform_default)
+}
+
+/// Called for every close request, which is always prevented so the setting
+/// decides what happens instead
+pub fn on_close_requested(window: &Window) {
+    let app_handle = window.app_handle();
+    if window.label() == SPOTLIGHT_LABEL {
+        quick_chat::hide(app_handle);
+        return;
+    }
+    let result = match saved_close_behavior(app_handle) {
+        #[cfg(target_os = "macos")]
+        CloseBehavior::Tray => app_handle.hide(),
+        #[cfg(not(target_os = "macos"))]
+        CloseBehavior::Tray => window.hide(),
+        CloseBehavior::Minimize => window.minimize(),
+        CloseBehavior::Quit => {
+            quit(app_handle);
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        println!("Failed to close {}: {}", window.label(), e);
+    }
+}
+
+/// Records that a webview has finished writing and closed its connection
+pub fn webview_ready(app_handle: &AppHandle, label: &str) -> Result<(), String> {
+    let state = app_handle.state::<ShutdownState>();
+    let mut ready = state.ready.lock().map_err(|e| e.to_string())?;
+    ready.insert(label.to_string());
+    state.ready_changed.notify_all();
+    Ok(())
+}
+
+fn wait_for_webviews(app_handle: &AppHandle) -> Result<(), String> {
+    let labels: HashSet<String> = app_handle.webview_windows().into_keys().collect();
+    app_handle
+        .emit(QUIT_REQUESTED_EVENT, ())
+        .map_err(|e| e.to_string())?;
+
+    let state = app_handle.state::<ShutdownState>();
+    let ready = state.ready.lock().map_err(|e| e.to_string())?;
+    let (ready, result) = state
+        .ready_changed
+        .wait_timeout_while(ready, WEBVIEW_TIMEOUT, |ready| !labels.is_subset(ready))
+        .map_err(|e| e.to_string())?;
+    if result.timed_out() {
+        let waiting: Vec<&String> = labels.difference(&ready).collect();
+        return Err(format!("No answer from {:?}", waiting));
+    }
+    Ok(())
+}
+
+// Waits for a backup that's being written, then folds the WAL into the
+// database so the file is complete on its own
+fn checkpoint(app_handle: &AppHandle) -> Result<(), String> {
+    backup::paused(|| {
+        let conn = db::open(app_handle)?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Quits the app once the webviews and child processes are done. Calling it
+/// again while quitting does nothing.
+pub fn quit(app_handle: &AppHandle) {
+    let state = app_handle.state::<ShutdownState>();
+    if state.quitting.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        // Until then the database is locked or being migrated, and the
+        // webviews haven't opened it
+        let database_open = migrator::is_done(&app_handle);
+        if database_open {
+            if let Err(e) = wait_for_webviews(&app_handle) {
+                println!("Quitting without waiting for the webviews: {}", e);
+            }
+        }
+        // The local API writes to the database too, so it's stopped before
+        // the checkpoint
+        app_handle.state::<mcp::McpSupervisor>().stop_all();
+        api::stop(&app_handle);
+        if database_open {
+            if let Err(e) = checkpoint(&app_handle) {
+                println!("Failed to checkpoint the database: {}", e);
+            }
+        }
+        app_handle.exit(0);
+    });
+}
@@ -0,0 +1,247 @@
+// Keeps one Chorus running per data directory. The first launch holds a lock
+// on instance.lock and listens on a loopback port. Later launches, including
+// the ones the OS starts to open a chorus:// link, send it their arguments
+// and exit. Dev instances with different CHORUS_INSTANCE_NAME values have
+// their own data directories, so they don't see each other, and a second
+// launch of the same one fails instead of forwarding.
+
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Url};
+
+use crate::{api, deep_link, global_actions, history, mcp, vault};
+
+pub const LOCK_FILE_NAME: &str = "instance.lock";
+pub const ADDRESS_FILE_NAME: &str = "instance.json";
+// The running instance may still be starting when the lock is found taken
+const CONNECT_ATTEMPTS: u32 = 20;
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST_BYTES: u64 = 64 * 1024;
+// Passed to the process `restart` starts, and to no other. A launch that has
+// it waits for the old process to let go of the lock instead of forwarding to
+// it.
+const RESTARTED_ARG: &str = "--chorus-restarted";
+const RESTART_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where the running instance listens, written next to the lock
+#[derive(Serialize, Deserialize)]
+struct Address {
+    port: u16,
+    token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Request {
+    token: String,
+    /// The second launch's arguments, without the program name
+    args: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Reply {
+    ok: bool,
+}
+
+/// The lock and the listener, kept for as long as the app runs
+pub struct Instance {
+    _lock: File,
+    listener: TcpListener,
+    token: String,
+}
+
+pub enum Launch {
+    /// No other instance is running
+    Primary(Instance),
+    /// The running instance took the arguments, so this one should exit
+    Forwarded,
+    /// The lock couldn't be set up, so the app runs without the check
+    Unguarded(String),
+}
+
+/// Takes the lock in the data directory `dir`, or hands this launch's
+/// arguments to the instance that has it. Fails if that instance doesn't
+/// answer, or if it's a named instance, since those are started on purpose
+/// and a second one would most likely be a mistake.
+pub fn acquire(dir: Option<PathBuf>, instance_name: Option<&str>) -> Result<Launch, String> {
+    let Some(dir) = dir else {
+        return Ok(Launch::Unguarded(
+            "The data directory couldn't be found".to_string(),
+        ));
+    };
+    let lock = fs::create_dir_all(&dir).and_then(|_| {
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(LOCK_FILE_NAME))
+    });
+    let lock = match lock {
+        Ok(lock) => lock,
+        Err(e) => return Ok(Launch::Unguarded(e.to_string())),
+    };
+    let restarted = std::env::args().any(|arg| arg == RESTARTED_ARG);
+    match lock_file(&lock, restarted) {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) if restarted => {
+            return Err(format!(
+                "Chorus restarted, but the previous process still holds {}",
+                dir.join(LOCK_FILE_NAME).display()
+            ));
+        }
+        Err(TryLockError::WouldBlock) => {
+            if let Some(name) = instance_name {
+                return Err(format!(
+                    "The Chorus instance \"{}\" is already running: another process holds {}. \
+                     Quit it first, or set {} to a different name.",
+                    name,
+                    dir.join(LOCK_FILE_NAME).display(),
+                    history::INSTANCE_NAME_ENV
+                ));
+            }
+            forward(&dir, std::env::args().skip(1).collect())?;
+            return Ok(Launch::Forwarded);
+        }
+        Err(TryLockError::Error(e)) => return Ok(Launch::Unguarded(e.to_string())),
+    }
+
+    let listen = || -> Result<Instance, String> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| e.to_string())?;
+        let address = Address {
+            port: listener.local_addr().map_err(|e| e.to_string())?.port(),
+            token: api::generate_token(),
+        };
+        let contents = serde_json::to_string(&address).map_err(|e| e.to_string())?;
+        vault::write_private(&dir.join(ADDRESS_FILE_NAME), &contents)?;
+        Ok(Instance {
+            _lock: lock,
+            listener,
+            token: address.token,
+        })
+    };
+    Ok(listen().map_or_else(Launch::Unguarded, Launch::Primary))
+}
+
+// Waits for the lock if this launch is a restart
+fn lock_file(lock: &File, restarted: bool) -> Result<(), TryLockError> {
+    let started = Instant::now();
+    loop {
+        match lock.try_lock() {
+            Err(TryLockError::WouldBlock) if restarted && started.elapsed() < RESTART_TIMEOUT => {
+                std::thread::sleep(RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn try_forward(dir: &Path, args: &[String]) -> Result<(), String> {
+    let contents = fs::read_to_string(dir.join(ADDRESS_FILE_NAME)).map_err(|e| e.to_string())?;
+    let address: Address = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    let mut stream = TcpStream::connect_timeout(
+        &SocketAddr::from((Ipv4Addr::LOCALHOST, address.port)),
+        IO_TIMEOUT,
+    )
+    .map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(IO_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let request = Request {
+        token: address.token,
+        args: args.to_vec(),
+    };
+    let mut line = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .map_err(|e| e.to_string())?;
+    match serde_json::from_str::<Reply>(&reply) {
+        Ok(Reply { ok: true }) => Ok(()),
+        _ => Err("The running instance refused the request".to_string()),
+    }
+}
+
+fn forward(dir: &Path, args: Vec<String>) -> Result<(), String> {
+    let mut last_error = String::new();
+    for _ in 0..CONNECT_ATTEMPTS {
+        match try_forward(dir, &args) {
+            Ok(()) => return Ok(()),
+            Err(e) => last_error = e,
+        }
+        std::thread::sleep(RETRY_DELAY);
+    }
+    Err(format!(
+        "Chorus is already running with {}, but didn't answer: {}",
+        dir.display(),
+        last_error
+    ))
+}
+
+/// Shows the main window and routes any chorus:// links among the arguments
+fn dispatch(app_handle: &AppHandle, args: Vec<String>) {
+    global_actions::show_main_window(app_handle);
+    let urls: Vec<Url> = args
+        .iter()
+        .filter(|arg| arg.starts_with(&format!("{}:", deep_link::SCHEME)))
+        .filter_map(|arg| Url::parse(arg).ok())
+        .collect();
+    deep_link::handle_urls(app_handle, urls);
+}
+
+fn handle_connection(app_handle: &AppHandle, token: &str, mut stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+    let mut line = String::new();
+    let read = stream
+        .try_clone()
+        .and_then(|reader| BufReader::new(reader.take(MAX_REQUEST_BYTES)).read_line(&mut line));
+    let request = read
+        .ok()
+        .and_then(|_| serde_json::from_str::<Request>(&line).ok())
+        .filter(|request| api::same_token(&request.token, token));
+    let reply = Reply {
+        ok: request.is_some(),
+    };
+    if let Ok(reply) = serde_json::to_string(&reply) {
+        let _ = stream.write_all(format!("{}\n", reply).as_bytes());
+    }
+    if let Some(request) = request {
+        dispatch(app_handle, request.args);
+    }
+}
+
+/// Restarts the app with the same arguments. Like `AppHandle::restart`, but
+/// the new process is told it's a restart, so it waits for the lock.
+pub fn restart(app_handle: &AppHandle) -> ! {
+    let mut env = app_handle.env();
+    env.args_os.retain(|arg| arg != RESTARTED_ARG);
+    env.args_os.push(RESTARTED_ARG.into());
+    // What the event loop would do on exit, which is skipped here
+    app_handle.state::<mcp::McpSupervisor>().stop_all();
+    api::stop(app_handle);
+    app_handle.cleanup_before_exit();
+    tauri::process::restart(&env)
+}
+
+/// Answers later launches for as long as the app runs
+pub fn serve(app_handle: AppHandle, instance: Instance) {
+    std::thread::spawn(move || {
+        // Bind the whole instance so the lock lives as long as the thread.
+        // The closure would otherwise capture only the fields it uses.
+        let instance = instance;
+        for stream in instance.listener.incoming() {
+            match stream {
+                Ok(stream) => handle_connection(&app_handle, &instance.token, stream),
+                Err(e) => println!("Failed to accept a launch request: {}", e),
+            }
+        }
+    });
+}
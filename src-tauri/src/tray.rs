@@ -0,0 +1,198 @@
+// The tray icon, on every platform. Its menu is built from the chat database:
+// pinned chats and the most recent ones, plus a switch for YOLO mode. It's
+// rebuilt whenever the chats change. Left clicking the icon toggles quick chat
+// where the platform reports clicks, which Linux doesn't.
+
+use rusqlite::params;
+use tauri::menu::{CheckMenuItem, Menu, MenuBuilder, MenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Listener, Manager};
+
+use crate::app_menu::{self, MenuChat};
+use crate::deep_link::{self, DeepLink};
+use crate::global_actions::{self, Action};
+use crate::{command, db, history, migrator, quick_chat, shutdown};
+
+const TRAY_ID: &str = "main";
+const RECENT_CHATS: i64 = 5;
+
+const OPEN_ID: &str = "tray-open";
+const NEW_CHAT_ID: &str = "tray-new-chat";
+const QUICK_CHAT_ID: &str = "tray-quick-chat";
+const PAUSE_TOOLS_ID: &str = "tray-pause-tools";
+const QUIT_ID: &str = "tray-quit";
+// Followed by the chat's id
+const CHAT_ID_PREFIX: &str = "tray-chat:";
+
+struct TrayContents {
+    pinned: Vec<MenuChat>,
+    recent: Vec<MenuChat>,
+    yolo_mode: bool,
+}
+
+fn read_contents(app_handle: &AppHandle) -> Result<TrayContents, String> {
+    let conn = db::open_read_only(app_handle)?;
+    let yolo_mode = conn
+        .query_row(
+            "SELECT value FROM app_metadata WHERE key = 'yolo_mode'",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .map(|value| value == "true")
+        .unwrap_or(false);
+    Ok(TrayContents {
+        pinned: app_menu::query_chats(&conn, Some(true), -1)?,
+        recent: app_menu::query_chats(&conn, Some(false), RECENT_CHATS)?,
+        yolo_mode,
+    })
+}
+
+fn build_menu(app_handle: &AppHandle, with_chats: bool) -> tauri::Result<Menu<tauri::Wry>> {
+    // A locked or missing database still leaves the other items
+    let contents = if with_chats {
+        read_contents(app_handle).map_err(|e| {
+            println!("Failed to read the chats for the tray menu: {}", e);
+        })
+    } else {
+        Err(())
+    };
+
+    let mut menu = MenuBuilder::new(app_handle)
+        .text(OPEN_ID, "Open Chorus")
+        .text(NEW_CHAT_ID, "New chat")
+        .text(QUICK_CHAT_ID, "Quick chat");
+    if let Ok(contents) = &contents {
+        for (heading, chats) in [("Pinned", &contents.pinned), ("Recent", &contents.recent)] {
+            if chats.is_empty() {
+                continue;
+            }
+            menu = menu
+                .separator()
+                .item(&MenuItem::new(app_handle, heading, false, None::<&str>)?);
+            for chat in chats {
+                menu = menu.text(
+                    format!("{}{}", CHAT_ID_PREFIX, chat.id),
+                    app_menu::menu_title(&chat.title, "Untitled Chat"),
+                );
+            }
+        }
+    }
+    menu.separator()
+        .item(&CheckMenuItem::with_id(
+            app_handle,
+            PAUSE_TOOLS_ID,
+            "Pause tools (YOLO off)",
+            contents.is_ok(),
+            contents.is_ok_and(|contents| !contents.yolo_mode),
+            None::<&str>,
+        )?)
+        .separator()
+        .text(QUIT_ID, "Quit")
+        .build()
+}
+
+// The webview reads app_metadata through its own connection, so it's told
+// to reload it along with the chats
+fn set_yolo_mode(app_handle: &AppHandle, enabled: bool) -> Result<(), String> {
+    let conn = db::open(app_handle)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_metadata (key, value) VALUES ('yolo_mode', ?1)",
+        params![if enabled { "true" } else { "false" }],
+    )
+    .map_err(|e| e.to_string())?;
+    command::refresh_projects_state(app_handle.clone());
+    Ok(())
+}
+
+fn on_menu_event(app_handle: &AppHandle, id: &str) {
+    match id {
+        OPEN_ID => global_actions::show_main_window(app_handle),
+        NEW_CHAT_ID => global_actions::run(app_handle, Action::NewChat),
+        QUICK_CHAT_ID => quick_chat::show(app_handle),
+        PAUSE_TOOLS_ID => {
+            let yolo_mode = read_contents(app_handle).is_ok_and(|contents| contents.yolo_mode);
+            if let Err(e) = set_yolo_mode(app_handle, !yolo_mode) {
+                println!("Failed to change YOLO mode: {}", e);
+                // The check mark flips on click, so it's put back
+                refresh(app_handle);
+            }
+        }
+        QUIT_ID => shutdown::quit(app_handle),
+        _ => {
+            if let Some(chat_id) = id.strip_prefix(CHAT_ID_PREFIX) {
+                quick_chat::hide(app_handle);
+                deep_link::deliver(
+                    app_handle,
+                    DeepLink::OpenChat {
+                        chat_id: chat_id.to_string(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Rebuilds the menu from the database
+pub fn refresh(app_handle: &AppHandle) {
+    let Some(tray) = app_handle.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_menu(app_handle, true) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                println!("Failed to update the tray menu: {}", e);
+            }
+        }
+        Err(e) => println!("Failed to build the tray menu: {}", e),
+    }
+}
+
+/// Rebuilds the menu once the database is migrated, which also means it's
+/// unlocked
+pub fn refresh_when_ready(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || match migrator::wait(&app_handle) {
+        Ok(()) => refresh(&app_handle),
+        Err(e) => println!("Not filling in the tray menu: {}", e),
+    });
+}
+
+/// Adds the tray icon and keeps its menu up to date
+pub fn setup(app_handle: &AppHandle) -> tauri::Result<()> {
+    let tooltip = match history::instance_name() {
+        Some(name) => format!("Chorus ({})", name),
+        None => "Chorus".to_string(),
+    };
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(app_handle.default_window_icon().unwrap().clone())
+        .tooltip(tooltip)
+        // Without the chats until the database is ready
+        .menu(&build_menu(app_handle, false)?)
+        // Left clicks toggle quick chat, the menu is on the right button
+        .show_menu_on_left_click(false)
+        .on_menu_event(|app, event| on_menu_event(app, event.id().as_ref()))
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                let app = tray.app_handle();
+                if quick_chat::is_visible(app) {
+                    quick_chat::hide(app);
+                } else {
+                    quick_chat::show(app);
+                }
+            }
+        })
+        .build(app_handle)?;
+
+    // Sent when the local API changes the chats, and by set_yolo_mode
+    for event in ["refresh_projects_state", "chat_deleted"] {
+        let handle = app_handle.clone();
+        app_handle.listen(event, move |_| refresh(&handle));
+    }
+    refresh_when_ready(app_handle);
+    Ok(())
+}
@@ -0,0 +1,620 @@
+use iota_stronghold::Client;
+use rand::RngCore;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_stronghold::kdf::KeyDerivation;
+use tauri_plugin_stronghold::stronghold::Stronghold;
+
+use crate::db_encryption::KeyringSlot;
+use crate::{data_dir, db, migrator, profiles};
+
+const SNAPSHOT_FILE_NAME: &str = "vault.hold";
+
+// Shared with the Stronghold plugin, so keys derived in the webview match the
+// ones derived here
+pub const SALT_FILE_NAME: &str = "vault-salt.txt";
+
+// Random key the vault is derived from until the user sets a password, kept
+// in the OS keyring. Its absence is what marks a vault as password protected.
+const KEYRING_SERVICE: &str = "Chorus vault";
+
+// Where older versions kept the machine key. It's moved into the keyring the
+// first time it's read.
+const LEGACY_MACHINE_KEY_FILE_NAME: &str = "vault.key";
+
+const CLIENT_NAME: &[u8] = b"chorus";
+
+/// Values starting with this prefix name a vault entry instead of holding the
+/// secret itself, e.g. `vault:toolset/github/GITHUB_TOKEN`.
+pub const REF_PREFIX: &str = "vault:";
+
+// Each profile's entries are stored under `profile/<id>/`, so profiles can
+// use the same names without sharing secrets. Names outside the vault,
+// including the ones references hold, leave it out.
+const PROFILE_ENTRY_PREFIX: &str = "profile/";
+
+struct OpenVault {
+    stronghold: Stronghold,
+    client: Client,
+}
+
+/// The unlocked vault, if any. It's opened lazily with the machine key, or by
+/// `unlock` when the user has set a password.
+#[derive(Default)]
+pub struct VaultState {
+    vault: Mutex<Option<OpenVault>>,
+}
+
+#[derive(Serialize)]
+pub struct VaultStatus {
+    pub unlocked: bool,
+    pub uses_password: bool,
+}
+
+fn vault_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    let dir = data_dir::config_dir(app_handle)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+pub fn salt_path(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    Ok(vault_dir(app_handle)?.join(SALT_FILE_NAME))
+}
+
+fn uses_password(app_handle: &AppHandle) -> Result<bool, String> {
+    let snapshot_exists = vault_dir(app_handle)?.join(SNAPSHOT_FILE_NAME).exists();
+    Ok(snapshot_exists
+        && machine_key(app_handle, KeyringSlot::Current)?.is_none()
+        && machine_key(app_handle, KeyringSlot::Pending)?.is_none())
+}
+
+pub fn write_private(path: &PathBuf, contents: &str) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .map_err(|e| e.to_string())?;
+        file.write_all(contents.as_bytes())
+            .map_err(|e| e.to_string())
+    }
+    #[cfg(not(unix))]
+    {
+        fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+fn generate_machine_key() -> String {
+    let mut key = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut key);
+    hex::encode(key)
+}
+
+// Keys are stored per vault, so profiles and instances with their own data
+// directory don't share one. While `set_password` replaces the key, the new one
+// waits in the pending slot.
+fn keyring_entry_in(dir: &Path, slot: KeyringSlot) -> Result<keyring::Entry, String> {
+    let path = dir.join(SNAPSHOT_FILE_NAME);
+    let path = fs::canonicalize(&path).unwrap_or(path);
+    let account = match slot {
+        KeyringSlot::Current => path.to_string_lossy().to_string(),
+        KeyringSlot::Pending => format!("{} (pending)", path.to_string_lossy()),
+    };
+    keyring::Entry::new(KEYRING_SERVICE, &account).map_err(|e| e.to_string())
+}
+
+fn keyring_entry(app_handle: &AppHandle, slot: KeyringSlot) -> Result<keyring::Entry, String> {
+    keyring_entry_in(&vault_dir(app_handle)?, slot)
+}
+
+/// Stores the machine keys of the vault in `from` for its copy in `to` too.
+/// The machine key of a vault that hasn't been opened since it was kept in
+/// a file is copied along with the file.
+pub fn copy_machine_keys(from: &Path, to: &Path) -> Result<(), String> {
+    for slot in [KeyringSlot::Current, KeyringSlot::Pending] {
+        match keyring_entry_in(from, slot)?.get_password() {
+            Ok(key) => keyring_entry_in(to, slot)?
+                .set_password(&key)
+                .map_err(|e| format!("Couldn't save the vault key to the keyring: {}", e))?,
+            Err(keyring::Error::NoEntry) => {}
+            Err(e) => {
+                return Err(format!(
+                    "Couldn't read the vault key from the keyring: {}",
+                    e
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Removes the machine keys of the vault in `dir`, like a copy that's being
+/// rolled back
+pub fn remove_machine_keys(dir: &Path) -> Result<(), String> {
+    for slot in [KeyringSlot::Current, KeyringSlot::Pending] {
+        match keyring_entry_in(dir, slot)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => {
+                return Err(format!(
+                    "Couldn't remove the vault key from the keyring: {}",
+                    e
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn store_machine_key(
+    app_handle: &AppHandle,
+    slot: KeyringSlot,
+    key: Option<&str>,
+) -> Result<(), String> {
+    let entry = keyring_entry(app_handle, slot)?;
+    match key {
+        Some(key) => entry
+            .set_password(key)
+            .map_err(|e| format!("Couldn't save the vault key to the keyring: {}", e)),
+        None => match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!(
+                "Couldn't remove the vault key from the keyring: {}",
+                e
+            )),
+        },
+    }
+}
+
+fn machine_key(app_handle: &AppHandle, slot: KeyringSlot) -> Result<Option<String>, String> {
+    if let KeyringSlot::Current = slot {
+        let legacy_path = vault_dir(app_handle)?.join(LEGACY_MACHINE_KEY_FILE_NAME);
+        if let Ok(key) = fs::read_to_string(&legacy_path) {
+            store_machine_key(app_handle, slot, Some(key.trim()))?;
+            fs::remove_file(&legacy_path).map_err(|e| e.to_string())?;
+        }
+    }
+    match keyring_entry(app_handle, slot)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!(
+            "Couldn't read the vault key from the keyring: {}",
+            e
+        )),
+    }
+}
+
+/// Opens the vault with the machine key, creating the key if the vault
+/// doesn't exist yet. A pending key means `set_password` was interrupted, and
+/// whichever key opens the snapshot is kept.
+fn open_with_machine_key(app_handle: &AppHandle) -> Result<OpenVault, String> {
+    let current = machine_key(app_handle, KeyringSlot::Current)?;
+    let pending = machine_key(app_handle, KeyringSlot::Pending)?;
+    if current.is_none() && pending.is_none() {
+        let key = generate_machine_key();
+        store_machine_key(app_handle, KeyringSlot::Current, Some(&key))?;
+        return open(app_handle, &key);
+    }
+    if let Some(key) = &current {
+        if let Ok(vault) = open(app_handle, key) {
+            if pending.is_some() {
+                store_machine_key(app_handle, KeyringSlot::Pending, None)?;
+            }
+            return Ok(vault);
+        }
+    }
+    let Some(key) = pending else {
+        return Err("Couldn't open the vault with the key in the keyring".to_string());
+    };
+    let vault = open(app_handle, &key)?;
+    store_machine_key(app_handle, KeyringSlot::Current, Some(&key))?;
+    store_machine_key(app_handle, KeyringSlot::Pending, None)?;
+    Ok(vault)
+}
+
+fn open_snapshot(
+    app_handle: &AppHandle,
+    path: &PathBuf,
+    password: &str,
+) -> Result<OpenVault, String> {
+    // Argon2 with the plugin's parameters, so a snapshot can be opened from
+    // either side
+    let key = KeyDerivation::argon2(password, &salt_path(app_handle)?);
+    let stronghold = Stronghold::new(path, key)
+        .map_err(|_| "Couldn't open the vault, the password may be wrong".to_string())?;
+    let client = stronghold
+        .load_client(CLIENT_NAME)
+        .or_else(|_| stronghold.create_client(CLIENT_NAME))
+        .map_err(|e| e.to_string())?;
+    Ok(OpenVault { stronghold, client })
+}
+
+fn open(app_handle: &AppHandle, password: &str) -> Result<OpenVault, String> {
+    let path = vault_dir(app_handle)?.join(SNAPSHOT_FILE_NAME);
+    open_snapshot(app_handle, &path, password)
+}
+
+fn save(vault: &OpenVault) -> Result<(), String> {
+    vault
+        .stronghold
+        .write_client(CLIENT_NAME)
+        .map_err(|e| e.to_string())?;
+    vault.stronghold.save().map_err(|e| e.to_string())
+}
+
+fn with_vault<T>(
+    app_handle: &AppHandle,
+    f: impl FnOnce(&OpenVault) -> Result<T, String>,
+) -> Result<T, String> {
+    let state = app_handle.state::<VaultState>();
+    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
+    if vault.is_none() {
+        if uses_password(app_handle)? {
+            return Err("The vault is locked".to_string());
+        }
+        let opened = open_with_machine_key(app_handle)?;
+        split_shared_entries(app_handle, &opened)?;
+        *vault = Some(opened);
+    }
+    f(vault.as_ref().unwrap())
+}
+
+fn profile_prefix(profile_id: &str) -> String {
+    format!("{}{}/", PROFILE_ENTRY_PREFIX, profile_id)
+}
+
+// The active profile's prefix
+fn active_prefix(app_handle: &AppHandle) -> Result<String, String> {
+    Ok(profile_prefix(&profiles::active(app_handle)?.id))
+}
+
+// Entries from before they were kept per profile were shared by every
+// profile, so each one gets its own copy
+fn split_shared_entries(app_handle: &AppHandle, vault: &OpenVault) -> Result<(), String> {
+    let shared: Vec<(String, String)> = read_all(vault)?
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with(PROFILE_ENTRY_PREFIX))
+        .collect();
+    if shared.is_empty() {
+        return Ok(());
+    }
+    let profiles = profiles::list(app_handle)?.profiles;
+    for (name, value) in &shared {
+        for profile in &profiles {
+            let copy = format!("{}{}", profile_prefix(&profile.id), name);
+            if read_entry(vault, &copy)?.is_none() {
+                insert_entry(vault, &copy, value)?;
+            }
+        }
+        vault
+            .client
+            .store()
+            .delete(name.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    save(vault)?;
+    println!(
+        "Gave each of {} profiles its own copy of {} vault entries",
+        profiles.len(),
+        shared.len()
+    );
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.chars().any(|c| c.is_control()) {
+        return Err(format!("Invalid secret name: {:?}", name));
+    }
+    Ok(())
+}
+
+fn read_entry(vault: &OpenVault, name: &str) -> Result<Option<String>, String> {
+    vault
+        .client
+        .store()
+        .get(name.as_bytes())
+        .map_err(|e| e.to_string())?
+        .map(|value| String::from_utf8(value).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+fn insert_entry(vault: &OpenVault, name: &str, value: &str) -> Result<(), String> {
+    vault
+        .client
+        .store()
+        .insert(name.as_bytes().to_vec(), value.as_bytes().to_vec(), None)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn read_all(vault: &OpenVault) -> Result<Vec<(String, String)>, String> {
+    let keys = vault.client.store().keys().map_err(|e| e.to_string())?;
+    let mut entries = Vec::new();
+    for key in keys {
+        let name = String::from_utf8(key).map_err(|e| e.to_string())?;
+        if let Some(value) = read_entry(vault, &name)? {
+            entries.push((name, value));
+        }
+    }
+    Ok(entries)
+}
+
+pub fn to_ref(name: &str) -> String {
+    format!("{}{}", REF_PREFIX, name)
+}
+
+pub fn status(app_handle: &AppHandle) -> Result<VaultStatus, String> {
+    let state = app_handle.state::<VaultState>();
+    let unlocked = state.vault.lock().map_err(|e| e.to_string())?.is_some();
+    Ok(VaultStatus {
+        unlocked,
+        uses_password: uses_password(app_handle)?,
+    })
+}
+
+pub fn put(app_handle: &AppHandle, name: &str, value: &str) -> Result<(), String> {
+    validate_name(name)?;
+    let name = format!("{}{}", active_prefix(app_handle)?, name);
+    with_vault(app_handle, |vault| {
+        insert_entry(vault, &name, value)?;
+        save(vault)
+    })
+}
+
+pub fn get(app_handle: &AppHandle, name: &str) -> Result<Option<String>, String> {
+    let name = format!("{}{}", active_prefix(app_handle)?, name);
+    with_vault(app_handle, |vault| read_entry(vault, &name))
+}
+
+/// Deletes an entry, returning whether it existed.
+pub fn delete(app_handle: &AppHandle, name: &str) -> Result<bool, String> {
+    let name = format!("{}{}", active_prefix(app_handle)?, name);
+    with_vault(app_handle, |vault| {
+        let existed = vault
+            .client
+            .store()
+            .delete(name.as_bytes())
+            .map_err(|e| e.to_string())?
+            .is_some();
+        if existed {
+            save(vault)?;
+        }
+        Ok(existed)
+    })
+}
+
+/// Lists the active profile's entry names. Values are never returned in bulk.
+pub fn list(app_handle: &AppHandle) -> Result<Vec<String>, String> {
+    let prefix = active_prefix(app_handle)?;
+    with_vault(app_handle, |vault| {
+        let mut names = vault
+            .client
+            .store()
+            .keys()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .filter_map(|key| String::from_utf8(key).ok())
+            .filter_map(|name| name.strip_prefix(&prefix).map(str::to_string))
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    })
+}
+
+/// Deletes every entry of a profile that's being deleted
+pub fn delete_profile(app_handle: &AppHandle, profile_id: &str) -> Result<(), String> {
+    let prefix = profile_prefix(profile_id);
+    with_vault(app_handle, |vault| {
+        let names: Vec<String> = read_all(vault)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| name.starts_with(&prefix))
+            .collect();
+        if names.is_empty() {
+            return Ok(());
+        }
+        for name in names {
+            vault
+                .client
+                .store()
+                .delete(name.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+        save(vault)
+    })
+}
+
+/// Replaces vault references with the secrets they point to. Other values
+/// are passed through unchanged.
+pub fn resolve(
+    app_handle: &AppHandle,
+    values: HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    if !values.values().any(|value| value.starts_with(REF_PREFIX)) {
+        return Ok(values);
+    }
+    let prefix = active_prefix(app_handle)?;
+    with_vault(app_handle, |vault| {
+        let mut resolved = HashMap::new();
+        for (key, value) in values {
+            let value = match value.strip_prefix(REF_PREFIX) {
+                Some(name) => read_entry(vault, &format!("{}{}", prefix, name))?
+                    .ok_or_else(|| format!("Secret {} is missing from the vault", name))?,
+                None => value,
+            };
+            resolved.insert(key, value);
+        }
+        Ok(resolved)
+    })
+}
+
+pub fn unlock(app_handle: &AppHandle, password: &str) -> Result<(), String> {
+    let opened = open(app_handle, password)?;
+    split_shared_entries(app_handle, &opened)?;
+    let state = app_handle.state::<VaultState>();
+    *state.vault.lock().map_err(|e| e.to_string())? = Some(opened);
+    migrate_plaintext(app_handle)
+}
+
+/// Re-encrypts the vault with a key derived from `password`, or from a new
+/// machine key if `password` is None.
+pub fn set_password(app_handle: &AppHandle, password: Option<&str>) -> Result<(), String> {
+    if password.is_some_and(|password| password.is_empty()) {
+        return Err("The password can't be empty".to_string());
+    }
+    let entries = with_vault(app_handle, read_all)?;
+
+    let dir = vault_dir(app_handle)?;
+    let snapshot_path = dir.join(SNAPSHOT_FILE_NAME);
+    let new_snapshot_path = dir.join(format!("{}.new", SNAPSHOT_FILE_NAME));
+    let _ = fs::remove_file(&new_snapshot_path);
+
+    let new_password = match password {
+        Some(password) => password.to_string(),
+        None => {
+            let key = generate_machine_key();
+            store_machine_key(app_handle, KeyringSlot::Pending, Some(&key))?;
+            key
+        }
+    };
+
+    let state = app_handle.state::<VaultState>();
+    let mut vault = state.vault.lock().map_err(|e| e.to_string())?;
+    let rekeyed = open_snapshot(app_handle, &new_snapshot_path, &new_password)?;
+    for (name, value) in &entries {
+        insert_entry(&rekeyed, name, value)?;
+    }
+    save(&rekeyed)?;
+    drop(rekeyed);
+
+    // Swap the snapshot first: a leftover key only means the password has to
+    // be entered, while a missing one would lose the vault
+    fs::rename(&new_snapshot_path, &snapshot_path).map_err(|e| e.to_string())?;
+    let new_machine_key = password.is_none().then_some(new_password.as_str());
+    store_machine_key(app_handle, KeyringSlot::Current, new_machine_key)?;
+    store_machine_key(app_handle, KeyringSlot::Pending, None)?;
+    *vault = Some(open(app_handle, &new_password)?);
+    Ok(())
+}
+
+/// Moves plaintext values in `object` into the vault under `prefix/<key>`,
+/// leaving references behind. Returns how many values were moved.
+fn move_into_vault(
+    app_handle: &AppHandle,
+    prefix: &str,
+    object: &mut serde_json::Map<String, Value>,
+    skip: impl Fn(&str) -> bool,
+) -> Result<usize, String> {
+    let mut moved = 0;
+    for (key, value) in object.iter_mut() {
+        let Some(plain) = value.as_str() else {
+            continue;
+        };
+        if plain.is_empty() || plain.starts_with(REF_PREFIX) || skip(key) {
+            continue;
+        }
+        let name = format!("{}/{}", prefix, key);
+        put(app_handle, &name, plain)?;
+        *value = Value::String(to_ref(&name));
+        moved += 1;
+    }
+    Ok(moved)
+}
+
+fn migrate_toolset_env(app_handle: &AppHandle) -> Result<usize, String> {
+    let conn = db::open(app_handle)?;
+    let mut stmt = conn
+        .prepare("SELECT name, env FROM custom_toolsets")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut moved = 0;
+    for (name, env) in rows {
+        let Some(Value::Object(mut env)) = env.and_then(|env| serde_json::from_str(&env).ok())
+        else {
+            continue;
+        };
+        let count = move_into_vault(app_handle, &format!("toolset/{}", name), &mut env, |_| {
+            false
+        })?;
+        if count > 0 {
+            conn.execute(
+                "UPDATE custom_toolsets SET env = ?1 WHERE name = ?2",
+                rusqlite::params![Value::Object(env).to_string(), name],
+            )
+            .map_err(|e| e.to_string())?;
+            moved += count;
+        }
+    }
+    Ok(moved)
+}
+
+fn migrate_api_keys(app_handle: &AppHandle) -> Result<usize, String> {
+    let store = profiles::settings_store(app_handle)?;
+    let Some(mut settings) = store.get("settings") else {
+        return Ok(0);
+    };
+    let Some(api_keys) = settings
+        .get_mut("apiKeys")
+        .and_then(|api_keys| api_keys.as_object_mut())
+    else {
+        return Ok(0);
+    };
+    // Base URLs live next to the keys but aren't secret
+    let moved = move_into_vault(app_handle, "api-key", api_keys, |key| {
+        key.to_lowercase().contains("url")
+    })?;
+    if moved > 0 {
+        store.set("settings", settings);
+        store.save().map_err(|e| e.to_string())?;
+    }
+    Ok(moved)
+}
+
+/// Moves secrets that are still stored in plaintext (custom toolset env vars
+/// and provider API keys) into the vault.
+fn migrate_plaintext(app_handle: &AppHandle) -> Result<(), String> {
+    let toolset_secrets = migrate_toolset_env(app_handle)?;
+    let api_keys = migrate_api_keys(app_handle)?;
+    if toolset_secrets + api_keys > 0 {
+        println!(
+            "Moved {} toolset secrets and {} API keys into the vault",
+            toolset_secrets, api_keys
+        );
+    }
+    Ok(())
+}
+
+/// Opens the vault once migrations are done and moves any plaintext secrets
+/// into it. A password protected vault waits for `unlock` instead.
+pub fn start(app_handle: AppHandle) {
+    std::thread::spawn(move || {
+        if migrator::wait(&app_handle).is_err() {
+            return;
+        }
+        match uses_password(&app_handle) {
+            Ok(false) => {
+                if let Err(e) = migrate_plaintext(&app_handle) {
+                    println!("Failed to move secrets into the vault: {}", e);
+                }
+            }
+            Ok(true) => {}
+            Err(e) => println!("Failed to open the vault: {}", e),
+        }
+    });
+}
@@ -0,0 +1,12 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * Rebuilds the app menu (src-tauri/src/app_menu.rs) with `currentChatId` as
+ * the chat its Projects submenu moves. The main window calls this whenever
+ * it opens another chat or its chats, projects or selected models change.
+ */
+export async function updateAppMenu(
+    currentChatId: string | null,
+): Promise<void> {
+    await invoke("update_app_menu", { currentChatId });
+}
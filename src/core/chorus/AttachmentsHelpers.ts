@@ -9,6 +9,7 @@ import mime from "mime-types";
 import { invoke } from "@tauri-apps/api/core";
 import * as pdfjsLib from "pdfjs-dist/legacy/build/pdf.mjs";
 import { Attachment } from "./api/AttachmentsAPI";
+import { config } from "@core/config";
 
 // Initialize PDF.js worker
 pdfjsLib.GlobalWorkerOptions.workerSrc = new URL(
@@ -120,8 +121,11 @@ export const fileTypeToAttachmentType = (
 };
 
 export const ensureUploadsOriginalsDirectory = async () => {
-    const appDir = await appDataDir();
-    const originalsDir = path.join(appDir, "uploads", "originals");
+    const originalsDir = path.join(
+        config.profile.dataDir,
+        "uploads",
+        "originals",
+    );
     await mkdir(originalsDir, { recursive: true });
     return originalsDir;
 };
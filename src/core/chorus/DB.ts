@@ -1,4 +1,13 @@
 import { config } from "@core/config";
+import { invoke } from "@tauri-apps/api/core";
 import Database from "@tauri-apps/plugin-sql";
+import { waitForDatabaseUnlock } from "./DatabaseUnlock";
+
+// An encrypted database has to be unlocked before migrations can run
+await waitForDatabaseUnlock();
+
+// Migrations run in the Rust backend on startup, wait for them to finish
+// before touching the database
+await invoke("wait_for_migrations");
 
 export const db = await Database.load(config.dbUrl);
@@ -0,0 +1,36 @@
+import { invoke } from "@tauri-apps/api/core";
+import { runWithDatabaseClosed } from "./DatabaseEncryption";
+
+/**
+ * Where the app keeps its files. The backend (src-tauri/src/data_location.rs)
+ * picks it from --data-dir, CHORUS_DATA_DIR, a ChorusData folder next to the
+ * app, or the directory the data was last moved to.
+ */
+export type DataDirectorySource =
+    | "argument"
+    | "environment"
+    | "portable"
+    | "setting"
+    | "default";
+
+export type DataDirectory = {
+    /** Holds the databases, backups and the vault */
+    configDir: string;
+    /** Holds the stores, uploads and generated images */
+    dataDir: string;
+    source: DataDirectorySource;
+    /** False when an argument, the environment or portable mode decides */
+    movable: boolean;
+};
+
+export async function getDataDirectory(): Promise<DataDirectory> {
+    return await invoke<DataDirectory>("get_data_directory");
+}
+
+/**
+ * Copies everything to `path`, which must be empty, and restarts the app
+ * using it. The old files are left behind.
+ */
+export async function moveDataDirectory(path: string): Promise<void> {
+    await runWithDatabaseClosed("move_data_directory", { path });
+}
@@ -0,0 +1,40 @@
+import { config } from "@core/config";
+import { invoke } from "@tauri-apps/api/core";
+import Database from "@tauri-apps/plugin-sql";
+import { db } from "./DB";
+
+export type DatabaseKeySource =
+    | { source: "keyring" }
+    | { source: "passphrase"; passphrase: string };
+
+/**
+ * The backend rewrites or swaps the database file, so our connection has to
+ * be closed first. On success the app restarts or reloads; on failure the
+ * connection is reopened.
+ */
+export async function runWithDatabaseClosed(
+    command: string,
+    args?: Record<string, unknown>,
+): Promise<void> {
+    await db.close();
+    try {
+        await invoke(command, args);
+    } catch (error) {
+        // `db` only holds the URL, so loading it again makes it usable
+        await Database.load(config.dbUrl);
+        throw error;
+    }
+}
+
+/** Encrypts the chat database and its backups with SQLCipher. */
+export async function encryptDatabase(key: DatabaseKeySource): Promise<void> {
+    await runWithDatabaseClosed("encrypt_database", { key });
+}
+
+export async function rekeyDatabase(key: DatabaseKeySource): Promise<void> {
+    await runWithDatabaseClosed("rekey_database", { key });
+}
+
+export async function decryptDatabase(): Promise<void> {
+    await runWithDatabaseClosed("decrypt_database");
+}
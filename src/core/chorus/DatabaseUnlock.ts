@@ -0,0 +1,77 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+
+export type DatabaseEncryptionStatus = {
+    encrypted: boolean;
+    locked: boolean;
+    key_source: "keyring" | "passphrase" | null;
+};
+
+export async function getDatabaseEncryptionStatus(): Promise<DatabaseEncryptionStatus> {
+    return await invoke<DatabaseEncryptionStatus>(
+        "database_encryption_status",
+    );
+}
+
+/**
+ * Resolves once the chat database can be opened. If it's protected by a
+ * passphrase, the main window asks for it and other windows wait until it's
+ * been entered.
+ */
+export async function waitForDatabaseUnlock(): Promise<void> {
+    let onUnlocked = () => {};
+    const unlocked = new Promise<void>((resolve) => (onUnlocked = resolve));
+    const unlisten = await listen("database-unlocked", () => onUnlocked());
+    try {
+        const status = await getDatabaseEncryptionStatus();
+        if (!status.locked) return;
+        if (getCurrentWindow().label === "main") {
+            await promptForPassphrase();
+        } else {
+            await unlocked;
+        }
+    } finally {
+        unlisten();
+    }
+}
+
+// This runs before React mounts (DB.ts blocks on it), so the prompt is plain DOM
+function promptForPassphrase(): Promise<void> {
+    return new Promise((resolve) => {
+        const form = document.createElement("form");
+        form.style.cssText =
+            "position: fixed; inset: 0; display: flex; flex-direction: column; align-items: center; justify-content: center; gap: 8px; font-family: system-ui, sans-serif; font-size: 14px;";
+
+        const label = document.createElement("label");
+        label.textContent = "Enter the passphrase for your chats";
+        const input = document.createElement("input");
+        input.type = "password";
+        input.style.cssText =
+            "padding: 4px 8px; border: 1px solid #d4d4d8; border-radius: 6px;";
+        const button = document.createElement("button");
+        button.type = "submit";
+        button.textContent = "Unlock";
+        const error = document.createElement("p");
+        error.style.color = "#dc2626";
+
+        form.append(label, input, button, error);
+        form.addEventListener("submit", (event) => {
+            event.preventDefault();
+            button.disabled = true;
+            invoke("unlock_database", { passphrase: input.value })
+                .then(() => {
+                    form.remove();
+                    resolve();
+                })
+                .catch((e: unknown) => {
+                    error.textContent = String(e);
+                    button.disabled = false;
+                    input.select();
+                });
+        });
+
+        document.body.append(form);
+        input.focus();
+    });
+}
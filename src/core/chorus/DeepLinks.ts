@@ -0,0 +1,30 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * chorus:// links are parsed and checked by the Rust backend
+ * (src-tauri/src/deep_link.rs), which sends each one to the window that
+ * should act on it as a DEEP_LINK_EVENT.
+ */
+export const DEEP_LINK_EVENT = "deep-link";
+
+export type DeepLink =
+    | {
+          action: "new";
+          prompt: string | null;
+          modelConfigIds: string[];
+          projectId: string | null;
+      }
+    | { action: "chat"; chatId: string }
+    | { action: "quick"; prompt: string | null }
+    | { action: "import"; file: string; source: "openai" | "anthropic" }
+    | { action: "slack"; accessToken: string; teamId: string }
+    | { action: "github"; accessToken: string }
+    | { action: "rejected"; reason: string };
+
+/**
+ * Links that arrived before this window was listening, such as the one the
+ * app was launched with. After this, links come as events.
+ */
+export async function takePendingDeepLinks(): Promise<DeepLink[]> {
+    return await invoke<DeepLink[]>("take_pending_deep_links");
+}
@@ -0,0 +1,68 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * Actions with a global shortcut, which works while other apps have focus.
+ * They run in the backend (src-tauri/src/global_actions.rs).
+ */
+export type GlobalAction =
+    | "quickChat"
+    | "screenshot"
+    | "clipboard"
+    | "newChat"
+    | "selection";
+
+/** Every action but quick chat, whose shortcut is in `settings.quickChat` */
+export type ExtraGlobalAction = Exclude<GlobalAction, "quickChat">;
+
+export const EXTRA_GLOBAL_ACTIONS: {
+    action: ExtraGlobalAction;
+    label: string;
+    description: string;
+    /** Only X11 and Wayland have a selection other apps can read */
+    linuxOnly?: boolean;
+}[] = [
+    {
+        action: "screenshot",
+        label: "Screenshot and ask",
+        description: "Start an ambient chat with a screenshot attached.",
+    },
+    {
+        action: "clipboard",
+        label: "Ask about the clipboard",
+        description: "Start an ambient chat with the clipboard's text.",
+    },
+    {
+        action: "newChat",
+        label: "New chat",
+        description: "Open the main window to a new chat.",
+    },
+    {
+        action: "selection",
+        label: "Ask about the selection",
+        description: "Start an ambient chat quoting the selected text.",
+        linuxOnly: true,
+    },
+];
+
+/** A new quick chat's contents, sent with the new_quick_chat event */
+export type QuickChatDraft = {
+    text: string | null;
+    /** A base64 PNG from capture_whole_screen */
+    screenshot: string | null;
+};
+
+export const NEW_QUICK_CHAT_EVENT = "new_quick_chat";
+export const NEW_CHAT_EVENT = "global_new_chat";
+
+/**
+ * Registers a new global shortcut for `action` in place of its current one.
+ * An empty shortcut removes the action's, except quick chat's. Rejects with
+ * the reason if the shortcut is invalid, clashes with a menu item or another
+ * action, or can't be registered, leaving the current one in place.
+ */
+export async function setGlobalShortcut(
+    action: GlobalAction,
+    shortcut: string,
+): Promise<void> {
+    await invoke("set_global_shortcut", { action, shortcut });
+}
@@ -0,0 +1,27 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * The local API is an HTTP server on 127.0.0.1 for scripting Chorus, run by
+ * the Rust backend (src-tauri/src/api.rs). It's turned on with
+ * `settings.localApi` and every request needs the bearer token.
+ */
+export const LOCAL_API_STATUS_EVENT = "local-api-status";
+export const DEFAULT_LOCAL_API_PORT = 6070;
+
+export type LocalApiStatus = {
+    enabled: boolean;
+    running: boolean;
+    port: number;
+    url: string | null;
+    token: string;
+    error: string | null;
+};
+
+export async function getLocalApiStatus(): Promise<LocalApiStatus> {
+    return await invoke<LocalApiStatus>("local_api_status");
+}
+
+/** Replaces the token. Scripts using the old one stop working. */
+export async function regenerateLocalApiToken(): Promise<string> {
+    return await invoke<string>("regenerate_local_api_token");
+}
@@ -1,15 +1,19 @@
 /**
- * This file copied out of @modelcontextprotocol/sdk
- * and modified to work with Tauri.
+ * Client transport for MCP servers. The Rust backend (src-tauri/src/mcp.rs)
+ * owns the connections: it runs stdio servers as processes and talks to
+ * remote ones over Streamable HTTP or SSE, restarts or reconnects them when
+ * they fail, health checks them and stops them when the app quits. This
+ * transport only relays JSON-RPC messages to and from the backend.
  */
 
-import {
-    ReadBuffer,
-    serializeMessage,
-} from "@modelcontextprotocol/sdk/shared/stdio.js";
 import { Transport } from "@modelcontextprotocol/sdk/shared/transport.js";
-import { JSONRPCMessage } from "@modelcontextprotocol/sdk/types.js";
-import { Child, Command } from "@tauri-apps/plugin-shell";
+import {
+    JSONRPCMessage,
+    JSONRPCMessageSchema,
+} from "@modelcontextprotocol/sdk/types.js";
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+import { getCurrentWindow } from "@tauri-apps/api/window";
 
 export type StdioServerParameters =
     | {
@@ -25,179 +29,146 @@ export type StdioServerParameters =
           env?: Record<string, string>;
       };
 
-/**
- * Environment variables to inherit by default, if an environment is not explicitly given.
- */
-export const DEFAULT_INHERITED_ENV_VARS =
-    process.platform === "win32"
-        ? [
-              "APPDATA",
-              "HOMEDRIVE",
-              "HOMEPATH",
-              "LOCALAPPDATA",
-              "PATH",
-              "PROCESSOR_ARCHITECTURE",
-              "SYSTEMDRIVE",
-              "SYSTEMROOT",
-              "TEMP",
-              "USERNAME",
-              "USERPROFILE",
-          ]
-        : /* list inspired by the default env inheritance of sudo */
-          ["HOME", "LOGNAME", "PATH", "SHELL", "TERM", "USER"];
-
-/**
- * Returns a default environment object including only environment variables deemed safe to inherit.
- */
-export function getDefaultEnvironment(): Record<string, string> {
-    const env: Record<string, string> = {};
-
-    for (const key of DEFAULT_INHERITED_ENV_VARS) {
-        const value = process.env[key];
-        if (value === undefined) {
-            continue;
-        }
-
-        if (value.startsWith("()")) {
-            // Skip functions, which are a security risk.
-            continue;
-        }
-
-        env[key] = value;
-    }
-
-    return env;
+export type HttpServerParameters = {
+    type: "streamable_http" | "sse";
+    url: string;
+    headers?: Record<string, string>;
+    bearerToken?: string; // may be a vault reference
+};
+
+export type MCPServerParameters = StdioServerParameters | HttpServerParameters;
+
+export type MCPServerStatus =
+    | { status: "stopped" }
+    | { status: "idle" }
+    | { status: "running" }
+    | { status: "restarting"; attempt: number; delay_ms: number }
+    | { status: "failed"; error: string };
+
+type MCPServerEvent<T> = {
+    window: string;
+    name: string;
+} & T;
+
+export type MCPServerInfo = {
+    window: string | null;
+    name: string;
+    command: string;
+    status: MCPServerStatus;
+    pid: number | null;
+    restarts: number;
+};
+
+/** Every MCP server the backend knows about, running or not. */
+export async function listMCPServers(): Promise<MCPServerInfo[]> {
+    return await invoke<MCPServerInfo[]>("mcp_list");
 }
 
-/**
- * Client transport for stdio: this will connect to a server by spawning a process and communicating with it over stdin/stdout.
- *
- * This transport is only available in Node.js environments.
- */
-export class StdioClientTransportChorus implements Transport {
-    private _process?: Child; // changed from ChildProcess to Child
-    private _command?: Command<string>;
-    private _abortController: AbortController = new AbortController();
-    private _readBuffer: ReadBuffer = new ReadBuffer();
-    private _serverParams: StdioServerParameters;
+export class MCPClientTransportChorus implements Transport {
+    private _started = false;
+    private _unlisteners: UnlistenFn[] = [];
 
     onclose?: () => void;
     onerror?: (error: Error) => void;
     onmessage?: (message: JSONRPCMessage) => void;
 
-    constructor(server: StdioServerParameters) {
-        this._serverParams = server;
-    }
+    /**
+     * @param name Identifies the server within this window
+     * @param serverParams How the backend should launch or reach it
+     */
+    constructor(
+        private readonly name: string,
+        private readonly serverParams: MCPServerParameters,
+    ) {}
 
     /**
-     * Starts the server process and prepares to communicate with it.
+     * Registers the server with the backend. The process is started, or the
+     * connection opened, by the first message sent to it.
      */
     async start(): Promise<void> {
-        if (this._process) {
+        if (this._started) {
             throw new Error(
-                "StdioClientTransport already started! If using Client class, note that connect() calls start() automatically.",
+                "MCPClientTransport already started! If using Client class, note that connect() calls start() automatically.",
             );
         }
-
-        this._command =
-            this._serverParams.type === "sidecar"
-                ? Command.sidecar(
-                      this._serverParams.sidecarBinary,
-                      this._serverParams.args ?? [],
-                      {
-                          env:
-                              this._serverParams.env ?? getDefaultEnvironment(),
-                      },
-                  )
-                : Command.sidecar(
-                      "binaries/run-mcp",
-                      [
-                          this._serverParams.command,
-                          ...(this._serverParams.args ?? []),
-                      ],
-                      {
-                          env:
-                              this._serverParams.env ?? getDefaultEnvironment(),
-                      },
-                  );
-
-        // this._process = spawn(
-        //     this._serverParams.command,
-        //     this._serverParams.args ?? [],
-        //     {
-        //         env: this._serverParams.env ?? getDefaultEnvironment(),
-        //         stdio: [
-        //             "pipe",
-        //             "pipe",
-        //             this._serverParams.stderr ?? "inherit",
-        //         ],
-        //         shell: false,
-        //         signal: this._abortController.signal,
-        //         windowsHide: process.platform === "win32" && isElectron(),
-        //         cwd: this._serverParams.cwd,
-        //     },
-        // );
-
-        this._command.on("error", (error) => {
-            const err = new Error(error);
-            this.onerror?.(err);
-        });
-
-        this._command.on("close", (_code) => {
-            this._process = undefined;
-            this.onclose?.();
-        });
-
-        // this._process.stdin?.on("error", (error) => {
-        //     this.onerror?.(error);
-        // });
-
-        this._command.stdout?.on("data", (chunk) => {
-            console.log("Received chunk:", chunk);
-            this._readBuffer.append(Buffer.from(chunk));
-            this.processReadBuffer();
-        });
-
-        this._command.stderr?.on("data", (chunk) => {
-            console.log("Received stderr chunk:", chunk, this.onerror);
-            this.onerror?.(new Error(chunk));
-        });
-
-        // this._command.stdout?.on("error", (error) => {
-        //     this.onerror?.(error);
-        // });
-
-        this._process = await this._command.spawn();
-
-        console.log("[MCPStdioTauri] Started server");
+        this._started = true;
+
+        const label = getCurrentWindow().label;
+        const isOurs = (event: { window: string; name: string }) =>
+            event.window === label && event.name === this.name;
+
+        this._unlisteners.push(
+            await listen<MCPServerEvent<{ status: MCPServerStatus }>>(
+                "mcp-server-status",
+                ({ payload }) => {
+                    if (!isOurs(payload)) return;
+                    const status = payload.status;
+                    if (status.status === "failed") {
+                        this.onerror?.(new Error(status.error));
+                    }
+                    if (
+                        status.status === "failed" ||
+                        status.status === "stopped"
+                    ) {
+                        this.onclose?.();
+                    }
+                },
+            ),
+            await listen<MCPServerEvent<{ line: string }>>(
+                "mcp-server-log",
+                ({ payload }) => {
+                    if (!isOurs(payload)) return;
+                    this.onerror?.(new Error(payload.line));
+                },
+            ),
+        );
+
+        const onMessage = new Channel<unknown>();
+        onMessage.onmessage = (message) => {
+            const parsed = JSONRPCMessageSchema.safeParse(message);
+            if (parsed.success) {
+                this.onmessage?.(parsed.data);
+            } else {
+                this.onerror?.(parsed.error);
+            }
+        };
+
+        try {
+            await invoke("mcp_start", {
+                name: this.name,
+                params: this.serverParams,
+                onMessage,
+            });
+        } catch (error) {
+            this.unlisten();
+            throw new Error(String(error));
+        }
     }
 
-    private processReadBuffer() {
-        while (true) {
-            try {
-                const message = this._readBuffer.readMessage();
-                if (message === null) {
-                    break;
-                }
-                console.log("Received message:", message);
-                this.onmessage?.(message);
-            } catch (error) {
-                this.onerror?.(error as Error);
-            }
+    async send(message: JSONRPCMessage): Promise<void> {
+        try {
+            await invoke("mcp_send", { name: this.name, message });
+        } catch (error) {
+            throw new Error(String(error));
         }
     }
 
     async close(): Promise<void> {
-        this._abortController.abort();
-        this._process = undefined;
-        this._command = undefined;
-        this._readBuffer.clear();
-        return Promise.resolve();
+        this.unlisten();
+        if (!this._started) return;
+        this._started = false;
+        try {
+            await invoke("mcp_stop", { name: this.name });
+        } catch (error) {
+            console.error("[MCPStdioTauri] Error stopping server", error);
+        }
+        this.onclose?.();
     }
 
-    async send(message: JSONRPCMessage): Promise<void> {
-        console.log("Sending message:", message);
-        const json = serializeMessage(message);
-        await this._process?.write(json);
+    private unlisten() {
+        for (const unlisten of this._unlisteners) {
+            unlisten();
+        }
+        this._unlisteners = [];
     }
 }
@@ -1,6 +1,7 @@
 import { fetch } from "@tauri-apps/plugin-http";
 import { writeFile, mkdir } from "@tauri-apps/plugin-fs";
-import { join, appDataDir } from "@tauri-apps/api/path";
+import { join } from "@tauri-apps/api/path";
+import { config } from "@core/config";
 import { convertFileSrc } from "@tauri-apps/api/core";
 import { ApiKeys } from "./Models";
 
@@ -82,8 +83,10 @@ export class MediaTools {
             byteArray[i] = byteString.charCodeAt(i);
         }
 
-        const appCoreDir = await appDataDir();
-        const imagesDir = await join(appCoreDir, GENERATED_IMAGES_SUBDIR);
+        const imagesDir = await join(
+            config.profile.dataDir,
+            GENERATED_IMAGES_SUBDIR,
+        );
         await mkdir(imagesDir, { recursive: true });
 
         // Get timestamp and format it
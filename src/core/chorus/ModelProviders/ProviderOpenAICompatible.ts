@@ -13,9 +13,9 @@ export class ProviderOpenAICompatible implements IProvider {
         onError,
         tools,
     }: StreamResponseParams): Promise<void> {
-        const settings = await SettingsManager.getInstance().get();
-        const baseURL = settings.apiKeys?.["openai-compatible-url"];
-        const apiKey = settings.apiKeys?.["openai-compatible"];
+        const apiKeys = await SettingsManager.getInstance().getApiKeys();
+        const baseURL = apiKeys["openai-compatible-url"];
+        const apiKey = apiKeys["openai-compatible"];
 
         if (!baseURL) {
             throw new Error("Please configure OpenAI-Compatible endpoint URL in Settings.");
@@ -0,0 +1,55 @@
+import { invoke } from "@tauri-apps/api/core";
+import { runWithDatabaseClosed } from "./DatabaseEncryption";
+
+/**
+ * Profiles keep separate chats, attachments and settings. The backend
+ * (src-tauri/src/profiles.rs) lists them in profiles.json and sends
+ * PROFILES_CHANGED_EVENT with the new ProfileList whenever it changes.
+ */
+export const PROFILES_CHANGED_EVENT = "profiles-changed";
+
+/** Keeps the files from before there were profiles. It can't be deleted. */
+export const DEFAULT_PROFILE_ID = "default";
+
+export type Profile = {
+    id: string;
+    name: string;
+};
+
+export type ProfileList = {
+    active: string;
+    profiles: Profile[];
+};
+
+export type ActiveProfile = Profile & {
+    /** Absolute, like the other paths */
+    database: string;
+    settingsStore: string;
+    /** Where uploads and generated images go */
+    dataDir: string;
+};
+
+export async function listProfiles(): Promise<ProfileList> {
+    return await invoke<ProfileList>("list_profiles");
+}
+
+export async function createProfile(name: string): Promise<Profile> {
+    return await invoke<Profile>("create_profile", { name });
+}
+
+export async function renameProfile(
+    id: string,
+    name: string,
+): Promise<Profile> {
+    return await invoke<Profile>("rename_profile", { id, name });
+}
+
+/** Deletes a profile's chats, attachments and settings for good. */
+export async function deleteProfile(id: string): Promise<void> {
+    await invoke("delete_profile", { id });
+}
+
+/** Every window reloads with the new profile once this succeeds. */
+export async function switchProfile(id: string): Promise<void> {
+    await runWithDatabaseClosed("switch_profile", { id });
+}
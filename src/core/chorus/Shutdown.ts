@@ -0,0 +1,53 @@
+import { invoke } from "@tauri-apps/api/core";
+import { platform } from "@tauri-apps/plugin-os";
+import { db } from "./DB";
+import { stopAllStreamingMessages } from "./api/MessageAPI";
+
+/** What closing the main window does, `settings.closeBehavior` */
+export type CloseBehavior = "tray" | "minimize" | "quit";
+
+export const CLOSE_BEHAVIORS: {
+    behavior: CloseBehavior;
+    label: string;
+}[] = [
+    {
+        behavior: "tray",
+        // macOS hides the whole app, which the Dock brings back
+        label:
+            platform() === "macos"
+                ? "Hide Chorus"
+                : "Keep running in the tray",
+    },
+    {
+        behavior: "minimize",
+        label: "Minimize the window",
+    },
+    {
+        behavior: "quit",
+        label: "Quit Chorus",
+    },
+];
+
+/**
+ * Used when the setting is missing. Not every Linux desktop shows tray
+ * icons, so closing quits there. Keep in sync with src-tauri/src/shutdown.rs.
+ */
+export function defaultCloseBehavior(): CloseBehavior {
+    return platform() === "linux" ? "quit" : "tray";
+}
+
+/** Sent by the backend (src-tauri/src/shutdown.rs) before it quits */
+export const QUIT_REQUESTED_EVENT = "quit_requested";
+
+/**
+ * Marks streaming messages as stopped and closes the database connection,
+ * then tells the backend it can quit. It waits a few seconds at most.
+ */
+export async function prepareToQuit(): Promise<void> {
+    try {
+        await stopAllStreamingMessages();
+        await db.close();
+    } finally {
+        await invoke("ready_to_quit");
+    }
+}
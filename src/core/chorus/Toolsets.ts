@@ -1,8 +1,8 @@
 import { Client } from "@modelcontextprotocol/sdk/client/index.js";
 import { Transport } from "@modelcontextprotocol/sdk/shared/transport.js";
 import {
-    StdioClientTransportChorus,
-    StdioServerParameters,
+    MCPClientTransportChorus,
+    MCPServerParameters,
 } from "@core/chorus/MCPStdioTauri";
 import {
     GlobeIcon,
@@ -136,11 +136,18 @@ export type ToolsetConfig = {
 
 export type ToolPermissionType = "always_allow" | "always_deny" | "ask";
 
+export type CustomToolsetTransport = "stdio" | "streamable_http" | "sse";
+
 export type CustomToolsetConfig = {
     name: string;
+    transport?: CustomToolsetTransport; // defaults to stdio
     command: string;
     args: string;
     env: string;
+    // remote servers only
+    url?: string;
+    headers?: string; // JSON object, like env
+    bearerToken?: string;
     defaultPermission?: ToolPermissionType;
 };
 
@@ -267,6 +274,8 @@ export abstract class MCPServer {
     private _status: ToolsetStatus = { status: "stopped" };
     private _logs: string = ""; // accumulated logs
     private activeConfig?: Record<string, string> = undefined;
+    /** Identifies the server to the backend. Set by Toolset.addServer. */
+    serverName = "";
 
     constructor() {
         this.mcp = new Client({ name: "mcp-client-cli", version: "1.0.0" });
@@ -311,6 +320,7 @@ export abstract class MCPServer {
 
         try {
             console.log("starting mcp server");
+            // Vault references in env are resolved by the backend
             const serverParams = this.getExecutionParameters(config);
 
             this.mcp.onerror = (error: Error) => {
@@ -325,7 +335,10 @@ export abstract class MCPServer {
                 };
             };
 
-            const transport = new StdioClientTransportChorus(serverParams);
+            const transport = new MCPClientTransportChorus(
+                this.serverName,
+                serverParams,
+            );
             this.transport = transport;
             await this.mcp.connect(this.transport);
 
@@ -416,7 +429,7 @@ export abstract class MCPServer {
      */
     protected abstract getExecutionParameters(
         config: Record<string, string>,
-    ): StdioServerParameters;
+    ): MCPServerParameters;
 }
 
 /**
@@ -505,6 +518,10 @@ export class Toolset {
         renameMap?: Record<string, string>,
         descriptionMap?: Record<string, string>,
     ): void {
+        server.serverName =
+            this.servers.length === 0
+                ? this.name
+                : `${this.name}-${this.servers.length}`;
         this.servers.push(server);
 
         // Store the registration options for this server
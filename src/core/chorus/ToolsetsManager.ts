@@ -245,9 +245,13 @@ export class ToolsetsManager {
                     this._customToolsets.push(toolset);
                 }
                 const config = {
+                    transport: customConfig.transport ?? "stdio",
                     command: customConfig.command,
                     args: customConfig.args,
                     env: customConfig.env,
+                    url: customConfig.url ?? "",
+                    headers: customConfig.headers ?? "{}",
+                    bearerToken: customConfig.bearerToken ?? "",
                 };
                 if (toolsetsConfig[customConfig.name]?.enabled === "true") {
                     console.log("starting custom toolset", config);
@@ -0,0 +1,104 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * Secrets (API keys, MCP tokens) live in an encrypted Stronghold vault managed
+ * by the Rust backend. Settings and the database only hold references like
+ * `vault:api-key/openai`, which are resolved right before use.
+ */
+export const VAULT_REF_PREFIX = "vault:";
+
+export type VaultStatus = {
+    unlocked: boolean;
+    uses_password: boolean;
+};
+
+export function isVaultRef(value: string): boolean {
+    return value.startsWith(VAULT_REF_PREFIX);
+}
+
+function definedValues(
+    values: Record<string, string | undefined>,
+): Record<string, string> {
+    const defined: Record<string, string> = {};
+    for (const [key, value] of Object.entries(values)) {
+        if (value !== undefined) defined[key] = value;
+    }
+    return defined;
+}
+
+export async function getVaultStatus(): Promise<VaultStatus> {
+    return await invoke<VaultStatus>("vault_status");
+}
+
+export async function unlockVault(password: string): Promise<void> {
+    await invoke("unlock_vault", { password });
+}
+
+/**
+ * Protects the vault with a password. Pass undefined to go back to a key
+ * stored on this machine.
+ */
+export async function setVaultPassword(
+    password: string | undefined,
+): Promise<void> {
+    await invoke("set_vault_password", { password: password ?? null });
+}
+
+/** Stores a secret and returns the reference to save in its place. */
+export async function putSecret(name: string, value: string): Promise<string> {
+    return await invoke<string>("put_secret", { name, value });
+}
+
+export async function deleteSecret(name: string): Promise<boolean> {
+    return await invoke<boolean>("delete_secret", { name });
+}
+
+export async function listSecrets(): Promise<string[]> {
+    return await invoke<string[]>("list_secrets");
+}
+
+/** Replaces vault references with their secrets. Other values are kept. */
+export async function resolveSecretRefs(
+    values: Record<string, string | undefined>,
+): Promise<Record<string, string>> {
+    const defined = definedValues(values);
+    if (!Object.values(defined).some(isVaultRef)) {
+        return defined;
+    }
+    return await invoke<Record<string, string>>("resolve_secret_refs", {
+        values: defined,
+    });
+}
+
+/**
+ * Moves plaintext values into the vault under `prefix/<key>` and returns the
+ * values with references in their place. Empty values delete the secret.
+ */
+export async function storeSecrets(
+    prefix: string,
+    values: Record<string, string | undefined>,
+    { skip }: { skip?: (key: string) => boolean } = {},
+): Promise<Record<string, string>> {
+    const stored: Record<string, string> = {};
+    for (const [key, value] of Object.entries(definedValues(values))) {
+        if (skip?.(key) || isVaultRef(value)) {
+            stored[key] = value;
+        } else if (value === "") {
+            await deleteSecret(`${prefix}/${key}`);
+            stored[key] = value;
+        } else {
+            stored[key] = await putSecret(`${prefix}/${key}`, value);
+        }
+    }
+    return stored;
+}
+
+/** Deletes every secret stored under `prefix/`. */
+export async function deleteSecretsWithPrefix(prefix: string): Promise<void> {
+    const names = await listSecrets();
+    await Promise.all(
+        names
+            .filter((name) => name.startsWith(`${prefix}/`))
+            .map((name) => deleteSecret(name)),
+    );
+}
@@ -197,7 +197,19 @@ export function useSetVisionModeEnabled() {
     });
 }
 
+/**
+ * The API keys to send to providers. Throws if one is still in a locked vault.
+ */
 export async function getApiKeys() {
+    const settingsManager = SettingsManager.getInstance();
+    return (await settingsManager.getApiKeys()) as Models.ApiKeys;
+}
+
+/**
+ * The API keys as saved, for checking which providers have one. A key that's
+ * still in a locked vault is its `vault:` reference here, so it counts as set.
+ */
+export async function getSavedApiKeys() {
     const settingsManager = SettingsManager.getInstance();
     const settings = await settingsManager.get();
     return (settings.apiKeys || {}) as Models.ApiKeys;
@@ -250,12 +262,13 @@ export function useSetCustomBaseUrl() {
 }
 
 /**
- * Hook to access the user's API keys
+ * Hook to check which API keys the user has set. Works while the vault is
+ * locked, so it's not for sending the keys anywhere.
  */
 export function useApiKeys() {
     return useQuery({
         queryKey: ["apiKeys"],
-        queryFn: getApiKeys,
+        queryFn: getSavedApiKeys,
     });
 }
 
@@ -3,7 +3,7 @@ import { produce } from "immer";
 import { useNavigate } from "react-router-dom";
 import { db } from "../DB";
 
-const chatKeys = {
+export const chatKeys = {
     all: () => ["chats"] as const,
     allDetails: () => [...chatKeys.all(), "detail"] as const,
 };
@@ -317,8 +317,9 @@ export function useConvertQuickChatToRegularChat() {
     return useMutation({
         mutationKey: ["convertQuickChatToRegularChat"] as const,
         mutationFn: async ({ chatId }: { chatId: string }) => {
+            // The local API opens regular chats this way too, leave them be
             await db.execute(
-                "UPDATE chats SET quick_chat = 0, project_id = 'default' WHERE id = $1",
+                "UPDATE chats SET quick_chat = 0, project_id = 'default' WHERE id = $1 AND quick_chat = 1",
                 [chatId],
             );
         },
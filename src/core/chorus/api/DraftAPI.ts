@@ -11,8 +11,9 @@ import {
 
 export const draftKeys = {
     messageDraft: (chatId: string) => ["messageDraft", chatId] as const,
+    allMessageDraftAttachments: () => ["messageDraftAttachments"] as const,
     messageDraftAttachments: (chatId: string) =>
-        ["messageDraftAttachments", chatId] as const,
+        [...draftKeys.allMessageDraftAttachments(), chatId] as const,
 };
 
 async function fetchDraftAttachments(chatId: string): Promise<Attachment[]> {
@@ -29,7 +29,7 @@ export const projectQueries = {
     }),
 };
 
-const projectContextQueryKeys = {
+export const projectContextQueryKeys = {
     all: () => ["projectContext"] as const,
 };
 
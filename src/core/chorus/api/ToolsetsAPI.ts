@@ -1,9 +1,20 @@
 import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
 import { db } from "../DB";
-import { CustomToolsetConfig, ToolPermissionType } from "../Toolsets";
+import {
+    CustomToolsetConfig,
+    CustomToolsetTransport,
+    getEnvFromJSON,
+    ToolPermissionType,
+} from "../Toolsets";
+import {
+    deleteSecret,
+    deleteSecretsWithPrefix,
+    isVaultRef,
+    putSecret,
+    storeSecrets,
+} from "../Vault";
 import { ToolsetsManager } from "../ToolsetsManager";
-import { homeDir, join } from "@tauri-apps/api/path";
-import { exists, readTextFile } from "@tauri-apps/plugin-fs";
+import { invoke } from "@tauri-apps/api/core";
 
 export const toolsetsKeys = {
     // toolset configs
@@ -40,21 +51,35 @@ async function fetchToolsetsConfig() {
 
 export type CustomToolsetConfigDBRow = {
     name: string;
+    transport: string;
     command: string;
     args: string;
     env: string;
+    url: string | null;
+    headers: string | null;
+    bearer_token: string | null;
     default_permission?: string;
     updated_at: string;
 };
 
+function readTransport(transport: string): CustomToolsetTransport {
+    return transport === "streamable_http" || transport === "sse"
+        ? transport
+        : "stdio";
+}
+
 export function readCustomToolset(
     row: CustomToolsetConfigDBRow,
 ): CustomToolsetConfig {
     return {
         name: row.name,
+        transport: readTransport(row.transport),
         command: row.command,
         args: row.args,
         env: row.env,
+        url: row.url ?? undefined,
+        headers: row.headers ?? undefined,
+        bearerToken: row.bearer_token ?? undefined,
         defaultPermission:
             (row.default_permission as ToolPermissionType) ?? "ask",
     };
@@ -65,7 +90,7 @@ export async function fetchCustomToolsetConfigs(): Promise<
 > {
     return (
         await db.select<CustomToolsetConfigDBRow[]>(
-            "SELECT name, command, args, env, default_permission, updated_at FROM custom_toolsets ORDER BY name",
+            "SELECT name, transport, command, args, env, url, headers, bearer_token, default_permission, updated_at FROM custom_toolsets ORDER BY name",
         )
     ).map(readCustomToolset);
 }
@@ -113,13 +138,48 @@ export function useUpdateCustomToolsetConfig() {
     return useMutation({
         mutationKey: ["updateCustomToolsetConfig"] as const,
         mutationFn: async ({ toolset }: { toolset: CustomToolsetConfig }) => {
+            // keep env values in the vault; the table only stores references
+            const env = getEnvFromJSON(toolset.env);
+            const storedEnv =
+                "_type" in env
+                    ? toolset.env
+                    : JSON.stringify(
+                          await storeSecrets(`toolset/${toolset.name}`, env),
+                      );
+            // headers often carry API keys, so they're kept in the vault too
+            const headers = getEnvFromJSON(toolset.headers);
+            const storedHeaders =
+                toolset.headers === undefined || "_type" in headers
+                    ? (toolset.headers ?? null)
+                    : JSON.stringify(
+                          await storeSecrets(
+                              `toolset/${toolset.name}/headers`,
+                              headers,
+                          ),
+                      );
+            const bearerTokenName = `toolset/${toolset.name}/bearer-token`;
+            let storedBearerToken: string | null = null;
+            if (toolset.bearerToken && isVaultRef(toolset.bearerToken)) {
+                storedBearerToken = toolset.bearerToken;
+            } else if (toolset.bearerToken) {
+                storedBearerToken = await putSecret(
+                    bearerTokenName,
+                    toolset.bearerToken,
+                );
+            } else {
+                await deleteSecret(bearerTokenName);
+            }
             await db.execute(
-                "INSERT OR REPLACE INTO custom_toolsets (name, command, args, env, default_permission, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+                "INSERT OR REPLACE INTO custom_toolsets (name, transport, command, args, env, url, headers, bearer_token, default_permission, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                 [
                     toolset.name,
+                    toolset.transport ?? "stdio",
                     toolset.command,
                     toolset.args,
-                    toolset.env,
+                    storedEnv,
+                    toolset.url ?? null,
+                    storedHeaders,
+                    storedBearerToken,
                     toolset.defaultPermission ?? "ask",
                     new Date().toISOString(),
                 ],
@@ -160,6 +220,7 @@ export function useDeleteCustomToolsetConfig() {
                 "DELETE FROM toolsets_config WHERE toolset_name = ?",
                 [name],
             );
+            await deleteSecretsWithPrefix(`toolset/${name}`);
         },
         onSuccess: async () => {
             // Invalidate both custom toolsets and general toolsets queries
@@ -268,93 +329,130 @@ export function useUpdateToolsetsConfig() {
     });
 }
 
-type ClaudeDesktopMCPServerConfig = {
-    command: string;
-    args?: string[];
-    env?: Record<string, string>;
+/** Clients whose MCP config files can be imported and exported */
+export const MCP_CONFIG_FORMAT_IDS = [
+    "claude_desktop",
+    "cursor",
+    "vs_code",
+    "windsurf",
+] as const;
+
+export type MCPConfigFormat = (typeof MCP_CONFIG_FORMAT_IDS)[number];
+
+export const MCP_CONFIG_FORMATS: Record<
+    MCPConfigFormat,
+    { label: string; fileName: string }
+> = {
+    claude_desktop: {
+        label: "Claude Desktop",
+        fileName: "claude_desktop_config.json",
+    },
+    cursor: { label: "Cursor", fileName: "mcp.json" },
+    vs_code: { label: "VS Code", fileName: "mcp.json" },
+    windsurf: { label: "Windsurf", fileName: "mcp_config.json" },
 };
 
-export function useImportFromClaudeDesktop() {
-    const queryClient = useQueryClient();
-    const updateCustomToolsetConfig = useUpdateCustomToolsetConfig();
+export type MCPConfigLocation = {
+    format: MCPConfigFormat;
+    path: string;
+};
 
-    return useMutation({
-        mutationKey: ["importFromClaudeDesktop"] as const,
-        mutationFn: async () => {
-            // Determine the path to Claude Desktop MCP config based on platform
-            // Note: this will only work on macOS
-            const configPath = await join(
-                await homeDir(),
-                "Library",
-                "Application Support",
-                "claude",
-                "claude_desktop_config.json",
-            );
+export type MCPImportCandidate = {
+    source_name: string;
+    name: string;
+    transport: CustomToolsetTransport;
+    command: string;
+    args: string;
+    url: string | null;
+    env_keys: string[];
+    header_names: string[];
+    conflict: boolean;
+    suggested_name: string;
+    warnings: string[];
+};
 
-            // Check if the config file exists
-            const fileExists = await exists(configPath);
-            if (!fileExists) {
-                throw new Error(
-                    `Claude Desktop configuration file not found at ${configPath}`,
-                );
-            }
+export type MCPImportPreview = {
+    path: string;
+    candidates: MCPImportCandidate[];
+    skipped: string[];
+};
 
-            // Read and parse the config file
-            const configText = await readTextFile(configPath);
-            const config = JSON.parse(configText) as {
-                mcpServers?: Record<string, ClaudeDesktopMCPServerConfig>;
-            };
+export type MCPImportSelection = {
+    sourceName: string;
+    name: string;
+    replace: boolean;
+};
 
-            // Check if the config contains MCP server configurations
-            if (!config.mcpServers || typeof config.mcpServers !== "object") {
-                throw new Error(
-                    `No MCP server configurations found in Claude Desktop config at ${configPath}`,
-                );
-            }
+/** Config files of other MCP clients found on this machine */
+export function useMCPConfigLocations() {
+    return useQuery({
+        queryKey: ["mcpConfigLocations"] as const,
+        queryFn: () => invoke<MCPConfigLocation[]>("mcp_config_locations"),
+    });
+}
 
-            const mcpServerConfigs = config.mcpServers;
-
-            // Process each MCP server configuration
-            const importedTools = [];
-            for (const [serverName, serverConfig] of Object.entries(
-                mcpServerConfigs,
-            )) {
-                if (serverConfig.command) {
-                    // Create a custom toolset config from this MCP server
-                    const customToolset = {
-                        name: serverName,
-                        command: serverConfig.command,
-                        args: serverConfig.args
-                            ? serverConfig.args
-                                  .map((arg) => `"${arg}"`)
-                                  .join(" ")
-                            : "",
-                        env: serverConfig.env
-                            ? JSON.stringify(serverConfig.env)
-                            : "{}",
-                    };
-
-                    // Add to database using the existing mutation
-                    await updateCustomToolsetConfig.mutateAsync({
-                        toolset: customToolset,
-                    });
-                    importedTools.push(customToolset);
-                }
-            }
+export async function previewMCPImport(
+    path: string,
+): Promise<MCPImportPreview> {
+    return await invoke<MCPImportPreview>("preview_mcp_import", { path });
+}
 
-            return { imported: importedTools.length, tools: importedTools };
+export function useImportMCPServers() {
+    const queryClient = useQueryClient();
+    const getToolsetsConfig = useGetToolsetsConfig();
+    const getCustomToolsetConfigs = useGetCustomToolsetConfigs();
+
+    return useMutation({
+        mutationKey: ["importMCPServers"] as const,
+        mutationFn: async ({
+            path,
+            selections,
+        }: {
+            path: string;
+            selections: MCPImportSelection[];
+        }) => {
+            return await invoke<string[]>("import_mcp_servers", {
+                path,
+                selections,
+            });
         },
         onSuccess: async () => {
-            // Invalidate queries to refresh the UI
-            await queryClient.invalidateQueries({
-                queryKey: toolsetsKeys.customToolsetConfigs(),
-            });
+            // Invalidate both custom toolsets and general toolsets queries
             await queryClient.invalidateQueries({
                 queryKey: toolsetsKeys.toolsetsConfig(),
             });
+            // ask ToolsetManager to refresh
+            await ToolsetsManager.instance.refreshToolsets(
+                await getToolsetsConfig(),
+                await getCustomToolsetConfigs(),
+            );
+            // invalidate toolsets query
             await queryClient.invalidateQueries({
                 queryKey: toolsetsKeys.toolsets(),
             });
         },
     });
 }
+
+export type MCPExportResult = {
+    exported: number;
+    /** Servers the file already had, which are never replaced */
+    skipped: string[];
+};
+
+/**
+ * Writes custom toolsets to `path` in another client's format, with secrets
+ * replaced by a placeholder. Servers are added to an existing config file,
+ * except ones it already has by the same name.
+ */
+export async function exportMCPServers(
+    format: MCPConfigFormat,
+    path: string,
+    names?: string[],
+): Promise<MCPExportResult> {
+    return await invoke<MCPExportResult>("export_mcp_servers", {
+        format,
+        path,
+        names: names ?? null,
+    });
+}
@@ -1,7 +1,9 @@
 import Database from "@tauri-apps/plugin-sql";
+import { invoke } from "@tauri-apps/api/core";
 import { config } from "@core/config";
 
-// Get database instance
+// Get database instance once the backend has finished migrating it
+await invoke("wait_for_migrations");
 const db = await Database.load(config.dbUrl);
 
 export type GCMessage = {
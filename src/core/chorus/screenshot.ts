@@ -12,14 +12,7 @@ export async function captureWholeScreenCompressed() {
 
         console.timeEnd("captureWholeScreen");
 
-        const response = await fetch(`data:image/png;base64,${base64Image}`);
-        const blob = await response.blob();
-
-        const file = new File([blob], `screenshot.png`, {
-            type: "image/png",
-        });
-
-        return file;
+        return await screenshotFile(base64Image);
     } catch (error) {
         console.timeEnd("captureWholeScreen");
         console.error("Screenshot capture failed:", error);
@@ -28,3 +21,13 @@ export async function captureWholeScreenCompressed() {
         );
     }
 }
+
+/** Turns a screenshot from capture_whole_screen into a file */
+export async function screenshotFile(base64Image: string): Promise<File> {
+    const response = await fetch(`data:image/png;base64,${base64Image}`);
+    const blob = await response.blob();
+
+    return new File([blob], `screenshot.png`, {
+        type: "image/png",
+    });
+}
@@ -1,13 +1,26 @@
 import { ToolsetConfig } from "../Toolsets";
 import { Toolset } from "../Toolsets";
 import { MCPServer, getEnvFromJSON } from "../Toolsets";
-import { StdioServerParameters } from "../MCPStdioTauri";
+import { MCPServerParameters } from "../MCPStdioTauri";
 import { parseArgsStringToArgv } from "string-argv";
 
 export class MCPServerCustom extends MCPServer {
     protected getExecutionParameters(
         config: Record<string, string>,
-    ): StdioServerParameters {
+    ): MCPServerParameters {
+        if (
+            config.transport === "streamable_http" ||
+            config.transport === "sse"
+        ) {
+            const headers = getEnvFromJSON(config.headers);
+            return {
+                type: config.transport,
+                url: config.url,
+                headers: headers._type === "error" ? {} : headers,
+                bearerToken: config.bearerToken || undefined,
+            };
+        }
+
         const env = getEnvFromJSON(config.env);
         return {
             type: "custom",
@@ -44,6 +57,26 @@ export class CustomToolset extends Toolset {
                     displayName: "Environment",
                     type: "string",
                 },
+                transport: {
+                    id: "transport",
+                    displayName: "Transport",
+                    type: "string",
+                },
+                url: {
+                    id: "url",
+                    displayName: "URL",
+                    type: "string",
+                },
+                headers: {
+                    id: "headers",
+                    displayName: "Headers",
+                    type: "string",
+                },
+                bearerToken: {
+                    id: "bearerToken",
+                    displayName: "Bearer token",
+                    type: "string",
+                },
             },
             "",
             undefined,
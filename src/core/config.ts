@@ -1,7 +1,19 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { ActiveProfile } from "@core/chorus/Profiles";
+import type { DataDirectory } from "@core/chorus/DataDirectory";
+
 // Environment detection
 const isDev = import.meta.env.DEV;
 
-const DB_URL = "sqlite:chats.db";
+// Switching profiles reloads every window and moving the data restarts the
+// app, so neither can change while the app is running
+const [profile, dataDirectory] = await Promise.all([
+    invoke<ActiveProfile>("get_active_profile"),
+    invoke<DataDirectory>("get_data_directory"),
+]);
+
+// An absolute path, since the data may not be in the app config dir
+const DB_URL = `sqlite:${profile.database}`;
 
 // Note: meltyProxyUrl is kept for backwards compatibility with feedback submission
 // It can be removed once feedback is handled differently
@@ -10,5 +22,7 @@ const MELTY_PROXY_URL = "https://app.chorus.sh";
 export const config = {
     tellPostHogIAmATestUser: isDev,
     dbUrl: DB_URL,
+    profile,
+    dataDirectory,
     meltyProxyUrl: MELTY_PROXY_URL,
 } as const;
@@ -0,0 +1,22 @@
+import { create } from "zustand";
+
+/**
+ * A file picked by a chorus://import link. The import dialog offers to
+ * import it, and nothing is read until the user confirms.
+ */
+interface PendingImportStore {
+    file: string | null;
+    setFile: (file: string | null) => void;
+}
+
+const usePendingImportStore = create<PendingImportStore>((set) => ({
+    file: null,
+    setFile: (file) => set({ file }),
+}));
+
+export const pendingImportActions = {
+    setFile: (file: string | null) =>
+        usePendingImportStore.getState().setFile(file),
+};
+
+export { usePendingImportStore };
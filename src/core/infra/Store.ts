@@ -1,9 +1,14 @@
 import { Store, load } from "@tauri-apps/plugin-store";
+import { isAbsolute, join } from "@tauri-apps/api/path";
+import { config } from "@core/config";
 
 /**
- * Loads a store from the app's data directory, which is in
- * ~/Library/Application\ Support/sh.melty.app
+ * Loads a store. Names that aren't absolute paths are in the app's data
+ * directory, which may have been moved out of the default location.
  */
 export async function getStore(storeName: string): Promise<Store> {
-    return await load(storeName, { autoSave: true });
+    const path = (await isAbsolute(storeName))
+        ? storeName
+        : await join(config.dataDirectory.dataDir, storeName);
+    return await load(path, { autoSave: true });
 }
@@ -1,5 +1,16 @@
 import { getStore } from "@core/infra/Store";
+import { config } from "@core/config";
 import { emit } from "@tauri-apps/api/event";
+import {
+    isVaultRef,
+    resolveSecretRefs,
+    storeSecrets,
+} from "@core/chorus/Vault";
+import type { ExtraGlobalAction } from "@core/chorus/GlobalActions";
+import type { CloseBehavior } from "@core/chorus/Shutdown";
+
+// Base URLs are stored next to the API keys, but they aren't secret
+const isNotSecret = (key: string) => key.toLowerCase().includes("url");
 
 export interface Settings {
     defaultEditor: string;
@@ -23,13 +34,27 @@ export interface Settings {
         modelConfigId?: string;
         shortcut?: string;
     };
+    /** Shortcuts of the other global actions. Missing or empty means none. */
+    globalShortcuts?: Partial<Record<ExtraGlobalAction, string>>;
+    /** Missing means the platform's default, see `defaultCloseBehavior` */
+    closeBehavior?: CloseBehavior;
     lmStudioBaseUrl?: string;
     cautiousEnter?: boolean;
+    backups?: {
+        enabled?: boolean;
+        intervalHours?: number;
+        keepDaily?: number;
+        keepWeekly?: number;
+    };
+    localApi?: {
+        enabled?: boolean;
+        port?: number;
+    };
 }
 
 export class SettingsManager {
     private static instance: SettingsManager;
-    private storeName = "settings";
+    private storeName = config.profile.settingsStore;
 
     private constructor() {}
 
@@ -64,7 +89,11 @@ export class SettingsManager {
                 return defaultSettings;
             }
 
-            return (settings as Settings) || defaultSettings;
+            const stored = settings as Settings;
+            return {
+                ...stored,
+                apiKeys: await resolveApiKeys(stored.apiKeys),
+            };
         } catch (error) {
             console.error("Failed to get settings:", error);
             return {
@@ -83,10 +112,34 @@ export class SettingsManager {
         }
     }
 
+    /**
+     * The API keys to send to providers. Unlike `get`, this throws if a key
+     * couldn't be read from the vault, so a provider is never sent the
+     * reference instead.
+     */
+    public async getApiKeys(): Promise<NonNullable<Settings["apiKeys"]>> {
+        const { apiKeys = {} } = await this.get();
+        const unresolved = Object.entries(apiKeys)
+            .filter(([, value]) => value !== undefined && isVaultRef(value))
+            .map(([provider]) => provider);
+        if (unresolved.length > 0) {
+            throw new Error(
+                `Couldn't read the API keys for ${unresolved.join(", ")} from the vault. If it's locked, unlock it in Settings.`,
+            );
+        }
+        return apiKeys;
+    }
+
     public async set(settings: Settings): Promise<void> {
         try {
             const store = await getStore(this.storeName);
-            await store.set("settings", settings);
+            // API keys go to the vault, the settings file only keeps references
+            const apiKeys = settings.apiKeys
+                ? await storeSecrets("api-key", settings.apiKeys, {
+                      skip: isNotSecret,
+                  })
+                : undefined;
+            await store.set("settings", { ...settings, apiKeys });
             await store.save();
             await emit("settings-changed", settings);
         } catch (error) {
@@ -105,3 +158,17 @@ export class SettingsManager {
         }
     }
 }
+
+async function resolveApiKeys(
+    apiKeys: Settings["apiKeys"],
+): Promise<Settings["apiKeys"]> {
+    if (!apiKeys) return apiKeys;
+    try {
+        return await resolveSecretRefs(apiKeys);
+    } catch (error) {
+        // Keep the references, so saving settings while the vault is locked
+        // doesn't drop the keys. `getApiKeys` won't hand them to providers.
+        console.error("Failed to read API keys from the vault:", error);
+        return apiKeys;
+    }
+}
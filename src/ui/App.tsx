@@ -30,10 +30,6 @@ import NewPrompt from "./components/NewPrompt";
 import ListPrompts from "./components/ListPrompts";
 import Onboarding from "./components/Onboarding";
 import ProjectView from "./components/ProjectView";
-import {
-    onOpenUrl,
-    getCurrent as getCurrentDeepLink,
-} from "@tauri-apps/plugin-deep-link";
 import { config } from "@core/config";
 import Database from "@tauri-apps/plugin-sql";
 import { listen } from "@tauri-apps/api/event";
@@ -71,12 +67,37 @@ import { useShortcut } from "./hooks/useShortcut";
 import { Button } from "./components/ui/button";
 import { DatabaseProvider } from "./providers/DatabaseProvider";
 import { Alert, AlertTitle, AlertDescription } from "./components/ui/alert";
-import { relaunch } from "@tauri-apps/plugin-process";
+import { invoke } from "@tauri-apps/api/core";
 import { ToolPermissionDialog } from "./components/ToolPermissionDialog";
 import * as AppMetadataAPI from "@core/chorus/api/AppMetadataAPI";
 import * as ToolsetsAPI from "@core/chorus/api/ToolsetsAPI";
 import * as ChatAPI from "@core/chorus/api/ChatAPI";
 import * as ProjectAPI from "@core/chorus/api/ProjectAPI";
+import { draftKeys, setMessageDraft } from "@core/chorus/api/DraftAPI";
+import * as MessageAPI from "@core/chorus/api/MessageAPI";
+import {
+    fetchModelConfigs,
+    modelConfigQueries,
+    useModelConfigs,
+    useSelectedModelConfigsCompare,
+} from "@core/chorus/api/ModelsAPI";
+import { updateAppMenu } from "@core/chorus/AppMenu";
+import { prepareToQuit, QUIT_REQUESTED_EVENT } from "@core/chorus/Shutdown";
+import { getVaultStatus } from "@core/chorus/Vault";
+import {
+    DEEP_LINK_EVENT,
+    DeepLink,
+    takePendingDeepLinks,
+} from "@core/chorus/DeepLinks";
+import { pendingImportActions } from "@core/infra/PendingImportStore";
+import * as AttachmentsAPI from "@core/chorus/api/AttachmentsAPI";
+import { getScreenshotAttachment } from "@core/chorus/AttachmentsHelpers";
+import { screenshotFile } from "@core/chorus/screenshot";
+import {
+    NEW_CHAT_EVENT,
+    NEW_QUICK_CHAT_EVENT,
+    QuickChatDraft,
+} from "@core/chorus/GlobalActions";
 
 scan({
     enabled: true,
@@ -285,99 +306,6 @@ function AppContent() {
         }
     });
 
-    const handleDeepLink = useCallback(
-        (urls: string[]) => {
-            const url = urls[0];
-            console.log("handleDeepLink", url);
-            try {
-                const urlObj = new URL(url);
-                if (urlObj.protocol === "chorus:") {
-                    if (urlObj.hostname === "slack") {
-                        const accessToken =
-                            urlObj.searchParams.get("access_token");
-                        const teamId = urlObj.searchParams.get("team_id");
-                        if (accessToken && teamId) {
-                            updateToolsetsConfig.mutate({
-                                toolsetName: "slack",
-                                parameterId: "apiToken",
-                                value: accessToken,
-                            });
-                            updateToolsetsConfig.mutate({
-                                toolsetName: "slack",
-                                parameterId: "teamId",
-                                value: teamId,
-                            });
-                            updateToolsetsConfig.mutate({
-                                toolsetName: "slack",
-                                parameterId: "enabled",
-                                value: "true",
-                            });
-                        }
-                    } else if (urlObj.hostname === "github_integration") {
-                        const accessToken =
-                            urlObj.searchParams.get("access_token");
-                        if (accessToken) {
-                            updateToolsetsConfig.mutate({
-                                toolsetName: "github",
-                                parameterId: "personalAccessToken",
-                                value: accessToken,
-                            });
-                            updateToolsetsConfig.mutate({
-                                toolsetName: "github",
-                                parameterId: "enabled",
-                                value: "true",
-                            });
-                            toast.success("GitHub successfully connected", {
-                                description:
-                                    "You can now manage repos, code, issues, and PRs from Chorus",
-                            });
-                        }
-                    } else if (urlObj.hostname === "chat") {
-                        const chatId = urlObj.pathname.split("/")[1];
-                        if (chatId) {
-                            navigate(`/chat/${chatId}`);
-                        }
-                    } else {
-                        throw new Error(`Unrecognized deep link ${url}`);
-                    }
-                }
-            } catch (error) {
-                console.error("Failed to parse deep link URL:", error);
-                toast.error("Invalid Deep Link", {
-                    description: `The URL format is invalid — ${url}`,
-                });
-            }
-        },
-        // eslint-disable-next-line react-hooks/exhaustive-deps
-        [updateToolsetsConfig, navigate],
-    );
-
-    // Check for deep link on app load
-    useEffect(() => {
-        const checkDeepLink = async () => {
-            const urls = await getCurrentDeepLink();
-            if (urls) {
-                handleDeepLink(urls);
-            }
-        };
-
-        void checkDeepLink().catch(console.error);
-        // TODO figure out a safe solution for this (we want it to run only on app load)
-        // eslint-disable-next-line react-hooks/exhaustive-deps
-    }, []);
-
-    // Deep link listener for when app is already running
-    useEffect(() => {
-        const unlistenPromise = onOpenUrl((urls) => {
-            console.log("Deep link received:", urls);
-            void handleDeepLink(urls);
-        });
-
-        return () => {
-            void unlistenPromise.then((fn) => fn()).catch(console.error);
-        };
-    }, [handleDeepLink]);
-
     const [isDownloadingUpdate, setIsDownloadingUpdate] = useState(false);
     const isDownloadingRef = useRef(false);
 
@@ -401,7 +329,7 @@ function AppContent() {
                         size="sm"
                         onClick={() => {
                             localStorage.removeItem("pendingUpdateVersion");
-                            void relaunch().catch(console.error);
+                            void invoke("restart_app").catch(console.error);
                         }}
                     >
                         Restart
@@ -737,6 +665,254 @@ function AppContent() {
         };
     }, [db, navigate, convertQuickChatToRegularChat]);
 
+    const updateSelectedModelConfigsCompare =
+        MessageAPI.useUpdateSelectedModelConfigsCompare();
+
+    // Puts a linked prompt in a chat's composer. It isn't sent, so the user
+    // sees what the link asks before anything goes to a model.
+    const fillDraft = useCallback(
+        async (chatId: string, prompt: string | null) => {
+            if (!prompt) return;
+            await setMessageDraft(chatId, prompt);
+            await queryClient.invalidateQueries({
+                queryKey: draftKeys.messageDraft(chatId),
+            });
+        },
+        [],
+    );
+
+    // chorus:// links, checked by src-tauri/src/deep_link.rs and sent only to
+    // the window that should act on them
+    const handleDeepLink = useCallback(
+        async (link: DeepLink) => {
+            console.log("handleDeepLink", link.action);
+            switch (link.action) {
+                case "new": {
+                    const projectId = link.projectId ?? "default";
+                    try {
+                        await ProjectAPI.fetchProject(projectId);
+                    } catch {
+                        toast.error("Invalid Deep Link", {
+                            description: "The link's project doesn't exist",
+                        });
+                        return;
+                    }
+                    if (link.modelConfigIds.length > 0) {
+                        const modelConfigs = await fetchModelConfigs();
+                        const selected = link.modelConfigIds.flatMap((id) =>
+                            modelConfigs.filter((m) => m.id === id),
+                        );
+                        if (selected.length < link.modelConfigIds.length) {
+                            toast("Some models weren't found", {
+                                description:
+                                    "The link names models that aren't set up in Chorus",
+                            });
+                        }
+                        if (selected.length > 0) {
+                            await updateSelectedModelConfigsCompare.mutateAsync(
+                                { modelConfigs: selected },
+                            );
+                        }
+                    }
+                    const chatId = await getOrCreateNewChat.mutateAsync({
+                        projectId,
+                    });
+                    await fillDraft(chatId, link.prompt);
+                    break;
+                }
+                case "chat":
+                    navigate(`/chat/${link.chatId}`);
+                    break;
+                case "quick": {
+                    const chatId = await getOrCreateNewQuickChat.mutateAsync();
+                    await fillDraft(chatId, link.prompt);
+                    break;
+                }
+                case "import":
+                    pendingImportActions.setFile(link.file);
+                    dialogActions.openDialog(`import-${link.source}`);
+                    break;
+                case "slack":
+                    updateToolsetsConfig.mutate({
+                        toolsetName: "slack",
+                        parameterId: "apiToken",
+                        value: link.accessToken,
+                    });
+                    updateToolsetsConfig.mutate({
+                        toolsetName: "slack",
+                        parameterId: "teamId",
+                        value: link.teamId,
+                    });
+                    updateToolsetsConfig.mutate({
+                        toolsetName: "slack",
+                        parameterId: "enabled",
+                        value: "true",
+                    });
+                    break;
+                case "github":
+                    updateToolsetsConfig.mutate({
+                        toolsetName: "github",
+                        parameterId: "personalAccessToken",
+                        value: link.accessToken,
+                    });
+                    updateToolsetsConfig.mutate({
+                        toolsetName: "github",
+                        parameterId: "enabled",
+                        value: "true",
+                    });
+                    toast.success("GitHub successfully connected", {
+                        description:
+                            "You can now manage repos, code, issues, and PRs from Chorus",
+                    });
+                    break;
+                case "rejected":
+                    toast.error("Invalid Deep Link", {
+                        description: link.reason,
+                    });
+                    break;
+            }
+        },
+        [
+            updateToolsetsConfig,
+            updateSelectedModelConfigsCompare,
+            getOrCreateNewChat,
+            getOrCreateNewQuickChat,
+            fillDraft,
+            navigate,
+        ],
+    );
+
+    useEffect(() => {
+        // Links are sent to one window, so listen on this one only
+        const unlistenPromise = getCurrentWindow().listen<DeepLink>(
+            DEEP_LINK_EVENT,
+            (event) => {
+                void handleDeepLink(event.payload).catch(console.error);
+            },
+        );
+
+        // Once we're listening, pick up links that came before, like the one
+        // the app was launched with
+        void unlistenPromise
+            .then(() => takePendingDeepLinks())
+            .then(async (links) => {
+                for (const link of links) {
+                    await handleDeepLink(link);
+                }
+            })
+            .catch(console.error);
+
+        return () => {
+            void unlistenPromise.then((fn) => fn()).catch(console.error);
+        };
+    }, [handleDeepLink]);
+
+    const createAttachment = AttachmentsAPI.useCreateAttachment();
+
+    // Global shortcuts (src-tauri/src/global_actions.rs) start new chats in
+    // the quick chat window, with what they captured, or in the main window
+    useEffect(() => {
+        const unlistenPromise = isQuickChatWindow
+            ? getCurrentWindow().listen<QuickChatDraft>(
+                  NEW_QUICK_CHAT_EVENT,
+                  (event) => {
+                      void (async () => {
+                          const { text, screenshot } = event.payload;
+                          const chatId =
+                              await getOrCreateNewQuickChat.mutateAsync();
+                          await fillDraft(chatId, text);
+                          if (screenshot) {
+                              const attachment = await getScreenshotAttachment(
+                                  await screenshotFile(screenshot),
+                              );
+                              await createAttachment.mutateAsync({
+                                  type: "image",
+                                  originalName: "screenshot",
+                                  path: attachment.path,
+                                  association: { type: "draft", chatId },
+                                  isLoading: false,
+                              });
+                          }
+                      })().catch(console.error);
+                  },
+              )
+            : getCurrentWindow().listen(NEW_CHAT_EVENT, () => {
+                  if (isDialogOpen) {
+                      dialogActions.closeDialog();
+                  }
+                  getOrCreateNewChat.mutate({ projectId: "default" });
+              });
+
+        return () => {
+            void unlistenPromise.then((fn) => fn()).catch(console.error);
+        };
+    }, [
+        isQuickChatWindow,
+        isDialogOpen,
+        getOrCreateNewChat,
+        getOrCreateNewQuickChat,
+        createAttachment,
+        fillDraft,
+    ]);
+
+    // The local API (src-tauri/src/api.rs), the tray menu and the app menu
+    // write to the database directly and tell us what they changed
+    useEffect(() => {
+        const unlistenRefresh = listen("refresh_projects_state", () => {
+            void queryClient.invalidateQueries({
+                queryKey: ChatAPI.chatKeys.all(),
+            });
+            void queryClient.invalidateQueries({
+                queryKey: ProjectAPI.projectKeys.all(),
+            });
+            void queryClient.invalidateQueries({
+                queryKey: ProjectAPI.projectContextQueryKeys.all(),
+            });
+            void queryClient.invalidateQueries({
+                queryKey: draftKeys.allMessageDraftAttachments(),
+            });
+            // The tray menu's YOLO switch (src-tauri/src/tray.rs)
+            void queryClient.invalidateQueries({
+                queryKey: AppMetadataAPI.appMetadataKeys.appMetadata(),
+            });
+            // The app menu's Models submenu (src-tauri/src/app_menu.rs)
+            void queryClient.invalidateQueries(modelConfigQueries.compare());
+        });
+
+        const unlistenChatDeleted = listen<string>("chat_deleted", (event) => {
+            const chatId = event.payload;
+            queryClient.removeQueries(ChatAPI.chatQueries.detail(chatId));
+            void queryClient.invalidateQueries(ChatAPI.chatQueries.list());
+            if (chatId === currentChatId) {
+                navigate("/");
+            }
+        });
+
+        return () => {
+            void unlistenRefresh.then((fn) => fn()).catch(console.error);
+            void unlistenChatDeleted.then((fn) => fn()).catch(console.error);
+        };
+    }, [navigate, currentChatId]);
+
+    // The app menu lists recent chats, projects and models, and moves the
+    // open chat between projects, so it's rebuilt when any of them change
+    const { data: projects } = useQuery(ProjectAPI.projectQueries.list());
+    const { data: modelConfigs } = useModelConfigs();
+    const { data: selectedModelConfigs } = useSelectedModelConfigsCompare();
+    useEffect(() => {
+        if (isQuickChatWindow) {
+            return;
+        }
+        void updateAppMenu(currentChatId ?? null).catch(console.error);
+    }, [
+        isQuickChatWindow,
+        currentChatId,
+        chats,
+        projects,
+        modelConfigs,
+        selectedModelConfigs,
+    ]);
+
     const skipOnboarding = AppMetadataAPI.useSkipOnboarding();
     const onCompleteOnboarding = () => {
         skipOnboarding.mutate();
@@ -776,6 +952,28 @@ function AppContent() {
         };
     }, []);
 
+    // A password protected vault stays locked until the password is entered,
+    // and until then the API keys in it can't be used
+    useEffect(() => {
+        if (isQuickChatWindow) return;
+        void getVaultStatus()
+            .then((status) => {
+                if (!status.uses_password || status.unlocked) return;
+                toast("Your vault is locked", {
+                    description: "Unlock it to use your API keys.",
+                    duration: Infinity,
+                    action: {
+                        label: "Unlock",
+                        onClick: () => {
+                            setDefaultSettingsTab("api-keys");
+                            dialogActions.openDialog(SETTINGS_DIALOG_ID);
+                        },
+                    },
+                });
+            })
+            .catch(console.error);
+    }, [isQuickChatWindow]);
+
     const dismissEducationalTooltip = () => {
         if (currentAppVersion) {
             setDismissedAlertVersion.mutate({ version: currentAppVersion });
@@ -1011,6 +1209,16 @@ function App() {
         };
     }, []);
 
+    // The backend waits for every window before it quits
+    useEffect(() => {
+        const unlisten = listen(QUIT_REQUESTED_EVENT, () => {
+            void prepareToQuit().catch(console.error);
+        });
+        return () => {
+            void unlisten.then((fn) => fn()).catch(console.error);
+        };
+    }, []);
+
     return (
         <QueryClientProvider client={queryClient}>
             <script src="https://unpkg.com/react-scan/dist/auto.global.js"></script>
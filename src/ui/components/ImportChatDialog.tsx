@@ -19,6 +19,10 @@ import { cn } from "../lib/utils";
 import { getCurrentWebview } from "@tauri-apps/api/webview";
 import { DialogDescription } from "@radix-ui/react-dialog";
 import { useDialogStore } from "@core/infra/DialogStore";
+import {
+    usePendingImportStore,
+    pendingImportActions,
+} from "@core/infra/PendingImportStore";
 
 interface ImportChatDialogProps {
     provider: "openai" | "anthropic";
@@ -38,6 +42,8 @@ export default function ImportChatDialog({ provider }: ImportChatDialogProps) {
     const isImportDialogOpen = useDialogStore(
         (state) => state.activeDialogId === dialogId,
     );
+    // Picked by a chorus://import link
+    const pendingFile = usePendingImportStore((state) => state.file);
 
     const providerConfig = {
         openai: {
@@ -132,6 +138,7 @@ export default function ImportChatDialog({ provider }: ImportChatDialogProps) {
             });
 
             if (!selected) return;
+            pendingImportActions.setFile(null);
 
             // Check if it's a ZIP file
             if (selected.toLowerCase().endsWith(".zip")) {
@@ -290,6 +297,23 @@ export default function ImportChatDialog({ provider }: ImportChatDialogProps) {
         }
     };
 
+    const handleImportPendingFile = async (path: string) => {
+        pendingImportActions.setFile(null);
+        try {
+            if (path.toLowerCase().endsWith(".zip")) {
+                await handleZipFile(path);
+            } else {
+                const content = await readTextFile(path);
+                await processFile(content);
+            }
+        } catch (error) {
+            console.error("Error reading file:", error);
+            toast.error("Error", {
+                description: "Failed to read file",
+            });
+        }
+    };
+
     useEffect(() => {
         const unlisten = getCurrentWebview().onDragDropEvent((event) => {
             if (event.payload.type === "drop" && isImportDialogOpen) {
@@ -313,6 +337,7 @@ export default function ImportChatDialog({ provider }: ImportChatDialogProps) {
             id={dialogId}
             onOpenChange={(open) => {
                 if (!open) {
+                    pendingImportActions.setFile(null);
                     setIsImporting(false);
                     setNumImported(0);
                     setImportProgress({ current: 0, total: 0 });
@@ -414,6 +439,21 @@ export default function ImportChatDialog({ provider }: ImportChatDialogProps) {
                                                     </p>
                                                 </div>
                                             </>
+                                        ) : pendingFile ? (
+                                            <>
+                                                <FileJson className="h-12 w-12 text-muted-foreground" />
+                                                <div>
+                                                    <p className="font-semibold">
+                                                        {pendingFile
+                                                            .split(/[\\/]/)
+                                                            .pop()}
+                                                    </p>
+                                                    <p className="text-sm text-muted-foreground mt-1">
+                                                        A link asked to import
+                                                        this file
+                                                    </p>
+                                                </div>
+                                            </>
                                         ) : (
                                             <>
                                                 <Upload className="h-12 w-12 text-muted-foreground" />
@@ -430,7 +470,26 @@ export default function ImportChatDialog({ provider }: ImportChatDialogProps) {
                                             </>
                                         )}
                                     </div>
-                                    {!isImporting && (
+                                    {!isImporting && pendingFile && (
+                                        <div className="flex justify-center gap-2 mt-4">
+                                            <Button
+                                                onClick={() =>
+                                                    void handleImportPendingFile(
+                                                        pendingFile,
+                                                    )
+                                                }
+                                            >
+                                                Import
+                                            </Button>
+                                            <Button
+                                                onClick={handleFileSelect}
+                                                variant="outline"
+                                            >
+                                                Select another file
+                                            </Button>
+                                        </div>
+                                    )}
+                                    {!isImporting && !pendingFile && (
                                         <Button
                                             onClick={handleFileSelect}
                                             variant="outline"
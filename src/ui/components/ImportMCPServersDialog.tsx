@@ -0,0 +1,327 @@
+import { useState } from "react";
+import {
+    Dialog,
+    DialogContent,
+    DialogDescription,
+    DialogFooter,
+    DialogHeader,
+    DialogTitle,
+} from "@ui/components/ui/dialog";
+import { Button } from "@ui/components/ui/button";
+import { Checkbox } from "@ui/components/ui/checkbox";
+import { Input } from "@ui/components/ui/input";
+import {
+    Select,
+    SelectContent,
+    SelectItem,
+    SelectTrigger,
+    SelectValue,
+} from "@ui/components/ui/select";
+import { open } from "@tauri-apps/plugin-dialog";
+import { toast } from "sonner";
+import { FileJson, Loader2 } from "lucide-react";
+import * as ToolsetsAPI from "@core/chorus/api/ToolsetsAPI";
+import {
+    MCP_CONFIG_FORMATS,
+    MCPImportCandidate,
+    MCPImportPreview,
+} from "@core/chorus/api/ToolsetsAPI";
+import { dialogActions } from "@core/infra/DialogStore";
+
+export const IMPORT_MCP_SERVERS_DIALOG_ID = "import-mcp-servers";
+
+type Choice = {
+    selected: boolean;
+    name: string;
+    replace: boolean;
+};
+
+function initialChoices(preview: MCPImportPreview): Record<string, Choice> {
+    const choices: Record<string, Choice> = {};
+    for (const candidate of preview.candidates) {
+        // Servers that already exist are left alone unless the user says so
+        choices[candidate.source_name] = {
+            selected: !candidate.conflict,
+            name: candidate.name,
+            replace: false,
+        };
+    }
+    return choices;
+}
+
+function CandidateRow({
+    candidate,
+    choice,
+    nameTaken,
+    onChange,
+}: {
+    candidate: MCPImportCandidate;
+    choice: Choice;
+    nameTaken: boolean;
+    onChange: (choice: Choice) => void;
+}) {
+    const summary =
+        candidate.transport === "stdio"
+            ? `${candidate.command} ${candidate.args}`.trim()
+            : (candidate.url ?? "");
+
+    return (
+        <div className="flex items-start gap-3 border rounded-md p-3">
+            <Checkbox
+                className="mt-2"
+                checked={choice.selected}
+                onCheckedChange={(checked) =>
+                    onChange({ ...choice, selected: checked === true })
+                }
+            />
+            <div className="flex-1 min-w-0 space-y-2">
+                <div className="flex items-center gap-2">
+                    <Input
+                        value={choice.name}
+                        onChange={(e) =>
+                            onChange({ ...choice, name: e.target.value })
+                        }
+                        disabled={!choice.selected}
+                        className="h-8"
+                        autoCapitalize="off"
+                        autoComplete="off"
+                        spellCheck={false}
+                    />
+                    {nameTaken && choice.selected && (
+                        <Select
+                            value={choice.replace ? "replace" : "keep"}
+                            onValueChange={(value) =>
+                                value === "rename"
+                                    ? onChange({
+                                          ...choice,
+                                          name: candidate.suggested_name,
+                                          replace: false,
+                                      })
+                                    : onChange({
+                                          ...choice,
+                                          replace: value === "replace",
+                                      })
+                            }
+                        >
+                            <SelectTrigger className="h-8 w-44">
+                                <SelectValue />
+                            </SelectTrigger>
+                            <SelectContent>
+                                <SelectItem value="keep">
+                                    Name already used
+                                </SelectItem>
+                                <SelectItem value="replace">
+                                    Replace existing
+                                </SelectItem>
+                                <SelectItem value="rename">
+                                    Rename to {candidate.suggested_name}
+                                </SelectItem>
+                            </SelectContent>
+                        </Select>
+                    )}
+                </div>
+                <div className="text-xs font-mono truncate text-muted-foreground">
+                    {summary}
+                </div>
+                {(candidate.env_keys.length > 0 ||
+                    candidate.header_names.length > 0) && (
+                    <div className="text-[10px] text-muted-foreground">
+                        {candidate.env_keys.length > 0 &&
+                            `Env: ${candidate.env_keys.join(", ")} `}
+                        {candidate.header_names.length > 0 &&
+                            `Headers: ${candidate.header_names.join(", ")}`}
+                    </div>
+                )}
+                {candidate.warnings.map((warning) => (
+                    <div key={warning} className="text-[10px] text-yellow-600">
+                        {warning}
+                    </div>
+                ))}
+            </div>
+        </div>
+    );
+}
+
+export default function ImportMCPServersDialog() {
+    const { data: locations = [] } = ToolsetsAPI.useMCPConfigLocations();
+    const { data: customToolsetConfigs = [] } =
+        ToolsetsAPI.useCustomToolsetConfigs();
+    const importMCPServers = ToolsetsAPI.useImportMCPServers();
+    const [preview, setPreview] = useState<MCPImportPreview>();
+    const [choices, setChoices] = useState<Record<string, Choice>>({});
+    const [isLoading, setIsLoading] = useState(false);
+
+    const reset = () => {
+        setPreview(undefined);
+        setChoices({});
+        setIsLoading(false);
+    };
+
+    const loadPreview = async (path: string) => {
+        setIsLoading(true);
+        try {
+            const result = await ToolsetsAPI.previewMCPImport(path);
+            setPreview(result);
+            setChoices(initialChoices(result));
+        } catch (error) {
+            toast.error("Couldn't read config", {
+                description: String(error),
+            });
+        } finally {
+            setIsLoading(false);
+        }
+    };
+
+    const handleChooseFile = async () => {
+        const selected = await open({
+            multiple: false,
+            filters: [{ name: "MCP config", extensions: ["json"] }],
+            title: "Select an MCP config file",
+        });
+        if (selected) {
+            await loadPreview(selected);
+        }
+    };
+
+    const isNameTaken = (name: string) =>
+        customToolsetConfigs.some((t) => t.name === name);
+
+    const selections = Object.entries(choices)
+        .filter(([, choice]) => choice.selected)
+        .map(([sourceName, choice]) => ({
+            sourceName,
+            name: choice.name,
+            replace: choice.replace && isNameTaken(choice.name),
+        }));
+    const hasUnresolvedConflict = selections.some(
+        (selection) => isNameTaken(selection.name) && !selection.replace,
+    );
+
+    const handleImport = async () => {
+        if (!preview) return;
+        try {
+            const imported = await importMCPServers.mutateAsync({
+                path: preview.path,
+                selections,
+            });
+            toast.success("Import Successful", {
+                description: `Imported ${imported.length} MCP server${imported.length === 1 ? "" : "s"}`,
+            });
+            dialogActions.closeDialog(IMPORT_MCP_SERVERS_DIALOG_ID);
+            reset();
+        } catch (error) {
+            toast.error("Import Failed", { description: String(error) });
+        }
+    };
+
+    return (
+        <Dialog
+            id={IMPORT_MCP_SERVERS_DIALOG_ID}
+            onOpenChange={(open) => {
+                if (!open) reset();
+            }}
+        >
+            <DialogContent className="p-4 max-w-2xl">
+                <DialogHeader>
+                    <DialogTitle>Import MCP servers</DialogTitle>
+                    <DialogDescription className="text-sm">
+                        Bring in servers you've set up in another app. Env
+                        values and headers are moved into the encrypted vault.
+                    </DialogDescription>
+                </DialogHeader>
+
+                {!preview ? (
+                    <div className="space-y-2">
+                        {locations.map((location) => (
+                            <button
+                                key={location.path}
+                                type="button"
+                                disabled={isLoading}
+                                className="flex items-center gap-3 w-full border rounded-md p-3 hover:bg-muted text-left disabled:opacity-50"
+                                onClick={() => void loadPreview(location.path)}
+                            >
+                                <FileJson className="size-5 shrink-0" />
+                                <div className="min-w-0">
+                                    <div className="font-semibold">
+                                        {MCP_CONFIG_FORMATS[location.format].label}
+                                    </div>
+                                    <div className="text-xs text-muted-foreground truncate">
+                                        {location.path}
+                                    </div>
+                                </div>
+                            </button>
+                        ))}
+                        <Button
+                            variant="outline"
+                            className="w-full"
+                            disabled={isLoading}
+                            onClick={() => void handleChooseFile()}
+                        >
+                            {isLoading ? (
+                                <Loader2 className="size-4 animate-spin" />
+                            ) : (
+                                "Choose a config file..."
+                            )}
+                        </Button>
+                    </div>
+                ) : (
+                    <div className="space-y-2 max-h-[60vh] overflow-y-auto">
+                        <div className="text-xs text-muted-foreground truncate">
+                            {preview.path}
+                        </div>
+                        {preview.candidates.length === 0 && (
+                            <div className="text-sm">
+                                No servers could be read from this file.
+                            </div>
+                        )}
+                        {preview.candidates.map((candidate) => (
+                            <CandidateRow
+                                key={candidate.source_name}
+                                candidate={candidate}
+                                choice={choices[candidate.source_name]}
+                                nameTaken={isNameTaken(
+                                    choices[candidate.source_name].name,
+                                )}
+                                onChange={(choice) =>
+                                    setChoices((prev) => ({
+                                        ...prev,
+                                        [candidate.source_name]: choice,
+                                    }))
+                                }
+                            />
+                        ))}
+                        {preview.skipped.map((skipped) => (
+                            <div
+                                key={skipped}
+                                className="text-xs text-muted-foreground"
+                            >
+                                Skipped {skipped}
+                            </div>
+                        ))}
+                    </div>
+                )}
+
+                {preview && (
+                    <DialogFooter>
+                        <Button variant="outline" size="sm" onClick={reset}>
+                            Back
+                        </Button>
+                        <Button
+                            size="sm"
+                            disabled={
+                                selections.length === 0 ||
+                                hasUnresolvedConflict ||
+                                importMCPServers.isPending
+                            }
+                            onClick={() => void handleImport()}
+                        >
+                            {importMCPServers.isPending
+                                ? "Importing..."
+                                : `Import ${selections.length}`}
+                        </Button>
+                    </DialogFooter>
+                )}
+            </DialogContent>
+        </Dialog>
+    );
+}
@@ -461,12 +461,12 @@ export function ManageModelsBox({
             } else if (provider === "openrouter") {
                 await refreshOpenRouter.mutateAsync();
             } else if (provider === "openai-compatible") {
-                const settings = await SettingsManager.getInstance().get();
-                const baseUrl = settings.apiKeys?.["openai-compatible-url"];
+                const keys = await SettingsManager.getInstance().getApiKeys();
+                const baseUrl = keys["openai-compatible-url"];
                 if (baseUrl) {
                     await refreshOpenAICompatible.mutateAsync({
                         baseUrl,
-                        apiKey: settings.apiKeys?.["openai-compatible"],
+                        apiKey: keys["openai-compatible"],
                     });
                 }
             }
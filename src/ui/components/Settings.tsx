@@ -20,7 +20,6 @@ import {
 } from "@ui/components/ui/dialog";
 import { Separator } from "./ui/separator";
 import {
-    Loader2,
     ChevronDown,
     ExternalLink,
     Pencil,
@@ -57,7 +56,6 @@ import ApiKeysForm from "./ApiKeysForm";
 import Database from "@tauri-apps/plugin-sql";
 import { Input } from "./ui/input";
 import { Textarea } from "./ui/textarea";
-import { relaunch } from "@tauri-apps/plugin-process";
 import { useDatabase } from "@ui/hooks/useDatabase";
 import {
     Collapsible,
@@ -67,7 +65,11 @@ import {
 import { InfoCircledIcon } from "@radix-ui/react-icons";
 import { AccessibilitySettings } from "./AccessibilityCheck";
 import { UNIVERSAL_SYSTEM_PROMPT_DEFAULT } from "@core/chorus/prompts/prompts";
-import { CustomToolsetConfig, getEnvFromJSON } from "@core/chorus/Toolsets";
+import {
+    CustomToolsetConfig,
+    CustomToolsetTransport,
+    getEnvFromJSON,
+} from "@core/chorus/Toolsets";
 import * as ToolsetsAPI from "@core/chorus/api/ToolsetsAPI";
 import { useQueryClient } from "@tanstack/react-query";
 import { useReactQueryAutoSync } from "use-react-query-auto-sync";
@@ -83,6 +85,22 @@ import ShortcutRecorder from "./ShortcutRecorder";
 import FeedbackButton from "./FeedbackButton";
 import { SiOpenai } from "react-icons/si";
 import ImportChatDialog from "./ImportChatDialog";
+import ImportMCPServersDialog, {
+    IMPORT_MCP_SERVERS_DIALOG_ID,
+} from "./ImportMCPServersDialog";
+import {
+    exportMCPServers,
+    MCP_CONFIG_FORMAT_IDS,
+    MCP_CONFIG_FORMATS,
+    MCPConfigFormat,
+} from "@core/chorus/api/ToolsetsAPI";
+import {
+    DropdownMenu,
+    DropdownMenuContent,
+    DropdownMenuItem,
+    DropdownMenuTrigger,
+} from "./ui/dropdown-menu";
+import { open, save } from "@tauri-apps/plugin-dialog";
 import { dialogActions } from "@core/infra/DialogStore";
 import * as AppMetadataAPI from "@core/chorus/api/AppMetadataAPI";
 import * as ChatAPI from "@core/chorus/api/ChatAPI";
@@ -91,6 +109,17 @@ import { PermissionsTab } from "./PermissionsTab";
 import { cn } from "@ui/lib/utils";
 import { ProviderLogo } from "./ui/provider-logo";
 import { getProviderName } from "@core/chorus/Models";
+import * as LocalAPI from "@core/chorus/LocalAPI";
+import * as Profiles from "@core/chorus/Profiles";
+import * as DataDirectory from "@core/chorus/DataDirectory";
+import * as DatabaseEncryption from "@core/chorus/DatabaseEncryption";
+import * as DatabaseUnlock from "@core/chorus/DatabaseUnlock";
+import * as GlobalActions from "@core/chorus/GlobalActions";
+import * as Shutdown from "@core/chorus/Shutdown";
+import * as Vault from "@core/chorus/Vault";
+import { platform } from "@tauri-apps/plugin-os";
+import SimpleCopyButton from "./CopyButton";
+import { listen } from "@tauri-apps/api/event";
 
 type ToolsetFormProps = {
     toolset: CustomToolsetConfig;
@@ -104,6 +133,73 @@ type ToolsetFormProps = {
     docsUrl?: string;
 };
 
+type RemoteTransport = Exclude<CustomToolsetTransport, "stdio">;
+
+type RemoteToolset = {
+    name: string;
+    url: string;
+    transport: RemoteTransport;
+    bearerToken: string;
+};
+
+function isRemoteToolset(toolset: CustomToolsetConfig) {
+    return (
+        toolset.transport === "streamable_http" || toolset.transport === "sse"
+    );
+}
+
+// Servers on the older SSE transport conventionally live at .../sse
+function guessTransport(url: string): RemoteTransport {
+    try {
+        return new URL(url).pathname.replace(/\/$/, "").endsWith("/sse")
+            ? "sse"
+            : "streamable_http";
+    } catch {
+        return "streamable_http";
+    }
+}
+
+// Validate a remote server's URL
+function validateRemoteUrl(value: string | undefined) {
+    if (!value?.trim()) {
+        return "URL is required";
+    } else if (!value.startsWith("http://") && !value.startsWith("https://")) {
+        return "URL must start with http:// or https://";
+    } else {
+        try {
+            new URL(value);
+        } catch {
+            return "Invalid URL format";
+        }
+    }
+    return undefined;
+}
+
+function TransportSelect({
+    value,
+    onChange,
+}: {
+    value: RemoteTransport;
+    onChange: (value: RemoteTransport) => void;
+}) {
+    return (
+        <Select
+            value={value}
+            onValueChange={(value) =>
+                onChange(value === "sse" ? "sse" : "streamable_http")
+            }
+        >
+            <SelectTrigger>
+                <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+                <SelectItem value="streamable_http">Streamable HTTP</SelectItem>
+                <SelectItem value="sse">SSE (legacy)</SelectItem>
+            </SelectContent>
+        </Select>
+    );
+}
+
 function RemoteToolsetForm({
     isOpen,
     onClose,
@@ -111,10 +207,14 @@ function RemoteToolsetForm({
 }: {
     isOpen: boolean;
     onClose: () => void;
-    onSubmit: (name: string, url: string) => void;
+    onSubmit: (toolset: RemoteToolset) => void;
 }) {
     const [name, setName] = useState("");
     const [url, setUrl] = useState("");
+    const [transport, setTransport] =
+        useState<RemoteTransport>("streamable_http");
+    const [transportChosen, setTransportChosen] = useState(false);
+    const [bearerToken, setBearerToken] = useState("");
     const [errors, setErrors] = useState<{ name?: string; url?: string }>({});
     const { data: customToolsetConfigs = [] } =
         ToolsetsAPI.useCustomToolsetConfigs();
@@ -131,24 +231,7 @@ function RemoteToolsetForm({
         return undefined;
     };
 
-    // Validate URL field
-    const validateUrl = (value: string) => {
-        if (!value.trim()) {
-            return "URL is required";
-        } else if (
-            !value.startsWith("http://") &&
-            !value.startsWith("https://")
-        ) {
-            return "URL must start with http:// or https://";
-        } else {
-            try {
-                new URL(value);
-            } catch {
-                return "Invalid URL format";
-            }
-        }
-        return undefined;
-    };
+    const validateUrl = validateRemoteUrl;
 
     const validateForm = () => {
         const nameError = validateName(name);
@@ -180,6 +263,9 @@ function RemoteToolsetForm({
     const handleUrlChange = (e: React.ChangeEvent<HTMLInputElement>) => {
         const value = e.target.value;
         setUrl(value);
+        if (!transportChosen) {
+            setTransport(guessTransport(value));
+        }
         const error = validateUrl(value);
         setErrors((prev) => {
             const newErrors = { ...prev };
@@ -194,9 +280,10 @@ function RemoteToolsetForm({
 
     const handleSubmit = () => {
         if (validateForm()) {
-            onSubmit(name, url);
+            onSubmit({ name, url, transport, bearerToken });
             setName("");
             setUrl("");
+            setBearerToken("");
             setErrors({});
         }
     };
@@ -206,6 +293,9 @@ function RemoteToolsetForm({
         if (!isOpen) {
             setName("");
             setUrl("");
+            setTransport("streamable_http");
+            setTransportChosen(false);
+            setBearerToken("");
             setErrors({});
         }
     }, [isOpen]);
@@ -258,7 +348,7 @@ function RemoteToolsetForm({
                     value={url}
                     onChange={handleUrlChange}
                     onKeyDown={handleKeyDown}
-                    placeholder="https://mcp.zapier.com/api/mcp/s/.../sse"
+                    placeholder="https://mcp.zapier.com/api/mcp/s/.../mcp"
                     className={errors.url ? "border-destructive" : ""}
                 />
                 {errors.url && (
@@ -269,6 +359,40 @@ function RemoteToolsetForm({
                 </p>
             </div>
 
+            <div className="space-y-2">
+                <label className="font-semibold">Transport</label>
+                <TransportSelect
+                    value={transport}
+                    onChange={(value) => {
+                        setTransport(value);
+                        setTransportChosen(true);
+                    }}
+                />
+                <p className="text-xs text-muted-foreground">
+                    Most servers use Streamable HTTP. Older ones, usually at a
+                    URL ending in /sse, use SSE.
+                </p>
+            </div>
+
+            <div className="space-y-2">
+                <label htmlFor="remote-mcp-token" className="font-semibold">
+                    Bearer token (optional)
+                </label>
+                <Input
+                    id="remote-mcp-token"
+                    type="password"
+                    value={bearerToken}
+                    onChange={(e) => setBearerToken(e.target.value)}
+                    onKeyDown={handleKeyDown}
+                    autoComplete="off"
+                    spellCheck={false}
+                />
+                <p className="text-xs text-muted-foreground">
+                    Sent as an Authorization header, for example an API key or
+                    an OAuth access token. It's kept in the encrypted vault.
+                </p>
+            </div>
+
             <div className="flex justify-end space-x-2 pt-2">
                 <Button variant="outline" size="sm" onClick={onClose}>
                     Cancel
@@ -337,6 +461,119 @@ function ToolsetForm({
                 </div>
             )}
 
+            {isRemoteToolset(toolset) ? (
+                <RemoteToolsetFields
+                    toolset={toolset}
+                    errors={errors}
+                    onChange={onChange}
+                />
+            ) : (
+                <StdioToolsetFields
+                    toolset={toolset}
+                    errors={errors}
+                    apiKeyUrl={apiKeyUrl}
+                    onChange={onChange}
+                />
+            )}
+
+            <div className="flex justify-end space-x-2 pt-2">
+                <Button variant="outline" size="sm" onClick={onCancel}>
+                    Cancel
+                </Button>
+                <Button
+                    size="sm"
+                    onClick={onSave}
+                    disabled={Object.keys(errors).length > 0}
+                >
+                    Save
+                </Button>
+            </div>
+        </div>
+    );
+}
+
+type ToolsetFieldsProps = Pick<
+    ToolsetFormProps,
+    "toolset" | "errors" | "apiKeyUrl" | "onChange"
+>;
+
+function RemoteToolsetFields({
+    toolset,
+    errors,
+    onChange,
+}: ToolsetFieldsProps) {
+    return (
+        <>
+            <div className="space-y-2">
+                <label className="font-semibold">URL</label>
+                <Input
+                    value={toolset.url || ""}
+                    spellCheck={false}
+                    onChange={(e) => onChange("url", e.target.value)}
+                    className={errors.url ? "border-destructive" : ""}
+                    placeholder="https://example.com/mcp"
+                />
+                <span className="text-[10px] ">
+                    The URL of the remote MCP server.
+                </span>
+                {errors.url && (
+                    <div className="text-destructive ">{errors.url}</div>
+                )}
+            </div>
+
+            <div className="space-y-2">
+                <label className="font-semibold">Transport</label>
+                <TransportSelect
+                    value={
+                        toolset.transport === "sse" ? "sse" : "streamable_http"
+                    }
+                    onChange={(value) => onChange("transport", value)}
+                />
+            </div>
+
+            <div className="space-y-2">
+                <label className="font-semibold">Headers (JSON)</label>
+                <Input
+                    spellCheck={false} // prevent smart quotes
+                    value={toolset.headers || "{}"}
+                    onChange={(e) => onChange("headers", e.target.value)}
+                    className={errors.headers ? "border-destructive" : ""}
+                />
+                <span className="text-[10px] ">
+                    HTTP headers to send with every request. For example:{" "}
+                    <code>{`{ "X-API-Key": "..." }`}</code>
+                </span>
+                {errors.headers && (
+                    <div className="text-destructive ">{errors.headers}</div>
+                )}
+            </div>
+
+            <div className="space-y-2">
+                <label className="font-semibold">Bearer token</label>
+                <Input
+                    type="password"
+                    value={toolset.bearerToken || ""}
+                    spellCheck={false}
+                    autoComplete="off"
+                    onChange={(e) => onChange("bearerToken", e.target.value)}
+                />
+                <span className="text-[10px] ">
+                    Optional. Sent as an Authorization header and kept in the
+                    encrypted vault.
+                </span>
+            </div>
+        </>
+    );
+}
+
+function StdioToolsetFields({
+    toolset,
+    errors,
+    apiKeyUrl,
+    onChange,
+}: ToolsetFieldsProps) {
+    return (
+        <>
             <div className="space-y-2">
                 <div className="flex items-center justify-between">
                     <label className="font-semibold">Command</label>
@@ -423,20 +660,7 @@ function ToolsetForm({
                     <div className="text-destructive ">{errors.env}</div>
                 )}
             </div>
-
-            <div className="flex justify-end space-x-2 pt-2">
-                <Button variant="outline" size="sm" onClick={onCancel}>
-                    Cancel
-                </Button>
-                <Button
-                    size="sm"
-                    onClick={onSave}
-                    disabled={Object.keys(errors).length > 0}
-                >
-                    Save
-                </Button>
-            </div>
-        </div>
+        </>
     );
 }
 
@@ -468,10 +692,12 @@ function CustomToolsetRow({
     };
 
     // Create a "full" command (for copying) and a truncated command (for display)
-    const fullCommandText =
-        `${envToCommands()} ${toolset.command} ${toolset.args || ""}`.trim();
-    const displayCommandText =
-        `${toolset.command} ${toolset.args || ""}`.trim();
+    const fullCommandText = isRemoteToolset(toolset)
+        ? toolset.url || ""
+        : `${envToCommands()} ${toolset.command} ${toolset.args || ""}`.trim();
+    const displayCommandText = isRemoteToolset(toolset)
+        ? toolset.url || ""
+        : `${toolset.command} ${toolset.args || ""}`.trim();
     const truncatedCommandText =
         displayCommandText.length > 75
             ? displayCommandText.slice(0, 75) + "..."
@@ -617,7 +843,6 @@ function ToolsTab() {
         ToolsetsAPI.useCustomToolsetConfigs();
     const updateToolset = ToolsetsAPI.useUpdateCustomToolsetConfig();
     const deleteToolset = ToolsetsAPI.useDeleteCustomToolsetConfig();
-    const importFromClaudeDesktop = ToolsetsAPI.useImportFromClaudeDesktop();
 
     // Form state
     const [formMode, setFormMode] = useState<
@@ -640,7 +865,16 @@ function ToolsTab() {
     ) => {
         const errors: Record<string, string> = {};
         if (!toolset.name) errors.name = "Name is required";
-        if (!toolset.command) errors.command = "Command is required";
+        if (isRemoteToolset(toolset)) {
+            const urlError = validateRemoteUrl(toolset.url);
+            if (urlError) errors.url = urlError;
+            const headersParsed = getEnvFromJSON(toolset.headers);
+            if (headersParsed._type === "error") {
+                errors.headers = headersParsed.error;
+            }
+        } else if (!toolset.command) {
+            errors.command = "Command is required";
+        }
 
         // Validate name format (one word, lowercase, alphanumeric with dashes)
         if (toolset.name && !/^[a-z0-9-]+$/.test(toolset.name)) {
@@ -731,12 +965,15 @@ function ToolsTab() {
         }
     };
 
-    const handleCreateRemoteToolset = async (name: string, url: string) => {
+    const handleCreateRemoteToolset = async (remote: RemoteToolset) => {
         await updateToolset.mutateAsync({
             toolset: {
-                name: name,
-                command: "npx",
-                args: `-y mcp-remote ${url}`,
+                name: remote.name,
+                transport: remote.transport,
+                url: remote.url,
+                bearerToken: remote.bearerToken,
+                command: "",
+                args: "",
                 env: "{}",
             },
         });
@@ -799,19 +1036,34 @@ function ToolsTab() {
         }
     };
 
-    const onClaudeDesktopImportClick = async () => {
+    const handleExport = async (format: MCPConfigFormat) => {
+        const { label, fileName } = MCP_CONFIG_FORMATS[format];
+        const path = await save({
+            title: `Export MCPs for ${label}`,
+            defaultPath: fileName,
+            filters: [{ name: "JSON", extensions: ["json"] }],
+        });
+        if (!path) return;
         try {
-            const result = await importFromClaudeDesktop.mutateAsync();
-            toast.success("Import Successful", {
-                description: `Imported ${result.imported} tools from Claude Desktop`,
+            const { exported, skipped } = await exportMCPServers(
+                format,
+                path,
+            );
+            const skippedNote =
+                skipped.length > 0
+                    ? ` Skipped ${skipped.join(", ")}, which the file already has.`
+                    : "";
+            if (exported === 0) {
+                toast.error("Nothing Exported", {
+                    description: `The file already has every MCP.${skippedNote}`,
+                });
+                return;
+            }
+            toast.success("Export Successful", {
+                description: `Exported ${exported} MCPs for ${label}. Secrets were replaced with placeholders.${skippedNote}`,
             });
         } catch (error) {
-            toast.error("Import Failed", {
-                description:
-                    error instanceof Error
-                        ? error.message
-                        : "Failed to import tools from Claude Desktop",
-            });
+            toast.error("Export Failed", { description: String(error) });
         }
     };
 
@@ -819,6 +1071,27 @@ function ToolsTab() {
         <div className="space-y-6">
             <div className="flex justify-between items-center">
                 <h3 className="text-2xl font-semibold">MCP Connections</h3>
+                <DropdownMenu>
+                    <DropdownMenuTrigger asChild>
+                        <Button
+                            variant="outline"
+                            size="sm"
+                            disabled={customToolsetConfigs.length === 0}
+                        >
+                            Export <ChevronDown className="h-4 w-4" />
+                        </Button>
+                    </DropdownMenuTrigger>
+                    <DropdownMenuContent align="end">
+                        {MCP_CONFIG_FORMAT_IDS.map((format) => (
+                            <DropdownMenuItem
+                                key={format}
+                                onSelect={() => void handleExport(format)}
+                            >
+                                For {MCP_CONFIG_FORMATS[format].label}
+                            </DropdownMenuItem>
+                        ))}
+                    </DropdownMenuContent>
+                </DropdownMenu>
             </div>
 
             {/* Form (for create, edit, and remote) */}
@@ -826,8 +1099,8 @@ function ToolsTab() {
                 <RemoteToolsetForm
                     isOpen={true}
                     onClose={handleCancelForm}
-                    onSubmit={(name, url) => {
-                        void handleCreateRemoteToolset(name, url);
+                    onSubmit={(remote) => {
+                        void handleCreateRemoteToolset(remote);
                     }}
                 />
             ) : formMode ? (
@@ -883,31 +1156,23 @@ function ToolsTab() {
                                 <TooltipTrigger asChild>
                                     <button
                                         onClick={() =>
-                                            void onClaudeDesktopImportClick()
+                                            dialogActions.openDialog(
+                                                IMPORT_MCP_SERVERS_DIALOG_ID,
+                                            )
                                         }
                                         className={`flex flex-col font-semibold items-center gap-2  border border-border hover:bg-muted rounded-md w-full py-4 disabled:opacity-50 disabled:hover:bg-transparent disabled:cursor-not-allowed`}
                                     >
-                                        {importFromClaudeDesktop.isPending ? (
-                                            <>
-                                                <Loader2 className="size-9 animate-spin" />
-                                                Importing...
-                                            </>
-                                        ) : (
-                                            <>
-                                                <RiClaudeFill className="size-9" />
-                                                Import from Claude Desktop
-                                            </>
-                                        )}
+                                        <Import className="size-9" />
+                                        Import from other apps
                                     </button>
                                 </TooltipTrigger>
                                 <TooltipContent
                                     side="bottom"
                                     className="max-w-[300px]"
                                 >
-                                    Import MCPs from Claude Desktop. If you've
-                                    made changes to your MCPs in Claude Desktop,
-                                    you can click this button again to refresh
-                                    your Chorus MCPs.
+                                    Import MCPs from Claude Desktop, Cursor, VS
+                                    Code, Windsurf or any mcp.json file. You
+                                    can preview them and pick which to import.
                                 </TooltipContent>
                             </Tooltip>
                         </div>
@@ -1208,93 +1473,1000 @@ function InternalTaskModelSelector() {
     );
 }
 
-function DangerZone() {
-    const [confirming, setConfirming] = useState(false);
-    const deleteAllChats = ChatAPI.useDeleteAllChats();
+function LocalApiSettings() {
+    const settingsManager = SettingsManager.getInstance();
+    const [status, setStatus] = useState<LocalAPI.LocalApiStatus>();
+    const [port, setPort] = useState("");
+    const [showToken, setShowToken] = useState(false);
 
-    const handleDelete = async () => {
+    useEffect(() => {
+        void LocalAPI.getLocalApiStatus().then((status) => {
+            setStatus(status);
+            setPort(String(status.port));
+        });
+        const unlisten = listen<LocalAPI.LocalApiStatus>(
+            LocalAPI.LOCAL_API_STATUS_EVENT,
+            (event) => setStatus(event.payload),
+        );
+        return () => {
+            void unlisten.then((fn) => fn()).catch(console.error);
+        };
+    }, []);
+
+    // The backend starts and stops the server when the settings change
+    const updateSettings = async (localApi: {
+        enabled?: boolean;
+        port?: number;
+    }) => {
+        const currentSettings = await settingsManager.get();
+        await settingsManager.set({
+            ...currentSettings,
+            localApi: { ...currentSettings.localApi, ...localApi },
+        });
+    };
+
+    const handlePortBlur = () => {
+        const value = Number(port);
+        if (!Number.isInteger(value) || value < 1024 || value > 65535) {
+            toast.error("Invalid port", {
+                description: "Choose a port between 1024 and 65535",
+            });
+            setPort(String(status?.port ?? LocalAPI.DEFAULT_LOCAL_API_PORT));
+            return;
+        }
+        if (value !== status?.port) {
+            void updateSettings({ port: value });
+        }
+    };
+
+    const handleRegenerate = async () => {
         try {
-            await deleteAllChats.mutateAsync();
-            toast.success("All chats deleted");
-            setConfirming(false);
-        } catch {
-            toast.error("Failed to delete chats");
+            await LocalAPI.regenerateLocalApiToken();
+            toast.success("Token regenerated", {
+                description: "Scripts using the old token will stop working",
+            });
+        } catch (error) {
+            toast.error("Couldn't regenerate the token", {
+                description: String(error),
+            });
         }
     };
 
+    if (!status) return null;
+
     return (
-        <div className="space-y-2">
-            <div className="font-semibold text-destructive">Danger Zone</div>
+        <div className="space-y-4">
             <div className="flex items-center justify-between">
-                <p className="text-sm text-muted-foreground">
-                    This action cannot be undone. All your chat history will be permanently deleted.
-                </p>
-                {confirming ? (
-                    <div className="flex gap-2 shrink-0 ml-4">
-                        <Button variant="outline" size="sm" onClick={() => setConfirming(false)}>
-                            Cancel
+                <div className="space-y-0.5">
+                    <div className="font-semibold">Local API</div>
+                    <div>
+                        Let scripts on this computer read and change your
+                        chats over HTTP. Requests need the token below.
+                    </div>
+                </div>
+                <Switch
+                    checked={status.enabled}
+                    onCheckedChange={(enabled) =>
+                        void updateSettings({ enabled })
+                    }
+                />
+            </div>
+            {status.enabled && (
+                <div className="space-y-3 text-sm">
+                    <div className="flex items-center gap-2">
+                        <label className="w-14 shrink-0">Port</label>
+                        <Input
+                            value={port}
+                            onChange={(e) => setPort(e.target.value)}
+                            onBlur={handlePortBlur}
+                            className="h-8 w-28 font-mono"
+                            inputMode="numeric"
+                        />
+                        {status.url && (
+                            <span className="font-mono text-muted-foreground truncate">
+                                {status.url}
+                            </span>
+                        )}
+                    </div>
+                    <div className="flex items-center gap-2">
+                        <label className="w-14 shrink-0">Token</label>
+                        <code className="flex-1 min-w-0 truncate font-mono">
+                            {showToken ? status.token : "\u2022".repeat(24)}
+                        </code>
+                        <Button
+                            variant="ghost"
+                            size="sm"
+                            onClick={() => setShowToken(!showToken)}
+                        >
+                            {showToken ? "Hide" : "Show"}
                         </Button>
+                        <SimpleCopyButton text={status.token} size="md" />
                         <Button
                             variant="outline"
                             size="sm"
-                            className="text-destructive hover:text-destructive border-destructive"
-                            onClick={() => void handleDelete()}
-                            disabled={deleteAllChats.isPending}
+                            onClick={() => void handleRegenerate()}
                         >
-                            {deleteAllChats.isPending ? "Deleting..." : "Confirm"}
+                            Regenerate
                         </Button>
                     </div>
-                ) : (
-                    <Button
-                        variant="outline"
-                        size="sm"
-                        className="text-destructive hover:text-destructive shrink-0 ml-4"
-                        onClick={() => setConfirming(true)}
-                    >
-                        <Trash2 className="h-4 w-4 mr-2" />
-                        Delete All Chats
-                    </Button>
-                )}
-            </div>
+                    {status.error && (
+                        <div className="text-destructive">{status.error}</div>
+                    )}
+                </div>
+            )}
         </div>
     );
 }
 
-export default function Settings({ tab = "general" }: SettingsProps) {
-    const settingsManager = SettingsManager.getInstance();
-    const { mode, setMode, setSansFont, setMonoFont, sansFont } = useTheme();
-    const [apiKeys, setApiKeys] = useState<Record<string, string>>({});
-    const [autoConvertLongText, setAutoConvertLongText] = useState(true);
-    const [autoScrapeUrls, setAutoScrapeUrls] = useState(true);
-    const [cautiousEnter, setCautiousEnter] = useState(false);
-    const { db } = useDatabase();
-    const [searchParams] = useSearchParams();
-    const defaultTab =
-        tab || (searchParams.get("tab") as SettingsTabId) || "general";
-    const [quickChatEnabled, setQuickChatEnabled] = useState(true);
-    const [quickChatShortcut, setQuickChatShortcut] = useState("Alt+Space");
-    const [lmStudioBaseUrl, setLmStudioBaseUrl] = useState(
-        "http://localhost:1234/v1",
-    );
-    const queryClient = useQueryClient();
+function VaultSettings({ onUnlock }: { onUnlock: () => void }) {
+    const [status, setStatus] = useState<Vault.VaultStatus>();
+    const [password, setPassword] = useState("");
+    const [confirmation, setConfirmation] = useState("");
+    const [busy, setBusy] = useState(false);
 
-    // Use React Query hooks for custom base URL
-    const customBaseUrl = AppMetadataAPI.useCustomBaseUrl() || "";
-    const setCustomBaseUrlMutation = AppMetadataAPI.useSetCustomBaseUrl();
+    const refreshStatus = async () => {
+        setStatus(await Vault.getVaultStatus());
+    };
 
-    // Universal system prompt autosync
-    const { draft: universalSystemPrompt, setDraft: setUniversalSystemPrompt } =
-        useReactQueryAutoSync({
-            queryOptions: {
-                queryKey: ["universalSystemPrompt"],
-                queryFn: async () => {
-                    const appMetadata = await AppMetadataAPI.fetchAppMetadata();
-                    return (
-                        appMetadata["universal_system_prompt"] ??
-                        UNIVERSAL_SYSTEM_PROMPT_DEFAULT
-                    );
-                },
-            },
+    useEffect(() => {
+        void refreshStatus().catch(console.error);
+    }, []);
+
+    const run = async (action: () => Promise<void>, success: string) => {
+        setBusy(true);
+        try {
+            await action();
+            setPassword("");
+            setConfirmation("");
+            toast.success(success);
+        } catch (error) {
+            toast.error("Vault error", {
+                description: String(error),
+            });
+        } finally {
+            setBusy(false);
+            await refreshStatus().catch(console.error);
+        }
+    };
+
+    const handleUnlock = async () => {
+        await run(async () => {
+            await Vault.unlockVault(password);
+            onUnlock();
+        }, "Vault unlocked");
+    };
+
+    const handleSetPassword = async () => {
+        if (password !== confirmation) {
+            toast.error("The passwords don't match");
+            return;
+        }
+        await run(() => Vault.setVaultPassword(password), "Password set");
+    };
+
+    const handleRemovePassword = async () => {
+        await run(() => Vault.setVaultPassword(undefined), "Password removed");
+    };
+
+    if (!status) return null;
+
+    const locked = status.uses_password && !status.unlocked;
+    let description =
+        "Your API keys and MCP secrets are encrypted with a key kept in your system's keychain. Set a password to require it every time Chorus starts.";
+    if (locked) {
+        description =
+            "Your API keys and MCP secrets are locked. Enter the vault password to use them.";
+    } else if (status.uses_password) {
+        description =
+            "Your API keys and MCP secrets are encrypted with your password, which Chorus asks for every time it starts.";
+    }
+
+    return (
+        <div className="space-y-4">
+            <div className="space-y-0.5">
+                <div className="font-semibold">Vault</div>
+                <div>{description}</div>
+            </div>
+            {locked ? (
+                <div className="flex items-center gap-2">
+                    <Input
+                        type="password"
+                        value={password}
+                        onChange={(e) => setPassword(e.target.value)}
+                        onKeyDown={(e) => {
+                            if (e.key === "Enter" && password) {
+                                void handleUnlock();
+                            }
+                        }}
+                        placeholder="Vault password"
+                        className="h-8"
+                    />
+                    <Button
+                        size="sm"
+                        disabled={busy || !password}
+                        onClick={() => void handleUnlock()}
+                    >
+                        Unlock
+                    </Button>
+                </div>
+            ) : (
+                <div className="space-y-2">
+                    <div className="flex items-center gap-2">
+                        <Input
+                            type="password"
+                            value={password}
+                            onChange={(e) => setPassword(e.target.value)}
+                            placeholder={
+                                status.uses_password
+                                    ? "New password"
+                                    : "Password"
+                            }
+                            className="h-8"
+                        />
+                        <Input
+                            type="password"
+                            value={confirmation}
+                            onChange={(e) => setConfirmation(e.target.value)}
+                            placeholder="Confirm password"
+                            className="h-8"
+                        />
+                        <Button
+                            variant="outline"
+                            size="sm"
+                            disabled={busy || !password}
+                            onClick={() => void handleSetPassword()}
+                        >
+                            {status.uses_password
+                                ? "Change password"
+                                : "Set password"}
+                        </Button>
+                    </div>
+                    {status.uses_password && (
+                        <Button
+                            variant="ghost"
+                            size="sm"
+                            disabled={busy}
+                            onClick={() => void handleRemovePassword()}
+                        >
+                            Remove password
+                        </Button>
+                    )}
+                </div>
+            )}
+        </div>
+    );
+}
+
+function ProfileSettings() {
+    const [profiles, setProfiles] = useState<Profiles.ProfileList>();
+    const [newName, setNewName] = useState("");
+    const [editing, setEditing] = useState<{ id: string; name: string }>();
+    const [deleting, setDeleting] = useState<string>();
+    const [switching, setSwitching] = useState(false);
+
+    useEffect(() => {
+        void Profiles.listProfiles().then(setProfiles);
+        const unlisten = listen<Profiles.ProfileList>(
+            Profiles.PROFILES_CHANGED_EVENT,
+            (event) => setProfiles(event.payload),
+        );
+        return () => {
+            void unlisten.then((fn) => fn()).catch(console.error);
+        };
+    }, []);
+
+    const handleCreate = async () => {
+        try {
+            await Profiles.createProfile(newName);
+            setNewName("");
+        } catch (error) {
+            toast.error("Couldn't create the profile", {
+                description: String(error),
+            });
+        }
+    };
+
+    const handleRename = async () => {
+        if (!editing) return;
+        try {
+            await Profiles.renameProfile(editing.id, editing.name);
+            setEditing(undefined);
+        } catch (error) {
+            toast.error("Couldn't rename the profile", {
+                description: String(error),
+            });
+        }
+    };
+
+    const handleDelete = async (id: string) => {
+        try {
+            await Profiles.deleteProfile(id);
+            setDeleting(undefined);
+        } catch (error) {
+            toast.error("Couldn't delete the profile", {
+                description: String(error),
+            });
+        }
+    };
+
+    const handleSwitch = async (id: string) => {
+        setSwitching(true);
+        try {
+            // Every window reloads once the switch is done
+            await Profiles.switchProfile(id);
+        } catch (error) {
+            setSwitching(false);
+            toast.error("Couldn't switch profiles", {
+                description: String(error),
+            });
+        }
+    };
+
+    if (!profiles) return null;
+
+    return (
+        <div className="space-y-4">
+            <div className="space-y-0.5">
+                <div className="font-semibold">Profiles</div>
+                <div>
+                    Each profile has its own chats, attachments and settings.
+                    Switching reloads Chorus with the other profile.
+                </div>
+            </div>
+            <div className="space-y-2 text-sm">
+                {profiles.profiles.map((profile) => {
+                    const isActive = profile.id === profiles.active;
+                    const canDelete =
+                        !isActive &&
+                        profile.id !== Profiles.DEFAULT_PROFILE_ID;
+                    if (editing?.id === profile.id) {
+                        return (
+                            <div
+                                key={profile.id}
+                                className="flex items-center gap-2"
+                            >
+                                <Input
+                                    value={editing.name}
+                                    onChange={(e) =>
+                                        setEditing({
+                                            ...editing,
+                                            name: e.target.value,
+                                        })
+                                    }
+                                    onKeyDown={(e) => {
+                                        if (e.key === "Enter") {
+                                            void handleRename();
+                                        } else if (e.key === "Escape") {
+                                            setEditing(undefined);
+                                        }
+                                    }}
+                                    className="h-8 flex-1"
+                                    autoFocus
+                                />
+                                <Button
+                                    variant="outline"
+                                    size="sm"
+                                    onClick={() => void handleRename()}
+                                >
+                                    Save
+                                </Button>
+                                <Button
+                                    variant="ghost"
+                                    size="sm"
+                                    onClick={() => setEditing(undefined)}
+                                >
+                                    Cancel
+                                </Button>
+                            </div>
+                        );
+                    }
+                    return (
+                        <div
+                            key={profile.id}
+                            className="flex items-center gap-2"
+                        >
+                            <span className="flex-1 truncate">
+                                {profile.name}
+                                {isActive && (
+                                    <span className="text-muted-foreground">
+                                        {" "}
+                                        (active)
+                                    </span>
+                                )}
+                            </span>
+                            {deleting === profile.id ? (
+                                <>
+                                    <span className="text-muted-foreground">
+                                        Delete its chats for good?
+                                    </span>
+                                    <Button
+                                        variant="outline"
+                                        size="sm"
+                                        onClick={() => setDeleting(undefined)}
+                                    >
+                                        Cancel
+                                    </Button>
+                                    <Button
+                                        variant="outline"
+                                        size="sm"
+                                        className="text-destructive hover:text-destructive border-destructive"
+                                        onClick={() =>
+                                            void handleDelete(profile.id)
+                                        }
+                                    >
+                                        Delete
+                                    </Button>
+                                </>
+                            ) : (
+                                <>
+                                    {!isActive && (
+                                        <Button
+                                            variant="outline"
+                                            size="sm"
+                                            disabled={switching}
+                                            onClick={() =>
+                                                void handleSwitch(profile.id)
+                                            }
+                                        >
+                                            Switch
+                                        </Button>
+                                    )}
+                                    <Button
+                                        variant="ghost"
+                                        size="iconSm"
+                                        onClick={() =>
+                                            setEditing({
+                                                id: profile.id,
+                                                name: profile.name,
+                                            })
+                                        }
+                                    >
+                                        <Pencil className="h-4 w-4" />
+                                    </Button>
+                                    {canDelete && (
+                                        <Button
+                                            variant="ghost"
+                                            size="iconSm"
+                                            onClick={() =>
+                                                setDeleting(profile.id)
+                                            }
+                                        >
+                                            <Trash2 className="h-4 w-4" />
+                                        </Button>
+                                    )}
+                                </>
+                            )}
+                        </div>
+                    );
+                })}
+            </div>
+            <div className="flex items-center gap-2">
+                <Input
+                    value={newName}
+                    onChange={(e) => setNewName(e.target.value)}
+                    onKeyDown={(e) => {
+                        if (e.key === "Enter" && newName.trim()) {
+                            void handleCreate();
+                        }
+                    }}
+                    placeholder="New profile name"
+                    className="h-8 flex-1"
+                />
+                <Button
+                    variant="outline"
+                    size="sm"
+                    disabled={!newName.trim()}
+                    onClick={() => void handleCreate()}
+                >
+                    <Plus className="h-4 w-4 mr-2" />
+                    Add profile
+                </Button>
+            </div>
+        </div>
+    );
+}
+
+const DATA_DIRECTORY_SOURCES: Record<
+    DataDirectory.DataDirectorySource,
+    string
+> = {
+    argument: "Set by the --data-dir argument.",
+    environment: "Set by the CHORUS_DATA_DIR environment variable.",
+    portable:
+        "Chorus is running portably from the ChorusData folder next to it.",
+    setting: "Moved from the default location.",
+    default: "The default location.",
+};
+
+function DataDirectorySettings() {
+    const { dataDirectory } = config;
+    const [destination, setDestination] = useState<string>();
+    const [moving, setMoving] = useState(false);
+
+    const handleChoose = async () => {
+        const selected = await open({
+            directory: true,
+            multiple: false,
+            title: "Choose an empty folder for the Chorus data",
+        });
+        if (typeof selected === "string") {
+            setDestination(selected);
+        }
+    };
+
+    const handleMove = async () => {
+        if (!destination) return;
+        setMoving(true);
+        try {
+            // Chorus restarts from the new location once the copy is done
+            await DataDirectory.moveDataDirectory(destination);
+        } catch (error) {
+            setMoving(false);
+            toast.error("Couldn't move the data", {
+                description: String(error),
+            });
+        }
+    };
+
+    return (
+        <div className="space-y-4">
+            <div className="space-y-0.5">
+                <div className="font-semibold">Data location</div>
+                <div>
+                    Chats, attachments, settings and backups are kept in{" "}
+                    <span className="font-mono text-sm break-all">
+                        {dataDirectory.configDir}
+                    </span>
+                    . {DATA_DIRECTORY_SOURCES[dataDirectory.source]}
+                </div>
+            </div>
+            {dataDirectory.movable &&
+                (destination ? (
+                    <div className="space-y-2 text-sm">
+                        <div>
+                            Copy everything to{" "}
+                            <span className="font-mono break-all">
+                                {destination}
+                            </span>{" "}
+                            and restart? The old files are left where they
+                            are.
+                        </div>
+                        <div className="flex gap-2">
+                            <Button
+                                variant="outline"
+                                size="sm"
+                                disabled={moving}
+                                onClick={() => void handleMove()}
+                            >
+                                {moving ? "Moving..." : "Move and restart"}
+                            </Button>
+                            <Button
+                                variant="ghost"
+                                size="sm"
+                                disabled={moving}
+                                onClick={() => setDestination(undefined)}
+                            >
+                                Cancel
+                            </Button>
+                        </div>
+                    </div>
+                ) : (
+                    <Button
+                        variant="outline"
+                        size="sm"
+                        onClick={() => void handleChoose()}
+                    >
+                        Move...
+                    </Button>
+                ))}
+        </div>
+    );
+}
+
+function DatabaseEncryptionSettings() {
+    const [status, setStatus] =
+        useState<DatabaseUnlock.DatabaseEncryptionStatus>();
+    const [action, setAction] = useState<"encrypt" | "rekey" | "decrypt">();
+    const [usePassphrase, setUsePassphrase] = useState(false);
+    const [passphrase, setPassphrase] = useState("");
+    const [confirmation, setConfirmation] = useState("");
+    const [working, setWorking] = useState(false);
+
+    useEffect(() => {
+        void DatabaseUnlock.getDatabaseEncryptionStatus()
+            .then(setStatus)
+            .catch(console.error);
+    }, []);
+
+    const startAction = (next: "encrypt" | "rekey" | "decrypt") => {
+        setAction(next);
+        setUsePassphrase(false);
+        setPassphrase("");
+        setConfirmation("");
+    };
+
+    const handleConfirm = async () => {
+        let key: DatabaseEncryption.DatabaseKeySource = { source: "keyring" };
+        if (action !== "decrypt" && usePassphrase) {
+            if (!passphrase) {
+                toast.error("Enter a passphrase");
+                return;
+            }
+            if (passphrase !== confirmation) {
+                toast.error("The passphrases don't match");
+                return;
+            }
+            key = { source: "passphrase", passphrase };
+        }
+        setWorking(true);
+        try {
+            // Chorus restarts once the database has been rewritten
+            if (action === "encrypt") {
+                await DatabaseEncryption.encryptDatabase(key);
+            } else if (action === "rekey") {
+                await DatabaseEncryption.rekeyDatabase(key);
+            } else {
+                await DatabaseEncryption.decryptDatabase();
+            }
+        } catch (error) {
+            setWorking(false);
+            toast.error("Couldn't change the database encryption", {
+                description: String(error),
+            });
+        }
+    };
+
+    if (!status) return null;
+
+    let description =
+        "Your chats and their backups are stored unencrypted. Encrypt them with a key kept in your system's keychain, or with a passphrase Chorus asks for every time it starts.";
+    if (status.key_source === "keyring") {
+        description =
+            "Your chats and their backups are encrypted with a key kept in your system's keychain.";
+    } else if (status.key_source === "passphrase") {
+        description =
+            "Your chats and their backups are encrypted with your passphrase, which Chorus asks for every time it starts.";
+    }
+
+    let confirmLabel = "Decrypt and restart";
+    if (action === "encrypt") {
+        confirmLabel = "Encrypt and restart";
+    } else if (action === "rekey") {
+        confirmLabel = "Change key and restart";
+    }
+
+    return (
+        <div className="space-y-4">
+            <div className="space-y-0.5">
+                <div className="font-semibold">Database encryption</div>
+                <div>{description}</div>
+            </div>
+            {action ? (
+                <div className="space-y-2 text-sm">
+                    {action === "decrypt" ? (
+                        <div>
+                            Store your chats and their backups unencrypted and
+                            restart?
+                        </div>
+                    ) : (
+                        <>
+                            <div className="flex items-center gap-2">
+                                <Switch
+                                    id="database-passphrase-switch"
+                                    checked={usePassphrase}
+                                    onCheckedChange={setUsePassphrase}
+                                />
+                                <label htmlFor="database-passphrase-switch">
+                                    Ask for a passphrase when Chorus starts
+                                </label>
+                            </div>
+                            {usePassphrase && (
+                                <div className="space-y-1">
+                                    <div className="flex items-center gap-2">
+                                        <Input
+                                            type="password"
+                                            value={passphrase}
+                                            onChange={(e) =>
+                                                setPassphrase(e.target.value)
+                                            }
+                                            placeholder="Passphrase"
+                                            className="h-8"
+                                        />
+                                        <Input
+                                            type="password"
+                                            value={confirmation}
+                                            onChange={(e) =>
+                                                setConfirmation(e.target.value)
+                                            }
+                                            placeholder="Confirm passphrase"
+                                            className="h-8"
+                                        />
+                                    </div>
+                                    <div className="text-muted-foreground">
+                                        There's no way to recover your chats
+                                        without it.
+                                    </div>
+                                </div>
+                            )}
+                        </>
+                    )}
+                    <div className="flex gap-2">
+                        <Button
+                            variant="outline"
+                            size="sm"
+                            disabled={working}
+                            onClick={() => void handleConfirm()}
+                        >
+                            {working ? "Working..." : confirmLabel}
+                        </Button>
+                        <Button
+                            variant="ghost"
+                            size="sm"
+                            disabled={working}
+                            onClick={() => setAction(undefined)}
+                        >
+                            Cancel
+                        </Button>
+                    </div>
+                </div>
+            ) : status.encrypted ? (
+                <div className="flex gap-2">
+                    <Button
+                        variant="outline"
+                        size="sm"
+                        onClick={() => startAction("rekey")}
+                    >
+                        Change key...
+                    </Button>
+                    <Button
+                        variant="outline"
+                        size="sm"
+                        onClick={() => startAction("decrypt")}
+                    >
+                        Decrypt...
+                    </Button>
+                </div>
+            ) : (
+                <Button
+                    variant="outline"
+                    size="sm"
+                    onClick={() => startAction("encrypt")}
+                >
+                    Encrypt...
+                </Button>
+            )}
+        </div>
+    );
+}
+
+function CloseBehaviorSettings() {
+    const settingsManager = SettingsManager.getInstance();
+    const [closeBehavior, setCloseBehavior] =
+        useState<Shutdown.CloseBehavior>(Shutdown.defaultCloseBehavior());
+
+    useEffect(() => {
+        void settingsManager.get().then((settings) => {
+            if (settings.closeBehavior) {
+                setCloseBehavior(settings.closeBehavior);
+            }
+        });
+    }, [settingsManager]);
+
+    // The backend reads it from the settings each time a window is closed
+    const handleChange = async (value: string) => {
+        const behavior = Shutdown.CLOSE_BEHAVIORS.find(
+            (option) => option.behavior === value,
+        )?.behavior;
+        if (!behavior) {
+            return;
+        }
+        setCloseBehavior(behavior);
+        const currentSettings = await settingsManager.get();
+        await settingsManager.set({
+            ...currentSettings,
+            closeBehavior: behavior,
+        });
+    };
+
+    return (
+        <div className="space-y-2">
+            <div className="space-y-0.5">
+                <label
+                    htmlFor="close-behavior-selector"
+                    className="font-semibold"
+                >
+                    Closing the Window
+                </label>
+                <p className="text-sm text-muted-foreground">
+                    What the main window's close button does. The quick
+                    chat window always just hides.
+                </p>
+            </div>
+            <Select
+                onValueChange={(value) => void handleChange(value)}
+                value={closeBehavior}
+            >
+                <SelectTrigger id="close-behavior-selector" className="w-full">
+                    <SelectValue />
+                </SelectTrigger>
+                <SelectContent>
+                    {Shutdown.CLOSE_BEHAVIORS.map(({ behavior, label }) => (
+                        <SelectItem key={behavior} value={behavior}>
+                            {label}
+                        </SelectItem>
+                    ))}
+                </SelectContent>
+            </Select>
+        </div>
+    );
+}
+
+function GlobalShortcutSettings() {
+    const settingsManager = SettingsManager.getInstance();
+    const [shortcuts, setShortcuts] = useState<
+        Partial<Record<GlobalActions.ExtraGlobalAction, string>>
+    >({});
+
+    useEffect(() => {
+        void settingsManager
+            .get()
+            .then((settings) => setShortcuts(settings.globalShortcuts ?? {}));
+    }, [settingsManager]);
+
+    const actions = GlobalActions.EXTRA_GLOBAL_ACTIONS.filter(
+        ({ linuxOnly }) => !linuxOnly || platform() === "linux",
+    );
+
+    // The backend registers it first, so it's only saved if that worked
+    const handleChange = async (
+        action: GlobalActions.ExtraGlobalAction,
+        value: string,
+    ) => {
+        setShortcuts({ ...shortcuts, [action]: value });
+        try {
+            await GlobalActions.setGlobalShortcut(action, value);
+        } catch (error) {
+            toast.error("Invalid shortcut", {
+                description: String(error),
+            });
+            setShortcuts(shortcuts);
+            return;
+        }
+        const currentSettings = await settingsManager.get();
+        await settingsManager.set({
+            ...currentSettings,
+            globalShortcuts: {
+                ...currentSettings.globalShortcuts,
+                [action]: value,
+            },
+        });
+    };
+
+    return (
+        <div className="space-y-4">
+            <div className="space-y-0.5">
+                <div className="font-semibold">Other Global Shortcuts</div>
+                <p className="text-sm text-muted-foreground">
+                    These work from any app. Leave one empty to turn it off.
+                </p>
+            </div>
+            {actions.map(({ action, label, description }) => (
+                <div key={action} className="space-y-2">
+                    <div className="space-y-0.5">
+                        <label className="text-sm font-medium">{label}</label>
+                        <p className="text-sm text-muted-foreground">
+                            {description}
+                        </p>
+                    </div>
+                    <div className="flex items-center gap-2">
+                        <ShortcutRecorder
+                            value={shortcuts[action] ?? ""}
+                            onChange={(shortcut) =>
+                                void handleChange(action, shortcut)
+                            }
+                        />
+                        <Button
+                            variant="outline"
+                            size="sm"
+                            disabled={!shortcuts[action]}
+                            onClick={() => void handleChange(action, "")}
+                        >
+                            Clear
+                        </Button>
+                    </div>
+                </div>
+            ))}
+        </div>
+    );
+}
+
+function DangerZone() {
+    const [confirming, setConfirming] = useState(false);
+    const deleteAllChats = ChatAPI.useDeleteAllChats();
+
+    const handleDelete = async () => {
+        try {
+            await deleteAllChats.mutateAsync();
+            toast.success("All chats deleted");
+            setConfirming(false);
+        } catch {
+            toast.error("Failed to delete chats");
+        }
+    };
+
+    return (
+        <div className="space-y-2">
+            <div className="font-semibold text-destructive">Danger Zone</div>
+            <div className="flex items-center justify-between">
+                <p className="text-sm text-muted-foreground">
+                    This action cannot be undone. All your chat history will be permanently deleted.
+                </p>
+                {confirming ? (
+                    <div className="flex gap-2 shrink-0 ml-4">
+                        <Button variant="outline" size="sm" onClick={() => setConfirming(false)}>
+                            Cancel
+                        </Button>
+                        <Button
+                            variant="outline"
+                            size="sm"
+                            className="text-destructive hover:text-destructive border-destructive"
+                            onClick={() => void handleDelete()}
+                            disabled={deleteAllChats.isPending}
+                        >
+                            {deleteAllChats.isPending ? "Deleting..." : "Confirm"}
+                        </Button>
+                    </div>
+                ) : (
+                    <Button
+                        variant="outline"
+                        size="sm"
+                        className="text-destructive hover:text-destructive shrink-0 ml-4"
+                        onClick={() => setConfirming(true)}
+                    >
+                        <Trash2 className="h-4 w-4 mr-2" />
+                        Delete All Chats
+                    </Button>
+                )}
+            </div>
+        </div>
+    );
+}
+
+export default function Settings({ tab = "general" }: SettingsProps) {
+    const settingsManager = SettingsManager.getInstance();
+    const { mode, setMode, setSansFont, setMonoFont, sansFont } = useTheme();
+    const [apiKeys, setApiKeys] = useState<Record<string, string>>({});
+    const [autoConvertLongText, setAutoConvertLongText] = useState(true);
+    const [autoScrapeUrls, setAutoScrapeUrls] = useState(true);
+    const [cautiousEnter, setCautiousEnter] = useState(false);
+    const { db } = useDatabase();
+    const [searchParams] = useSearchParams();
+    const defaultTab =
+        tab || (searchParams.get("tab") as SettingsTabId) || "general";
+    const [quickChatEnabled, setQuickChatEnabled] = useState(true);
+    const [quickChatShortcut, setQuickChatShortcut] = useState("Alt+Space");
+    const [lmStudioBaseUrl, setLmStudioBaseUrl] = useState(
+        "http://localhost:1234/v1",
+    );
+    const queryClient = useQueryClient();
+
+    // Use React Query hooks for custom base URL
+    const customBaseUrl = AppMetadataAPI.useCustomBaseUrl() || "";
+    const setCustomBaseUrlMutation = AppMetadataAPI.useSetCustomBaseUrl();
+
+    // Universal system prompt autosync
+    const { draft: universalSystemPrompt, setDraft: setUniversalSystemPrompt } =
+        useReactQueryAutoSync({
+            queryOptions: {
+                queryKey: ["universalSystemPrompt"],
+                queryFn: async () => {
+                    const appMetadata = await AppMetadataAPI.fetchAppMetadata();
+                    return (
+                        appMetadata["universal_system_prompt"] ??
+                        UNIVERSAL_SYSTEM_PROMPT_DEFAULT
+                    );
+                },
+            },
             mutationOptions: {
                 mutationFn: async (value: string) => {
                     await db.execute(
@@ -1344,6 +2516,13 @@ export default function Settings({ tab = "general" }: SettingsProps) {
         void queryClient.invalidateQueries({ queryKey: ["apiKeys"] });
     };
 
+    // The keys were shown as vault references while the vault was locked
+    const handleVaultUnlock = async () => {
+        const settings = (await settingsManager.get()) as Settings;
+        setApiKeys(settings.apiKeys ?? {});
+        void queryClient.invalidateQueries({ queryKey: ["apiKeys"] });
+    };
+
     useEffect(() => {
         const loadSettings = async () => {
             const settings = (await settingsManager.get()) as Settings;
@@ -1365,6 +2544,19 @@ export default function Settings({ tab = "general" }: SettingsProps) {
 
     const handleQuickChatShortcutChange = async (value: string) => {
         setQuickChatShortcut(value);
+        try {
+            await GlobalActions.setGlobalShortcut("quickChat", value);
+        } catch (error) {
+            toast.error("Invalid shortcut", {
+                description: String(error),
+            });
+            // Show the shortcut that's still registered
+            const currentSettings = await settingsManager.get();
+            setQuickChatShortcut(
+                currentSettings.quickChat?.shortcut ?? "Alt+Space",
+            );
+            return;
+        }
         const currentSettings = await settingsManager.get();
         void settingsManager.set({
             ...currentSettings,
@@ -1426,6 +2618,12 @@ export default function Settings({ tab = "general" }: SettingsProps) {
     };
 
     const onDefaultQcShortcutClick = async () => {
+        try {
+            await GlobalActions.setGlobalShortcut("quickChat", "Alt+Space");
+        } catch (error) {
+            toast.error("Error", { description: String(error) });
+            return;
+        }
         setQuickChatShortcut("Alt+Space");
         setQuickChatEnabled(true);
         const currentSettings = await settingsManager.get();
@@ -1733,6 +2931,22 @@ export default function Settings({ tab = "general" }: SettingsProps) {
 
                             <Separator className="my-4" />
 
+                            <ProfileSettings />
+
+                            <Separator className="my-4" />
+
+                            <DataDirectorySettings />
+
+                            <Separator className="my-4" />
+
+                            <DatabaseEncryptionSettings />
+
+                            <Separator className="my-4" />
+
+                            <LocalApiSettings />
+
+                            <Separator className="my-4" />
+
                             <DangerZone />
 
                             <div className="flex justify-end mt-4 mb-2"></div>
@@ -1849,6 +3063,10 @@ export default function Settings({ tab = "general" }: SettingsProps) {
                                     }
                                 />
                                 <Separator className="my-4" />
+                                <VaultSettings
+                                    onUnlock={() => void handleVaultUnlock()}
+                                />
+                                <Separator className="my-4" />
                                 <Collapsible className="space-y-2">
                                     <div className="flex items-center justify-between">
                                         <CollapsibleTrigger className="flex items-center w-full gap-2 hover:opacity-80">
@@ -1935,32 +3153,19 @@ export default function Settings({ tab = "general" }: SettingsProps) {
                                         >
                                             Set to default
                                         </Button>
-                                        <Button
-                                            variant="default"
-                                            size="sm"
-                                            onClick={() => {
-                                                if (!quickChatShortcut.trim()) {
-                                                    toast.error(
-                                                        "Invalid shortcut",
-                                                        {
-                                                            description:
-                                                                "Shortcut cannot be empty",
-                                                        },
-                                                    );
-                                                    return;
-                                                }
-                                                void relaunch().catch(
-                                                    console.error,
-                                                );
-                                            }}
-                                        >
-                                            Save and restart
-                                        </Button>
                                     </div>
                                 </div>
 
                                 <Separator />
 
+                                <GlobalShortcutSettings />
+
+                                <Separator />
+
+                                <CloseBehaviorSettings />
+
+                                <Separator />
+
                                 <div className="space-y-4">
                                     <AccessibilitySettings />
                                 </div>
@@ -2107,6 +3312,7 @@ export default function Settings({ tab = "general" }: SettingsProps) {
             </Dialog>
             <ImportChatDialog provider="openai" />
             <ImportChatDialog provider="anthropic" />
+            <ImportMCPServersDialog />
         </>
     );
 }
@@ -11,6 +11,7 @@ const SHIFT_KEY = "Shift";
 const ALT_KEY = "Alt";
 const META_KEY = "Meta";
 const COMMAND_KEY = "Command";
+const MODIFIER_CODES = ["Control", "Shift", "Alt", "Meta", "OS"];
 
 export default function ShortcutRecorder({
     value,
@@ -35,14 +36,14 @@ export default function ShortcutRecorder({
 
     // Gets the key from the current event, handling special cases like spaces
     const getKey = (e: React.KeyboardEvent<HTMLInputElement>) => {
-        const isStringKey = e.code === "Space";
-
-        // We use e.code for keys and digits to get their raw keyboard value
-        // so modifier keys can't affect their values
-        if (isStringKey) return "Space";
-        else if (e.code.startsWith("Key")) return e.code.replace("Key", "");
+        // We use e.code for everything but modifiers to get the raw keyboard
+        // value, so Shift can't turn / into ? and every key gets a name the
+        // backend understands (Slash, Numpad1, ArrowUp, F5)
+        if (MODIFIER_CODES.some((code) => e.code.startsWith(code))) {
+            return e.key;
+        } else if (e.code.startsWith("Key")) return e.code.replace("Key", "");
         else if (e.code.startsWith("Digit")) return e.code.replace("Digit", "");
-        else return e.key;
+        else return e.code || e.key;
     };
 
     const handleKeyDown = (e: React.KeyboardEvent<HTMLInputElement>) => {
@@ -201,8 +201,8 @@ export function useAttachUrl({
             const path = await generateStorePath(url, "md");
 
             // Get Firecrawl API key from settings
-            const settings = await SettingsManager.getInstance().get();
-            const firecrawlApiKey = settings.apiKeys?.firecrawl;
+            const apiKeys = await SettingsManager.getInstance().getApiKeys();
+            const firecrawlApiKey = apiKeys.firecrawl;
 
             if (!firecrawlApiKey) {
                 toast.error("Firecrawl API key not configured", {